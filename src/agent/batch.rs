@@ -0,0 +1,162 @@
+//! Quota-friendly batch runner: one prompt template applied across a
+//! set of files.
+//!
+//! Requests go out strictly one at a time with a minimum gap between
+//! them, so rate-limited providers see a steady trickle instead of a
+//! burst. Each response is collected as a proposed rewrite of its file;
+//! the user reviews the diffs and applies the approved ones in bulk.
+
+use std::path::PathBuf;
+use std::time::{Duration, Instant};
+
+/// Minimum gap between consecutive batch requests.
+pub const SEND_INTERVAL: Duration = Duration::from_secs(2);
+
+/// Where one file is in the batch pipeline.
+pub enum ItemState {
+    Queued,
+    /// Request in flight.
+    Sent,
+    /// Response collected: the proposed new contents, pending review.
+    /// The rendered diff lives in the conversation's review entry.
+    Ready { proposed: String },
+    /// Request failed; the error went to the conversation.
+    Failed,
+    Applied,
+}
+
+pub struct BatchItem {
+    pub path: PathBuf,
+    pub state: ItemState,
+}
+
+/// One batch run: a template plus the per-file pipeline.
+pub struct BatchRun {
+    pub template: String,
+    pub items: Vec<BatchItem>,
+    /// Index of the item whose request is in flight.
+    pub in_flight: Option<usize>,
+    last_sent: Option<Instant>,
+}
+
+impl BatchRun {
+    pub fn new(template: String, paths: Vec<PathBuf>) -> Self {
+        Self {
+            template,
+            items: paths
+                .into_iter()
+                .map(|path| BatchItem {
+                    path,
+                    state: ItemState::Queued,
+                })
+                .collect(),
+            in_flight: None,
+            last_sent: None,
+        }
+    }
+
+    /// The next queued item, once nothing is in flight and the send
+    /// interval has passed.
+    pub fn ready_to_send(&self) -> Option<usize> {
+        if self.in_flight.is_some() {
+            return None;
+        }
+        if let Some(at) = self.last_sent {
+            if at.elapsed() < SEND_INTERVAL {
+                return None;
+            }
+        }
+        self.items
+            .iter()
+            .position(|item| matches!(item.state, ItemState::Queued))
+    }
+
+    /// Mark an item as sent and record the send time.
+    pub fn mark_sent(&mut self, idx: usize) {
+        self.items[idx].state = ItemState::Sent;
+        self.in_flight = Some(idx);
+        self.last_sent = Some(Instant::now());
+    }
+
+    /// Number of items still queued or in flight.
+    pub fn remaining(&self) -> usize {
+        self.items
+            .iter()
+            .filter(|item| matches!(item.state, ItemState::Queued | ItemState::Sent))
+            .count()
+    }
+
+    /// One-line progress summary for the status bar.
+    pub fn summary(&self) -> String {
+        let mut queued = 0;
+        let mut ready = 0;
+        let mut failed = 0;
+        let mut applied = 0;
+        for item in &self.items {
+            match item.state {
+                ItemState::Queued | ItemState::Sent => queued += 1,
+                ItemState::Ready { .. } => ready += 1,
+                ItemState::Failed => failed += 1,
+                ItemState::Applied => applied += 1,
+            }
+        }
+        format!("batch: {queued} pending, {ready} ready, {applied} applied, {failed} failed")
+    }
+}
+
+/// The proposed file contents inside a response: the first fenced code
+/// block when the model wrapped it, otherwise the whole response.
+pub fn extract_contents(response: &str) -> String {
+    let mut lines = response.lines();
+    let mut body = Vec::new();
+    let mut in_fence = false;
+    for line in &mut lines {
+        if line.trim_start().starts_with("```") {
+            if in_fence {
+                return body.join("\n") + "\n";
+            }
+            in_fence = true;
+            continue;
+        }
+        if in_fence {
+            body.push(line);
+        }
+    }
+    let mut out = response.trim_end().to_string();
+    out.push('\n');
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn extract_contents_prefers_the_first_fenced_block() {
+        let response = "Here you go:\n```rust\nfn main() {}\n```\nAnything else?";
+        assert_eq!(extract_contents(response), "fn main() {}\n");
+        assert_eq!(extract_contents("plain text"), "plain text\n");
+    }
+
+    #[test]
+    fn batch_sends_one_item_at_a_time() {
+        let mut run = BatchRun::new(
+            "add docs".to_string(),
+            vec![PathBuf::from("a.rs"), PathBuf::from("b.rs")],
+        );
+        let idx = run.ready_to_send().unwrap();
+        assert_eq!(idx, 0);
+        run.mark_sent(idx);
+        // In flight: nothing else goes out, even though b.rs is queued.
+        assert_eq!(run.ready_to_send(), None);
+        assert_eq!(run.remaining(), 2);
+        run.items[0].state = ItemState::Ready {
+            proposed: String::new(),
+        };
+        run.in_flight = None;
+        // The send interval still gates the next request.
+        assert_eq!(run.ready_to_send(), None);
+        run.last_sent = Some(Instant::now() - SEND_INTERVAL);
+        assert_eq!(run.ready_to_send(), Some(1));
+    }
+}
@@ -0,0 +1,185 @@
+//! Language-aware chunking of oversized buffer context.
+//!
+//! Attaching a whole buffer to an [`AgentRequest`](crate::agent::AgentRequest)
+//! does not scale to huge files. Instead of truncating at a byte count,
+//! the buffer is split along its symbol outline (functions, types,
+//! classes): the chunk containing the cursor is sent verbatim, the other
+//! definitions are listed as signatures, and the omission is stated so
+//! the model does not invent the missing code.
+
+/// Buffers above this size are abridged before being attached.
+pub const MAX_CONTEXT_CHARS: usize = 48 * 1024;
+
+/// One outline entry: the 0-based line it starts on and its signature
+/// line, trimmed.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Symbol {
+    pub line: usize,
+    pub signature: String,
+}
+
+/// Return `text` unchanged while it fits, otherwise the symbol chunk
+/// around `cursor_line` with an inventory of the omitted definitions.
+pub fn abridge(text: &str, language: Option<&str>, cursor_line: usize) -> String {
+    if text.len() <= MAX_CONTEXT_CHARS {
+        return text.to_string();
+    }
+    let lines: Vec<&str> = text.lines().collect();
+    let symbols = outline(&lines, language);
+    let (start, end) = match chunk_bounds(&symbols, cursor_line, lines.len()) {
+        Some(bounds) => bounds,
+        // No outline (unknown language or none found): fall back to a
+        // window centered on the cursor rather than the file head.
+        None => window_bounds(&lines, cursor_line),
+    };
+    let mut out = format!(
+        "NOTE: file abridged for length; showing lines {}-{} (cursor region). \
+         Everything else is omitted - do not guess its contents.\n",
+        start + 1,
+        end
+    );
+    let siblings: Vec<&Symbol> = symbols
+        .iter()
+        .filter(|s| s.line < start || s.line >= end)
+        .collect();
+    if !siblings.is_empty() {
+        out.push_str("Omitted definitions (signatures only):\n");
+        for symbol in siblings {
+            out.push_str("  ");
+            out.push_str(&symbol.signature);
+            out.push('\n');
+        }
+    }
+    out.push('\n');
+    let mut chunk = lines[start..end].join("\n");
+    if chunk.len() > MAX_CONTEXT_CHARS {
+        let cut = floor_char_boundary(&chunk, MAX_CONTEXT_CHARS);
+        chunk.truncate(cut);
+        chunk.push_str("\n… (chunk itself truncated)");
+    }
+    out.push_str(&chunk);
+    out
+}
+
+/// The half-open line range of the symbol containing `cursor_line`, or
+/// `None` when there is no outline to chunk along.
+fn chunk_bounds(symbols: &[Symbol], cursor_line: usize, total: usize) -> Option<(usize, usize)> {
+    if symbols.is_empty() {
+        return None;
+    }
+    match symbols.iter().rposition(|s| s.line <= cursor_line) {
+        // Before the first symbol: the file preamble up to it.
+        None => Some((0, symbols[0].line)),
+        Some(idx) => {
+            let start = symbols[idx].line;
+            let end = symbols.get(idx + 1).map_or(total, |s| s.line);
+            Some((start, end))
+        }
+    }
+}
+
+/// Fallback for unknown languages: as many whole lines around the cursor
+/// as fit in the budget.
+fn window_bounds(lines: &[&str], cursor_line: usize) -> (usize, usize) {
+    let cursor = cursor_line.min(lines.len().saturating_sub(1));
+    let mut start = cursor;
+    let mut end = cursor + 1;
+    let mut used = lines.get(cursor).map_or(0, |l| l.len());
+    loop {
+        let mut grew = false;
+        if start > 0 && used + lines[start - 1].len() < MAX_CONTEXT_CHARS {
+            start -= 1;
+            used += lines[start].len() + 1;
+            grew = true;
+        }
+        if end < lines.len() && used + lines[end].len() < MAX_CONTEXT_CHARS {
+            used += lines[end].len() + 1;
+            end += 1;
+            grew = true;
+        }
+        if !grew {
+            return (start, end);
+        }
+    }
+}
+
+/// A line-based symbol outline: lines that open a definition for the
+/// buffer's language. Deliberately heuristic - it only has to produce
+/// reasonable chunk boundaries, not a faithful parse.
+pub fn outline(lines: &[&str], language: Option<&str>) -> Vec<Symbol> {
+    let keywords: &[&str] = match language {
+        Some("rust") => &[
+            "fn ", "struct ", "enum ", "trait ", "impl ", "mod ", "macro_rules!",
+        ],
+        Some("python") => &["def ", "async def ", "class "],
+        Some("javascript" | "typescript") => &["function ", "async function ", "class "],
+        Some("go") => &["func ", "type "],
+        _ => return Vec::new(),
+    };
+    let visibility: &[&str] = &["pub(crate) ", "pub ", "export default ", "export "];
+    lines
+        .iter()
+        .enumerate()
+        .filter_map(|(line, text)| {
+            let mut head = text.trim_start();
+            for prefix in visibility {
+                head = head.strip_prefix(prefix).unwrap_or(head);
+            }
+            keywords
+                .iter()
+                .any(|kw| head.starts_with(kw))
+                .then(|| Symbol {
+                    line,
+                    signature: text.trim().trim_end_matches('{').trim_end().to_string(),
+                })
+        })
+        .collect()
+}
+
+fn floor_char_boundary(text: &str, mut index: usize) -> usize {
+    while index > 0 && !text.is_char_boundary(index) {
+        index -= 1;
+    }
+    index
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn outlines_rust_definitions() {
+        let lines = vec![
+            "use std::fmt;",
+            "pub fn alpha() {",
+            "}",
+            "struct Beta {",
+            "}",
+            "    fn method(&self) {",
+        ];
+        let symbols = outline(&lines, Some("rust"));
+        let sigs: Vec<&str> = symbols.iter().map(|s| s.signature.as_str()).collect();
+        assert_eq!(sigs, vec!["pub fn alpha()", "struct Beta", "fn method(&self)"]);
+        assert_eq!(symbols[0].line, 1);
+        assert!(outline(&lines, None).is_empty());
+    }
+
+    #[test]
+    fn abridges_around_the_cursor_with_sibling_signatures() {
+        let filler = "x".repeat(200);
+        let mut text = String::new();
+        for i in 0..400 {
+            text.push_str(&format!("fn item{i}() {{\n    let _ = \"{filler}\";\n}}\n"));
+        }
+        assert!(text.len() > MAX_CONTEXT_CHARS);
+        // Cursor inside fn item7's body.
+        let abridged = abridge(&text, Some("rust"), 22);
+        assert!(abridged.contains("fn item7()"));
+        assert!(abridged.contains("file abridged"));
+        assert!(abridged.contains("  fn item0()"));
+        assert!(!abridged.contains(&format!("let _ = \"{filler}\";\n}}\nfn item9")));
+
+        let small = "fn tiny() {}\n";
+        assert_eq!(abridge(small, Some("rust"), 0), small);
+    }
+}
@@ -0,0 +1,288 @@
+//! Conversation persistence under `.clide/conversations/`.
+//!
+//! Each conversation is one JSON file named by its id, so chats survive
+//! restarts and the history overlay can browse, rename, and delete them
+//! without a database.
+
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+
+use super::AgentPanelEntry;
+
+/// A conversation on disk.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SavedConversation {
+    pub id: String,
+    pub title: String,
+    /// Human-readable local time of the last save.
+    pub saved_at: String,
+    pub entries: Vec<AgentPanelEntry>,
+}
+
+/// The listing row for one saved conversation.
+#[derive(Debug, Clone)]
+pub struct ConversationMeta {
+    pub id: String,
+    pub title: String,
+    pub saved_at: String,
+    pub entries: usize,
+}
+
+fn dir(root: &Path) -> PathBuf {
+    root.join(".clide").join("conversations")
+}
+
+fn file(root: &Path, id: &str) -> PathBuf {
+    dir(root).join(format!("{id}.json"))
+}
+
+/// A fresh conversation id: the save timestamp, unique enough for one
+/// workspace.
+pub fn new_id() -> String {
+    chrono::Local::now().format("%Y%m%d-%H%M%S").to_string()
+}
+
+/// A display title for a conversation: its first user prompt, clipped.
+pub fn derive_title(entries: &[AgentPanelEntry]) -> String {
+    let first = entries.iter().find_map(|entry| match entry {
+        AgentPanelEntry::User(text) => Some(text.trim()),
+        _ => None,
+    });
+    let title = first.unwrap_or("untitled").replace('\n', " ");
+    if title.chars().count() > 48 {
+        let clipped: String = title.chars().take(47).collect();
+        format!("{clipped}…")
+    } else {
+        title
+    }
+}
+
+/// Every saved conversation, newest first. Files that fail to parse are
+/// skipped rather than blocking the overlay.
+pub fn list(root: &Path) -> Vec<ConversationMeta> {
+    let mut out = Vec::new();
+    let Ok(entries) = fs::read_dir(dir(root)) else {
+        return out;
+    };
+    for entry in entries.flatten() {
+        let Ok(text) = fs::read_to_string(entry.path()) else {
+            continue;
+        };
+        let Ok(saved) = serde_json::from_str::<SavedConversation>(&text) else {
+            continue;
+        };
+        out.push(ConversationMeta {
+            id: saved.id,
+            title: saved.title,
+            saved_at: saved.saved_at,
+            entries: saved.entries.len(),
+        });
+    }
+    out.sort_by(|a, b| b.id.cmp(&a.id));
+    out
+}
+
+pub fn save(root: &Path, conversation: &SavedConversation) -> Result<()> {
+    let path = file(root, &conversation.id);
+    if let Some(dir) = path.parent() {
+        fs::create_dir_all(dir)
+            .with_context(|| format!("failed to create {}", dir.display()))?;
+    }
+    let text = serde_json::to_string_pretty(conversation)?;
+    fs::write(&path, text).with_context(|| format!("failed to write {}", path.display()))
+}
+
+pub fn load(root: &Path, id: &str) -> Option<SavedConversation> {
+    let text = fs::read_to_string(file(root, id)).ok()?;
+    serde_json::from_str(&text).ok()
+}
+
+pub fn rename(root: &Path, id: &str, title: &str) -> Result<()> {
+    let mut saved = load(root, id)
+        .with_context(|| format!("conversation {id} not found"))?;
+    saved.title = title.to_string();
+    save(root, &saved)
+}
+
+pub fn delete(root: &Path, id: &str) -> Result<()> {
+    let path = file(root, id);
+    fs::remove_file(&path).with_context(|| format!("failed to remove {}", path.display()))
+}
+
+/// One keyword match inside a saved conversation.
+#[derive(Debug, Clone)]
+pub struct SearchHit {
+    pub id: String,
+    pub title: String,
+    /// Index of the matching entry, for scrolling near it on reopen.
+    pub entry: usize,
+    /// The matching region with a little surrounding context.
+    pub snippet: String,
+}
+
+/// The searchable text of one panel entry.
+fn entry_text(entry: &AgentPanelEntry) -> &str {
+    match entry {
+        AgentPanelEntry::Info(t)
+        | AgentPanelEntry::User(t)
+        | AgentPanelEntry::Response(t)
+        | AgentPanelEntry::Error(t) => t,
+        AgentPanelEntry::Diff { diff, .. } => diff,
+        AgentPanelEntry::ToolOutput { output, .. } => output,
+        AgentPanelEntry::Image(_) => "",
+    }
+}
+
+/// Case-insensitive keyword search across every saved conversation,
+/// newest first, at most one hit per entry and `limit` hits in total.
+pub fn search(root: &Path, query: &str, limit: usize) -> Vec<SearchHit> {
+    let needle = query.to_lowercase();
+    let mut hits = Vec::new();
+    if needle.is_empty() {
+        return hits;
+    }
+    for meta in list(root) {
+        let Some(saved) = load(root, &meta.id) else {
+            continue;
+        };
+        for (i, entry) in saved.entries.iter().enumerate() {
+            let text = entry_text(entry);
+            let Some(pos) = text.to_lowercase().find(&needle) else {
+                continue;
+            };
+            hits.push(SearchHit {
+                id: saved.id.clone(),
+                title: saved.title.clone(),
+                entry: i,
+                snippet: snippet(text, pos),
+            });
+            if hits.len() >= limit {
+                return hits;
+            }
+        }
+    }
+    hits
+}
+
+/// Render a conversation as a standalone Markdown document, one section
+/// per entry, for the export command.
+pub fn export_markdown(title: &str, entries: &[AgentPanelEntry]) -> String {
+    let mut out = format!("# {title}\n");
+    for entry in entries {
+        match entry {
+            AgentPanelEntry::Info(text) => out.push_str(&format!("\n> {text}\n")),
+            AgentPanelEntry::User(text) => out.push_str(&format!("\n## You\n\n{text}\n")),
+            AgentPanelEntry::Response(text) => out.push_str(&format!("\n## Agent\n\n{text}\n")),
+            AgentPanelEntry::Diff { path, diff } => out.push_str(&format!(
+                "\n## Patch: {}\n\n```diff\n{diff}\n```\n",
+                path.display()
+            )),
+            AgentPanelEntry::ToolOutput { name, output } => out.push_str(&format!(
+                "\n## Tool: {name}\n\n```\n{output}\n```\n"
+            )),
+            AgentPanelEntry::Image(path) => {
+                out.push_str(&format!("\n![image]({})\n", path.display()))
+            }
+            AgentPanelEntry::Error(text) => out.push_str(&format!("\n## Error\n\n{text}\n")),
+        }
+    }
+    out
+}
+
+/// Clip a window of text around byte offset `pos`, flattened to one line.
+fn snippet(text: &str, pos: usize) -> String {
+    let start = text
+        .char_indices()
+        .map(|(i, _)| i)
+        .filter(|&i| i <= pos)
+        .rev()
+        .nth(30)
+        .unwrap_or(0);
+    let clipped: String = text[start..].chars().take(100).collect();
+    let mut flat = clipped.replace('\n', " ");
+    if start > 0 {
+        flat.insert(0, '…');
+    }
+    if text[start..].chars().count() > 100 {
+        flat.push('…');
+    }
+    flat
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn derives_title_from_first_user_prompt() {
+        let entries = vec![
+            AgentPanelEntry::Info("profile ready".to_string()),
+            AgentPanelEntry::User("explain this\nfunction".to_string()),
+        ];
+        assert_eq!(derive_title(&entries), "explain this function");
+        assert_eq!(derive_title(&[]), "untitled");
+    }
+
+    #[test]
+    fn export_markdown_sections_every_entry_kind() {
+        let entries = vec![
+            AgentPanelEntry::User("fix the bug".to_string()),
+            AgentPanelEntry::Response("done".to_string()),
+            AgentPanelEntry::ToolOutput {
+                name: "read_file".to_string(),
+                output: "contents".to_string(),
+            },
+        ];
+        let doc = export_markdown("session", &entries);
+        assert!(doc.starts_with("# session\n"));
+        assert!(doc.contains("## You\n\nfix the bug"));
+        assert!(doc.contains("## Agent\n\ndone"));
+        assert!(doc.contains("## Tool: read_file\n\n```\ncontents\n```"));
+    }
+
+    #[test]
+    fn saves_lists_renames_and_deletes() {
+        let root = std::env::temp_dir().join(format!("clide-hist-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&root);
+        let saved = SavedConversation {
+            id: "20260101-000000".to_string(),
+            title: "first".to_string(),
+            saved_at: "now".to_string(),
+            entries: vec![AgentPanelEntry::User("hi".to_string())],
+        };
+        save(&root, &saved).unwrap();
+        let listed = list(&root);
+        assert_eq!(listed.len(), 1);
+        assert_eq!(listed[0].title, "first");
+        rename(&root, &saved.id, "renamed").unwrap();
+        assert_eq!(load(&root, &saved.id).unwrap().title, "renamed");
+        delete(&root, &saved.id).unwrap();
+        assert!(list(&root).is_empty());
+        let _ = fs::remove_dir_all(&root);
+    }
+
+    #[test]
+    fn search_finds_hits_across_conversations() {
+        let root = std::env::temp_dir().join(format!("clide-hist-search-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&root);
+        let saved = SavedConversation {
+            id: "20260101-000001".to_string(),
+            title: "borrow question".to_string(),
+            saved_at: "now".to_string(),
+            entries: vec![
+                AgentPanelEntry::User("what is a lifetime?".to_string()),
+                AgentPanelEntry::Response("A lifetime names a borrow's scope.".to_string()),
+            ],
+        };
+        save(&root, &saved).unwrap();
+        let hits = search(&root, "LIFETIME", 10);
+        assert_eq!(hits.len(), 2);
+        assert_eq!(hits[0].entry, 0);
+        assert!(hits[1].snippet.contains("borrow's scope"));
+        assert!(search(&root, "quaternion", 10).is_empty());
+        let _ = fs::remove_dir_all(&root);
+    }
+}
@@ -0,0 +1,66 @@
+//! Per-project agent instructions (`CLIDE.md`).
+//!
+//! A `CLIDE.md` at the workspace root — or `.clide/instructions.md` for
+//! projects that keep clide files out of the top level — is prepended to
+//! every provider's system prompt, like other AI IDEs' project rules
+//! files. The file is re-read before each request, so edits apply
+//! without a restart.
+
+use std::path::{Path, PathBuf};
+
+/// The instructions file for `root`: `CLIDE.md` wins when it exists,
+/// otherwise `.clide/instructions.md`.
+pub fn path(root: &Path) -> PathBuf {
+    let top = root.join("CLIDE.md");
+    if top.is_file() {
+        return top;
+    }
+    root.join(".clide").join("instructions.md")
+}
+
+/// The trimmed instructions text, if the file exists and says anything.
+pub fn load(root: &Path) -> Option<String> {
+    let text = std::fs::read_to_string(path(root)).ok()?;
+    let text = text.trim().to_string();
+    (!text.is_empty()).then_some(text)
+}
+
+/// Project instructions and the profile's own system prompt, combined
+/// with the instructions first.
+pub fn combine(instructions: Option<&str>, system: Option<&str>) -> Option<String> {
+    match (instructions, system) {
+        (Some(instructions), Some(system)) => Some(format!("{instructions}\n\n{system}")),
+        (Some(instructions), None) => Some(instructions.to_string()),
+        (None, Some(system)) => Some(system.to_string()),
+        (None, None) => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn combine_prefers_instructions_first() {
+        assert_eq!(
+            combine(Some("rules"), Some("persona")).as_deref(),
+            Some("rules\n\npersona")
+        );
+        assert_eq!(combine(None, Some("persona")).as_deref(), Some("persona"));
+        assert_eq!(combine(Some("rules"), None).as_deref(), Some("rules"));
+        assert_eq!(combine(None, None), None);
+    }
+
+    #[test]
+    fn load_falls_back_to_the_dot_clide_file() {
+        let root = std::env::temp_dir().join(format!("clide-instr-{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&root);
+        std::fs::create_dir_all(root.join(".clide")).unwrap();
+        assert_eq!(load(&root), None);
+        std::fs::write(root.join(".clide").join("instructions.md"), "be terse\n").unwrap();
+        assert_eq!(load(&root).as_deref(), Some("be terse"));
+        std::fs::write(root.join("CLIDE.md"), "top level wins\n").unwrap();
+        assert_eq!(load(&root).as_deref(), Some("top level wins"));
+        let _ = std::fs::remove_dir_all(&root);
+    }
+}
@@ -0,0 +1,129 @@
+//! API key storage in the OS keyring.
+//!
+//! Talks to the platform's keyring through its CLI helper — `security`
+//! on macOS, `secret-tool` (libsecret) elsewhere — so no daemon bindings
+//! are linked in. When neither helper works, callers fall back to the
+//! plain `agents.toml` storage.
+
+use std::io::Write;
+use std::process::{Command, Stdio};
+
+use anyhow::{bail, Context, Result};
+
+/// Service name all clide secrets are filed under.
+const SERVICE: &str = "clide";
+
+fn helper() -> &'static str {
+    if cfg!(target_os = "macos") {
+        "security"
+    } else {
+        "secret-tool"
+    }
+}
+
+/// Whether the platform helper is present at all.
+pub fn available() -> bool {
+    Command::new(helper())
+        .arg("--version")
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .status()
+        .is_ok()
+}
+
+/// Store `secret` under `account` in the OS keyring, replacing any
+/// previous value.
+pub fn store(account: &str, secret: &str) -> Result<()> {
+    if cfg!(target_os = "macos") {
+        // `security -i` reads commands from stdin, so the secret never
+        // shows up in argv where any local process could read it.
+        let mut child = Command::new("security")
+            .arg("-i")
+            .stdin(Stdio::piped())
+            .stdout(Stdio::null())
+            .stderr(Stdio::null())
+            .spawn()
+            .context("failed to run security")?;
+        let command = format!(
+            "add-generic-password -U -s {} -a {} -w {}\n",
+            security_quote(SERVICE),
+            security_quote(account),
+            security_quote(secret),
+        );
+        child
+            .stdin
+            .take()
+            .context("security has no stdin")?
+            .write_all(command.as_bytes())?;
+        let status = child.wait()?;
+        if !status.success() {
+            bail!("security exited with {status}");
+        }
+        return Ok(());
+    }
+    let mut child = Command::new("secret-tool")
+        .args([
+            "store",
+            "--label",
+            &format!("clide agent key ({account})"),
+            "service",
+            SERVICE,
+            "account",
+            account,
+        ])
+        .stdin(Stdio::piped())
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .spawn()
+        .context("failed to run secret-tool")?;
+    child
+        .stdin
+        .take()
+        .context("secret-tool has no stdin")?
+        .write_all(secret.as_bytes())?;
+    let status = child.wait()?;
+    if !status.success() {
+        bail!("secret-tool exited with {status}");
+    }
+    Ok(())
+}
+
+/// Quote an argument for the `security` interactive command parser,
+/// which understands double quotes with backslash escapes.
+fn security_quote(arg: &str) -> String {
+    format!("\"{}\"", arg.replace('\\', "\\\\").replace('"', "\\\""))
+}
+
+/// Read the secret stored under `account`, if any.
+pub fn lookup(account: &str) -> Option<String> {
+    let output = if cfg!(target_os = "macos") {
+        Command::new("security")
+            .args(["find-generic-password", "-s", SERVICE, "-a", account, "-w"])
+            .stderr(Stdio::null())
+            .output()
+    } else {
+        Command::new("secret-tool")
+            .args(["lookup", "service", SERVICE, "account", account])
+            .stderr(Stdio::null())
+            .output()
+    }
+    .ok()?;
+    if !output.status.success() {
+        return None;
+    }
+    let secret = String::from_utf8_lossy(&output.stdout)
+        .trim_end_matches('\n')
+        .to_string();
+    (!secret.is_empty()).then_some(secret)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn security_quote_escapes_quotes_and_backslashes() {
+        assert_eq!(security_quote("plain"), "\"plain\"");
+        assert_eq!(security_quote(r#"a"b\c"#), r#""a\"b\\c""#);
+    }
+}
@@ -0,0 +1,518 @@
+//! The agent subsystem: profiles, conversations, and backend dispatch.
+
+pub mod batch;
+pub mod context;
+pub mod history;
+pub mod instructions;
+pub mod keyring;
+pub mod profile;
+pub mod providers;
+pub mod rag;
+pub mod redact;
+pub mod review;
+pub mod stats;
+pub mod templates;
+pub mod tokens;
+pub mod tools;
+pub mod transcript;
+
+use std::path::PathBuf;
+use std::sync::Arc;
+
+use anyhow::{Context, Result};
+
+use crate::event::{AppEvent, AppEventSender};
+use profile::{AgentProfile, AgentsConfig, BackendConfig};
+use providers::http::HttpBackend;
+use providers::local::LocalProcessBackend;
+
+/// One request sent to the active agent.
+#[derive(Debug, Clone)]
+pub struct AgentRequest {
+    pub prompt: String,
+    /// Contents of the active buffer attached as context, if any.
+    pub context: Option<String>,
+    pub context_path: Option<PathBuf>,
+}
+
+impl AgentRequest {
+    /// Flatten the request into a single prompt string for backends that
+    /// take plain text.
+    pub fn full_prompt(&self) -> String {
+        match (&self.context, &self.context_path) {
+            (Some(context), Some(path)) => format!(
+                "Current file `{}`:\n```\n{}\n```\n\n{}",
+                path.display(),
+                context,
+                self.prompt
+            ),
+            (Some(context), None) => {
+                format!("Current buffer:\n```\n{}\n```\n\n{}", context, self.prompt)
+            }
+            _ => self.prompt.clone(),
+        }
+    }
+}
+
+/// Events agent backends push back to the main loop.
+pub enum AgentEvent {
+    Response {
+        profile: String,
+        text: String,
+    },
+    /// An incremental fragment from a streaming provider; the complete
+    /// reply still follows as one [`AgentEvent::Response`].
+    ResponseChunk {
+        profile: String,
+        text: String,
+    },
+    /// The model asked for built-in tools; the app runs them (with
+    /// approval for mutating ones) and resumes the exchange.
+    ToolCalls {
+        profile: String,
+        transcript: serde_json::Value,
+        calls: Vec<tools::ToolCall>,
+        /// Assistant text alongside the calls, shown before the outputs.
+        text: Option<String>,
+        /// How many model round trips this exchange has made so far.
+        round: usize,
+    },
+    /// An agent-side tool wants to write a file in the workspace.
+    ToolWrite {
+        path: PathBuf,
+        content: String,
+    },
+    Error(String),
+}
+
+/// One rendered row in the agent panel history.
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+pub enum AgentPanelEntry {
+    Info(String),
+    User(String),
+    Response(String),
+    /// A reviewable diff of a file an agent tool wrote.
+    Diff { path: PathBuf, diff: String },
+    /// What one built-in tool call returned (or why it did not run).
+    ToolOutput { name: String, output: String },
+    /// A local image referenced by a response, drawn inline when the
+    /// terminal supports a graphics protocol.
+    Image(PathBuf),
+    Error(String),
+}
+
+impl AgentPanelEntry {
+    /// The entry body as plain text, for copying and re-use.
+    pub fn text(&self) -> String {
+        match self {
+            AgentPanelEntry::Info(text)
+            | AgentPanelEntry::User(text)
+            | AgentPanelEntry::Response(text)
+            | AgentPanelEntry::Error(text) => text.clone(),
+            AgentPanelEntry::Diff { diff, .. } => diff.clone(),
+            AgentPanelEntry::ToolOutput { output, .. } => output.clone(),
+            AgentPanelEntry::Image(path) => path.display().to_string(),
+        }
+    }
+
+    /// Short kind label for entry list rows.
+    pub fn kind(&self) -> &'static str {
+        match self {
+            AgentPanelEntry::Info(_) => "info",
+            AgentPanelEntry::User(_) => "user",
+            AgentPanelEntry::Response(_) => "reply",
+            AgentPanelEntry::Diff { .. } => "diff",
+            AgentPanelEntry::ToolOutput { .. } => "tool",
+            AgentPanelEntry::Image(_) => "image",
+            AgentPanelEntry::Error(_) => "error",
+        }
+    }
+}
+
+/// The visible conversation history, with per-category visibility.
+pub struct AgentConversation {
+    pub entries: Vec<AgentPanelEntry>,
+    pub scroll: usize,
+    /// Show info/system entries at all.
+    pub show_info: bool,
+    /// Show diff bodies under tool-write entries.
+    pub show_diffs: bool,
+    /// Render runs of info entries individually instead of as one
+    /// collapsed group line.
+    pub expand_info_groups: bool,
+}
+
+impl Default for AgentConversation {
+    fn default() -> Self {
+        Self {
+            entries: Vec::new(),
+            scroll: 0,
+            show_info: true,
+            show_diffs: true,
+            expand_info_groups: false,
+        }
+    }
+}
+
+impl AgentConversation {
+    pub fn push(&mut self, entry: AgentPanelEntry) {
+        self.entries.push(entry);
+    }
+}
+
+/// Owns the configured profiles and the live backend for the active one.
+pub struct AgentManager {
+    pub config: AgentsConfig,
+    pub active: usize,
+    http: Arc<HttpBackend>,
+    /// Live local backends, one per profile, so two agents can run side
+    /// by side without respawning on every switch.
+    locals: std::collections::HashMap<String, LocalProcessBackend>,
+    /// Consecutive-failure restart tracking per local profile, driving
+    /// the automatic-restart backoff.
+    restarts: std::collections::HashMap<String, RestartState>,
+    /// Connected MCP servers; their tools join the built-in set.
+    pub mcp: providers::mcp::McpManager,
+    events: AppEventSender,
+    runtime: tokio::runtime::Handle,
+    /// True while a request is in flight.
+    pub busy: bool,
+    /// Project instructions (`CLIDE.md`) prepended to every system
+    /// prompt; refreshed by the app before each request.
+    pub instructions: Option<String>,
+    /// Where the active profile selection came from, shown alongside
+    /// the profile name when switching.
+    pub profile_source: &'static str,
+}
+
+impl AgentManager {
+    pub fn bootstrap(events: AppEventSender, runtime: tokio::runtime::Handle) -> Self {
+        let config = profile::load_agents_config();
+        let active = config
+            .default_profile
+            .as_ref()
+            .and_then(|name| config.profiles.iter().position(|p| &p.name == name))
+            .unwrap_or(0);
+        let mcp = providers::mcp::McpManager::bootstrap(&config.mcp_servers, runtime.clone());
+        Self {
+            config,
+            active,
+            http: Arc::new(HttpBackend::new()),
+            locals: std::collections::HashMap::new(),
+            restarts: std::collections::HashMap::new(),
+            mcp,
+            events,
+            runtime,
+            busy: false,
+            instructions: None,
+            profile_source: "agents.toml",
+        }
+    }
+
+    pub fn active_profile(&self) -> Option<&AgentProfile> {
+        self.config.profiles.get(self.active)
+    }
+
+    pub fn cycle_profile(&mut self) {
+        if !self.config.profiles.is_empty() {
+            self.active = (self.active + 1) % self.config.profiles.len();
+        }
+    }
+
+    /// Re-read `agents.toml` in place: the active selection follows its
+    /// profile by name, and live local backends whose profiles went away
+    /// are dropped. Returns the added and removed profile names.
+    pub fn reload_config(&mut self) -> (Vec<String>, Vec<String>) {
+        let previous: Vec<String> = self.config.profiles.iter().map(|p| p.name.clone()).collect();
+        let active_name = self.active_profile().map(|p| p.name.clone());
+        self.config = profile::load_agents_config();
+        let current: Vec<String> = self.config.profiles.iter().map(|p| p.name.clone()).collect();
+        let added = current
+            .iter()
+            .filter(|name| !previous.contains(name))
+            .cloned()
+            .collect();
+        let removed = previous
+            .into_iter()
+            .filter(|name| !current.contains(name))
+            .collect();
+        self.locals.retain(|name, _| current.contains(name));
+        self.active = active_name
+            .and_then(|name| self.config.profiles.iter().position(|p| p.name == name))
+            .or_else(|| {
+                self.config.default_profile.as_ref().and_then(|name| {
+                    self.config.profiles.iter().position(|p| &p.name == name)
+                })
+            })
+            .unwrap_or(0);
+        (added, removed)
+    }
+
+    /// Switch to the named profile; returns false when it does not exist.
+    pub fn select_profile(&mut self, name: &str) -> bool {
+        match self.config.profiles.iter().position(|p| p.name == name) {
+            Some(idx) => {
+                self.active = idx;
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Dispatch a request to the active profile's backend. Responses come
+    /// back asynchronously as [`AgentEvent`]s.
+    pub fn send(&mut self, request: AgentRequest) -> Result<()> {
+        let profile = self
+            .active_profile()
+            .cloned()
+            .context("no agent profiles configured")?;
+        self.busy = true;
+        transcript::record(&profile.name, "request", &request.full_prompt());
+        match &profile.backend {
+            BackendConfig::HttpApi(http_config) => {
+                let http = Arc::clone(&self.http);
+                let events = self.events.clone();
+                let config = http_config.clone();
+                let system = instructions::combine(
+                    self.instructions.as_deref(),
+                    profile.system_prompt.as_deref(),
+                );
+                let name = profile.name.clone();
+                let extra = self.mcp.external_tools();
+                self.runtime.spawn(async move {
+                    let chunk_events = events.clone();
+                    let chunk_name = name.clone();
+                    let sink = move |delta: &str| {
+                        let _ = chunk_events.send(AppEvent::Agent(AgentEvent::ResponseChunk {
+                            profile: chunk_name.clone(),
+                            text: delta.to_string(),
+                        }));
+                    };
+                    let result = http
+                        .send(&config, system.as_deref(), &request, &extra, &sink)
+                        .await;
+                    let event = reply_event(result, name, 1);
+                    let _ = events.send(AppEvent::Agent(event));
+                });
+            }
+            BackendConfig::LocalProcess { command, args } => {
+                if !self.locals.contains_key(&profile.name) {
+                    self.locals.insert(
+                        profile.name.clone(),
+                        LocalProcessBackend::spawn(
+                            command,
+                            args,
+                            &profile.name,
+                            self.events.clone(),
+                        )?,
+                    );
+                }
+                if let Some(local) = self.locals.get_mut(&profile.name) {
+                    local.send(&request)?;
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Heartbeat the live local backends. A process that exited or
+    /// outlived the protocol timeout with no output is dropped and
+    /// restarted automatically, with a backoff that grows while the
+    /// failures keep coming; every finding is returned for the panel.
+    pub fn poll_local_health(&mut self) -> Vec<HealthReport> {
+        let mut reports = Vec::new();
+        let mut failed = Vec::new();
+        self.locals.retain(|name, local| {
+            if local.exited() {
+                failed.push((name.clone(), "exited"));
+                return false;
+            }
+            if local.check_health() == providers::local::Health::Hung {
+                failed.push((name.clone(), "stopped responding"));
+                return false;
+            }
+            true
+        });
+        let now = std::time::Instant::now();
+        for (name, reason) in failed {
+            if self.config.profiles.get(self.active).is_some_and(|p| p.name == name) {
+                self.busy = false;
+            }
+            let state = self.restarts.entry(name.clone()).or_insert(RestartState {
+                attempts: 0,
+                next_attempt: now,
+                last_failure: now,
+            });
+            if now.duration_since(state.last_failure) > RESTART_RESET {
+                state.attempts = 0;
+            }
+            state.attempts += 1;
+            state.last_failure = now;
+            let delay = restart_backoff(state.attempts);
+            state.next_attempt = now + delay;
+            reports.push(HealthReport {
+                message: if delay.is_zero() {
+                    format!("agent {name} {reason}; restarting")
+                } else {
+                    format!(
+                        "agent {name} {reason}; restart in {}s (attempt {})",
+                        delay.as_secs(),
+                        state.attempts
+                    )
+                },
+                recovered: false,
+            });
+        }
+        // Respawn whatever is due, backing off further when that fails.
+        let due: Vec<String> = self
+            .restarts
+            .iter()
+            .filter(|(name, state)| {
+                now >= state.next_attempt && !self.locals.contains_key(*name)
+            })
+            .map(|(name, _)| name.clone())
+            .collect();
+        for name in due {
+            let Some(profile) = self.config.profiles.iter().find(|p| p.name == name) else {
+                self.restarts.remove(&name);
+                continue;
+            };
+            let BackendConfig::LocalProcess { command, args } = profile.backend.clone() else {
+                self.restarts.remove(&name);
+                continue;
+            };
+            match LocalProcessBackend::spawn(&command, &args, &name, self.events.clone()) {
+                Ok(backend) => {
+                    self.locals.insert(name.clone(), backend);
+                    reports.push(HealthReport {
+                        message: format!("agent {name} restarted"),
+                        recovered: true,
+                    });
+                }
+                Err(err) => {
+                    let state = self.restarts.get_mut(&name).expect("due entry exists");
+                    state.attempts += 1;
+                    state.last_failure = now;
+                    state.next_attempt = now + restart_backoff(state.attempts);
+                    reports.push(HealthReport {
+                        message: format!("agent {name} restart failed: {err:#}"),
+                        recovered: false,
+                    });
+                }
+            }
+        }
+        reports
+    }
+
+    /// Ask the active provider for its model list; the result arrives as
+    /// [`AppEvent::ModelList`].
+    pub fn fetch_models(&self) -> Result<()> {
+        let profile = self
+            .active_profile()
+            .cloned()
+            .context("no agent profiles configured")?;
+        let BackendConfig::HttpApi(config) = profile.backend else {
+            anyhow::bail!("local profiles do not list models");
+        };
+        let http = Arc::clone(&self.http);
+        let events = self.events.clone();
+        self.runtime.spawn(async move {
+            let result = http
+                .list_models(&config)
+                .await
+                .map_err(|err| format!("{err:#}"));
+            let _ = events.send(AppEvent::ModelList(result));
+        });
+        Ok(())
+    }
+
+    /// Continue a tool-calling exchange with the finished call results.
+    /// Only HTTP profiles reach here; the active profile is looked up
+    /// again so a mid-loop switch fails loudly instead of cross-wiring.
+    pub fn resume_tools(
+        &mut self,
+        transcript: serde_json::Value,
+        results: Vec<(tools::ToolCall, String)>,
+        round: usize,
+    ) -> Result<()> {
+        let profile = self
+            .active_profile()
+            .cloned()
+            .context("no agent profiles configured")?;
+        let BackendConfig::HttpApi(http_config) = &profile.backend else {
+            anyhow::bail!("active profile does not support tool calls");
+        };
+        self.busy = true;
+        for (call, output) in &results {
+            transcript::record(&profile.name, "tool-result", &format!("{}: {output}", call.name));
+        }
+        let http = Arc::clone(&self.http);
+        let events = self.events.clone();
+        let config = http_config.clone();
+        let system = instructions::combine(
+            self.instructions.as_deref(),
+            profile.system_prompt.as_deref(),
+        );
+        let name = profile.name.clone();
+        let extra = self.mcp.external_tools();
+        self.runtime.spawn(async move {
+            let result = http
+                .resume(&config, system.as_deref(), transcript, &results, &extra)
+                .await;
+            let event = reply_event(result, name, round + 1);
+            let _ = events.send(AppEvent::Agent(event));
+        });
+        Ok(())
+    }
+}
+
+/// Restart attempts reset after this long without a failure.
+const RESTART_RESET: std::time::Duration = std::time::Duration::from_secs(120);
+
+/// Consecutive-failure restart tracking for one local profile.
+struct RestartState {
+    attempts: u32,
+    next_attempt: std::time::Instant,
+    last_failure: std::time::Instant,
+}
+
+/// One user-visible finding from the local-agent health sweep.
+pub struct HealthReport {
+    pub message: String,
+    /// True when the finding is a successful restart rather than a
+    /// failure.
+    pub recovered: bool,
+}
+
+/// The delay before restart attempt `attempts`: immediate at first,
+/// then doubling from two seconds up to a minute.
+fn restart_backoff(attempts: u32) -> std::time::Duration {
+    if attempts <= 1 {
+        return std::time::Duration::ZERO;
+    }
+    std::time::Duration::from_secs((1u64 << (attempts.min(7) - 1)).min(60))
+}
+
+/// Map a provider reply (or failure) onto the event the main loop reads.
+fn reply_event(
+    result: Result<providers::http::ProviderReply>,
+    profile: String,
+    round: usize,
+) -> AgentEvent {
+    use providers::http::ProviderReply;
+    match result {
+        Ok(ProviderReply::Text(text)) => AgentEvent::Response { profile, text },
+        Ok(ProviderReply::ToolCalls {
+            transcript,
+            calls,
+            text,
+        }) => AgentEvent::ToolCalls {
+            profile,
+            transcript,
+            calls,
+            text,
+            round,
+        },
+        Err(err) => AgentEvent::Error(format!("{profile}: {err:#}")),
+    }
+}
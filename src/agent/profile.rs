@@ -0,0 +1,316 @@
+//! Agent profile configuration loaded from `config/agents.toml`.
+
+use std::fs;
+use std::path::PathBuf;
+
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+
+/// Which HTTP API dialect a profile speaks.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum HttpProvider {
+    Openai,
+    Anthropic,
+    Gemini,
+    Openrouter,
+    Mistral,
+    Groq,
+    Deepseek,
+    /// AWS Bedrock's Converse API, authenticated with SigV4 from the
+    /// standard `AWS_*` environment variables.
+    Bedrock,
+    Ollama,
+    LlamaCpp,
+    Custom,
+}
+
+/// Connection settings for an HTTP-backed profile.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct HttpApiConfig {
+    pub provider: HttpProvider,
+    #[serde(default)]
+    pub base_url: Option<String>,
+    pub model: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub api_key: Option<String>,
+    /// Environment variable to read the key from when `api_key` is unset.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub api_key_env: Option<String>,
+    /// Where the key lives: `"keyring"` reads it from the OS keyring
+    /// instead of this file or the environment.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub api_key_source: Option<String>,
+    /// Keyring account the key is filed under; defaults to the provider
+    /// name. Written by `apply_agent_api_key` with the profile name.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub keyring_account: Option<String>,
+    /// Proxy URL for this profile's requests. When unset, the standard
+    /// `HTTPS_PROXY`/`HTTP_PROXY` environment variables still apply.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub proxy: Option<String>,
+    /// Path to an extra root CA bundle (PEM) trusted for this profile,
+    /// for providers behind corporate TLS interception.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub ca_bundle: Option<std::path::PathBuf>,
+    /// AWS region for Bedrock profiles; defaults to `us-east-1`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub region: Option<String>,
+    /// Send the Gemini key as a `?key=` query parameter instead of the
+    /// `x-goog-api-key` header, for older endpoints. Off by default:
+    /// query strings leak into logs and proxies.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub key_in_query: Option<bool>,
+    /// For `provider = "custom"`: where the reply fields live in the
+    /// server's response JSON, so internal inference servers with
+    /// arbitrary shapes work without code changes.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub response_map: Option<ResponseMap>,
+}
+
+/// Dot-separated paths (`choices.0.message.content`) into a Custom
+/// provider's response JSON. Only `text` is required; `patch`, `file`,
+/// and `line` describe an edit suggestion the reply carries, rendered
+/// under the text as a diff block.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ResponseMap {
+    pub text: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub patch: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub file: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub line: Option<String>,
+}
+
+impl HttpApiConfig {
+    /// The API key to use for a request: the OS keyring when the profile
+    /// opts in, then the inline value, then the environment.
+    pub fn resolved_api_key(&self) -> Option<String> {
+        if self.api_key_source.as_deref() == Some("keyring") {
+            let account = self.keyring_account.as_deref().unwrap_or(match self.provider {
+                HttpProvider::Openai => "openai",
+                HttpProvider::Anthropic => "anthropic",
+                HttpProvider::Gemini => "gemini",
+                HttpProvider::Openrouter => "openrouter",
+                HttpProvider::Mistral => "mistral",
+                HttpProvider::Groq => "groq",
+                HttpProvider::Deepseek => "deepseek",
+                HttpProvider::Bedrock => "bedrock",
+                HttpProvider::Ollama => "ollama",
+                HttpProvider::LlamaCpp => "llama-cpp",
+                HttpProvider::Custom => "custom",
+            });
+            if let Some(key) = crate::agent::keyring::lookup(account) {
+                return Some(key);
+            }
+        }
+        if let Some(key) = &self.api_key {
+            if !key.is_empty() {
+                return Some(key.clone());
+            }
+        }
+        let var = self.api_key_env.as_deref().unwrap_or(match self.provider {
+            HttpProvider::Openai => "OPENAI_API_KEY",
+            HttpProvider::Anthropic => "ANTHROPIC_API_KEY",
+            HttpProvider::Gemini => "GEMINI_API_KEY",
+            HttpProvider::Openrouter => "OPENROUTER_API_KEY",
+            HttpProvider::Mistral => "MISTRAL_API_KEY",
+            HttpProvider::Groq => "GROQ_API_KEY",
+            HttpProvider::Deepseek => "DEEPSEEK_API_KEY",
+            _ => return None,
+        });
+        std::env::var(var).ok().filter(|k| !k.is_empty())
+    }
+
+    pub fn effective_base_url(&self) -> String {
+        if let Some(url) = &self.base_url {
+            return url.trim_end_matches('/').to_string();
+        }
+        match self.provider {
+            HttpProvider::Openai => "https://api.openai.com/v1".to_string(),
+            HttpProvider::Anthropic => "https://api.anthropic.com/v1".to_string(),
+            HttpProvider::Gemini => {
+                "https://generativelanguage.googleapis.com/v1beta".to_string()
+            }
+            HttpProvider::Openrouter => "https://openrouter.ai/api/v1".to_string(),
+            HttpProvider::Mistral => "https://api.mistral.ai/v1".to_string(),
+            HttpProvider::Groq => "https://api.groq.com/openai/v1".to_string(),
+            HttpProvider::Deepseek => "https://api.deepseek.com/v1".to_string(),
+            HttpProvider::Bedrock => format!(
+                "https://bedrock-runtime.{}.amazonaws.com",
+                self.region.as_deref().unwrap_or("us-east-1")
+            ),
+            HttpProvider::Ollama => "http://localhost:11434".to_string(),
+            HttpProvider::LlamaCpp => "http://localhost:8080".to_string(),
+            HttpProvider::Custom => String::new(),
+        }
+    }
+}
+
+/// How a profile's backend is launched.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case", tag = "kind")]
+pub enum BackendConfig {
+    HttpApi(Box<HttpApiConfig>),
+    /// A local process speaking newline-delimited JSON on stdio.
+    LocalProcess {
+        command: String,
+        #[serde(default)]
+        args: Vec<String>,
+    },
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AgentProfile {
+    pub name: String,
+    #[serde(flatten)]
+    pub backend: BackendConfig,
+    #[serde(default)]
+    pub system_prompt: Option<String>,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct AgentsConfig {
+    #[serde(default)]
+    pub default_profile: Option<String>,
+    #[serde(default, rename = "profile")]
+    pub profiles: Vec<AgentProfile>,
+    #[serde(default, rename = "mcp-server")]
+    pub mcp_servers: Vec<crate::agent::providers::mcp::McpServerConfig>,
+}
+
+/// Per-workspace overrides from `.clide/settings.toml`, used to pin a
+/// profile (and optionally its model) for one workspace — e.g. a
+/// local-only profile for a confidential repo.
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct WorkspaceSettings {
+    #[serde(default)]
+    pub agent: WorkspaceAgentSettings,
+}
+
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub struct WorkspaceAgentSettings {
+    /// Profile to activate for this workspace, overriding the global
+    /// default.
+    pub profile: Option<String>,
+    /// Model override applied to that profile's HTTP backend.
+    pub model: Option<String>,
+}
+
+/// Load `.clide/settings.toml`; missing or unparsable files yield the
+/// empty settings.
+pub fn load_workspace_settings(root: &std::path::Path) -> WorkspaceSettings {
+    let path = root.join(".clide").join("settings.toml");
+    let Ok(text) = fs::read_to_string(&path) else {
+        return WorkspaceSettings::default();
+    };
+    toml::from_str(&text).unwrap_or_default()
+}
+
+/// Path of `agents.toml` inside the user config directory.
+pub fn agents_config_path() -> PathBuf {
+    dirs::config_dir()
+        .unwrap_or_else(|| PathBuf::from("."))
+        .join("clide")
+        .join("agents.toml")
+}
+
+pub fn load_agents_config() -> AgentsConfig {
+    let path = agents_config_path();
+    let Ok(text) = fs::read_to_string(&path) else {
+        return AgentsConfig::default();
+    };
+    toml::from_str(&text).unwrap_or_default()
+}
+
+pub fn save_agents_config(config: &AgentsConfig) -> Result<()> {
+    let path = agents_config_path();
+    if let Some(dir) = path.parent() {
+        fs::create_dir_all(dir)?;
+    }
+    let text = toml::to_string_pretty(config).context("failed to serialize agents.toml")?;
+    fs::write(&path, text).with_context(|| format!("failed to write {}", path.display()))
+}
+
+/// Store an API key for the named profile: in the OS keyring when one
+/// is available (only a `api_key_source = "keyring"` pointer lands in
+/// `agents.toml`), otherwise inline as before. Returns where it went,
+/// for the status line.
+pub fn apply_agent_api_key(profile_name: &str, key: &str) -> Result<&'static str> {
+    let mut config = load_agents_config();
+    let profile = config
+        .profiles
+        .iter_mut()
+        .find(|p| p.name == profile_name)
+        .with_context(|| format!("no profile named {profile_name}"))?;
+    let BackendConfig::HttpApi(http) = &mut profile.backend else {
+        anyhow::bail!("profile {profile_name} does not use an API key")
+    };
+    let destination = if crate::agent::keyring::available()
+        && crate::agent::keyring::store(profile_name, key).is_ok()
+    {
+        http.api_key = None;
+        http.api_key_source = Some("keyring".to_string());
+        http.keyring_account = Some(profile_name.to_string());
+        "the OS keyring"
+    } else {
+        http.api_key = Some(key.to_string());
+        "agents.toml"
+    };
+    save_agents_config(&config)?;
+    Ok(destination)
+}
+
+/// Persist a new model choice for the named profile to `agents.toml`.
+pub fn apply_agent_model(profile_name: &str, model: &str) -> Result<()> {
+    let mut config = load_agents_config();
+    let profile = config
+        .profiles
+        .iter_mut()
+        .find(|p| p.name == profile_name)
+        .with_context(|| format!("no profile named {profile_name}"))?;
+    let BackendConfig::HttpApi(http) = &mut profile.backend else {
+        anyhow::bail!("profile {profile_name} does not use a model field")
+    };
+    http.model = model.to_string();
+    save_agents_config(&config)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_profiles_from_toml() {
+        let config: AgentsConfig = toml::from_str(
+            r#"
+            default_profile = "local"
+
+            [[profile]]
+            name = "local"
+            kind = "http-api"
+            provider = "ollama"
+            model = "qwen2.5-coder"
+
+            [[profile]]
+            name = "cloud"
+            kind = "http-api"
+            provider = "anthropic"
+            model = "claude-sonnet-4-5"
+            api_key_env = "ANTHROPIC_API_KEY"
+            "#,
+        )
+        .unwrap();
+        assert_eq!(config.profiles.len(), 2);
+        assert_eq!(config.default_profile.as_deref(), Some("local"));
+        match &config.profiles[0].backend {
+            BackendConfig::HttpApi(http) => {
+                assert_eq!(http.provider, HttpProvider::Ollama);
+                assert_eq!(http.effective_base_url(), "http://localhost:11434");
+            }
+            _ => panic!("expected http backend"),
+        }
+    }
+}
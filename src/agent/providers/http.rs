@@ -0,0 +1,760 @@
+//! HTTP-backed agent providers (OpenAI, Anthropic, Gemini, local servers).
+
+use anyhow::{Context, Result};
+use serde_json::{json, Value};
+
+use crate::agent::profile::{HttpApiConfig, HttpProvider, ResponseMap};
+use crate::agent::tools::{self, ExternalTool, ToolCall};
+use crate::agent::AgentRequest;
+
+/// What one provider exchange produced: either a final text reply, or
+/// tool calls the app must run before the conversation can continue.
+pub enum ProviderReply {
+    Text(String),
+    ToolCalls {
+        /// The provider-format message transcript so far, including the
+        /// assistant turn that requested the tools. Passed back to
+        /// [`HttpBackend::resume`] untouched.
+        transcript: Value,
+        calls: Vec<ToolCall>,
+        /// Assistant text that accompanied the tool calls, if any.
+        text: Option<String>,
+    },
+}
+
+/// Receives incremental text while a streaming provider generates; the
+/// full reply still arrives as one [`ProviderReply::Text`] at the end.
+pub type ChunkSink = dyn Fn(&str) + Send + Sync;
+
+/// Shared HTTP state for all profiles backed by a remote API.
+pub struct HttpBackend {
+    client: reqwest::Client,
+}
+
+impl HttpBackend {
+    pub fn new() -> Self {
+        Self {
+            client: reqwest::Client::new(),
+        }
+    }
+
+    /// The client used for one profile's requests. The shared client
+    /// already honours `HTTPS_PROXY`/`HTTP_PROXY`; profiles that set an
+    /// explicit proxy or an extra CA bundle get a client of their own.
+    fn client_for(&self, config: &HttpApiConfig) -> Result<reqwest::Client> {
+        if config.proxy.is_none() && config.ca_bundle.is_none() {
+            return Ok(self.client.clone());
+        }
+        let mut builder = reqwest::Client::builder();
+        if let Some(proxy) = &config.proxy {
+            builder = builder
+                .proxy(reqwest::Proxy::all(proxy).with_context(|| format!("bad proxy {proxy}"))?);
+        }
+        if let Some(path) = &config.ca_bundle {
+            let pem = std::fs::read(path)
+                .with_context(|| format!("failed to read CA bundle {}", path.display()))?;
+            for cert in reqwest::Certificate::from_pem_bundle(&pem)
+                .with_context(|| format!("{} is not a PEM bundle", path.display()))?
+            {
+                builder = builder.add_root_certificate(cert);
+            }
+        }
+        builder.build().context("failed to build http client")
+    }
+
+    /// Send one request. OpenAI and Anthropic profiles advertise the
+    /// built-in tool set and may reply with tool calls; the other
+    /// providers always return plain text. Ollama and llama.cpp stream,
+    /// feeding `chunk` as tokens arrive.
+    pub async fn send(
+        &self,
+        config: &HttpApiConfig,
+        system_prompt: Option<&str>,
+        request: &AgentRequest,
+        extra_tools: &[ExternalTool],
+        chunk: &ChunkSink,
+    ) -> Result<ProviderReply> {
+        let prompt = request.full_prompt();
+        if let (HttpProvider::Custom, Some(map)) = (&config.provider, &config.response_map) {
+            return self
+                .handle_custom_mapped(config, system_prompt, &prompt, map)
+                .await
+                .map(ProviderReply::Text);
+        }
+        match config.provider {
+            HttpProvider::Openai
+            | HttpProvider::Openrouter
+            | HttpProvider::Mistral
+            | HttpProvider::Groq
+            | HttpProvider::Deepseek
+            | HttpProvider::Custom => {
+                let mut messages = Vec::new();
+                if let Some(system) = system_prompt {
+                    messages.push(json!({ "role": "system", "content": system }));
+                }
+                messages.push(json!({ "role": "user", "content": prompt }));
+                self.openai_exchange(config, messages, extra_tools).await
+            }
+            HttpProvider::Anthropic => {
+                let messages = vec![json!({ "role": "user", "content": prompt })];
+                self.anthropic_exchange(config, system_prompt, messages, extra_tools)
+                    .await
+            }
+            HttpProvider::Gemini => self
+                .handle_gemini(config, system_prompt, &prompt)
+                .await
+                .map(ProviderReply::Text),
+            HttpProvider::Ollama => self
+                .handle_ollama(config, system_prompt, &prompt, chunk)
+                .await
+                .map(ProviderReply::Text),
+            HttpProvider::LlamaCpp => self
+                .handle_llama_cpp(config, &prompt, chunk)
+                .await
+                .map(ProviderReply::Text),
+            HttpProvider::Bedrock => self
+                .handle_bedrock(config, system_prompt, &prompt)
+                .await
+                .map(ProviderReply::Text),
+        }
+    }
+
+    /// Continue a tool-calling exchange with the results of the calls the
+    /// app just ran. `transcript` is the value a previous reply carried.
+    pub async fn resume(
+        &self,
+        config: &HttpApiConfig,
+        system_prompt: Option<&str>,
+        transcript: Value,
+        results: &[(ToolCall, String)],
+        extra_tools: &[ExternalTool],
+    ) -> Result<ProviderReply> {
+        let mut messages: Vec<Value> = transcript
+            .as_array()
+            .cloned()
+            .context("tool transcript was not a message list")?;
+        match config.provider {
+            HttpProvider::Openai
+            | HttpProvider::Openrouter
+            | HttpProvider::Mistral
+            | HttpProvider::Groq
+            | HttpProvider::Deepseek
+            | HttpProvider::Custom => {
+                for (call, output) in results {
+                    messages.push(json!({
+                        "role": "tool",
+                        "tool_call_id": call.id,
+                        "content": output,
+                    }));
+                }
+                self.openai_exchange(config, messages, extra_tools).await
+            }
+            HttpProvider::Anthropic => {
+                let blocks: Vec<Value> = results
+                    .iter()
+                    .map(|(call, output)| {
+                        json!({
+                            "type": "tool_result",
+                            "tool_use_id": call.id,
+                            "content": output,
+                        })
+                    })
+                    .collect();
+                messages.push(json!({ "role": "user", "content": blocks }));
+                self.anthropic_exchange(config, system_prompt, messages, extra_tools)
+                    .await
+            }
+            _ => anyhow::bail!("provider does not support tool calls"),
+        }
+    }
+
+    /// Fetch the model IDs the provider offers, for the model picker.
+    pub async fn list_models(&self, config: &HttpApiConfig) -> Result<Vec<String>> {
+        let base = config.effective_base_url();
+        let client = self.client_for(config)?;
+        let names = match config.provider {
+            HttpProvider::Openai
+            | HttpProvider::Openrouter
+            | HttpProvider::Mistral
+            | HttpProvider::Groq
+            | HttpProvider::Deepseek
+            | HttpProvider::Custom => {
+                let mut req = client.get(format!("{base}/models"));
+                if let Some(key) = config.resolved_api_key() {
+                    req = req.bearer_auth(key);
+                }
+                let body: Value = req.send().await?.error_for_status()?.json().await?;
+                collect_field(&body["data"], "id")
+            }
+            HttpProvider::Anthropic => {
+                let key = config
+                    .resolved_api_key()
+                    .context("anthropic profile has no API key")?;
+                let body: Value = client
+                    .get(format!("{base}/models"))
+                    .header("x-api-key", key)
+                    .header("anthropic-version", "2023-06-01")
+                    .send()
+                    .await?
+                    .error_for_status()?
+                    .json()
+                    .await?;
+                collect_field(&body["data"], "id")
+            }
+            HttpProvider::Gemini => {
+                let key = config
+                    .resolved_api_key()
+                    .context("gemini profile has no API key")?;
+                let req = if config.key_in_query.unwrap_or(false) {
+                    client.get(format!("{base}/models?key={key}"))
+                } else {
+                    client.get(format!("{base}/models")).header("x-goog-api-key", key)
+                };
+                let body: Value = req
+                    .send()
+                    .await?
+                    .error_for_status()?
+                    .json()
+                    .await?;
+                collect_field(&body["models"], "name")
+                    .into_iter()
+                    .map(|name| name.trim_start_matches("models/").to_string())
+                    .collect()
+            }
+            HttpProvider::Ollama => {
+                let body: Value = client
+                    .get(format!("{base}/api/tags"))
+                    .send()
+                    .await?
+                    .error_for_status()?
+                    .json()
+                    .await?;
+                collect_field(&body["models"], "name")
+            }
+            HttpProvider::Bedrock => self.list_bedrock_models(config, &client).await?,
+            HttpProvider::LlamaCpp => {
+                anyhow::bail!("llama.cpp serves the model it was started with")
+            }
+        };
+        if names.is_empty() {
+            anyhow::bail!("provider returned no models");
+        }
+        Ok(names)
+    }
+
+    /// Bedrock lists models on the control plane, signed like the
+    /// runtime calls.
+    async fn list_bedrock_models(
+        &self,
+        config: &HttpApiConfig,
+        client: &reqwest::Client,
+    ) -> Result<Vec<String>> {
+        use crate::agent::providers::sigv4;
+
+        let access_key =
+            std::env::var("AWS_ACCESS_KEY_ID").context("bedrock profile needs AWS_ACCESS_KEY_ID")?;
+        let secret_key = std::env::var("AWS_SECRET_ACCESS_KEY")
+            .context("bedrock profile needs AWS_SECRET_ACCESS_KEY")?;
+        let session_token = std::env::var("AWS_SESSION_TOKEN").ok();
+        let region = config.region.as_deref().unwrap_or("us-east-1");
+        let host = format!("bedrock.{region}.amazonaws.com");
+        let signed = sigv4::sign(&sigv4::SigningParams {
+            method: "GET",
+            host: &host,
+            path: "/foundation-models",
+            query: "",
+            content_type: None,
+            region,
+            service: "bedrock",
+            access_key: &access_key,
+            secret_key: &secret_key,
+            session_token: session_token.as_deref(),
+            payload: b"",
+            now: chrono::Utc::now(),
+        });
+        let mut req = client
+            .get(format!("https://{host}/foundation-models"))
+            .header("x-amz-date", &signed.amz_date)
+            .header("authorization", &signed.authorization);
+        if let Some(token) = &session_token {
+            req = req.header("x-amz-security-token", token);
+        }
+        let body: Value = req.send().await?.error_for_status()?.json().await?;
+        Ok(collect_field(&body["modelSummaries"], "modelId"))
+    }
+
+    async fn openai_exchange(
+        &self,
+        config: &HttpApiConfig,
+        mut messages: Vec<Value>,
+        extra_tools: &[ExternalTool],
+    ) -> Result<ProviderReply> {
+        let url = format!("{}/chat/completions", config.effective_base_url());
+        let mut req = self.client_for(config)?.post(&url).json(&json!({
+            "model": config.model,
+            "messages": messages,
+            "tools": tools::openai_specs(extra_tools),
+        }));
+        if let Some(key) = config.resolved_api_key() {
+            req = req.bearer_auth(key);
+        }
+        let body: Value = req.send().await?.error_for_status()?.json().await?;
+        let message = &body["choices"][0]["message"];
+        let text = message["content"].as_str().map(str::to_string);
+        let calls: Vec<ToolCall> = message["tool_calls"]
+            .as_array()
+            .map(|raw| {
+                raw.iter()
+                    .filter_map(|call| {
+                        Some(ToolCall {
+                            id: call["id"].as_str()?.to_string(),
+                            name: call["function"]["name"].as_str()?.to_string(),
+                            args: serde_json::from_str(
+                                call["function"]["arguments"].as_str().unwrap_or("{}"),
+                            )
+                            .unwrap_or(Value::Null),
+                        })
+                    })
+                    .collect()
+            })
+            .unwrap_or_default();
+        if calls.is_empty() {
+            return text.context("response contained no message content").map(ProviderReply::Text);
+        }
+        messages.push(message.clone());
+        Ok(ProviderReply::ToolCalls {
+            transcript: Value::Array(messages),
+            calls,
+            text: text.filter(|t| !t.is_empty()),
+        })
+    }
+
+    async fn anthropic_exchange(
+        &self,
+        config: &HttpApiConfig,
+        system_prompt: Option<&str>,
+        mut messages: Vec<Value>,
+        extra_tools: &[ExternalTool],
+    ) -> Result<ProviderReply> {
+        let url = format!("{}/messages", config.effective_base_url());
+        let key = config
+            .resolved_api_key()
+            .context("anthropic profile has no API key")?;
+        let mut payload = json!({
+            "model": config.model,
+            "max_tokens": 4096,
+            "messages": messages,
+            "tools": tools::anthropic_specs(extra_tools),
+        });
+        if let Some(system) = system_prompt {
+            payload["system"] = json!(system);
+        }
+        let body: Value = self
+            .client_for(config)?
+            .post(&url)
+            .header("x-api-key", key)
+            .header("anthropic-version", "2023-06-01")
+            .json(&payload)
+            .send()
+            .await?
+            .error_for_status()?
+            .json()
+            .await?;
+        let content = body["content"]
+            .as_array()
+            .cloned()
+            .context("response contained no content blocks")?;
+        let text: String = content
+            .iter()
+            .filter_map(|block| block["text"].as_str())
+            .collect::<Vec<_>>()
+            .join("\n");
+        let calls: Vec<ToolCall> = content
+            .iter()
+            .filter(|block| block["type"] == "tool_use")
+            .filter_map(|block| {
+                Some(ToolCall {
+                    id: block["id"].as_str()?.to_string(),
+                    name: block["name"].as_str()?.to_string(),
+                    args: block["input"].clone(),
+                })
+            })
+            .collect();
+        if calls.is_empty() {
+            if text.is_empty() {
+                anyhow::bail!("response contained no text block");
+            }
+            return Ok(ProviderReply::Text(text));
+        }
+        messages.push(json!({ "role": "assistant", "content": content }));
+        Ok(ProviderReply::ToolCalls {
+            transcript: Value::Array(messages),
+            calls,
+            text: (!text.is_empty()).then_some(text),
+        })
+    }
+
+    /// A Custom profile with a `response-map`: an OpenAI-style chat
+    /// request (no tools), with the reply fields pulled out of whatever
+    /// shape the server returns by the configured paths.
+    async fn handle_custom_mapped(
+        &self,
+        config: &HttpApiConfig,
+        system_prompt: Option<&str>,
+        prompt: &str,
+        map: &ResponseMap,
+    ) -> Result<String> {
+        let url = format!("{}/chat/completions", config.effective_base_url());
+        let mut messages = Vec::new();
+        if let Some(system) = system_prompt {
+            messages.push(json!({ "role": "system", "content": system }));
+        }
+        messages.push(json!({ "role": "user", "content": prompt }));
+        let mut req = self.client_for(config)?.post(&url).json(&json!({
+            "model": config.model,
+            "messages": messages,
+        }));
+        if let Some(key) = config.resolved_api_key() {
+            req = req.bearer_auth(key);
+        }
+        let body: Value = req.send().await?.error_for_status()?.json().await?;
+        map_response(map, &body)
+    }
+
+    async fn handle_gemini(
+        &self,
+        config: &HttpApiConfig,
+        system_prompt: Option<&str>,
+        prompt: &str,
+    ) -> Result<String> {
+        let key = config
+            .resolved_api_key()
+            .context("gemini profile has no API key")?;
+        // The key travels in a header unless the profile opts into the
+        // legacy query-string form, which leaks into logs and proxies.
+        let mut url = format!(
+            "{}/models/{}:generateContent",
+            config.effective_base_url(),
+            config.model,
+        );
+        let mut payload = json!({
+            "contents": [{ "parts": [{ "text": prompt }] }],
+        });
+        if let Some(system) = system_prompt {
+            payload["systemInstruction"] = json!({ "parts": [{ "text": system }] });
+        }
+        let client = self.client_for(config)?;
+        let req = if config.key_in_query.unwrap_or(false) {
+            url.push_str(&format!("?key={key}"));
+            client.post(&url)
+        } else {
+            client.post(&url).header("x-goog-api-key", key)
+        };
+        let body: Value = req
+            .json(&payload)
+            .send()
+            .await?
+            .error_for_status()?
+            .json()
+            .await?;
+        body["candidates"][0]["content"]["parts"][0]["text"]
+            .as_str()
+            .map(str::to_string)
+            .context("response contained no candidates")
+    }
+
+    /// Ollama streams newline-delimited JSON objects, each carrying a
+    /// `response` fragment until one with `"done": true`.
+    async fn handle_ollama(
+        &self,
+        config: &HttpApiConfig,
+        system_prompt: Option<&str>,
+        prompt: &str,
+        chunk: &ChunkSink,
+    ) -> Result<String> {
+        let url = format!("{}/api/generate", config.effective_base_url());
+        let mut payload = json!({
+            "model": config.model,
+            "prompt": prompt,
+            "stream": true,
+        });
+        if let Some(system) = system_prompt {
+            payload["system"] = json!(system);
+        }
+        let mut response = self
+            .client_for(config)?
+            .post(&url)
+            .json(&payload)
+            .send()
+            .await?
+            .error_for_status()?;
+        let mut text = String::new();
+        let mut pending = Vec::new();
+        while let Some(bytes) = response.chunk().await? {
+            pending.extend_from_slice(&bytes);
+            for line in drain_lines(&mut pending) {
+                let Ok(value) = serde_json::from_str::<Value>(&line) else {
+                    continue;
+                };
+                if let Some(delta) = value["response"].as_str() {
+                    chunk(delta);
+                    text.push_str(delta);
+                }
+                if value["done"].as_bool() == Some(true) {
+                    return Ok(text);
+                }
+            }
+        }
+        if text.is_empty() {
+            anyhow::bail!("response contained no text");
+        }
+        Ok(text)
+    }
+
+    /// Bedrock's Converse API, signed with SigV4 from the standard
+    /// `AWS_ACCESS_KEY_ID`/`AWS_SECRET_ACCESS_KEY` environment (plus
+    /// `AWS_SESSION_TOKEN` for temporary credentials).
+    async fn handle_bedrock(
+        &self,
+        config: &HttpApiConfig,
+        system_prompt: Option<&str>,
+        prompt: &str,
+    ) -> Result<String> {
+        use crate::agent::providers::sigv4;
+
+        let access_key =
+            std::env::var("AWS_ACCESS_KEY_ID").context("bedrock profile needs AWS_ACCESS_KEY_ID")?;
+        let secret_key = std::env::var("AWS_SECRET_ACCESS_KEY")
+            .context("bedrock profile needs AWS_SECRET_ACCESS_KEY")?;
+        let session_token = std::env::var("AWS_SESSION_TOKEN").ok();
+        let region = config.region.as_deref().unwrap_or("us-east-1");
+        let base = config.effective_base_url();
+        let host = base
+            .strip_prefix("https://")
+            .or_else(|| base.strip_prefix("http://"))
+            .unwrap_or(&base)
+            .to_string();
+        let path = format!("/model/{}/converse", sigv4::uri_encode(&config.model));
+        let mut payload = json!({
+            "messages": [{ "role": "user", "content": [{ "text": prompt }] }],
+        });
+        if let Some(system) = system_prompt {
+            payload["system"] = json!([{ "text": system }]);
+        }
+        let body = serde_json::to_vec(&payload)?;
+        let signed = sigv4::sign(&sigv4::SigningParams {
+            method: "POST",
+            host: &host,
+            path: &path,
+            query: "",
+            content_type: Some("application/json"),
+            region,
+            service: "bedrock",
+            access_key: &access_key,
+            secret_key: &secret_key,
+            session_token: session_token.as_deref(),
+            payload: &body,
+            now: chrono::Utc::now(),
+        });
+        let mut req = self
+            .client_for(config)?
+            .post(format!("{base}{path}"))
+            .header("content-type", "application/json")
+            .header("x-amz-date", &signed.amz_date)
+            .header("authorization", &signed.authorization)
+            .body(body);
+        if let Some(token) = &session_token {
+            req = req.header("x-amz-security-token", token);
+        }
+        let body: Value = req.send().await?.error_for_status()?.json().await?;
+        body["output"]["message"]["content"][0]["text"]
+            .as_str()
+            .map(str::to_string)
+            .context("response contained no output text")
+    }
+
+    /// llama.cpp streams SSE `data:` events, each with a `content`
+    /// fragment until one with `"stop": true`.
+    async fn handle_llama_cpp(
+        &self,
+        config: &HttpApiConfig,
+        prompt: &str,
+        chunk: &ChunkSink,
+    ) -> Result<String> {
+        let url = format!("{}/completion", config.effective_base_url());
+        let mut response = self
+            .client_for(config)?
+            .post(&url)
+            .json(&json!({ "prompt": prompt, "stream": true }))
+            .send()
+            .await?
+            .error_for_status()?;
+        let mut text = String::new();
+        let mut pending = Vec::new();
+        while let Some(bytes) = response.chunk().await? {
+            pending.extend_from_slice(&bytes);
+            for line in drain_lines(&mut pending) {
+                let Some(payload) = line.strip_prefix("data: ") else {
+                    continue;
+                };
+                let Ok(value) = serde_json::from_str::<Value>(payload) else {
+                    continue;
+                };
+                if let Some(delta) = value["content"].as_str() {
+                    chunk(delta);
+                    text.push_str(delta);
+                }
+                if value["stop"].as_bool() == Some(true) {
+                    return Ok(text);
+                }
+            }
+        }
+        if text.is_empty() {
+            anyhow::bail!("response contained no content");
+        }
+        Ok(text)
+    }
+}
+
+/// Pull one string field out of every object in a JSON array.
+fn collect_field(value: &Value, field: &str) -> Vec<String> {
+    value
+        .as_array()
+        .map(|items| {
+            items
+                .iter()
+                .filter_map(|item| item[field].as_str().map(str::to_string))
+                .collect()
+        })
+        .unwrap_or_default()
+}
+
+/// Pop every complete line out of a byte buffer that may end mid-line
+/// (network chunks split JSON objects arbitrarily); the partial tail
+/// stays in `pending` for the next chunk.
+fn drain_lines(pending: &mut Vec<u8>) -> Vec<String> {
+    let mut lines = Vec::new();
+    while let Some(pos) = pending.iter().position(|&b| b == b'\n') {
+        let rest = pending.split_off(pos + 1);
+        let mut line = std::mem::replace(pending, rest);
+        line.pop();
+        if line.last() == Some(&b'\r') {
+            line.pop();
+        }
+        let line = String::from_utf8_lossy(&line).into_owned();
+        if !line.is_empty() {
+            lines.push(line);
+        }
+    }
+    lines
+}
+
+/// Walk a dot-separated path (`choices.0.message.content`) into a
+/// response value; numeric segments index arrays.
+fn lookup_path<'a>(value: &'a Value, path: &str) -> Option<&'a Value> {
+    let mut current = value;
+    for segment in path.split('.') {
+        current = match segment.parse::<usize>() {
+            Ok(index) => current.get(index)?,
+            Err(_) => current.get(segment)?,
+        };
+    }
+    Some(current)
+}
+
+/// Pull the mapped fields out of a Custom provider's response. A
+/// missing or non-text `text` field is a config error, reported with
+/// the keys the response actually had.
+fn map_response(map: &ResponseMap, body: &Value) -> Result<String> {
+    let field = |path: &str| {
+        lookup_path(body, path).map(|value| match value {
+            Value::String(text) => text.clone(),
+            other => other.to_string(),
+        })
+    };
+    let mut text = field(&map.text).with_context(|| {
+        let keys = match body {
+            Value::Object(map) => map.keys().cloned().collect::<Vec<_>>().join(", "),
+            other => format!("a JSON {}", kind_name(other)),
+        };
+        format!(
+            "response has no `{}` field (response carried: {keys}); check the \
+             profile's response-map",
+            map.text
+        )
+    })?;
+    let patch = map.patch.as_deref().and_then(&field);
+    if let Some(patch) = patch {
+        let file = map.file.as_deref().and_then(&field).unwrap_or_default();
+        let line = map.line.as_deref().and_then(&field).unwrap_or_default();
+        let mut heading = String::from("Suggested patch");
+        if !file.is_empty() {
+            heading.push_str(&format!(" for {file}"));
+            if !line.is_empty() {
+                heading.push_str(&format!(":{line}"));
+            }
+        }
+        text.push_str(&format!("\n\n{heading}:\n```diff\n{patch}\n```"));
+    }
+    Ok(text)
+}
+
+/// The JSON type name, for response-map error messages.
+fn kind_name(value: &Value) -> &'static str {
+    match value {
+        Value::Null => "null",
+        Value::Bool(_) => "boolean",
+        Value::Number(_) => "number",
+        Value::String(_) => "string",
+        Value::Array(_) => "array",
+        Value::Object(_) => "object",
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn drain_lines_keeps_the_partial_tail() {
+        let mut pending = b"{\"response\":\"a\"}\n{\"resp".to_vec();
+        assert_eq!(drain_lines(&mut pending), vec!["{\"response\":\"a\"}"]);
+        assert_eq!(pending, b"{\"resp");
+        pending.extend_from_slice(b"onse\":\"b\"}\r\n");
+        assert_eq!(drain_lines(&mut pending), vec!["{\"response\":\"b\"}"]);
+        assert!(pending.is_empty());
+    }
+
+    #[test]
+    fn map_response_follows_paths_and_appends_the_patch() {
+        let map = ResponseMap {
+            text: "result.0.answer".to_string(),
+            patch: Some("edit.diff".to_string()),
+            file: Some("edit.path".to_string()),
+            line: Some("edit.line".to_string()),
+        };
+        let body = json!({
+            "result": [{ "answer": "done" }],
+            "edit": { "diff": "-a\n+b", "path": "src/x.rs", "line": 7 },
+        });
+        assert_eq!(
+            map_response(&map, &body).unwrap(),
+            "done\n\nSuggested patch for src/x.rs:7:\n```diff\n-a\n+b\n```"
+        );
+    }
+
+    #[test]
+    fn map_response_reports_a_bad_text_path() {
+        let map = ResponseMap {
+            text: "output.text".to_string(),
+            patch: None,
+            file: None,
+            line: None,
+        };
+        let err = map_response(&map, &json!({ "detail": "overloaded" }))
+            .unwrap_err()
+            .to_string();
+        assert!(err.contains("`output.text`"), "{err}");
+        assert!(err.contains("detail"), "{err}");
+    }
+}
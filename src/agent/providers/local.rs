@@ -0,0 +1,225 @@
+//! Local process agent: a child process speaking newline-delimited JSON.
+//!
+//! Protocol v2 adds a handshake on top of the original line protocol:
+//! Clide opens with `{"clide": 2, "capabilities": [...]}` and a v2 agent
+//! answers in kind; anything else marks the process as v1 and the extras
+//! below are skipped. Requests carry an `"id"` so replies to a
+//! superseded request can be discarded, and `{"ping": id}` /
+//! `{"pong": id}` heartbeats let a hung process be told apart from a
+//! slow one. Replies are unchanged from v1: `{"text": "..."}`,
+//! `{"error": "..."}`, or `{"write_file": "path", "content": "..."}`
+//! (reviewed by the app before trusting), now optionally echoing the
+//! request `"id"`.
+
+use std::io::{BufRead, BufReader, Write};
+use std::process::{Child, Command, Stdio};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use anyhow::{Context, Result};
+use serde_json::{json, Value};
+
+use crate::agent::{AgentEvent, AgentRequest};
+use crate::event::{AppEvent, AppEventSender};
+use crate::cli::LogLevel;
+use crate::logging;
+
+/// How long a request may sit unanswered before the process counts as
+/// hung; a heartbeat ping goes out at the halfway mark.
+const HUNG_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// State shared with the reader thread: what the handshake negotiated
+/// and when the process last said anything.
+struct ProtocolState {
+    /// Negotiated protocol version; 1 until the agent's hello arrives.
+    version: AtomicU64,
+    /// The id replies must echo to count; older ids are stale.
+    current_id: AtomicU64,
+    /// When the in-flight request went out and whether it has been
+    /// pinged; refreshed by partial output, cleared by a final reply.
+    pending: Mutex<Option<(Instant, bool)>>,
+}
+
+pub struct LocalProcessBackend {
+    child: Child,
+    stdin: std::process::ChildStdin,
+    next_id: u64,
+    state: Arc<ProtocolState>,
+}
+
+impl LocalProcessBackend {
+    pub fn spawn(
+        command: &str,
+        args: &[String],
+        profile_name: &str,
+        events: AppEventSender,
+    ) -> Result<Self> {
+        let mut child = Command::new(command)
+            .args(args)
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::null())
+            .spawn()
+            .with_context(|| format!("failed to spawn agent process {command}"))?;
+        let mut stdin = child.stdin.take().context("agent process has no stdin")?;
+        let stdout = child.stdout.take().context("agent process has no stdout")?;
+        // The hello is harmless to a v1 agent, which has no reply for it.
+        let hello = json!({ "clide": 2, "capabilities": ["prompt", "write_file"] });
+        writeln!(stdin, "{hello}").context("agent process stdin closed")?;
+        stdin.flush()?;
+        let state = Arc::new(ProtocolState {
+            version: AtomicU64::new(1),
+            current_id: AtomicU64::new(0),
+            pending: Mutex::new(None),
+        });
+        let reader_state = Arc::clone(&state);
+        let name = profile_name.to_string();
+        std::thread::spawn(move || {
+            let reader = BufReader::new(stdout);
+            for line in reader.lines() {
+                let Ok(line) = line else { break };
+                if line.trim().is_empty() {
+                    continue;
+                }
+                let event = match serde_json::from_str::<Value>(&line) {
+                    Ok(value) => {
+                        if let Some(version) = value.get("clide").and_then(Value::as_u64) {
+                            reader_state.version.store(version.min(2), Ordering::Relaxed);
+                            let caps: Vec<&str> = value["capabilities"]
+                                .as_array()
+                                .map(|caps| {
+                                    caps.iter().filter_map(Value::as_str).collect()
+                                })
+                                .unwrap_or_default();
+                            logging::log(
+                                LogLevel::Info,
+                                &format!(
+                                    "agent {name}: protocol v{} ({})",
+                                    version.min(2),
+                                    caps.join(", ")
+                                ),
+                            );
+                            continue;
+                        }
+                        if value.get("pong").is_some() {
+                            // Alive but still working; push the timeout out.
+                            if let Ok(mut pending) = reader_state.pending.lock() {
+                                *pending = pending.map(|_| (Instant::now(), false));
+                            }
+                            continue;
+                        }
+                        // A reply for a superseded request is dropped.
+                        if let Some(id) = value.get("id").and_then(Value::as_u64) {
+                            if id != reader_state.current_id.load(Ordering::Relaxed) {
+                                continue;
+                            }
+                        }
+                        if let Some(text) = value.get("text").and_then(Value::as_str) {
+                            if let Ok(mut pending) = reader_state.pending.lock() {
+                                *pending = None;
+                            }
+                            AgentEvent::Response {
+                                profile: name.clone(),
+                                text: text.to_string(),
+                            }
+                        } else if let Some(path) =
+                            value.get("write_file").and_then(Value::as_str)
+                        {
+                            if let Ok(mut pending) = reader_state.pending.lock() {
+                                *pending = pending.map(|_| (Instant::now(), false));
+                            }
+                            AgentEvent::ToolWrite {
+                                path: path.into(),
+                                content: value
+                                    .get("content")
+                                    .and_then(Value::as_str)
+                                    .unwrap_or_default()
+                                    .to_string(),
+                            }
+                        } else if let Some(err) = value.get("error").and_then(Value::as_str) {
+                            if let Ok(mut pending) = reader_state.pending.lock() {
+                                *pending = None;
+                            }
+                            AgentEvent::Error(err.to_string())
+                        } else {
+                            continue;
+                        }
+                    }
+                    Err(_) => AgentEvent::Error(format!("unparseable agent output: {line}")),
+                };
+                if events.send(AppEvent::Agent(event)).is_err() {
+                    break;
+                }
+            }
+        });
+        Ok(Self {
+            child,
+            stdin,
+            next_id: 0,
+            state,
+        })
+    }
+
+    pub fn send(&mut self, request: &AgentRequest) -> Result<()> {
+        self.next_id += 1;
+        self.state.current_id.store(self.next_id, Ordering::Relaxed);
+        if let Ok(mut pending) = self.state.pending.lock() {
+            *pending = Some((Instant::now(), false));
+        }
+        let line = serde_json::to_string(&json!({
+            "id": self.next_id,
+            "prompt": request.full_prompt(),
+        }))?;
+        writeln!(self.stdin, "{line}").context("agent process stdin closed")?;
+        self.stdin.flush()?;
+        Ok(())
+    }
+
+    /// Health of the in-flight request, if any: pings a v2 agent that
+    /// has been silent for half the timeout, and reports the process as
+    /// hung once the full timeout passes with no sign of life. A hung
+    /// verdict clears the pending state so it is reported once.
+    pub fn check_health(&mut self) -> Health {
+        let Ok(mut pending) = self.state.pending.lock() else {
+            return Health::Ok;
+        };
+        let Some((since, pinged)) = *pending else {
+            return Health::Ok;
+        };
+        if since.elapsed() >= HUNG_TIMEOUT {
+            *pending = None;
+            return Health::Hung;
+        }
+        if since.elapsed() >= HUNG_TIMEOUT / 2
+            && !pinged
+            && self.state.version.load(Ordering::Relaxed) >= 2
+        {
+            *pending = Some((since, true));
+            let id = self.state.current_id.load(Ordering::Relaxed);
+            let _ = writeln!(self.stdin, "{}", json!({ "ping": id }));
+            let _ = self.stdin.flush();
+        }
+        Health::Ok
+    }
+
+    /// Whether the child process has exited.
+    pub fn exited(&mut self) -> bool {
+        matches!(self.child.try_wait(), Ok(Some(_)))
+    }
+}
+
+/// What [`LocalProcessBackend::check_health`] found.
+#[derive(Debug, PartialEq, Eq)]
+pub enum Health {
+    Ok,
+    /// The in-flight request outlived the timeout with no output.
+    Hung,
+}
+
+impl Drop for LocalProcessBackend {
+    fn drop(&mut self) {
+        let _ = self.child.kill();
+        let _ = self.child.wait();
+    }
+}
@@ -0,0 +1,442 @@
+//! Model Context Protocol client: handshake, tools, resources, prompts.
+//!
+//! Servers declared in `agents.toml` are connected at startup over stdio
+//! (`command`) or HTTP with optional SSE responses (`url`). Discovered
+//! tools are advertised to the model alongside the built-ins under
+//! qualified `mcp__server__tool` names; resources and prompt templates
+//! are browsable from the MCP overlay and insert into the composer.
+
+use std::io::{BufRead, BufReader, Write};
+use std::process::{Child, ChildStdin, ChildStdout, Command, Stdio};
+
+use anyhow::{bail, Context, Result};
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+
+use crate::cli::LogLevel;
+
+/// An MCP server declared in `agents.toml`. `command` spawns a stdio
+/// server; `url` connects over HTTP instead.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct McpServerConfig {
+    pub name: String,
+    #[serde(default)]
+    pub command: String,
+    #[serde(default)]
+    pub args: Vec<String>,
+    #[serde(default)]
+    pub url: Option<String>,
+}
+
+/// One tool a connected server advertises.
+#[derive(Debug, Clone)]
+pub struct McpTool {
+    pub name: String,
+    pub description: String,
+    pub input_schema: Value,
+}
+
+/// One resource a server exposes for reading.
+#[derive(Debug, Clone)]
+pub struct McpResource {
+    pub uri: String,
+    pub name: String,
+}
+
+/// One prompt template a server exposes.
+#[derive(Debug, Clone)]
+pub struct McpPrompt {
+    pub name: String,
+    pub description: String,
+}
+
+enum Transport {
+    Stdio {
+        child: Child,
+        stdin: ChildStdin,
+        stdout: BufReader<ChildStdout>,
+    },
+    Http {
+        url: String,
+        client: reqwest::Client,
+        runtime: tokio::runtime::Handle,
+    },
+}
+
+impl Drop for Transport {
+    fn drop(&mut self) {
+        if let Transport::Stdio { child, .. } = self {
+            let _ = child.kill();
+            let _ = child.wait();
+        }
+    }
+}
+
+/// A live connection to one MCP server.
+pub struct McpClient {
+    pub name: String,
+    pub tools: Vec<McpTool>,
+    pub resources: Vec<McpResource>,
+    pub prompts: Vec<McpPrompt>,
+    transport: Transport,
+    next_id: u64,
+}
+
+impl McpClient {
+    /// Connect, run the initialize handshake, and discover what the
+    /// server offers. Listing failures are tolerated per capability.
+    pub fn connect(config: &McpServerConfig, runtime: tokio::runtime::Handle) -> Result<Self> {
+        let transport = match &config.url {
+            Some(url) => Transport::Http {
+                url: url.clone(),
+                client: reqwest::Client::new(),
+                runtime,
+            },
+            None => {
+                if config.command.is_empty() {
+                    bail!("mcp server {} has neither command nor url", config.name);
+                }
+                let mut child = Command::new(&config.command)
+                    .args(&config.args)
+                    .stdin(Stdio::piped())
+                    .stdout(Stdio::piped())
+                    .stderr(Stdio::null())
+                    .spawn()
+                    .with_context(|| format!("failed to spawn mcp server {}", config.command))?;
+                let stdin = child.stdin.take().context("mcp server has no stdin")?;
+                let stdout = child.stdout.take().context("mcp server has no stdout")?;
+                Transport::Stdio {
+                    child,
+                    stdin,
+                    stdout: BufReader::new(stdout),
+                }
+            }
+        };
+        let mut client = Self {
+            name: config.name.clone(),
+            tools: Vec::new(),
+            resources: Vec::new(),
+            prompts: Vec::new(),
+            transport,
+            next_id: 0,
+        };
+        client.request(
+            "initialize",
+            json!({
+                "protocolVersion": "2025-03-26",
+                "capabilities": {},
+                "clientInfo": {
+                    "name": "clide",
+                    "version": env!("CARGO_PKG_VERSION"),
+                },
+            }),
+        )?;
+        client.notify("notifications/initialized")?;
+        client.tools = client.list_tools().unwrap_or_default();
+        client.resources = client.list_resources().unwrap_or_default();
+        client.prompts = client.list_prompts().unwrap_or_default();
+        Ok(client)
+    }
+
+    fn list_tools(&mut self) -> Result<Vec<McpTool>> {
+        let result = self.request("tools/list", json!({}))?;
+        Ok(result["tools"]
+            .as_array()
+            .map(|tools| {
+                tools
+                    .iter()
+                    .filter_map(|tool| {
+                        Some(McpTool {
+                            name: tool["name"].as_str()?.to_string(),
+                            description: tool["description"].as_str().unwrap_or("").to_string(),
+                            input_schema: tool["inputSchema"].clone(),
+                        })
+                    })
+                    .collect()
+            })
+            .unwrap_or_default())
+    }
+
+    fn list_resources(&mut self) -> Result<Vec<McpResource>> {
+        let result = self.request("resources/list", json!({}))?;
+        Ok(result["resources"]
+            .as_array()
+            .map(|resources| {
+                resources
+                    .iter()
+                    .filter_map(|res| {
+                        Some(McpResource {
+                            uri: res["uri"].as_str()?.to_string(),
+                            name: res["name"].as_str().unwrap_or("").to_string(),
+                        })
+                    })
+                    .collect()
+            })
+            .unwrap_or_default())
+    }
+
+    fn list_prompts(&mut self) -> Result<Vec<McpPrompt>> {
+        let result = self.request("prompts/list", json!({}))?;
+        Ok(result["prompts"]
+            .as_array()
+            .map(|prompts| {
+                prompts
+                    .iter()
+                    .filter_map(|prompt| {
+                        Some(McpPrompt {
+                            name: prompt["name"].as_str()?.to_string(),
+                            description: prompt["description"].as_str().unwrap_or("").to_string(),
+                        })
+                    })
+                    .collect()
+            })
+            .unwrap_or_default())
+    }
+
+    /// Invoke one of the server's tools, returning its text content.
+    pub fn call_tool(&mut self, name: &str, args: &Value) -> Result<String> {
+        let result = self.request(
+            "tools/call",
+            json!({ "name": name, "arguments": args }),
+        )?;
+        let text = content_text(&result["content"]);
+        if result["isError"].as_bool().unwrap_or(false) {
+            bail!(
+                "{}",
+                if text.is_empty() {
+                    "tool failed"
+                } else {
+                    text.as_str()
+                }
+            );
+        }
+        Ok(text)
+    }
+
+    /// Read one resource, returning its (text) contents.
+    pub fn read_resource(&mut self, uri: &str) -> Result<String> {
+        let result = self.request("resources/read", json!({ "uri": uri }))?;
+        let text = result["contents"]
+            .as_array()
+            .map(|contents| {
+                contents
+                    .iter()
+                    .filter_map(|block| block["text"].as_str())
+                    .collect::<Vec<_>>()
+                    .join("\n")
+            })
+            .unwrap_or_default();
+        Ok(text)
+    }
+
+    /// Expand one prompt template into plain text.
+    pub fn get_prompt(&mut self, name: &str) -> Result<String> {
+        let result = self.request("prompts/get", json!({ "name": name, "arguments": {} }))?;
+        let text = result["messages"]
+            .as_array()
+            .map(|messages| {
+                messages
+                    .iter()
+                    .filter_map(|msg| msg["content"]["text"].as_str())
+                    .collect::<Vec<_>>()
+                    .join("\n")
+            })
+            .unwrap_or_default();
+        Ok(text)
+    }
+
+    /// One JSON-RPC round trip.
+    fn request(&mut self, method: &str, params: Value) -> Result<Value> {
+        self.next_id += 1;
+        let id = self.next_id;
+        let payload = json!({
+            "jsonrpc": "2.0",
+            "id": id,
+            "method": method,
+            "params": params,
+        });
+        let reply = match &mut self.transport {
+            Transport::Stdio { stdin, stdout, .. } => {
+                writeln!(stdin, "{payload}").context("mcp server stdin closed")?;
+                stdin.flush()?;
+                // Skip notifications and server-initiated requests until
+                // our id comes back.
+                loop {
+                    let mut line = String::new();
+                    if stdout.read_line(&mut line)? == 0 {
+                        bail!("mcp server closed its stdout");
+                    }
+                    if line.trim().is_empty() {
+                        continue;
+                    }
+                    let Ok(value) = serde_json::from_str::<Value>(&line) else {
+                        continue;
+                    };
+                    if value["id"] == json!(id) {
+                        break value;
+                    }
+                }
+            }
+            Transport::Http {
+                url,
+                client,
+                runtime,
+            } => {
+                let body = runtime.block_on(async {
+                    client
+                        .post(url.as_str())
+                        .header("accept", "application/json, text/event-stream")
+                        .json(&payload)
+                        .send()
+                        .await?
+                        .error_for_status()?
+                        .text()
+                        .await
+                })?;
+                let json_text = sse_payload(&body).unwrap_or(&body);
+                serde_json::from_str(json_text).context("unparseable mcp response")?
+            }
+        };
+        if let Some(error) = reply.get("error") {
+            bail!(
+                "{method} failed: {}",
+                error["message"].as_str().unwrap_or("unknown error")
+            );
+        }
+        Ok(reply["result"].clone())
+    }
+
+    /// One JSON-RPC notification (no reply expected).
+    fn notify(&mut self, method: &str) -> Result<()> {
+        let payload = json!({ "jsonrpc": "2.0", "method": method });
+        match &mut self.transport {
+            Transport::Stdio { stdin, .. } => {
+                writeln!(stdin, "{payload}").context("mcp server stdin closed")?;
+                stdin.flush()?;
+            }
+            Transport::Http {
+                url,
+                client,
+                runtime,
+            } => {
+                let _ = runtime.block_on(async {
+                    client.post(url.as_str()).json(&payload).send().await
+                });
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Text blocks of a tool-result `content` array, joined.
+fn content_text(content: &Value) -> String {
+    content
+        .as_array()
+        .map(|blocks| {
+            blocks
+                .iter()
+                .filter_map(|block| block["text"].as_str())
+                .collect::<Vec<_>>()
+                .join("\n")
+        })
+        .unwrap_or_default()
+}
+
+/// The JSON payload of the last `data:` line of an SSE body, if the body
+/// is an event stream at all.
+fn sse_payload(body: &str) -> Option<&str> {
+    body.lines()
+        .filter_map(|line| line.strip_prefix("data:"))
+        .map(str::trim)
+        .next_back()
+}
+
+/// Every connected server, with tool names qualified for the agent loop.
+#[derive(Default)]
+pub struct McpManager {
+    pub clients: Vec<McpClient>,
+}
+
+/// Qualified name a server tool is advertised under.
+pub fn qualify(server: &str, tool: &str) -> String {
+    format!("mcp__{server}__{tool}")
+}
+
+/// Split a qualified name back into (server, tool).
+pub fn unqualify(name: &str) -> Option<(&str, &str)> {
+    name.strip_prefix("mcp__")?.split_once("__")
+}
+
+impl McpManager {
+    /// Connect every configured server; failures are logged and skipped
+    /// so one bad server does not take the agent subsystem down.
+    pub fn bootstrap(configs: &[McpServerConfig], runtime: tokio::runtime::Handle) -> Self {
+        let mut clients = Vec::new();
+        for config in configs {
+            match McpClient::connect(config, runtime.clone()) {
+                Ok(client) => clients.push(client),
+                Err(err) => crate::logging::log(
+                    LogLevel::Warn,
+                    &format!("mcp server {} unavailable: {err:#}", config.name),
+                ),
+            }
+        }
+        Self { clients }
+    }
+
+    /// Whether a tool name belongs to a connected server.
+    pub fn owns(&self, name: &str) -> bool {
+        let Some((server, tool)) = unqualify(name) else {
+            return false;
+        };
+        self.clients
+            .iter()
+            .any(|c| c.name == server && c.tools.iter().any(|t| t.name == tool))
+    }
+
+    /// Call a qualified tool on the server that owns it.
+    pub fn call(&mut self, name: &str, args: &Value) -> Result<String> {
+        let (server, tool) = unqualify(name).context("not an mcp tool name")?;
+        let client = self
+            .clients
+            .iter_mut()
+            .find(|c| c.name == server)
+            .with_context(|| format!("mcp server {server} is not connected"))?;
+        let tool = tool.to_string();
+        client.call_tool(&tool, args)
+    }
+
+    /// Every discovered tool as an external tool for the model.
+    pub fn external_tools(&self) -> Vec<crate::agent::tools::ExternalTool> {
+        self.clients
+            .iter()
+            .flat_map(|client| {
+                client.tools.iter().map(|tool| crate::agent::tools::ExternalTool {
+                    name: qualify(&client.name, &tool.name),
+                    description: tool.description.clone(),
+                    schema: tool.input_schema.clone(),
+                })
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn qualified_names_round_trip() {
+        let name = qualify("files", "read");
+        assert_eq!(name, "mcp__files__read");
+        assert_eq!(unqualify(&name), Some(("files", "read")));
+        assert_eq!(unqualify("read_file"), None);
+    }
+
+    #[test]
+    fn sse_bodies_yield_their_last_data_line() {
+        let body = "event: message\ndata: {\"a\":1}\n\ndata: {\"b\":2}\n";
+        assert_eq!(sse_payload(body), Some("{\"b\":2}"));
+        assert_eq!(sse_payload("{\"plain\":true}"), None);
+    }
+}
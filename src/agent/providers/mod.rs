@@ -0,0 +1,6 @@
+//! Agent backend implementations.
+
+pub mod http;
+pub mod local;
+pub mod mcp;
+pub mod sigv4;
@@ -0,0 +1,186 @@
+//! Workspace context retrieval for agent requests.
+//!
+//! Files are chunked by line windows and embedded as hashed bag-of-words
+//! vectors — no model download, no network, and close enough for lexical
+//! retrieval over code. The index persists in `.clide/index.json`;
+//! `retrieve` returns the top-k chunks by cosine similarity so prompts
+//! can reach beyond the current buffer.
+
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+
+/// Embedding dimensionality. Small keeps the index file reasonable.
+const DIM: usize = 256;
+
+/// Lines per chunk and the stride between chunk starts (the difference
+/// is the overlap, so matches near a boundary are not lost).
+const CHUNK_LINES: usize = 40;
+const CHUNK_STRIDE: usize = 30;
+
+/// Files above this size are skipped, like generated bundles.
+const MAX_FILE_BYTES: u64 = 262_144;
+
+/// One embedded slice of a workspace file.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Chunk {
+    /// Path relative to the workspace root.
+    pub path: PathBuf,
+    /// 1-based first line of the chunk.
+    pub start_line: usize,
+    pub text: String,
+    vector: Vec<f32>,
+}
+
+/// The on-disk workspace index.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct WorkspaceIndex {
+    /// Human-readable local time of the build.
+    pub built_at: String,
+    pub chunks: Vec<Chunk>,
+}
+
+impl WorkspaceIndex {
+    /// The `k` most similar chunks to `query`, best first. Chunks under
+    /// a minimal similarity are dropped rather than padding the result.
+    pub fn retrieve(&self, query: &str, k: usize) -> Vec<&Chunk> {
+        let needle = embed(query);
+        let mut scored: Vec<(f32, &Chunk)> = self
+            .chunks
+            .iter()
+            .map(|chunk| (dot(&needle, &chunk.vector), chunk))
+            .filter(|(score, _)| *score > 0.1)
+            .collect();
+        scored.sort_by(|a, b| b.0.partial_cmp(&a.0).unwrap_or(std::cmp::Ordering::Equal));
+        scored.into_iter().take(k).map(|(_, chunk)| chunk).collect()
+    }
+}
+
+/// Embed text as an L2-normalised hashed term-frequency vector.
+fn embed(text: &str) -> Vec<f32> {
+    let mut vector = vec![0.0f32; DIM];
+    for token in text
+        .split(|c: char| !c.is_alphanumeric() && c != '_')
+        .filter(|t| t.len() >= 2)
+    {
+        vector[hash(&token.to_lowercase()) % DIM] += 1.0;
+    }
+    let norm = vector.iter().map(|v| v * v).sum::<f32>().sqrt();
+    if norm > 0.0 {
+        for v in &mut vector {
+            *v /= norm;
+        }
+    }
+    vector
+}
+
+/// FNV-1a, enough to spread tokens over the buckets.
+fn hash(token: &str) -> usize {
+    let mut h: u64 = 0xcbf2_9ce4_8422_2325;
+    for byte in token.bytes() {
+        h ^= byte as u64;
+        h = h.wrapping_mul(0x0000_0100_0000_01b3);
+    }
+    h as usize
+}
+
+fn dot(a: &[f32], b: &[f32]) -> f32 {
+    a.iter().zip(b).map(|(x, y)| x * y).sum()
+}
+
+/// Split text into overlapping line windows as (1-based start, text).
+fn chunk_text(text: &str) -> Vec<(usize, String)> {
+    let lines: Vec<&str> = text.lines().collect();
+    if lines.is_empty() {
+        return Vec::new();
+    }
+    let mut chunks = Vec::new();
+    let mut start = 0;
+    loop {
+        let end = (start + CHUNK_LINES).min(lines.len());
+        chunks.push((start + 1, lines[start..end].join("\n")));
+        if end == lines.len() {
+            break;
+        }
+        start += CHUNK_STRIDE;
+    }
+    chunks
+}
+
+/// Chunk and embed one file; unreadable or oversized files yield nothing.
+pub fn chunk_file(root: &Path, path: &Path) -> Vec<Chunk> {
+    if fs::metadata(path).map(|m| m.len() > MAX_FILE_BYTES).unwrap_or(true) {
+        return Vec::new();
+    }
+    let Ok(text) = fs::read_to_string(path) else {
+        return Vec::new();
+    };
+    let rel = path.strip_prefix(root).unwrap_or(path).to_path_buf();
+    chunk_text(&text)
+        .into_iter()
+        .map(|(start_line, text)| {
+            let vector = embed(&text);
+            Chunk {
+                path: rel.clone(),
+                start_line,
+                text,
+                vector,
+            }
+        })
+        .collect()
+}
+
+fn file(root: &Path) -> PathBuf {
+    root.join(".clide").join("index.json")
+}
+
+pub fn save(root: &Path, index: &WorkspaceIndex) -> Result<()> {
+    let path = file(root);
+    if let Some(dir) = path.parent() {
+        fs::create_dir_all(dir).with_context(|| format!("failed to create {}", dir.display()))?;
+    }
+    let text = serde_json::to_string(index)?;
+    fs::write(&path, text).with_context(|| format!("failed to write {}", path.display()))
+}
+
+pub fn load(root: &Path) -> Option<WorkspaceIndex> {
+    let text = fs::read_to_string(file(root)).ok()?;
+    serde_json::from_str(&text).ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn chunks_overlap_and_cover_the_file() {
+        let text = (1..=100).map(|i| format!("line {i}")).collect::<Vec<_>>().join("\n");
+        let chunks = chunk_text(&text);
+        assert_eq!(chunks[0].0, 1);
+        assert_eq!(chunks[1].0, 31);
+        assert!(chunks.last().unwrap().1.ends_with("line 100"));
+    }
+
+    #[test]
+    fn retrieval_ranks_lexically_similar_chunks_first() {
+        let mk = |start_line: usize, text: &str| Chunk {
+            path: PathBuf::from("a.rs"),
+            start_line,
+            text: text.to_string(),
+            vector: embed(text),
+        };
+        let index = WorkspaceIndex {
+            built_at: "now".to_string(),
+            chunks: vec![
+                mk(1, "fn parse_config(path: &Path) -> Config"),
+                mk(41, "const PALETTE: &[Color] = &[..]"),
+            ],
+        };
+        let hits = index.retrieve("parse_config path config", 1);
+        assert_eq!(hits.len(), 1);
+        assert_eq!(hits[0].start_line, 1);
+        assert!(index.retrieve("", 5).is_empty());
+    }
+}
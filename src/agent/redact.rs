@@ -0,0 +1,187 @@
+//! Redaction of secrets before content leaves the machine.
+//!
+//! Applied to prompts and attached buffers when the active profile is a
+//! cloud provider. Detection is deliberately conservative: known API-key
+//! prefixes, high-entropy-looking tokens, e-mail addresses, and literal
+//! patterns from the `[redaction]` config table. Replacements become
+//! `[REDACTED]` and every hit is reported so the panel can show an
+//! audit count.
+
+const REPLACEMENT: &str = "[REDACTED]";
+
+/// Common API-key prefixes that identify a token outright.
+const KEY_PREFIXES: &[&str] = &[
+    "sk-",
+    "ghp_",
+    "gho_",
+    "github_pat_",
+    "xoxb-",
+    "xoxp-",
+    "AKIA",
+    "AIza",
+    "ya29.",
+];
+
+/// Effective redaction rules, resolved from config defaults.
+#[derive(Debug, Clone, Default)]
+pub struct RedactionRules {
+    pub enabled: bool,
+    pub emails: bool,
+    pub api_keys: bool,
+    /// Literal substrings to strip (project codenames, hostnames…).
+    pub patterns: Vec<String>,
+}
+
+/// One redacted region: its 0-based line and what matched.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RedactionHit {
+    pub line: usize,
+    pub kind: &'static str,
+}
+
+fn is_token_char(c: char) -> bool {
+    c.is_ascii_alphanumeric() || matches!(c, '-' | '_' | '.' | '%' | '+' | '@' | '/')
+}
+
+fn looks_like_key(token: &str) -> bool {
+    if KEY_PREFIXES
+        .iter()
+        .any(|p| token.starts_with(p) && token.len() >= p.len() + 12)
+    {
+        return true;
+    }
+    // Long mixed-case tokens with digits, excluding plain hex (commit
+    // hashes) and paths.
+    token.len() >= 32
+        && !token.contains('/')
+        && !token.contains('.')
+        && token.chars().any(|c| c.is_ascii_uppercase())
+        && token.chars().any(|c| c.is_ascii_lowercase())
+        && token.chars().any(|c| c.is_ascii_digit())
+        && !token.chars().all(|c| c.is_ascii_hexdigit())
+}
+
+fn looks_like_email(token: &str) -> bool {
+    let mut parts = token.splitn(2, '@');
+    let (Some(local), Some(domain)) = (parts.next(), parts.next()) else {
+        return false;
+    };
+    !local.is_empty()
+        && !domain.contains('@')
+        && domain.contains('.')
+        && domain
+            .split('.')
+            .all(|p| !p.is_empty() && p.chars().all(|c| c.is_ascii_alphanumeric() || c == '-'))
+}
+
+/// Redact `text` per the rules, returning the sanitized copy and the
+/// hits. With redaction disabled the text passes through untouched.
+pub fn redact(text: &str, rules: &RedactionRules) -> (String, Vec<RedactionHit>) {
+    if !rules.enabled {
+        return (text.to_string(), Vec::new());
+    }
+    let mut out = String::with_capacity(text.len());
+    let mut hits = Vec::new();
+    for (line_no, line) in text.split_inclusive('\n').enumerate() {
+        let mut line = line.to_string();
+        for pattern in &rules.patterns {
+            if pattern.is_empty() {
+                continue;
+            }
+            // Resume after each replacement rather than from the line
+            // start, or a pattern that is a substring of the marker
+            // ("RED") would re-match inside its own replacement forever.
+            let mut from = 0;
+            while let Some(at) = line[from..].find(pattern.as_str()) {
+                let at = from + at;
+                line.replace_range(at..at + pattern.len(), REPLACEMENT);
+                from = at + REPLACEMENT.len();
+                hits.push(RedactionHit {
+                    line: line_no,
+                    kind: "pattern",
+                });
+            }
+        }
+        let mut token = String::new();
+        for c in line.chars() {
+            if is_token_char(c) {
+                token.push(c);
+                continue;
+            }
+            flush_token(&mut out, &mut token, rules, line_no, &mut hits);
+            out.push(c);
+        }
+        flush_token(&mut out, &mut token, rules, line_no, &mut hits);
+    }
+    (out, hits)
+}
+
+fn flush_token(
+    out: &mut String,
+    token: &mut String,
+    rules: &RedactionRules,
+    line_no: usize,
+    hits: &mut Vec<RedactionHit>,
+) {
+    if token.is_empty() {
+        return;
+    }
+    if rules.api_keys && looks_like_key(token) {
+        out.push_str(REPLACEMENT);
+        hits.push(RedactionHit {
+            line: line_no,
+            kind: "api key",
+        });
+    } else if rules.emails && token.contains('@') && looks_like_email(token) {
+        out.push_str(REPLACEMENT);
+        hits.push(RedactionHit {
+            line: line_no,
+            kind: "email",
+        });
+    } else {
+        out.push_str(token.as_str());
+    }
+    token.clear();
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn rules() -> RedactionRules {
+        RedactionRules {
+            enabled: true,
+            emails: true,
+            api_keys: true,
+            patterns: vec!["internal-codename".to_string()],
+        }
+    }
+
+    #[test]
+    fn redacts_keys_emails_and_patterns_with_hits() {
+        let text = "key=sk-abcdef1234567890abcd\nmail jane@example.com\nsee internal-codename\n";
+        let (out, hits) = redact(text, &rules());
+        assert!(!out.contains("sk-abcdef"));
+        assert!(!out.contains("jane@example.com"));
+        assert!(!out.contains("internal-codename"));
+        assert_eq!(hits.len(), 3);
+        assert_eq!(hits[1].kind, "email");
+    }
+
+    #[test]
+    fn pattern_inside_the_replacement_marker_terminates() {
+        let mut rules = rules();
+        rules.patterns = vec!["RED".to_string()];
+        let (out, hits) = redact("code RED here\n", &rules);
+        assert_eq!(out, "code [REDACTED] here\n");
+        assert_eq!(hits.len(), 1);
+    }
+
+    #[test]
+    fn leaves_hashes_and_paths_alone() {
+        let text = "commit 0123456789abcdef0123456789abcdef01234567 in src/agent/redact.rs\n";
+        let (out, hits) = redact(text, &rules());
+        assert_eq!(out, text);
+        assert!(hits.is_empty());
+    }
+}
@@ -0,0 +1,87 @@
+//! Review records for files written by agent tools.
+//!
+//! When an agent-side tool writes a file that isn't open in the editor,
+//! Clide snapshots the previous contents, renders a diff for the panel,
+//! and keeps the snapshot around so the write can be reverted.
+
+use std::path::PathBuf;
+
+/// Snapshot of one tool write, kept for the session so it can be undone.
+#[derive(Debug, Clone)]
+pub struct ToolWriteRecord {
+    pub path: PathBuf,
+    /// Contents before the write; `None` when the file did not exist.
+    pub before: Option<String>,
+    /// Profile that produced the write, for the acceptance statistics.
+    pub profile: String,
+    /// Set once the user has hand-edited the written file.
+    pub edited: bool,
+}
+
+/// A minimal line-based unified diff used for the panel's review entries.
+///
+/// This is a plain LCS diff without context collapsing; review entries are
+/// expected to be small. Inputs beyond `MAX_DIFF_LINES` fall back to a
+/// summary line rather than an O(n*m) table.
+pub fn unified_diff(before: &str, after: &str) -> String {
+    const MAX_DIFF_LINES: usize = 2_000;
+    let old: Vec<&str> = before.lines().collect();
+    let new: Vec<&str> = after.lines().collect();
+    if old.len() > MAX_DIFF_LINES || new.len() > MAX_DIFF_LINES {
+        return format!(
+            "(file too large to diff: {} -> {} lines)",
+            old.len(),
+            new.len()
+        );
+    }
+    // LCS table over lines.
+    let mut table = vec![vec![0usize; new.len() + 1]; old.len() + 1];
+    for i in (0..old.len()).rev() {
+        for j in (0..new.len()).rev() {
+            table[i][j] = if old[i] == new[j] {
+                table[i + 1][j + 1] + 1
+            } else {
+                table[i + 1][j].max(table[i][j + 1])
+            };
+        }
+    }
+    let mut out = String::new();
+    let (mut i, mut j) = (0, 0);
+    while i < old.len() && j < new.len() {
+        if old[i] == new[j] {
+            out.push_str(&format!("  {}\n", old[i]));
+            i += 1;
+            j += 1;
+        } else if table[i + 1][j] >= table[i][j + 1] {
+            out.push_str(&format!("- {}\n", old[i]));
+            i += 1;
+        } else {
+            out.push_str(&format!("+ {}\n", new[j]));
+            j += 1;
+        }
+    }
+    for line in &old[i..] {
+        out.push_str(&format!("- {line}\n"));
+    }
+    for line in &new[j..] {
+        out.push_str(&format!("+ {line}\n"));
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn diff_marks_changed_lines() {
+        let diff = unified_diff("a\nb\nc\n", "a\nx\nc\n");
+        assert_eq!(diff, "  a\n- b\n+ x\n  c\n");
+    }
+
+    #[test]
+    fn diff_of_new_file_is_all_additions() {
+        let diff = unified_diff("", "one\ntwo\n");
+        assert_eq!(diff, "+ one\n+ two\n");
+    }
+}
@@ -0,0 +1,117 @@
+//! Session telemetry for agent-suggested patches.
+//!
+//! Every patch an agent lands (currently tool writes; panel patch apply
+//! flows feed the same counters) is recorded as accepted, edited, or
+//! rejected per profile so users can see which profiles are actually
+//! useful in practice.
+
+use std::collections::HashMap;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PatchOutcome {
+    Accepted,
+    Edited,
+    Rejected,
+}
+
+#[derive(Debug, Clone, Copy, Default)]
+pub struct ProfileStats {
+    pub accepted: usize,
+    pub edited: usize,
+    pub rejected: usize,
+}
+
+impl ProfileStats {
+    pub fn total(&self) -> usize {
+        self.accepted + self.edited + self.rejected
+    }
+
+    /// Accepted and edited patches both counted as useful.
+    pub fn acceptance_rate(&self) -> f64 {
+        if self.total() == 0 {
+            return 0.0;
+        }
+        (self.accepted + self.edited) as f64 / self.total() as f64
+    }
+}
+
+/// Per-profile patch outcome counters for the current session.
+#[derive(Default)]
+pub struct AgentStats {
+    per_profile: HashMap<String, ProfileStats>,
+}
+
+impl AgentStats {
+    pub fn record(&mut self, profile: &str, outcome: PatchOutcome) {
+        let stats = self.per_profile.entry(profile.to_string()).or_default();
+        match outcome {
+            PatchOutcome::Accepted => stats.accepted += 1,
+            PatchOutcome::Edited => stats.edited += 1,
+            PatchOutcome::Rejected => stats.rejected += 1,
+        }
+    }
+
+    /// Move one previously-accepted patch to another outcome, e.g. when a
+    /// tool write is reverted (rejected) or hand-edited afterwards.
+    pub fn reclassify_accepted(&mut self, profile: &str, to: PatchOutcome) {
+        if let Some(stats) = self.per_profile.get_mut(profile) {
+            stats.accepted = stats.accepted.saturating_sub(1);
+            match to {
+                PatchOutcome::Accepted => stats.accepted += 1,
+                PatchOutcome::Edited => stats.edited += 1,
+                PatchOutcome::Rejected => stats.rejected += 1,
+            }
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.per_profile.is_empty()
+    }
+
+    /// Profiles ordered by practical usefulness: acceptance rate first,
+    /// volume as the tie breaker.
+    pub fn sorted_by_usefulness(&self) -> Vec<(&str, ProfileStats)> {
+        let mut rows: Vec<(&str, ProfileStats)> = self
+            .per_profile
+            .iter()
+            .map(|(name, stats)| (name.as_str(), *stats))
+            .collect();
+        rows.sort_by(|a, b| {
+            b.1.acceptance_rate()
+                .partial_cmp(&a.1.acceptance_rate())
+                .unwrap_or(std::cmp::Ordering::Equal)
+                .then_with(|| b.1.total().cmp(&a.1.total()))
+                .then_with(|| a.0.cmp(b.0))
+        });
+        rows
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn sorting_prefers_rate_then_volume() {
+        let mut stats = AgentStats::default();
+        stats.record("good", PatchOutcome::Accepted);
+        stats.record("good", PatchOutcome::Accepted);
+        stats.record("mixed", PatchOutcome::Accepted);
+        stats.record("mixed", PatchOutcome::Rejected);
+        stats.record("busy", PatchOutcome::Accepted);
+        stats.record("busy", PatchOutcome::Edited);
+        stats.record("busy", PatchOutcome::Accepted);
+        let order: Vec<&str> = stats.sorted_by_usefulness().iter().map(|r| r.0).collect();
+        assert_eq!(order, vec!["busy", "good", "mixed"]);
+    }
+
+    #[test]
+    fn reclassify_moves_accepted_to_rejected() {
+        let mut stats = AgentStats::default();
+        stats.record("p", PatchOutcome::Accepted);
+        stats.reclassify_accepted("p", PatchOutcome::Rejected);
+        let rows = stats.sorted_by_usefulness();
+        assert_eq!(rows[0].1.accepted, 0);
+        assert_eq!(rows[0].1.rejected, 1);
+    }
+}
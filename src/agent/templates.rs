@@ -0,0 +1,63 @@
+//! Prompt templates with `{variable}` placeholders.
+//!
+//! Templates live in the `[agent.templates]` config table, one
+//! `name = "body"` pair each. They are reachable from the template
+//! picker overlay and as slash commands (`/name trailing words`) in
+//! the composer. Supported variables: `{selection}`, `{file}`, and
+//! `{diagnostics}`; unknown placeholders pass through untouched.
+
+/// Values the variables expand to, gathered from the active buffer.
+#[derive(Debug, Default)]
+pub struct TemplateVars {
+    pub selection: Option<String>,
+    pub file: Option<String>,
+    pub diagnostics: Option<String>,
+}
+
+/// Substitute the known variables into `template`; missing values
+/// become empty text so a template still reads sensibly without a
+/// selection or diagnostics.
+pub fn expand(template: &str, vars: &TemplateVars) -> String {
+    template
+        .replace("{selection}", vars.selection.as_deref().unwrap_or(""))
+        .replace("{file}", vars.file.as_deref().unwrap_or(""))
+        .replace("{diagnostics}", vars.diagnostics.as_deref().unwrap_or(""))
+}
+
+/// Split a `/name trailing words` composer line into the template name
+/// and whatever follows it; `None` when the line is not a slash command.
+pub fn parse_slash(prompt: &str) -> Option<(&str, &str)> {
+    let rest = prompt.strip_prefix('/')?;
+    let (name, extra) = match rest.split_once(char::is_whitespace) {
+        Some((name, extra)) => (name, extra.trim()),
+        None => (rest, ""),
+    };
+    (!name.is_empty()).then_some((name, extra))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn expand_substitutes_known_variables() {
+        let vars = TemplateVars {
+            selection: Some("let x = 1;".to_string()),
+            file: Some("src/main.rs".to_string()),
+            diagnostics: None,
+        };
+        assert_eq!(
+            expand("Explain {selection} in {file}{diagnostics}", &vars),
+            "Explain let x = 1; in src/main.rs"
+        );
+        assert_eq!(expand("keep {unknown}", &vars), "keep {unknown}");
+    }
+
+    #[test]
+    fn parse_slash_splits_name_and_trailing_text() {
+        assert_eq!(parse_slash("/review and be brief"), Some(("review", "and be brief")));
+        assert_eq!(parse_slash("/review"), Some(("review", "")));
+        assert_eq!(parse_slash("plain prompt"), None);
+        assert_eq!(parse_slash("/"), None);
+    }
+}
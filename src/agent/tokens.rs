@@ -0,0 +1,152 @@
+//! Token estimates, context-window budgeting, and request cost.
+//!
+//! No tokenizer ships with the binary, so counts are a documented
+//! heuristic (~4 characters per English token, bounded below by the
+//! word count for code-heavy text). Close enough for budgeting and a
+//! cost ballpark; never treated as exact.
+
+/// Tokens reserved for the model's reply when fitting context.
+const RESPONSE_HEADROOM: usize = 1024;
+
+/// Estimated token count of `text`.
+pub fn estimate(text: &str) -> usize {
+    let chars = text.chars().count();
+    let words = text.split_whitespace().count();
+    (chars / 4).max(words)
+}
+
+/// Context window and USD pricing per million tokens for one model.
+#[derive(Debug, Clone, Copy)]
+pub struct ModelSpec {
+    pub context_window: usize,
+    pub input_per_million: f64,
+    pub output_per_million: f64,
+}
+
+const DEFAULT_SPEC: ModelSpec = ModelSpec {
+    context_window: 32_768,
+    input_per_million: 0.0,
+    output_per_million: 0.0,
+};
+
+/// Best-effort spec for a model name, by substring. Unknown (usually
+/// local) models get a conservative window and zero cost.
+pub fn spec_for(model: &str) -> ModelSpec {
+    let model = model.to_lowercase();
+    let table: &[(&str, ModelSpec)] = &[
+        (
+            "gpt-4o-mini",
+            ModelSpec {
+                context_window: 128_000,
+                input_per_million: 0.15,
+                output_per_million: 0.60,
+            },
+        ),
+        (
+            "gpt-4o",
+            ModelSpec {
+                context_window: 128_000,
+                input_per_million: 2.50,
+                output_per_million: 10.0,
+            },
+        ),
+        (
+            "opus",
+            ModelSpec {
+                context_window: 200_000,
+                input_per_million: 15.0,
+                output_per_million: 75.0,
+            },
+        ),
+        (
+            "sonnet",
+            ModelSpec {
+                context_window: 200_000,
+                input_per_million: 3.0,
+                output_per_million: 15.0,
+            },
+        ),
+        (
+            "haiku",
+            ModelSpec {
+                context_window: 200_000,
+                input_per_million: 0.80,
+                output_per_million: 4.0,
+            },
+        ),
+        (
+            "gemini",
+            ModelSpec {
+                context_window: 1_000_000,
+                input_per_million: 1.25,
+                output_per_million: 5.0,
+            },
+        ),
+        (
+            "deepseek",
+            ModelSpec {
+                context_window: 64_000,
+                input_per_million: 0.27,
+                output_per_million: 1.10,
+            },
+        ),
+    ];
+    table
+        .iter()
+        .find(|(needle, _)| model.contains(needle))
+        .map(|(_, spec)| *spec)
+        .unwrap_or(DEFAULT_SPEC)
+}
+
+/// Clamp `context` so prompt + context + reply headroom fit in the
+/// model's window. Returns the (possibly truncated) context and whether
+/// anything was dropped.
+pub fn budget_context(context: String, prompt_tokens: usize, spec: &ModelSpec) -> (String, bool) {
+    let allowed = spec
+        .context_window
+        .saturating_sub(prompt_tokens + RESPONSE_HEADROOM);
+    if estimate(&context) <= allowed {
+        return (context, false);
+    }
+    // ~4 chars per token; keep the head, which abridged context already
+    // centers on the cursor region.
+    let keep_chars = allowed.saturating_mul(4);
+    let mut kept: String = context.chars().take(keep_chars).collect();
+    kept.push_str("\n… [context truncated to fit the model window]");
+    (kept, true)
+}
+
+/// Approximate USD cost for a number of input and output tokens.
+pub fn cost(tokens_in: usize, tokens_out: usize, spec: &ModelSpec) -> f64 {
+    (tokens_in as f64 * spec.input_per_million + tokens_out as f64 * spec.output_per_million)
+        / 1_000_000.0
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn estimate_tracks_length() {
+        assert_eq!(estimate(""), 0);
+        let prose = "the quick brown fox jumps over the lazy dog";
+        assert!(estimate(prose) >= 9);
+        assert!(estimate(&"a".repeat(400)) >= 100);
+    }
+
+    #[test]
+    fn budget_truncates_oversized_context() {
+        let spec = ModelSpec {
+            context_window: 2_000,
+            input_per_million: 0.0,
+            output_per_million: 0.0,
+        };
+        let (kept, truncated) = budget_context("short".to_string(), 10, &spec);
+        assert!(!truncated);
+        assert_eq!(kept, "short");
+        let big = "word ".repeat(4_000);
+        let (kept, truncated) = budget_context(big, 10, &spec);
+        assert!(truncated);
+        assert!(kept.ends_with("[context truncated to fit the model window]"));
+    }
+}
@@ -0,0 +1,352 @@
+//! Built-in tools the agent can call, and their JSON schemas.
+//!
+//! Providers that support function calling (OpenAI, Anthropic) advertise
+//! this set with every request. Read-only tools run immediately; the
+//! mutating ones (`write_file`, `run_command`) go through an approval
+//! overlay first. Execution errors are returned as tool output so the
+//! model can react instead of aborting the exchange.
+
+use std::path::Path;
+use std::process::Command;
+
+use serde_json::{json, Value};
+
+/// Give up on a tool loop after this many model round trips.
+pub const MAX_ROUNDS: usize = 8;
+
+/// Tool output is clipped to this many characters before it is sent back.
+const OUTPUT_LIMIT: usize = 8_192;
+
+/// One tool invocation requested by the model.
+#[derive(Debug, Clone)]
+pub struct ToolCall {
+    /// Provider-assigned id, echoed back with the result.
+    pub id: String,
+    pub name: String,
+    pub args: Value,
+}
+
+/// Name, description, and parameter schema for one built-in tool.
+pub struct ToolSpec {
+    pub name: &'static str,
+    pub description: &'static str,
+    pub parameters: fn() -> Value,
+}
+
+pub const TOOLS: &[ToolSpec] = &[
+    ToolSpec {
+        name: "read_file",
+        description: "Read a file in the workspace. Path is relative to the workspace root.",
+        parameters: path_schema,
+    },
+    ToolSpec {
+        name: "write_file",
+        description: "Create or overwrite a file in the workspace with the given content.",
+        parameters: write_schema,
+    },
+    ToolSpec {
+        name: "list_dir",
+        description: "List the entries of a workspace directory. Omit path for the root.",
+        parameters: list_schema,
+    },
+    ToolSpec {
+        name: "run_command",
+        description: "Run a shell command in the workspace root and return its output.",
+        parameters: command_schema,
+    },
+    ToolSpec {
+        name: "search_workspace",
+        description: "Search workspace files for a literal string; returns path:line matches.",
+        parameters: query_schema,
+    },
+];
+
+fn path_schema() -> Value {
+    json!({
+        "type": "object",
+        "properties": { "path": { "type": "string" } },
+        "required": ["path"],
+    })
+}
+
+fn write_schema() -> Value {
+    json!({
+        "type": "object",
+        "properties": {
+            "path": { "type": "string" },
+            "content": { "type": "string" },
+        },
+        "required": ["path", "content"],
+    })
+}
+
+fn list_schema() -> Value {
+    json!({
+        "type": "object",
+        "properties": { "path": { "type": "string" } },
+    })
+}
+
+fn command_schema() -> Value {
+    json!({
+        "type": "object",
+        "properties": { "command": { "type": "string" } },
+        "required": ["command"],
+    })
+}
+
+fn query_schema() -> Value {
+    json!({
+        "type": "object",
+        "properties": { "query": { "type": "string" } },
+        "required": ["query"],
+    })
+}
+
+/// Mutating tools wait for user approval before running.
+pub fn is_mutating(name: &str) -> bool {
+    matches!(name, "write_file" | "run_command")
+}
+
+/// A tool contributed from outside the built-in set (an MCP server),
+/// advertised to the model alongside [`TOOLS`].
+#[derive(Debug, Clone)]
+pub struct ExternalTool {
+    pub name: String,
+    pub description: String,
+    pub schema: Value,
+}
+
+/// The tool set in OpenAI `tools` format.
+pub fn openai_specs(extra: &[ExternalTool]) -> Value {
+    let mut specs: Vec<Value> = TOOLS
+        .iter()
+        .map(|tool| {
+            json!({
+                "type": "function",
+                "function": {
+                    "name": tool.name,
+                    "description": tool.description,
+                    "parameters": (tool.parameters)(),
+                },
+            })
+        })
+        .collect();
+    specs.extend(extra.iter().map(|tool| {
+        json!({
+            "type": "function",
+            "function": {
+                "name": tool.name,
+                "description": tool.description,
+                "parameters": tool.schema,
+            },
+        })
+    }));
+    Value::Array(specs)
+}
+
+/// The tool set in Anthropic `tools` format.
+pub fn anthropic_specs(extra: &[ExternalTool]) -> Value {
+    let mut specs: Vec<Value> = TOOLS
+        .iter()
+        .map(|tool| {
+            json!({
+                "name": tool.name,
+                "description": tool.description,
+                "input_schema": (tool.parameters)(),
+            })
+        })
+        .collect();
+    specs.extend(extra.iter().map(|tool| {
+        json!({
+            "name": tool.name,
+            "description": tool.description,
+            "input_schema": tool.schema,
+        })
+    }));
+    Value::Array(specs)
+}
+
+/// Execute one read-only tool (or `run_command`) against the workspace.
+/// `write_file` is handled by the app so open-buffer guards and the diff
+/// review trail apply; routing it here is a bug.
+pub fn execute(root: &Path, call: &ToolCall) -> String {
+    let output = match call.name.as_str() {
+        "read_file" => read_file(root, &call.args),
+        "list_dir" => list_dir(root, &call.args),
+        "run_command" => run_command(root, &call.args),
+        "search_workspace" => search_workspace(root, &call.args),
+        other => format!("error: unknown tool `{other}`"),
+    };
+    clip(output)
+}
+
+fn arg<'a>(args: &'a Value, key: &str) -> Option<&'a str> {
+    args.get(key).and_then(Value::as_str)
+}
+
+/// Resolve a tool path argument inside the workspace, rejecting escapes.
+/// Absolute paths and `..` components are refused outright; when the
+/// target already exists its canonical form must still sit under the
+/// root, so a symlink inside the tree cannot point out of it.
+pub(crate) fn resolve(root: &Path, path: &str) -> Result<std::path::PathBuf, String> {
+    let escape = format!("error: path `{path}` leaves the workspace");
+    let requested = Path::new(path);
+    if requested.is_absolute() || requested.has_root() {
+        return Err(escape);
+    }
+    let joined = root.join(path);
+    if joined
+        .components()
+        .any(|c| matches!(c, std::path::Component::ParentDir))
+    {
+        return Err(escape);
+    }
+    if let (Ok(real), Ok(real_root)) = (joined.canonicalize(), root.canonicalize()) {
+        if !real.starts_with(&real_root) {
+            return Err(escape);
+        }
+    }
+    Ok(joined)
+}
+
+fn read_file(root: &Path, args: &Value) -> String {
+    let Some(path) = arg(args, "path") else {
+        return "error: read_file requires a `path` argument".to_string();
+    };
+    match resolve(root, path) {
+        Ok(full) => match std::fs::read_to_string(&full) {
+            Ok(text) => text,
+            Err(err) => format!("error: {err}"),
+        },
+        Err(err) => err,
+    }
+}
+
+fn list_dir(root: &Path, args: &Value) -> String {
+    let dir = match arg(args, "path") {
+        Some(path) => match resolve(root, path) {
+            Ok(full) => full,
+            Err(err) => return err,
+        },
+        None => root.to_path_buf(),
+    };
+    let entries = match std::fs::read_dir(&dir) {
+        Ok(entries) => entries,
+        Err(err) => return format!("error: {err}"),
+    };
+    let mut names: Vec<String> = entries
+        .flatten()
+        .map(|entry| {
+            let mut name = entry.file_name().to_string_lossy().into_owned();
+            if entry.path().is_dir() {
+                name.push('/');
+            }
+            name
+        })
+        .collect();
+    names.sort();
+    if names.is_empty() {
+        "(empty)".to_string()
+    } else {
+        names.join("\n")
+    }
+}
+
+fn run_command(root: &Path, args: &Value) -> String {
+    let Some(command) = arg(args, "command") else {
+        return "error: run_command requires a `command` argument".to_string();
+    };
+    match Command::new("sh")
+        .arg("-c")
+        .arg(command)
+        .current_dir(root)
+        .output()
+    {
+        Ok(output) => {
+            let mut text = String::from_utf8_lossy(&output.stdout).into_owned();
+            let stderr = String::from_utf8_lossy(&output.stderr);
+            if !stderr.is_empty() {
+                if !text.is_empty() {
+                    text.push('\n');
+                }
+                text.push_str(&stderr);
+            }
+            if !output.status.success() {
+                text.push_str(&format!("\n[exit status: {}]", output.status));
+            }
+            if text.is_empty() {
+                "(no output)".to_string()
+            } else {
+                text
+            }
+        }
+        Err(err) => format!("error: {err}"),
+    }
+}
+
+fn search_workspace(root: &Path, args: &Value) -> String {
+    let Some(query) = arg(args, "query") else {
+        return "error: search_workspace requires a `query` argument".to_string();
+    };
+    let mut hits = Vec::new();
+    for path in crate::workspace::walk_files(root, 5_000) {
+        let Ok(text) = std::fs::read_to_string(&path) else {
+            continue;
+        };
+        for (i, line) in text.lines().enumerate() {
+            if line.contains(query) {
+                let rel = path.strip_prefix(root).unwrap_or(&path);
+                hits.push(format!("{}:{}: {}", rel.display(), i + 1, line.trim()));
+                if hits.len() >= 200 {
+                    return hits.join("\n");
+                }
+            }
+        }
+    }
+    if hits.is_empty() {
+        "no matches".to_string()
+    } else {
+        hits.join("\n")
+    }
+}
+
+fn clip(mut text: String) -> String {
+    if text.chars().count() > OUTPUT_LIMIT {
+        text = text.chars().take(OUTPUT_LIMIT).collect();
+        text.push_str("\n… [output truncated]");
+    }
+    text
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn only_write_and_run_are_mutating() {
+        assert!(is_mutating("write_file"));
+        assert!(is_mutating("run_command"));
+        assert!(!is_mutating("read_file"));
+        assert!(!is_mutating("list_dir"));
+        assert!(!is_mutating("search_workspace"));
+    }
+
+    #[test]
+    fn read_file_rejects_workspace_escapes() {
+        let root = std::env::temp_dir();
+        let call = ToolCall {
+            id: "t1".to_string(),
+            name: "read_file".to_string(),
+            args: serde_json::json!({ "path": "../outside.txt" }),
+        };
+        assert!(execute(&root, &call).contains("leaves the workspace"));
+    }
+
+    #[test]
+    fn resolve_rejects_absolute_paths() {
+        let root = std::env::temp_dir();
+        assert!(resolve(&root, "/etc/passwd").is_err());
+        assert!(resolve(&root, "src/main.rs").is_ok());
+    }
+}
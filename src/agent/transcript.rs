@@ -0,0 +1,167 @@
+//! Opt-in JSONL transcript of provider exchanges.
+//!
+//! When enabled (`transcript-log` in the `[agent]` config table, or the
+//! "Agent: Toggle Transcript Log" command) every request, response, tool
+//! result, and error crossing a provider boundary is appended to
+//! `.clide/agent-transcript.jsonl`, with configured API keys scrubbed.
+//! The in-app viewer shows the most recent records when a provider
+//! misbehaves, so debugging does not need ad-hoc prints over the TUI.
+
+use std::fs::OpenOptions;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::sync::RwLock;
+
+use serde::{Deserialize, Serialize};
+
+use crate::agent::profile::BackendConfig;
+
+const FILE_NAME: &str = "agent-transcript.jsonl";
+
+/// One logged exchange line.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TranscriptRecord {
+    /// Local wall-clock time of the exchange.
+    pub at: String,
+    pub profile: String,
+    /// `request`, `response`, `tool-result`, or `error`.
+    pub direction: String,
+    pub body: String,
+}
+
+static STATE: RwLock<Option<(bool, PathBuf)>> = RwLock::new(None);
+
+/// Set the initial on/off state and the workspace the log lives in.
+pub fn init(enabled: bool, root: &Path) {
+    if let Ok(mut guard) = STATE.write() {
+        *guard = Some((enabled, root.join(".clide").join(FILE_NAME)));
+    }
+}
+
+pub fn enabled() -> bool {
+    STATE
+        .read()
+        .ok()
+        .and_then(|guard| guard.as_ref().map(|(on, _)| *on))
+        .unwrap_or(false)
+}
+
+/// Flip logging at runtime; returns the new state.
+pub fn toggle() -> bool {
+    let Ok(mut guard) = STATE.write() else {
+        return false;
+    };
+    match guard.as_mut() {
+        Some((on, _)) => {
+            *on = !*on;
+            *on
+        }
+        None => false,
+    }
+}
+
+/// Append one record if logging is on; failures are silently dropped
+/// (the transcript must never take a request down).
+pub fn record(profile: &str, direction: &str, body: &str) {
+    let Ok(guard) = STATE.read() else { return };
+    let Some((true, path)) = guard.as_ref() else {
+        return;
+    };
+    let keys = configured_keys();
+    let record = TranscriptRecord {
+        at: chrono::Local::now().format("%Y-%m-%d %H:%M:%S").to_string(),
+        profile: profile.to_string(),
+        direction: direction.to_string(),
+        body: scrub_keys(body, &keys),
+    };
+    let Ok(line) = serde_json::to_string(&record) else {
+        return;
+    };
+    if let Some(dir) = path.parent() {
+        let _ = std::fs::create_dir_all(dir);
+    }
+    if let Ok(mut file) = OpenOptions::new().create(true).append(true).open(path) {
+        let _ = writeln!(file, "{line}");
+    }
+}
+
+/// The last `limit` records, oldest first; a missing log yields an
+/// empty list.
+pub fn tail(root: &Path, limit: usize) -> Vec<TranscriptRecord> {
+    let path = root.join(".clide").join(FILE_NAME);
+    let Ok(text) = std::fs::read_to_string(&path) else {
+        return Vec::new();
+    };
+    let records: Vec<TranscriptRecord> = text
+        .lines()
+        .filter_map(|line| serde_json::from_str(line).ok())
+        .collect();
+    let skip = records.len().saturating_sub(limit);
+    records.into_iter().skip(skip).collect()
+}
+
+/// Every API key that could leak into a logged body: inline values from
+/// `agents.toml` plus the environment variables profiles read. Keyring
+/// keys never travel in message bodies, so they need no scrubbing.
+fn configured_keys() -> Vec<String> {
+    let config = crate::agent::profile::load_agents_config();
+    let mut keys = Vec::new();
+    for profile in &config.profiles {
+        if let BackendConfig::HttpApi(http) = &profile.backend {
+            if let Some(key) = &http.api_key {
+                keys.push(key.clone());
+            }
+            if let Some(var) = &http.api_key_env {
+                if let Ok(key) = std::env::var(var) {
+                    keys.push(key);
+                }
+            }
+        }
+    }
+    keys.retain(|key| key.len() >= 8);
+    keys
+}
+
+fn scrub_keys(text: &str, keys: &[String]) -> String {
+    let mut out = text.to_string();
+    for key in keys {
+        out = out.replace(key.as_str(), "[redacted]");
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn scrubbing_replaces_every_occurrence() {
+        let keys = vec!["sk-verysecretkey".to_string()];
+        let out = scrub_keys("key sk-verysecretkey and again sk-verysecretkey", &keys);
+        assert_eq!(out, "key [redacted] and again [redacted]");
+    }
+
+    #[test]
+    fn tail_returns_the_most_recent_records() {
+        let root = std::env::temp_dir().join(format!("clide-transcript-{}", std::process::id()));
+        let dir = root.join(".clide");
+        std::fs::create_dir_all(&dir).unwrap();
+        let mut lines = String::new();
+        for i in 0..5 {
+            let record = TranscriptRecord {
+                at: "now".to_string(),
+                profile: "local".to_string(),
+                direction: "request".to_string(),
+                body: format!("prompt {i}"),
+            };
+            lines.push_str(&serde_json::to_string(&record).unwrap());
+            lines.push('\n');
+        }
+        std::fs::write(dir.join(FILE_NAME), lines).unwrap();
+        let records = tail(&root, 2);
+        assert_eq!(records.len(), 2);
+        assert_eq!(records[0].body, "prompt 3");
+        assert_eq!(records[1].body, "prompt 4");
+        let _ = std::fs::remove_dir_all(&root);
+    }
+}
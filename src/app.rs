@@ -0,0 +1,5202 @@
+//! Central application state and the update logic behind every pane.
+
+use std::collections::{HashMap, HashSet};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant};
+
+use anyhow::{Context, Result};
+
+use crate::agent::batch::{BatchRun, ItemState};
+use crate::agent::review::{unified_diff, ToolWriteRecord};
+use crate::agent::stats::{AgentStats, PatchOutcome};
+use crate::agent::{AgentConversation, AgentEvent, AgentManager, AgentPanelEntry, AgentRequest};
+use crate::cli::{CliArgs, LogLevel};
+use crate::config::{ClideConfig, EditorSection, LayoutPreset, StartupHook};
+use crate::editor::crypt::CryptKind;
+use crate::editor::{Editor, EditorPreferences, Encoding, IndentKind, LineEnding, Position, WrapMode};
+use crate::event::{AppEvent, AppEventReceiver, AppEventSender};
+use crate::git::GitPanel;
+use crate::keymap::{KeyChord, Keymap};
+use crate::layout::{Focus, LayoutState};
+use crate::lsp::types::{uri_to_path, LspPosition, TextEdit, WorkspaceEdit};
+use crate::lsp::{LspClient, LspEvent};
+use crate::notify::{Notifications, Severity};
+use crate::task::{TaskEvent, TaskKind, TaskManager, TaskOutcome};
+use crate::terminal::TerminalPane;
+use crate::ui::images::ImageProtocol;
+use crate::ui::overlay::{
+    FileMatches, McpRow, Overlay, PendingFileEdit, PromptAction, SearchReplaceState,
+    WorkspaceEditPreviewState,
+};
+use crate::workspace::{walk_files, FileTree, IconSet};
+
+
+/// Pastes above either threshold go through a confirmation overlay.
+const PASTE_CONFIRM_LINES: usize = 100;
+const PASTE_CONFIRM_BYTES: usize = 1024 * 1024;
+
+/// Clicks closer together than this count as double/triple clicks.
+const MULTI_CLICK_WINDOW: Duration = Duration::from_millis(400);
+
+/// Files at least this large load on a background thread.
+const ASYNC_OPEN_BYTES: u64 = 8 * 1024 * 1024;
+/// Files at least this large additionally open read-only.
+const HUGE_FILE_BYTES: u64 = 64 * 1024 * 1024;
+
+/// How often crash-recovery snapshots of dirty buffers are refreshed.
+const RECOVERY_INTERVAL: Duration = Duration::from_secs(5);
+
+/// An action invokable from the command palette.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CommandId {
+    SaveFile,
+    CloseBuffer,
+    NextBuffer,
+    PrevBuffer,
+    ToggleTree,
+    ToggleTerminal,
+    TerminalCopyMode,
+    ToggleAgent,
+    ToggleGit,
+    ToggleHidden,
+    RestoreLastDeleted,
+    ToggleLineNumbers,
+    ToggleRelativeNumbers,
+    ToggleWrap,
+    ToggleLineEnding,
+    ToggleEncoding,
+    ReopenWithEncoding,
+    CycleIndent,
+    MoveLineUp,
+    MoveLineDown,
+    DuplicateLine,
+    JoinLines,
+    DeleteLine,
+    EvaluateSelection,
+    ToggleVim,
+    ToggleReadOnly,
+    FollowFile,
+    ToggleStats,
+    ToggleWhitespace,
+    CycleIconSet,
+    SearchReplace,
+    GotoLine,
+    RelatedFile,
+    ToggleBookmark,
+    NextBookmark,
+    PrevBookmark,
+    JumpBack,
+    JumpForward,
+    RenameSymbol,
+    GotoDefinition,
+    HoverInfo,
+    GitRefresh,
+    GenerateCommitMessage,
+    GitStage,
+    GitCommit,
+    AgentCycleProfile,
+    AgentSetApiKey,
+    AgentRevertToolWrite,
+    AgentShowStats,
+    AgentBatch,
+    AgentBatchApply,
+    AgentBatchDiscard,
+    AgentPreviewRedactions,
+    AgentCopyCode,
+    AgentInsertCode,
+    AgentToggleInfo,
+    AgentToggleDiffs,
+    AgentExpandInfo,
+    InsertFileHeader,
+    InspectCharacter,
+    InsertUnicode,
+    SelectLayoutPreset,
+    CommandPalette,
+    KeyboardHelp,
+    NotificationHistory,
+    CancelTasks,
+    ConversationHistory,
+    NewConversation,
+    SearchAgentHistory,
+    McpBrowser,
+    ReviewToolConflict,
+    BuildRagIndex,
+    SelectModel,
+    ToggleTranscriptLog,
+    TranscriptViewer,
+    EditInstructions,
+    PromptTemplates,
+    AgentAskSelection,
+    AgentExplainError,
+    AgentGenerateTests,
+    AgentEntryActions,
+    ExportConversation,
+    RunTask,
+    CargoCheck,
+    CargoTest,
+    ExportBundle,
+    ImportBundle,
+    SelectTheme,
+    ReloadTheme,
+    FocusTree,
+    FocusEditor,
+    FocusTerminal,
+    FocusAgent,
+    FocusGit,
+    Quit,
+}
+
+pub const PALETTE_COMMANDS: &[(&str, CommandId)] = &[
+    ("File: Save", CommandId::SaveFile),
+    ("File: Close Buffer", CommandId::CloseBuffer),
+    ("File: Next Buffer", CommandId::NextBuffer),
+    ("File: Previous Buffer", CommandId::PrevBuffer),
+    ("View: Toggle File Tree", CommandId::ToggleTree),
+    ("View: Toggle Terminal", CommandId::ToggleTerminal),
+    ("Terminal: Copy Mode", CommandId::TerminalCopyMode),
+    ("View: Toggle Agent Panel", CommandId::ToggleAgent),
+    ("View: Toggle Git Panel", CommandId::ToggleGit),
+    ("View: Toggle Hidden Files", CommandId::ToggleHidden),
+    ("Tree: Restore Last Deleted", CommandId::RestoreLastDeleted),
+    ("View: Toggle Line Numbers", CommandId::ToggleLineNumbers),
+    (
+        "View: Toggle Relative Line Numbers",
+        CommandId::ToggleRelativeNumbers,
+    ),
+    ("View: Toggle Line Wrap", CommandId::ToggleWrap),
+    ("Buffer: Toggle Line Ending (LF/CRLF)", CommandId::ToggleLineEnding),
+    ("Buffer: Toggle Encoding", CommandId::ToggleEncoding),
+    ("Buffer: Reopen with Encoding…", CommandId::ReopenWithEncoding),
+    ("Editor: Cycle Indent Preference", CommandId::CycleIndent),
+    ("Editor: Move Line Up", CommandId::MoveLineUp),
+    ("Editor: Move Line Down", CommandId::MoveLineDown),
+    ("Editor: Duplicate Line", CommandId::DuplicateLine),
+    ("Editor: Join Lines", CommandId::JoinLines),
+    ("Editor: Delete Line", CommandId::DeleteLine),
+    ("Editor: Evaluate Selection", CommandId::EvaluateSelection),
+    ("Editor: Toggle Vim Mode", CommandId::ToggleVim),
+    ("Editor: Toggle Read-Only", CommandId::ToggleReadOnly),
+    ("View: Follow File (tail)", CommandId::FollowFile),
+    ("View: Toggle Document Stats", CommandId::ToggleStats),
+    ("View: Toggle Whitespace Markers", CommandId::ToggleWhitespace),
+    ("Tree: Cycle Icon Set", CommandId::CycleIconSet),
+    ("Search: Replace in Files", CommandId::SearchReplace),
+    ("Go: Go to Line", CommandId::GotoLine),
+    ("Go: Switch to Related File", CommandId::RelatedFile),
+    ("Editor: Toggle Bookmark", CommandId::ToggleBookmark),
+    ("Go: Next Bookmark", CommandId::NextBookmark),
+    ("Go: Previous Bookmark", CommandId::PrevBookmark),
+    ("Go: Back (Jump List)", CommandId::JumpBack),
+    ("Go: Forward (Jump List)", CommandId::JumpForward),
+    ("LSP: Rename Symbol", CommandId::RenameSymbol),
+    ("LSP: Go to Definition", CommandId::GotoDefinition),
+    ("LSP: Hover Info", CommandId::HoverInfo),
+    ("Git: Refresh Status", CommandId::GitRefresh),
+    ("Git: Stage/Unstage Selected", CommandId::GitStage),
+    ("Git: Commit", CommandId::GitCommit),
+    ("Git: Generate Commit Message", CommandId::GenerateCommitMessage),
+    ("Agent: Next Profile", CommandId::AgentCycleProfile),
+    ("Agent: Set API Key for Active Profile", CommandId::AgentSetApiKey),
+    ("Agent: Revert Last Tool Write", CommandId::AgentRevertToolWrite),
+    ("Agent: Session Statistics", CommandId::AgentShowStats),
+    ("Agent: Batch Prompt over Files…", CommandId::AgentBatch),
+    ("Agent: Apply Batch Results", CommandId::AgentBatchApply),
+    ("Agent: Discard Batch", CommandId::AgentBatchDiscard),
+    ("Agent: Preview Redactions", CommandId::AgentPreviewRedactions),
+    ("Agent: Copy Last Code Block", CommandId::AgentCopyCode),
+    ("Agent: Insert Last Code Block at Cursor", CommandId::AgentInsertCode),
+    ("Agent: Conversation History", CommandId::ConversationHistory),
+    ("Agent: New Conversation", CommandId::NewConversation),
+    ("Agent: Search History…", CommandId::SearchAgentHistory),
+    ("Agent: MCP Servers", CommandId::McpBrowser),
+    ("Agent: Review Staged Tool Write", CommandId::ReviewToolConflict),
+    ("Agent: Build Workspace Index", CommandId::BuildRagIndex),
+    ("Agent: Select Model…", CommandId::SelectModel),
+    ("Agent: Toggle Transcript Log", CommandId::ToggleTranscriptLog),
+    ("Agent: View Transcript Log", CommandId::TranscriptViewer),
+    ("Agent: Edit Project Instructions", CommandId::EditInstructions),
+    ("Agent: Insert Prompt Template…", CommandId::PromptTemplates),
+    ("Agent: Ask About Selection", CommandId::AgentAskSelection),
+    ("Agent: Explain Error Under Cursor", CommandId::AgentExplainError),
+    ("Agent: Generate Tests for Function", CommandId::AgentGenerateTests),
+    ("Agent: Entry Actions…", CommandId::AgentEntryActions),
+    ("Agent: Export Conversation…", CommandId::ExportConversation),
+    ("Tasks: Run Task…", CommandId::RunTask),
+    ("Tasks: Cargo Check (Diagnostics)", CommandId::CargoCheck),
+    ("Tasks: Cargo Test (Diagnostics)", CommandId::CargoTest),
+    ("Workspace: Export Share Bundle", CommandId::ExportBundle),
+    ("Workspace: Import Share Bundle…", CommandId::ImportBundle),
+    ("Agent: Toggle Info Entries", CommandId::AgentToggleInfo),
+    ("Agent: Toggle Diff Bodies", CommandId::AgentToggleDiffs),
+    ("Agent: Expand/Collapse Info Groups", CommandId::AgentExpandInfo),
+    ("File: Insert/Update Header", CommandId::InsertFileHeader),
+    ("Editor: Inspect Character", CommandId::InspectCharacter),
+    ("Editor: Insert Unicode Character…", CommandId::InsertUnicode),
+    ("Help: Keyboard Shortcuts", CommandId::KeyboardHelp),
+    ("View: Notification History", CommandId::NotificationHistory),
+    ("View: Select Theme", CommandId::SelectTheme),
+    ("View: Layout Preset…", CommandId::SelectLayoutPreset),
+    ("View: Reload Theme", CommandId::ReloadTheme),
+    ("Focus: File Tree", CommandId::FocusTree),
+    ("Focus: Editor", CommandId::FocusEditor),
+    ("Focus: Terminal", CommandId::FocusTerminal),
+    ("Focus: Agent Panel", CommandId::FocusAgent),
+    ("Focus: Git Panel", CommandId::FocusGit),
+    ("Application: Cancel Background Tasks", CommandId::CancelTasks),
+    ("Application: Quit", CommandId::Quit),
+];
+
+/// Stable command names used by the `[keys.*]` config tables.
+pub const COMMAND_NAMES: &[(&str, CommandId)] = &[
+    ("file.save", CommandId::SaveFile),
+    ("buffer.close", CommandId::CloseBuffer),
+    ("buffer.next", CommandId::NextBuffer),
+    ("buffer.prev", CommandId::PrevBuffer),
+    ("view.toggle-tree", CommandId::ToggleTree),
+    ("view.toggle-terminal", CommandId::ToggleTerminal),
+    ("terminal.copy-mode", CommandId::TerminalCopyMode),
+    ("view.toggle-agent", CommandId::ToggleAgent),
+    ("view.toggle-git", CommandId::ToggleGit),
+    ("view.toggle-hidden", CommandId::ToggleHidden),
+    ("tree.restore-last-deleted", CommandId::RestoreLastDeleted),
+    ("view.toggle-line-numbers", CommandId::ToggleLineNumbers),
+    (
+        "view.toggle-relative-numbers",
+        CommandId::ToggleRelativeNumbers,
+    ),
+    ("view.toggle-wrap", CommandId::ToggleWrap),
+    ("buffer.toggle-line-ending", CommandId::ToggleLineEnding),
+    ("buffer.toggle-encoding", CommandId::ToggleEncoding),
+    ("buffer.reopen-with-encoding", CommandId::ReopenWithEncoding),
+    ("editor.cycle-indent", CommandId::CycleIndent),
+    ("editor.move-line-up", CommandId::MoveLineUp),
+    ("editor.move-line-down", CommandId::MoveLineDown),
+    ("editor.duplicate-line", CommandId::DuplicateLine),
+    ("editor.join-lines", CommandId::JoinLines),
+    ("editor.delete-line", CommandId::DeleteLine),
+    ("editor.evaluate-selection", CommandId::EvaluateSelection),
+    ("editor.toggle-vim", CommandId::ToggleVim),
+    ("editor.toggle-read-only", CommandId::ToggleReadOnly),
+    ("view.follow-file", CommandId::FollowFile),
+    ("view.toggle-stats", CommandId::ToggleStats),
+    ("view.toggle-whitespace", CommandId::ToggleWhitespace),
+    ("tree.cycle-icon-set", CommandId::CycleIconSet),
+    ("search.replace-in-files", CommandId::SearchReplace),
+    ("go.line", CommandId::GotoLine),
+    ("go.related-file", CommandId::RelatedFile),
+    ("editor.toggle-bookmark", CommandId::ToggleBookmark),
+    ("go.next-bookmark", CommandId::NextBookmark),
+    ("go.prev-bookmark", CommandId::PrevBookmark),
+    ("go.back", CommandId::JumpBack),
+    ("go.forward", CommandId::JumpForward),
+    ("lsp.rename", CommandId::RenameSymbol),
+    ("lsp.definition", CommandId::GotoDefinition),
+    ("lsp.hover", CommandId::HoverInfo),
+    ("git.refresh", CommandId::GitRefresh),
+    ("git.stage", CommandId::GitStage),
+    ("git.commit", CommandId::GitCommit),
+    ("git.generate-commit", CommandId::GenerateCommitMessage),
+    ("agent.next-profile", CommandId::AgentCycleProfile),
+    ("agent.set-api-key", CommandId::AgentSetApiKey),
+    ("agent.revert-tool-write", CommandId::AgentRevertToolWrite),
+    ("agent.stats", CommandId::AgentShowStats),
+    ("agent.batch", CommandId::AgentBatch),
+    ("agent.batch-apply", CommandId::AgentBatchApply),
+    ("agent.batch-discard", CommandId::AgentBatchDiscard),
+    ("agent.preview-redactions", CommandId::AgentPreviewRedactions),
+    ("agent.copy-code", CommandId::AgentCopyCode),
+    ("agent.insert-code", CommandId::AgentInsertCode),
+    ("agent.history", CommandId::ConversationHistory),
+    ("agent.new-conversation", CommandId::NewConversation),
+    ("agent.search-history", CommandId::SearchAgentHistory),
+    ("agent.mcp", CommandId::McpBrowser),
+    ("agent.review-conflict", CommandId::ReviewToolConflict),
+    ("agent.build-index", CommandId::BuildRagIndex),
+    ("agent.select-model", CommandId::SelectModel),
+    ("agent.transcript-log", CommandId::ToggleTranscriptLog),
+    ("agent.transcript", CommandId::TranscriptViewer),
+    ("agent.instructions", CommandId::EditInstructions),
+    ("agent.templates", CommandId::PromptTemplates),
+    ("agent.ask-selection", CommandId::AgentAskSelection),
+    ("agent.explain-error", CommandId::AgentExplainError),
+    ("agent.generate-tests", CommandId::AgentGenerateTests),
+    ("agent.entries", CommandId::AgentEntryActions),
+    ("agent.export", CommandId::ExportConversation),
+    ("tasks.run", CommandId::RunTask),
+    ("tasks.cargo-check", CommandId::CargoCheck),
+    ("tasks.cargo-test", CommandId::CargoTest),
+    ("workspace.export-bundle", CommandId::ExportBundle),
+    ("workspace.import-bundle", CommandId::ImportBundle),
+    ("agent.toggle-info", CommandId::AgentToggleInfo),
+    ("agent.toggle-diffs", CommandId::AgentToggleDiffs),
+    ("agent.expand-info", CommandId::AgentExpandInfo),
+    ("palette.open", CommandId::CommandPalette),
+    ("file.insert-header", CommandId::InsertFileHeader),
+    ("editor.inspect-char", CommandId::InspectCharacter),
+    ("editor.insert-unicode", CommandId::InsertUnicode),
+    ("help.keyboard", CommandId::KeyboardHelp),
+    ("view.notifications", CommandId::NotificationHistory),
+    ("view.select-theme", CommandId::SelectTheme),
+    ("view.layout-preset", CommandId::SelectLayoutPreset),
+    ("view.reload-theme", CommandId::ReloadTheme),
+    ("focus.tree", CommandId::FocusTree),
+    ("focus.editor", CommandId::FocusEditor),
+    ("focus.terminal", CommandId::FocusTerminal),
+    ("focus.agent", CommandId::FocusAgent),
+    ("focus.git", CommandId::FocusGit),
+    ("app.cancel-tasks", CommandId::CancelTasks),
+    ("app.quit", CommandId::Quit),
+];
+
+pub fn command_by_name(name: &str) -> Option<CommandId> {
+    COMMAND_NAMES
+        .iter()
+        .find(|(n, _)| *n == name)
+        .map(|(_, id)| *id)
+}
+
+/// Human-readable label for a command, as shown in the palette and the
+/// keyboard help overlay.
+pub fn command_label(id: CommandId) -> &'static str {
+    if let Some((label, _)) = PALETTE_COMMANDS.iter().find(|(_, c)| *c == id) {
+        return label;
+    }
+    match id {
+        CommandId::CommandPalette => "View: Command Palette",
+        _ => "(unnamed command)",
+    }
+}
+
+pub struct App {
+    pub root: PathBuf,
+    pub editor: Editor,
+    pub tree: FileTree,
+    pub terminal: TerminalPane,
+    pub git: GitPanel,
+    pub agent: AgentManager,
+    pub conversation: AgentConversation,
+    /// Id of the saved conversation currently loaded in the panel, once
+    /// it has been persisted at least once.
+    conversation_id: Option<String>,
+    /// User-chosen title, kept across saves; derived from the first
+    /// prompt otherwise.
+    conversation_title: Option<String>,
+    /// Conversation the pending rename prompt applies to.
+    pending_conversation_rename: Option<String>,
+    /// The agent composer input.
+    pub composer: String,
+    pub layout: LayoutState,
+    pub focus: Focus,
+    pub config: ClideConfig,
+    /// Pane the pointer is resting over and since when, for the optional
+    /// focus-follows-mouse mode.
+    hover_pane: Option<(Focus, Instant)>,
+    /// Graphics protocol the host terminal supports, detected once.
+    pub image_protocol: ImageProtocol,
+    /// Effective keybindings: defaults plus `[keys.*]` overrides.
+    pub keymap: Keymap,
+    /// Name of the active theme, for reloads and the picker.
+    pub theme_name: String,
+    /// Refuse buffer edits and saves (`--read-only`).
+    pub read_only: bool,
+    /// First chord of a pending two-chord sequence.
+    pub pending_chord: Option<KeyChord>,
+    /// Encrypted file waiting on its secret prompt before opening.
+    pending_decrypt: Option<(PathBuf, CryptKind)>,
+    /// Throttles disk polling for followed (tail mode) buffers.
+    last_follow_poll: Instant,
+    /// Throttles the `agents.toml` change check.
+    last_agents_poll: Instant,
+    /// Modification time of `agents.toml` at the last check, so edits
+    /// apply without a restart.
+    agents_config_mtime: Option<std::time::SystemTime>,
+    /// Throttles crash-recovery snapshot writes.
+    last_recovery: Instant,
+    /// Last time the autosave timer fired.
+    last_autosave: Instant,
+    /// Index of the unnamed buffer receiving piped stdin (`clide -`).
+    stdin_buffer: Option<usize>,
+    /// Name of the project task running in the terminal, for the exit
+    /// report.
+    running_task: Option<String>,
+    pub overlay: Option<Overlay>,
+    /// Toast queue; the newest entry doubles as the status-bar message.
+    pub notify: Notifications,
+    /// Resolved status bar segments, in display order.
+    pub status_segments: Vec<&'static crate::ui::status::StatusSegment>,
+    pub lsp: Option<LspClient>,
+    pub diagnostics: HashMap<PathBuf, Vec<crate::lsp::types::Diagnostic>>,
+    /// Files whose diagnostics came from the last cargo run, so a
+    /// cleaner run can clear them.
+    cargo_diagnostic_paths: Vec<PathBuf>,
+    /// Flattened document symbols per file, for the breadcrumb bar.
+    pub symbols: HashMap<PathBuf, Vec<(String, crate::lsp::types::LspRange)>>,
+    pub clipboard: String,
+    /// Snapshots of files written by agent tools, newest last.
+    pub tool_writes: Vec<ToolWriteRecord>,
+    /// Tool writes held back because the target buffer had unsaved
+    /// edits, as (path, proposed content) awaiting a merge choice.
+    pub pending_tool_patches: Vec<(PathBuf, String)>,
+    /// Index of the conversation entry a streaming provider is filling,
+    /// while a reply is arriving chunk by chunk.
+    streaming_entry: Option<usize>,
+    /// Conversations of profiles other than the active one, keyed by
+    /// profile name; switching profiles swaps tabs in and out, and
+    /// replies still in flight land in their own tab.
+    parked_conversations: HashMap<String, AgentConversation>,
+    /// The next agent reply is a drafted commit message and pre-fills
+    /// the commit prompt instead of joining the conversation.
+    awaiting_commit_message: bool,
+    /// Index of the `ToolOutput` entry mirroring terminal output while
+    /// an approved agent command runs.
+    agent_terminal_capture: Option<usize>,
+    /// The workspace retrieval index, once built or loaded from disk.
+    pub rag: Option<crate::agent::rag::WorkspaceIndex>,
+    /// Original path of the most recently trashed entry, for restore.
+    pub last_trashed: Option<PathBuf>,
+    /// Per-profile patch acceptance counters for this session.
+    pub agent_stats: AgentStats,
+    /// Estimated tokens sent/received this session, for the cost line.
+    pub agent_tokens_in: usize,
+    pub agent_tokens_out: usize,
+    /// Estimated size of the most recent request.
+    pub last_prompt_tokens: usize,
+    pub should_quit: bool,
+    events_rx: AppEventReceiver,
+    events_tx: AppEventSender,
+    /// Paths currently loading on a background thread.
+    loading_files: HashSet<PathBuf>,
+    /// Background work: typed handles with cancellation tokens.
+    pub tasks: TaskManager,
+    /// A refresh was requested while one was already running; run again
+    /// once the in-flight task finishes so the panel never shows stale
+    /// state.
+    git_refresh_queued: bool,
+    tree_scan_queued: bool,
+    /// Per-file "Reopen with Encoding…" choices, persisted in the
+    /// session and re-applied when the file opens again.
+    encoding_overrides: HashMap<PathBuf, Encoding>,
+    /// Onboarding hints already dismissed this session; see
+    /// [`App::hint_visible`].
+    dismissed_hints: HashSet<&'static str>,
+    /// Cursor locations remembered before jumps (go to definition, go to
+    /// line, bookmark navigation), oldest first.
+    jump_list: Vec<(PathBuf, Position)>,
+    /// Position in `jump_list` while walking back with Ctrl+O; equals
+    /// `jump_list.len()` when at the live end.
+    jump_index: usize,
+    /// The language server is still indexing; requests are queued in
+    /// `lsp_deferred` instead of being silently dropped.
+    pub lsp_indexing: bool,
+    lsp_deferred: Vec<DeferredLspRequest>,
+    /// Modal editing state, active while `prefs.vim_mode` is set.
+    pub vim: crate::vim::VimState,
+    /// In-progress mouse drag of a file-tree entry, if any.
+    tree_drag: Option<TreeDrag>,
+    /// Left button held down over the editor, for drag selection.
+    editor_drag: bool,
+    /// Scrollbar thumb being dragged, if any.
+    scrollbar_drag: Option<crate::layout::Scrollbar>,
+    /// Time, position, and click count of the last editor click, for
+    /// double (word) and triple (line) click detection.
+    last_editor_click: Option<(Instant, Position, u8)>,
+    /// File attached to the next agent prompt by dropping a tree entry
+    /// on the composer.
+    pub agent_attachment: Option<PathBuf>,
+    /// Batch run in progress, if any; see [`crate::agent::batch`].
+    pub batch: Option<BatchRun>,
+    /// Files collected by the first batch prompt, consumed by the second.
+    pending_batch_files: Vec<PathBuf>,
+}
+
+/// State for dragging a file-tree entry onto another pane.
+struct TreeDrag {
+    path: PathBuf,
+    /// Set once the pointer has left the tree pane, so an ordinary click
+    /// (press and release in place) is not mistaken for a drop.
+    armed: bool,
+}
+
+/// Where a clicked breadcrumb segment takes the user.
+pub enum BreadcrumbTarget {
+    Tree,
+    Editor,
+    /// Jump to the enclosing symbol's definition line.
+    Line(usize),
+}
+
+/// An LSP request made during the indexing phase, replayed once the
+/// server reports progress finished.
+enum DeferredLspRequest {
+    Definition(PathBuf, LspPosition),
+    Hover(PathBuf, LspPosition),
+}
+
+impl App {
+    /// Build the full application state for a workspace root.
+    pub fn init(root: PathBuf, runtime: tokio::runtime::Handle, cli: CliArgs) -> Self {
+        let (events_tx, events_rx) = crate::event::channel();
+        let lsp = LspClient::spawn(&root, events_tx.clone());
+        let agent = AgentManager::bootstrap(events_tx.clone(), runtime);
+        let mut conversation = AgentConversation::default();
+        conversation.push(AgentPanelEntry::Info(format!(
+            "{} agent profile(s) loaded",
+            agent.config.profiles.len()
+        )));
+        if let Some(profile) = agent.active_profile() {
+            conversation.push(AgentPanelEntry::Info(format!(
+                "active profile: {}",
+                profile.name
+            )));
+        }
+        let mut app = Self {
+            tree: FileTree::new(root.clone()),
+            terminal: TerminalPane::new(root.clone(), events_tx.clone()),
+            git: GitPanel::new(root.clone()),
+            agent,
+            conversation,
+            conversation_id: None,
+            conversation_title: None,
+            pending_conversation_rename: None,
+            composer: String::new(),
+            layout: LayoutState::default(),
+            focus: Focus::Editor,
+            overlay: None,
+            notify: Notifications::default(),
+            status_segments: crate::ui::status::default_segments(),
+            lsp,
+            diagnostics: HashMap::new(),
+            cargo_diagnostic_paths: Vec::new(),
+            symbols: HashMap::new(),
+            clipboard: String::new(),
+            tool_writes: Vec::new(),
+            pending_tool_patches: Vec::new(),
+            streaming_entry: None,
+            parked_conversations: HashMap::new(),
+            awaiting_commit_message: false,
+            agent_terminal_capture: None,
+            rag: None,
+            last_trashed: None,
+            agent_stats: AgentStats::default(),
+            agent_tokens_in: 0,
+            agent_tokens_out: 0,
+            last_prompt_tokens: 0,
+            should_quit: false,
+            editor: Editor::new(),
+            events_rx,
+            events_tx: events_tx.clone(),
+            loading_files: HashSet::new(),
+            tasks: TaskManager::default(),
+            git_refresh_queued: false,
+            tree_scan_queued: false,
+            encoding_overrides: HashMap::new(),
+            dismissed_hints: HashSet::new(),
+            jump_list: Vec::new(),
+            jump_index: 0,
+            lsp_indexing: false,
+            lsp_deferred: Vec::new(),
+            vim: crate::vim::VimState::default(),
+            tree_drag: None,
+            editor_drag: false,
+            scrollbar_drag: None,
+            last_editor_click: None,
+            agent_attachment: None,
+            batch: None,
+            pending_batch_files: Vec::new(),
+            config: ClideConfig::default(),
+            hover_pane: None,
+            image_protocol: ImageProtocol::detect(),
+            keymap: Keymap::with_defaults(),
+            theme_name: "dark".to_string(),
+            read_only: false,
+            pending_chord: None,
+            pending_decrypt: None,
+            last_follow_poll: Instant::now(),
+            last_agents_poll: Instant::now(),
+            agents_config_mtime: fs::metadata(crate::agent::profile::agents_config_path())
+                .and_then(|meta| meta.modified())
+                .ok(),
+            last_recovery: Instant::now(),
+            last_autosave: Instant::now(),
+            stdin_buffer: None,
+            running_task: None,
+            root,
+        };
+        app.read_only = cli.read_only;
+        match crate::config::load_config(&app.root, cli.config.as_deref()) {
+            Ok(config) => app.config = config,
+            Err(err) => {
+                crate::logging::log(LogLevel::Error, &format!("config error: {err:#}"));
+                app.set_error(format!("config error: {err:#}"));
+            }
+        }
+        app.apply_config();
+        crate::agent::transcript::init(
+            app.config.agent.transcript_log.unwrap_or(false),
+            &app.root,
+        );
+        app.restore_session();
+        app.restore_conversation();
+        app.rag = crate::agent::rag::load(&app.root);
+        app.run_startup_hooks();
+        match cli.target {
+            Some(crate::cli::Target::File { path, line }) => {
+                let path = path.canonicalize().unwrap_or(path);
+                match app.open_path(&path) {
+                    Ok(()) => {
+                        if let (Some(line), Some(buffer)) = (line, app.editor.active_buffer_mut()) {
+                            buffer.goto_line(line.saturating_sub(1));
+                        }
+                    }
+                    Err(err) => app.set_error(format!("open failed: {err:#}")),
+                }
+            }
+            Some(crate::cli::Target::Stdin) => {
+                // Read stdin on its own thread so a FIFO that trickles
+                // output never blocks the UI; lines arrive as events.
+                app.stdin_buffer = Some(app.editor.open_untitled());
+                app.focus = Focus::Editor;
+                app.set_status("reading from stdin");
+                let tx = events_tx.clone();
+                std::thread::spawn(move || {
+                    use std::io::BufRead;
+                    for line in std::io::stdin().lock().lines() {
+                        let Ok(line) = line else { break };
+                        if tx.send(AppEvent::StdinLine(line)).is_err() {
+                            break;
+                        }
+                    }
+                });
+            }
+            _ => {}
+        }
+        let pending = crate::recovery::pending(&app.root);
+        if !pending.is_empty() {
+            app.overlay = Some(Overlay::RecoveryFound { files: pending });
+        }
+        app
+    }
+
+    /// Apply the `[language.<name>]` overrides for the active buffer's
+    /// language, so the status bar controls show the effective values.
+    /// Called whenever the active buffer changes.
+    pub fn refresh_language_prefs(&mut self) {
+        let Some(language) = self
+            .editor
+            .active_buffer()
+            .and_then(|buffer| buffer.language.clone())
+        else {
+            return;
+        };
+        if let Some(section) = self.config.languages.get(&language).copied() {
+            apply_editor_section(&mut self.editor.prefs, &section);
+        }
+    }
+
+    /// Apply config.toml preferences on top of the built-in defaults.
+    /// Session state restored afterwards takes precedence where the two
+    /// overlap (e.g. pane visibility).
+    fn apply_config(&mut self) {
+        apply_editor_section(&mut self.editor.prefs, &self.config.editor);
+        self.refresh_language_prefs();
+        if let Some(icons) = self.config.ui.icons.clone() {
+            match icons.as_str() {
+                "emoji" => self.tree.icon_set = IconSet::Emoji,
+                "unicode" => self.tree.icon_set = IconSet::Unicode,
+                "nerd" | "nerd-font" => self.tree.icon_set = IconSet::NerdFont,
+                other => self.set_status(format!("unknown icon set {other:?}")),
+            }
+        }
+        if let Some(show) = self.config.ui.show_hidden {
+            if show != self.tree.show_hidden {
+                self.tree.toggle_hidden();
+            }
+        }
+        if let Some(names) = self.config.ui.status_segments.clone() {
+            let (segments, unknown) = crate::ui::status::resolve(&names);
+            if !unknown.is_empty() {
+                self.set_warning(format!(
+                    "unknown status segment(s): {}",
+                    unknown.join(", ")
+                ));
+            }
+            self.status_segments = segments;
+        }
+        if let Some(sort) = self.config.ui.tree_sort.clone() {
+            match crate::workspace::SortMode::from_name(&sort) {
+                Some(mode) => {
+                    self.tree.sort_mode = mode;
+                    self.tree.refresh();
+                }
+                None => self.set_status(format!("unknown tree sort mode {sort:?}")),
+            }
+        }
+        if let Some(profile) = self.config.agent.default_profile.clone() {
+            if self.agent.select_profile(&profile) {
+                self.agent.profile_source = "config";
+            } else {
+                self.set_status(format!("unknown agent profile {profile:?}"));
+            }
+        }
+        // `.clide/settings.toml` pins win over every global default.
+        let settings = crate::agent::profile::load_workspace_settings(&self.root);
+        if let Some(profile) = &settings.agent.profile {
+            if self.agent.select_profile(profile) {
+                self.agent.profile_source = "workspace";
+            } else {
+                self.set_status(format!("unknown workspace agent profile {profile:?}"));
+            }
+        }
+        if let Some(model) = settings.agent.model {
+            let active = self.agent.active;
+            if let Some(profile) = self.agent.config.profiles.get_mut(active) {
+                if let crate::agent::profile::BackendConfig::HttpApi(http) = &mut profile.backend
+                {
+                    http.model = model;
+                }
+            }
+        }
+        self.terminal.configure(&self.config.terminal, &self.root);
+        for error in self.keymap.apply_config(&self.config.keys) {
+            self.set_status(error);
+        }
+        if let Some(name) = self.config.ui.theme.clone() {
+            self.set_theme(&name);
+        }
+    }
+
+    /// Load and activate a theme by name, remembering it for reloads.
+    pub fn set_theme(&mut self, name: &str) -> bool {
+        match crate::ui::theme::load_named(name) {
+            Ok(theme) => {
+                crate::ui::theme::set_current(theme);
+                self.theme_name = name.to_string();
+                true
+            }
+            Err(err) => {
+                self.set_error(format!("theme error: {err:#}"));
+                false
+            }
+        }
+    }
+
+    /// Restore the previous session of this workspace, if one was saved.
+    fn restore_session(&mut self) {
+        let Some(session) = crate::session::load(&self.root) else {
+            return;
+        };
+        self.apply_session(&session);
+    }
+
+    /// Adopt a session snapshot: layout, tree state, and open files.
+    /// Shared by startup restore and bundle import.
+    fn apply_session(&mut self, session: &crate::session::Session) {
+        self.layout.tree_ratio = session.layout.tree_ratio;
+        self.layout.agent_ratio = session.layout.agent_ratio;
+        self.layout.terminal_ratio = session.layout.terminal_ratio;
+        self.layout.show_tree = session.layout.show_tree;
+        self.layout.show_terminal = session.layout.show_terminal;
+        self.layout.show_agent = session.layout.show_agent;
+        self.layout.show_git = session.layout.show_git;
+        self.tree.show_hidden = session.show_hidden;
+        let expanded = session
+            .expanded_dirs
+            .iter()
+            .map(|dir| self.resolve_session_path(dir))
+            .collect();
+        self.tree.set_expanded(expanded);
+        for file in &session.open_files {
+            if let Some(encoding) = file.encoding.as_deref().and_then(Encoding::from_name) {
+                let path = self.resolve_session_path(&file.path);
+                self.encoding_overrides.insert(path, encoding);
+            }
+        }
+        for file in &session.open_files {
+            let path = self.resolve_session_path(&file.path);
+            if self.open_path(&path).is_err() {
+                continue;
+            }
+            if let Some(buffer) = self.editor.active_buffer_mut() {
+                buffer.cursor = Position {
+                    line: file.cursor_line,
+                    col: file.cursor_col,
+                };
+                buffer.clamp_cursor();
+                buffer.scroll_line = file.scroll_line.min(buffer.line_count().saturating_sub(1));
+            }
+        }
+        if !self.editor.buffers.is_empty() {
+            self.editor.active = session.active.min(self.editor.buffers.len() - 1);
+        }
+        // The process itself is gone; what comes back is the scrollback,
+        // input history, and working directory.
+        if let Some(saved) = session
+            .terminal
+            .as_ref()
+            .filter(|_| self.config.terminal.persist.unwrap_or(false))
+        {
+            self.terminal.lines = saved.lines.clone();
+            self.terminal.lines.push("[restored from previous session]".to_string());
+            self.terminal.history = saved.history.clone();
+            self.terminal.follow = true;
+            let cwd = self.resolve_session_path(&saved.cwd);
+            self.terminal.set_cwd(cwd);
+        }
+    }
+
+    fn resolve_session_path(&self, path: &Path) -> PathBuf {
+        if path.is_absolute() {
+            path.to_path_buf()
+        } else {
+            self.root.join(path)
+        }
+    }
+
+    /// Snapshot the current session to the workspace-local session file.
+    fn save_session(&self) {
+        let _ = crate::session::save(&self.root, &self.current_session());
+    }
+
+    /// The current open-files/layout/tree state as a session snapshot.
+    fn current_session(&self) -> crate::session::Session {
+        let open_files = self
+            .editor
+            .buffers
+            .iter()
+            .filter_map(|buffer| {
+                let path = buffer.path.as_ref()?;
+                Some(crate::session::SessionFile {
+                    path: path.strip_prefix(&self.root).unwrap_or(path).to_path_buf(),
+                    cursor_line: buffer.cursor.line,
+                    cursor_col: buffer.cursor.col,
+                    scroll_line: buffer.scroll_line,
+                    encoding: self
+                        .encoding_overrides
+                        .get(path)
+                        .map(|encoding| encoding.name().to_string()),
+                })
+            })
+            .collect();
+        crate::session::Session {
+            open_files,
+            active: self.editor.active,
+            layout: crate::session::SessionLayout {
+                tree_ratio: self.layout.tree_ratio,
+                agent_ratio: self.layout.agent_ratio,
+                terminal_ratio: self.layout.terminal_ratio,
+                show_tree: self.layout.show_tree,
+                show_terminal: self.layout.show_terminal,
+                show_agent: self.layout.show_agent,
+                show_git: self.layout.show_git,
+            },
+            expanded_dirs: self
+                .tree
+                .expanded_dirs()
+                .iter()
+                .map(|dir| dir.strip_prefix(&self.root).unwrap_or(dir).to_path_buf())
+                .collect(),
+            show_hidden: self.tree.show_hidden,
+            terminal: self.config.terminal.persist.unwrap_or(false).then(|| {
+                let tail = self.terminal.lines.len().saturating_sub(1_000);
+                let cwd = self.terminal.cwd();
+                crate::session::SessionTerminal {
+                    lines: self.terminal.lines[tail..].to_vec(),
+                    history: self.terminal.history.clone(),
+                    cwd: cwd.strip_prefix(&self.root).unwrap_or(cwd).to_path_buf(),
+                }
+            }),
+        }
+    }
+
+    /// Run the `[[startup-hook]]` entries from config.toml once the
+    /// workspace is loaded; a failing hook is reported but does not stop
+    /// the rest.
+    fn run_startup_hooks(&mut self) {
+        for hook in &self.config.startup_hooks.clone() {
+            if let Err(err) = self.run_startup_hook(hook) {
+                self.set_error(format!("startup hook failed: {err:#}"));
+            }
+        }
+    }
+
+    fn run_startup_hook(&mut self, hook: &StartupHook) -> Result<()> {
+        match hook {
+            StartupHook::Open { path } => self.open_path(&self.root.join(path)),
+            StartupHook::Run { command } => self.terminal.run_command(command),
+            StartupHook::Show { pane } => {
+                let focus = match pane.as_str() {
+                    "tree" => Focus::Tree,
+                    "editor" => Focus::Editor,
+                    "terminal" => Focus::Terminal,
+                    "agent" => Focus::Agent,
+                    "git" => Focus::Git,
+                    other => anyhow::bail!("unknown pane {other:?}"),
+                };
+                match focus {
+                    Focus::Tree => self.layout.show_tree = true,
+                    Focus::Terminal => self.layout.show_terminal = true,
+                    Focus::Agent => self.layout.show_agent = true,
+                    Focus::Git => self.layout.show_git = true,
+                    Focus::Editor => {}
+                }
+                self.focus = focus;
+                Ok(())
+            }
+        }
+    }
+
+    pub fn set_status(&mut self, message: impl Into<String>) {
+        self.notify.push(Severity::Info, message);
+    }
+
+    pub fn set_warning(&mut self, message: impl Into<String>) {
+        self.notify.push(Severity::Warning, message);
+    }
+
+    pub fn set_error(&mut self, message: impl Into<String>) {
+        self.notify.push(Severity::Error, message);
+    }
+
+    /// Whether the onboarding hint for `key` should be drawn over its
+    /// pane. The first frame the pane stops being empty dismisses the
+    /// hint for the rest of the session, so it does not reappear when
+    /// the pane is cleared again.
+    pub fn hint_visible(&mut self, key: &'static str, pane_empty: bool) -> bool {
+        if !pane_empty {
+            self.dismissed_hints.insert(key);
+        }
+        pane_empty && !self.dismissed_hints.contains(key)
+    }
+
+    /// Per-frame upkeep: expire toasts, reap the terminal child.
+    pub fn tick(&mut self) {
+        self.notify.tick();
+        let was_running = self.terminal.is_running();
+        let exit = self.terminal.poll_exit();
+        if was_running && !self.terminal.is_running() {
+            self.agent_terminal_capture = None;
+        }
+        if let Some(code) = exit {
+            if let Some(name) = self.running_task.take() {
+                match code {
+                    Some(0) => self.set_status(format!("task {name}: ok")),
+                    Some(code) => self.set_error(format!("task {name}: exit code {code}")),
+                    None => self.set_error(format!("task {name}: killed")),
+                }
+            }
+        }
+        self.poll_followed_file();
+        self.poll_agents_config();
+        for report in self.agent.poll_local_health() {
+            if report.recovered {
+                self.conversation
+                    .push(AgentPanelEntry::Info(report.message.clone()));
+                self.set_status(report.message);
+            } else {
+                self.conversation
+                    .push(AgentPanelEntry::Error(report.message.clone()));
+                self.set_error(report.message);
+            }
+        }
+        self.pump_batch();
+        self.autosave_tick();
+        self.recovery_tick();
+        if let (Some(ffm), Some((pane, since))) = (self.config.focus_follows_mouse, self.hover_pane)
+        {
+            if pane != self.focus
+                && since.elapsed() >= std::time::Duration::from_millis(ffm.delay_ms)
+            {
+                self.focus = pane;
+            }
+        }
+    }
+
+    /// Whether new files with this extension get a header automatically.
+    fn header_auto_applies(&self, path: &Path) -> bool {
+        path.extension()
+            .and_then(|e| e.to_str())
+            .is_some_and(|ext| {
+                self.config
+                    .header
+                    .auto_extensions
+                    .iter()
+                    .any(|e| e == ext)
+            })
+    }
+
+    /// Insert the configured header at the top of the active buffer, or
+    /// refresh an existing block (new dates) without duplicating it.
+    pub fn insert_file_header(&mut self) {
+        if self.read_only {
+            self.set_status("read-only mode");
+            return;
+        }
+        let header = self.config.header.clone();
+        let Some(buffer) = self.editor.active_buffer_mut() else {
+            return;
+        };
+        if buffer.is_read_only() {
+            self.set_status("buffer is read-only");
+            return;
+        }
+        let Some(path) = buffer.path.clone() else {
+            self.set_status("buffer has no file name");
+            return;
+        };
+        let Some(rendered) = crate::editor::header::render(&header, &path) else {
+            self.set_status("no header template for this file type");
+            return;
+        };
+        let ext = path.extension().and_then(|e| e.to_str()).unwrap_or("");
+        let prefix = crate::editor::header::comment_prefix(ext).unwrap_or("//");
+        let text = buffer.rope.to_string();
+        let existing = crate::editor::header::existing_header_len(&text, prefix, &header);
+        buffer.apply_header(&rendered, existing.unwrap_or(0));
+        self.set_status(if existing.is_some() {
+            "file header refreshed"
+        } else {
+            "file header inserted"
+        });
+    }
+
+    /// Re-decode the active buffer's on-disk bytes in the chosen
+    /// encoding and remember the choice for this file.
+    pub fn reopen_with_encoding(&mut self, encoding: Encoding) {
+        let Some(buffer) = self.editor.active_buffer() else {
+            return;
+        };
+        if buffer.dirty {
+            self.set_warning("buffer has unsaved changes: save or undo first");
+            return;
+        }
+        let Some(path) = buffer.path.clone() else {
+            self.set_status("buffer has no file name");
+            return;
+        };
+        let decoded = fs::read(&path)
+            .with_context(|| format!("failed to read {}", path.display()))
+            .and_then(|bytes| crate::editor::decode_with(&bytes, encoding));
+        match decoded {
+            Ok(text) => {
+                if let Some(buffer) = self.editor.active_buffer_mut() {
+                    buffer.reload_with(&text, encoding);
+                }
+                self.encoding_overrides.insert(path, encoding);
+                self.set_status(format!("reopened as {}", encoding.label()));
+            }
+            Err(err) => self.set_error(format!("reopen failed: {err:#}")),
+        }
+    }
+
+    /// Save every dirty named buffer once the configured autosave
+    /// interval has elapsed. Off unless `autosave-secs` is set.
+    fn autosave_tick(&mut self) {
+        let Some(secs) = self.config.editor.autosave_secs.filter(|&secs| secs > 0) else {
+            return;
+        };
+        if self.read_only || self.last_autosave.elapsed() < Duration::from_secs(secs) {
+            return;
+        }
+        self.last_autosave = Instant::now();
+        let mut saved = 0usize;
+        let cleanup = self.editor.save_cleanup();
+        for buffer in &mut self.editor.buffers {
+            if !buffer.dirty || buffer.huge {
+                continue;
+            }
+            let Some(path) = buffer.path.clone() else {
+                continue;
+            };
+            if Editor::write_to(buffer, &path, cleanup).is_ok() {
+                crate::recovery::remove_snapshot(&self.root, &path);
+                saved += 1;
+            }
+        }
+        if saved > 0 {
+            self.set_status(format!("autosaved {saved} buffer(s)"));
+            self.refresh_git();
+        }
+    }
+
+    /// Refresh crash-recovery snapshots for dirty buffers and drop the
+    /// ones for buffers that are clean again. Decrypted buffers are
+    /// excluded so their plaintext never lands on disk, huge ones for
+    /// size.
+    fn recovery_tick(&mut self) {
+        if self.last_recovery.elapsed() < RECOVERY_INTERVAL {
+            return;
+        }
+        self.last_recovery = Instant::now();
+        for buffer in &self.editor.buffers {
+            let Some(path) = &buffer.path else { continue };
+            if buffer.crypt.is_some() || buffer.huge {
+                continue;
+            }
+            if buffer.dirty {
+                let _ = crate::recovery::write_snapshot(&self.root, path, &buffer.rope.to_string());
+            } else {
+                crate::recovery::remove_snapshot(&self.root, path);
+            }
+        }
+    }
+
+    /// Restore crash-recovery snapshots: each file is opened (or an
+    /// untitled buffer created when the original is gone) and its text
+    /// replaced by the snapshot as a normal undoable edit, left dirty
+    /// for review.
+    pub fn restore_recovery(&mut self, files: &[(PathBuf, PathBuf)]) {
+        let mut restored = 0usize;
+        for (original, swap) in files {
+            let Ok(text) = fs::read_to_string(swap) else {
+                continue;
+            };
+            let _ = self.open_path(original);
+            if self.editor.buffer_for_path(original).is_none() {
+                self.editor.open_untitled();
+            }
+            if let Some(buffer) = self.editor.active_buffer_mut() {
+                buffer.replace_contents(&text);
+                restored += 1;
+            }
+        }
+        crate::recovery::discard_all(&self.root);
+        self.set_status(format!("restored {restored} unsaved buffer(s)"));
+    }
+
+    /// Re-read the followed buffer from disk at most twice a second,
+    /// pinning the cursor to the new end unless follow is paused.
+    fn poll_followed_file(&mut self) {
+        if self.last_follow_poll.elapsed() < Duration::from_millis(500) {
+            return;
+        }
+        self.last_follow_poll = Instant::now();
+        let Some(buffer) = self.editor.active_buffer_mut() else {
+            return;
+        };
+        if !buffer.follow {
+            return;
+        }
+        match buffer.refresh_from_disk() {
+            Ok(true) if !buffer.follow_paused => buffer.move_to_end(),
+            Ok(_) => {}
+            Err(err) => {
+                buffer.follow = false;
+                self.set_status(format!("follow stopped: {err:#}"));
+            }
+        }
+    }
+
+    /// Pick up edits to `agents.toml` without a restart: when its mtime
+    /// moves, re-read the profiles in place (the conversation stays) and
+    /// report what appeared or went away.
+    fn poll_agents_config(&mut self) {
+        if self.last_agents_poll.elapsed() < Duration::from_secs(2) {
+            return;
+        }
+        self.last_agents_poll = Instant::now();
+        let mtime = fs::metadata(crate::agent::profile::agents_config_path())
+            .and_then(|meta| meta.modified())
+            .ok();
+        if mtime == self.agents_config_mtime {
+            return;
+        }
+        self.agents_config_mtime = mtime;
+        let (added, removed) = self.agent.reload_config();
+        let mut detail = Vec::new();
+        if !added.is_empty() {
+            detail.push(format!("added {}", added.join(", ")));
+        }
+        if !removed.is_empty() {
+            detail.push(format!("removed {}", removed.join(", ")));
+        }
+        self.set_status(if detail.is_empty() {
+            "agents.toml reloaded".to_string()
+        } else {
+            format!("agents.toml reloaded ({})", detail.join("; "))
+        });
+    }
+
+    /// Copy into the internal register and mirror to the system clipboard
+    /// where a mechanism is available, reporting which one was used.
+    pub fn copy_to_clipboard(&mut self, text: String) {
+        let mechanism = crate::clipboard::copy_to_system(&text);
+        self.set_status(format!(
+            "copied {} chars via {}",
+            text.chars().count(),
+            mechanism.label()
+        ));
+        self.clipboard = text;
+    }
+
+    /// The image to draw inline: the most recent image entry, when the
+    /// terminal supports a graphics protocol and the agent panel is shown.
+    pub fn inline_image(&self) -> Option<&PathBuf> {
+        if self.image_protocol == ImageProtocol::None || !self.layout.show_agent {
+            return None;
+        }
+        self.conversation.entries.iter().rev().find_map(|entry| {
+            if let AgentPanelEntry::Image(path) = entry {
+                Some(path)
+            } else {
+                None
+            }
+        })
+    }
+
+    /// Track pointer movement for focus-follows-mouse; the pane under the
+    /// pointer takes focus once it has dwelled there long enough.
+    pub fn mouse_moved(&mut self, x: u16, y: u16) {
+        let Some(ffm) = self.config.focus_follows_mouse else {
+            return;
+        };
+        if self.overlay.is_some() {
+            self.hover_pane = None;
+            return;
+        }
+        let pane = self.layout.pane_at(x, y);
+        let followed = match pane {
+            Some(Focus::Editor) => ffm.editor,
+            Some(_) => ffm.side_panes,
+            None => false,
+        };
+        if !followed {
+            self.hover_pane = None;
+            return;
+        }
+        let pane = pane.expect("followed implies a pane");
+        match self.hover_pane {
+            Some((current, _)) if current == pane => {}
+            _ => self.hover_pane = Some((pane, Instant::now())),
+        }
+    }
+
+    /// Drain all pending background events.
+    pub fn drain_events(&mut self) {
+        while let Ok(event) = self.events_rx.try_recv() {
+            match event {
+                AppEvent::Agent(event) => self.on_agent_event(event),
+                AppEvent::Lsp(event) => self.on_lsp_event(event),
+                AppEvent::TerminalOutput(line) => {
+                    // Approved agent commands mirror their output into
+                    // the conversation while they run.
+                    if let Some(idx) = self.agent_terminal_capture {
+                        if let Some(AgentPanelEntry::ToolOutput { output, .. }) =
+                            self.conversation.entries.get_mut(idx)
+                        {
+                            output.push_str(&line);
+                            output.push('\n');
+                        }
+                    }
+                    self.terminal.push_output(line);
+                }
+                AppEvent::ModelList(result) => self.on_model_list(result),
+                AppEvent::StdinLine(line) => self.append_stdin_line(&line),
+                AppEvent::Task(event) => self.on_task_event(event),
+            }
+        }
+    }
+
+    /// Reduce a background-task event into app state.
+    fn on_task_event(&mut self, event: TaskEvent) {
+        match event {
+            TaskEvent::Progress { id, done, total } => {
+                let message = self.tasks.on_progress(id, done, total).map(|task| {
+                    let pct = (done * 100).checked_div(total).unwrap_or(100);
+                    format!("{}: {pct}%", task.label)
+                });
+                if let Some(message) = message {
+                    self.set_status(message);
+                }
+            }
+            TaskEvent::Done { id, outcome } => {
+                let Some(task) = self.tasks.finish(id) else {
+                    return;
+                };
+                match outcome {
+                    TaskOutcome::Cancelled => self.set_status(format!("{} cancelled", task.label)),
+                    TaskOutcome::FileLoaded { path, result } => self.on_file_loaded(path, result),
+                    TaskOutcome::FileSaved { path, result } => self.on_file_saved(path, result),
+                    TaskOutcome::GitRefreshed { branch, entries } => {
+                        self.git.adopt(branch, entries);
+                        if std::mem::take(&mut self.git_refresh_queued) {
+                            self.refresh_git();
+                        }
+                    }
+                    TaskOutcome::TreeScanned { entries } => {
+                        self.tree.adopt(entries);
+                        if std::mem::take(&mut self.tree_scan_queued) {
+                            self.refresh_tree();
+                        }
+                    }
+                    TaskOutcome::RagIndexed { index } => {
+                        if let Err(err) = crate::agent::rag::save(&self.root, &index) {
+                            self.set_error(format!("index save failed: {err:#}"));
+                        } else {
+                            self.set_status(format!(
+                                "workspace index built: {} chunks",
+                                index.chunks.len()
+                            ));
+                        }
+                        self.rag = Some(index);
+                    }
+                    TaskOutcome::CargoDiagnostics { command, result } => {
+                        self.on_cargo_diagnostics(&command, result);
+                    }
+                }
+            }
+        }
+    }
+
+    /// Re-run `git status` on a background task. A refresh requested
+    /// while one is in flight coalesces into a single follow-up run.
+    pub fn refresh_git(&mut self) {
+        if self.tasks.is_running(TaskKind::GitRefresh) {
+            self.git_refresh_queued = true;
+            return;
+        }
+        let root = self.git.root.clone();
+        self.tasks
+            .spawn(TaskKind::GitRefresh, "git status", &self.events_tx, move |ctx| {
+                if ctx.is_cancelled() {
+                    return TaskOutcome::Cancelled;
+                }
+                let (branch, entries) = GitPanel::scan(&root);
+                TaskOutcome::GitRefreshed { branch, entries }
+            });
+    }
+
+    /// Rescan the workspace tree on a background task, coalescing like
+    /// [`refresh_git`](App::refresh_git).
+    pub fn refresh_tree(&mut self) {
+        if self.tasks.is_running(TaskKind::TreeScan) {
+            self.tree_scan_queued = true;
+            return;
+        }
+        let root = self.tree.root.clone();
+        let show_hidden = self.tree.show_hidden;
+        let sort_mode = self.tree.sort_mode;
+        let expanded = self.tree.expanded_paths().to_vec();
+        self.tasks
+            .spawn(TaskKind::TreeScan, "workspace scan", &self.events_tx, move |ctx| {
+                if ctx.is_cancelled() {
+                    return TaskOutcome::Cancelled;
+                }
+                let entries = FileTree::scan(&root, show_hidden, sort_mode, &expanded);
+                TaskOutcome::TreeScanned { entries }
+            });
+    }
+
+    /// Chunk and embed the workspace on a background task. The finished
+    /// index replaces the live one and is persisted for the next start.
+    pub fn build_rag_index(&mut self) {
+        if self.tasks.is_running(TaskKind::RagIndex) {
+            self.set_status("workspace index build already running");
+            return;
+        }
+        let root = self.root.clone();
+        self.tasks
+            .spawn(TaskKind::RagIndex, "indexing workspace", &self.events_tx, move |ctx| {
+                let files = walk_files(&root, 5_000);
+                let total = files.len() as u64;
+                let mut chunks = Vec::new();
+                for (i, path) in files.iter().enumerate() {
+                    if ctx.is_cancelled() {
+                        return TaskOutcome::Cancelled;
+                    }
+                    chunks.extend(crate::agent::rag::chunk_file(&root, path));
+                    ctx.progress(i as u64 + 1, total);
+                }
+                TaskOutcome::RagIndexed {
+                    index: crate::agent::rag::WorkspaceIndex {
+                        built_at: chrono::Local::now().format("%Y-%m-%d %H:%M").to_string(),
+                        chunks,
+                    },
+                }
+            });
+        self.set_status("indexing workspace…");
+    }
+
+    /// Run `cargo check`/`cargo test` with `--message-format=json` on a
+    /// background task and feed the compiler messages into the
+    /// diagnostics map, so the Problems panel and gutter work without
+    /// an LSP server.
+    pub fn run_cargo_diagnostics(&mut self, subcommand: &str) {
+        if !self.root.join("Cargo.toml").is_file() {
+            self.set_status("no Cargo.toml in the workspace root");
+            return;
+        }
+        if self.tasks.is_running(TaskKind::CargoDiagnostics) {
+            self.set_status("a cargo run is already in progress");
+            return;
+        }
+        let root = self.root.clone();
+        let command = format!("cargo {subcommand}");
+        let subcommand = subcommand.to_string();
+        let label = command.clone();
+        self.set_status(format!("{command} running…"));
+        self.tasks.spawn(
+            TaskKind::CargoDiagnostics,
+            &label,
+            &self.events_tx,
+            move |ctx| {
+                if ctx.is_cancelled() {
+                    return TaskOutcome::Cancelled;
+                }
+                let result = std::process::Command::new("cargo")
+                    .arg(&subcommand)
+                    .arg("--message-format=json")
+                    .arg("--quiet")
+                    .current_dir(&root)
+                    .output()
+                    .context("failed to run cargo")
+                    .map(|output| {
+                        let stdout = String::from_utf8_lossy(&output.stdout);
+                        crate::tasks::cargo_diagnostics(&root, &stdout)
+                    });
+                TaskOutcome::CargoDiagnostics { command, result }
+            },
+        );
+    }
+
+    /// Fold a finished cargo run into the diagnostics map: files the
+    /// previous run flagged are cleared first so fixed errors disappear.
+    fn on_cargo_diagnostics(
+        &mut self,
+        command: &str,
+        result: anyhow::Result<HashMap<PathBuf, Vec<crate::lsp::types::Diagnostic>>>,
+    ) {
+        let diagnostics = match result {
+            Ok(diagnostics) => diagnostics,
+            Err(err) => {
+                self.set_error(format!("{command}: {err:#}"));
+                return;
+            }
+        };
+        for path in std::mem::take(&mut self.cargo_diagnostic_paths) {
+            if !diagnostics.contains_key(&path) {
+                self.diagnostics.remove(&path);
+            }
+        }
+        let errors: usize = diagnostics
+            .values()
+            .flatten()
+            .filter(|d| d.severity == crate::lsp::types::DiagnosticSeverity::Error)
+            .count();
+        let warnings: usize = diagnostics.values().flatten().count() - errors;
+        self.cargo_diagnostic_paths = diagnostics.keys().cloned().collect();
+        self.diagnostics.extend(diagnostics);
+        if errors == 0 && warnings == 0 {
+            self.set_status(format!("{command}: no diagnostics"));
+        } else {
+            self.set_status(format!(
+                "{command}: {errors} error(s), {warnings} warning(s)"
+            ));
+        }
+    }
+
+    /// Append one line of piped input to the stdin buffer. Not an edit:
+    /// the buffer only becomes dirty once the user touches it.
+    fn append_stdin_line(&mut self, line: &str) {
+        let Some(buffer) = self
+            .stdin_buffer
+            .and_then(|idx| self.editor.buffers.get_mut(idx))
+        else {
+            return;
+        };
+        let end = buffer.rope.len_chars();
+        buffer.rope.insert(end, &format!("{line}\n"));
+        buffer.version += 1;
+    }
+
+    fn on_agent_event(&mut self, event: AgentEvent) {
+        // Batch replies are collected whole; drop their stream chunks.
+        if matches!(&event, AgentEvent::ResponseChunk { .. })
+            && self.batch.as_ref().is_some_and(|run| run.in_flight.is_some())
+        {
+            return;
+        }
+        // Replies to an in-flight batch item feed the review list rather
+        // than the conversation; tool writes still go through normally.
+        let batch_reply = match (&self.batch, &event) {
+            (Some(run), AgentEvent::Response { .. } | AgentEvent::Error(_)) => run.in_flight,
+            _ => None,
+        };
+        if let Some(idx) = batch_reply {
+            self.agent.busy = false;
+            match event {
+                AgentEvent::Response { text, .. } => {
+                    let run = self.batch.as_mut().expect("checked above");
+                    let path = run.items[idx].path.clone();
+                    let proposed = crate::agent::batch::extract_contents(&text);
+                    let before = fs::read_to_string(&path).unwrap_or_default();
+                    let diff = unified_diff(&before, &proposed);
+                    run.items[idx].state = ItemState::Ready { proposed };
+                    run.in_flight = None;
+                    let summary = run.summary();
+                    self.conversation.push(AgentPanelEntry::Diff { path, diff });
+                    self.set_status(summary);
+                }
+                AgentEvent::Error(message) => {
+                    let run = self.batch.as_mut().expect("checked above");
+                    let path = run.items[idx].path.clone();
+                    run.items[idx].state = ItemState::Failed;
+                    run.in_flight = None;
+                    self.conversation.push(AgentPanelEntry::Error(format!(
+                        "batch {}: {message}",
+                        path.display()
+                    )));
+                }
+                AgentEvent::ResponseChunk { .. }
+                | AgentEvent::ToolWrite { .. }
+                | AgentEvent::ToolCalls { .. } => {
+                    unreachable!("filtered above")
+                }
+            }
+            return;
+        }
+        match event {
+            AgentEvent::ResponseChunk { profile, text } => {
+                // Parked tabs (and commit drafts) get the full reply at
+                // the end instead.
+                if self.awaiting_commit_message
+                    || self.agent.active_profile().map(|p| p.name.as_str())
+                        != Some(profile.as_str())
+                {
+                    return;
+                }
+                let idx = match self.streaming_entry {
+                    Some(idx) => idx,
+                    None => {
+                        self.conversation
+                            .push(AgentPanelEntry::Info(format!("response from {profile}")));
+                        self.conversation
+                            .push(AgentPanelEntry::Response(String::new()));
+                        let idx = self.conversation.entries.len() - 1;
+                        self.streaming_entry = Some(idx);
+                        idx
+                    }
+                };
+                if let Some(AgentPanelEntry::Response(body)) =
+                    self.conversation.entries.get_mut(idx)
+                {
+                    body.push_str(&text);
+                }
+            }
+            AgentEvent::Response { profile, text } => {
+                self.agent.busy = false;
+                crate::agent::transcript::record(&profile, "response", &text);
+                self.agent_tokens_out += crate::agent::tokens::estimate(&text);
+                // A drafted commit message pre-fills the commit prompt
+                // instead of joining the conversation.
+                if self.awaiting_commit_message {
+                    self.awaiting_commit_message = false;
+                    self.overlay = Some(Overlay::Prompt {
+                        action: PromptAction::CommitMessage,
+                        input: strip_code_fences(&text).trim().to_string(),
+                    });
+                    self.set_status("edit the draft; Enter commits, Ctrl+G redrafts");
+                    return;
+                }
+                // A reply for a profile parked in another tab lands there.
+                if self.agent.active_profile().map(|p| p.name.as_str())
+                    != Some(profile.as_str())
+                {
+                    if let Some(parked) = self.parked_conversations.get_mut(&profile) {
+                        parked.push(AgentPanelEntry::Info(format!("response from {profile}")));
+                        parked.push(AgentPanelEntry::Response(text));
+                        self.set_status(format!("{profile} replied in its tab"));
+                        return;
+                    }
+                }
+                let refs = crate::ui::images::image_refs(&text);
+                let commands = crate::ui::markdown::shell_blocks(&text);
+                // A streamed reply already has its entry; swap in the
+                // final text instead of appending a duplicate.
+                match self.streaming_entry.take() {
+                    Some(idx) => {
+                        if let Some(entry) = self.conversation.entries.get_mut(idx) {
+                            *entry = AgentPanelEntry::Response(text);
+                        }
+                    }
+                    None => {
+                        self.conversation
+                            .push(AgentPanelEntry::Info(format!("response from {profile}")));
+                        self.conversation.push(AgentPanelEntry::Response(text));
+                    }
+                }
+                for path in refs {
+                    let path = if path.is_absolute() {
+                        path
+                    } else {
+                        self.root.join(path)
+                    };
+                    if !path.is_file() {
+                        continue;
+                    }
+                    if self.image_protocol.supports(&path) {
+                        self.conversation.push(AgentPanelEntry::Image(path));
+                    } else {
+                        self.conversation.push(AgentPanelEntry::Info(format!(
+                            "image referenced: {}",
+                            path.display()
+                        )));
+                    }
+                }
+                // Proposed shell commands wait for approval before they
+                // run in the terminal pane.
+                if !commands.is_empty() && self.overlay.is_none() {
+                    self.overlay = Some(Overlay::CommandApproval { commands });
+                }
+            }
+            AgentEvent::ToolCalls {
+                profile,
+                transcript,
+                calls,
+                text,
+                round,
+            } => self.on_tool_calls(profile, transcript, calls, text, round),
+            AgentEvent::ToolWrite { path, content } => self.on_tool_write(path, content),
+            AgentEvent::Error(message) => {
+                self.agent.busy = false;
+                self.streaming_entry = None;
+                self.awaiting_commit_message = false;
+                crate::agent::transcript::record("agent", "error", &message);
+                crate::logging::log(LogLevel::Warn, &format!("agent error: {message}"));
+                self.set_error(format!("agent: {message}"));
+                self.conversation.push(AgentPanelEntry::Error(message));
+            }
+        }
+    }
+
+    /// Apply a tool's file write, capturing a before/after snapshot and a
+    /// reviewable diff entry so headless changes can be audited.
+    fn on_tool_write(&mut self, path: PathBuf, content: String) {
+        if let Err(message) = self.apply_tool_write(path, content) {
+            self.conversation.push(AgentPanelEntry::Error(message));
+        }
+    }
+
+    /// Shared write path for [`AgentEvent::ToolWrite`] and the built-in
+    /// `write_file` tool: open-buffer guard, snapshot, diff entry. Returns
+    /// a one-line summary, or the failure as a message.
+    fn apply_tool_write(
+        &mut self,
+        path: PathBuf,
+        content: String,
+    ) -> std::result::Result<String, String> {
+        let path = if path.is_absolute() {
+            path
+        } else {
+            self.root.join(path)
+        };
+        if let Some(idx) = self.editor.buffer_for_path(&path) {
+            if self.editor.buffers[idx].dirty {
+                // The buffer and the tool's version have diverged: stage
+                // the write and let the user pick a side instead of
+                // silently clobbering either.
+                return Ok(self.stage_tool_conflict(path, content));
+            }
+            // Open but clean: write through and reload the buffer so it
+            // stays in step with disk.
+            return self.write_through_open_buffer(idx, path, content);
+        }
+        let before = fs::read_to_string(&path).ok();
+        if let Err(err) = fs::write(&path, &content) {
+            return Err(format!("tool write to {} failed: {err}", path.display()));
+        }
+        let diff = unified_diff(before.as_deref().unwrap_or(""), &content);
+        self.conversation.push(AgentPanelEntry::Diff {
+            path: path.clone(),
+            diff,
+        });
+        let profile = self
+            .agent
+            .active_profile()
+            .map(|p| p.name.clone())
+            .unwrap_or_default();
+        self.agent_stats.record(&profile, PatchOutcome::Accepted);
+        let summary = format!("wrote {} bytes to {}", content.len(), path.display());
+        self.tool_writes.push(ToolWriteRecord {
+            path,
+            before,
+            profile,
+            edited: false,
+        });
+        self.refresh_tree();
+        self.refresh_git();
+        Ok(summary)
+    }
+
+    /// Stage a conflicting tool write and open the merge-choice overlay.
+    /// Returns the result string reported back to the model.
+    fn stage_tool_conflict(&mut self, path: PathBuf, content: String) -> String {
+        let buffer_text = self
+            .editor
+            .buffer_for_path(&path)
+            .map(|idx| self.editor.buffers[idx].rope.to_string())
+            .unwrap_or_default();
+        let diff = unified_diff(&buffer_text, &content);
+        let result = format!(
+            "conflict: {} has unsaved changes in the editor; the write was staged for user review",
+            path.display()
+        );
+        self.conversation.push(AgentPanelEntry::Info(format!(
+            "tool write to {} conflicts with unsaved changes; staged for review",
+            path.display()
+        )));
+        self.pending_tool_patches.push((path.clone(), content));
+        self.overlay = Some(Overlay::ToolWriteConflict { path, diff });
+        result
+    }
+
+    /// Apply a tool write to a file that is open but clean: disk and
+    /// buffer move together, so no divergence is possible.
+    fn write_through_open_buffer(
+        &mut self,
+        idx: usize,
+        path: PathBuf,
+        content: String,
+    ) -> std::result::Result<String, String> {
+        let before = fs::read_to_string(&path).ok();
+        if let Err(err) = fs::write(&path, &content) {
+            return Err(format!("tool write to {} failed: {err}", path.display()));
+        }
+        let buffer = &mut self.editor.buffers[idx];
+        let encoding = buffer.encoding;
+        buffer.reload_with(&content, encoding);
+        let version = buffer.version;
+        if let Some(lsp) = &mut self.lsp {
+            let _ = lsp.did_change(&path, version, &content);
+        }
+        let diff = unified_diff(before.as_deref().unwrap_or(""), &content);
+        self.conversation.push(AgentPanelEntry::Diff {
+            path: path.clone(),
+            diff,
+        });
+        let profile = self
+            .agent
+            .active_profile()
+            .map(|p| p.name.clone())
+            .unwrap_or_default();
+        self.agent_stats.record(&profile, PatchOutcome::Accepted);
+        let summary = format!(
+            "wrote {} bytes to {} (open buffer reloaded)",
+            content.len(),
+            path.display()
+        );
+        self.tool_writes.push(ToolWriteRecord {
+            path,
+            before,
+            profile,
+            edited: false,
+        });
+        self.refresh_git();
+        Ok(summary)
+    }
+
+    /// Resolve a staged conflicting write: keep the buffer's version or
+    /// take the agent's (overwriting the unsaved edits).
+    pub fn resolve_tool_conflict(&mut self, path: &Path, take_agent: bool) {
+        let Some(pos) = self
+            .pending_tool_patches
+            .iter()
+            .position(|(p, _)| p == path)
+        else {
+            self.set_status("staged patch is gone");
+            return;
+        };
+        let (path, content) = self.pending_tool_patches.remove(pos);
+        if !take_agent {
+            self.conversation.push(AgentPanelEntry::Info(format!(
+                "kept the buffer's version of {}",
+                path.display()
+            )));
+            self.set_status("agent write discarded");
+            return;
+        }
+        match self.editor.buffer_for_path(&path) {
+            Some(idx) => match self.write_through_open_buffer(idx, path.clone(), content) {
+                Ok(summary) => self.set_status(summary),
+                Err(message) => self.set_error(message),
+            },
+            // Buffer closed since staging; a plain write is safe now.
+            None => match self.apply_tool_write(path, content) {
+                Ok(summary) => self.set_status(summary),
+                Err(message) => self.set_error(message),
+            },
+        }
+    }
+
+    /// Reopen the merge-choice overlay for the oldest staged write.
+    pub fn review_tool_conflict(&mut self) {
+        let Some((path, content)) = self.pending_tool_patches.first().cloned() else {
+            self.set_status("no staged tool writes");
+            return;
+        };
+        let buffer_text = self
+            .editor
+            .buffer_for_path(&path)
+            .map(|idx| self.editor.buffers[idx].rope.to_string())
+            .or_else(|| fs::read_to_string(&path).ok())
+            .unwrap_or_default();
+        let diff = unified_diff(&buffer_text, &content);
+        self.overlay = Some(Overlay::ToolWriteConflict { path, diff });
+    }
+
+    /// Fold a model's tool-call turn into the conversation: run read-only
+    /// calls immediately, hold mutating ones behind the approval overlay.
+    fn on_tool_calls(
+        &mut self,
+        profile: String,
+        transcript: serde_json::Value,
+        calls: Vec<crate::agent::tools::ToolCall>,
+        text: Option<String>,
+        round: usize,
+    ) {
+        if let Some(text) = text {
+            self.agent_tokens_out += crate::agent::tokens::estimate(&text);
+            self.conversation
+                .push(AgentPanelEntry::Info(format!("response from {profile}")));
+            self.conversation.push(AgentPanelEntry::Response(text));
+        }
+        for call in &calls {
+            let args = call.args.to_string();
+            crate::agent::transcript::record(
+                &profile,
+                "response",
+                &format!("tool call: {} {args}", call.name),
+            );
+            self.conversation.push(AgentPanelEntry::Info(format!(
+                "tool call: {} {}",
+                call.name,
+                truncate(&args, 120)
+            )));
+        }
+        if round > crate::agent::tools::MAX_ROUNDS {
+            self.agent.busy = false;
+            self.conversation.push(AgentPanelEntry::Error(format!(
+                "tool loop stopped after {} rounds",
+                crate::agent::tools::MAX_ROUNDS
+            )));
+            return;
+        }
+        if calls.iter().any(|call| self.tool_needs_approval(&call.name)) {
+            self.overlay = Some(Overlay::ToolApproval {
+                transcript,
+                calls,
+                round,
+            });
+            return;
+        }
+        self.finish_tool_calls(transcript, calls, round, true);
+    }
+
+    /// Run the calls (mutating ones only when approved), record each
+    /// output in the conversation, and resume the exchange. Denied calls
+    /// report the refusal back so the model can adjust.
+    pub fn finish_tool_calls(
+        &mut self,
+        transcript: serde_json::Value,
+        calls: Vec<crate::agent::tools::ToolCall>,
+        round: usize,
+        approved: bool,
+    ) {
+        // A reply that writes several files goes through the per-file
+        // review overlay instead of landing write-by-write.
+        let write_count = if approved {
+            calls.iter().filter(|call| call.name == "write_file").count()
+        } else {
+            0
+        };
+        let mut staged: Vec<crate::ui::overlay::ReviewFile> = Vec::new();
+        let mut results = Vec::new();
+        for call in calls {
+            let output = if self.tool_needs_approval(&call.name) && !approved {
+                "denied by user".to_string()
+            } else if write_count >= 2 && call.name == "write_file" {
+                match self.stage_review_file(&call, &mut staged) {
+                    Ok(summary) => summary,
+                    Err(message) => message,
+                }
+            } else {
+                self.run_tool_call(&call)
+            };
+            self.conversation.push(AgentPanelEntry::ToolOutput {
+                name: call.name.clone(),
+                output: output.clone(),
+            });
+            results.push((call, output));
+        }
+        if !staged.is_empty() {
+            self.conversation.push(AgentPanelEntry::Info(format!(
+                "agent proposed edits to {} files; review staged",
+                staged.len()
+            )));
+            self.overlay = Some(Overlay::MultiFileReview {
+                files: staged,
+                selected: 0,
+            });
+        }
+        if let Err(err) = self.agent.resume_tools(transcript, results, round) {
+            self.agent.busy = false;
+            self.set_error(format!("agent: {err:#}"));
+            self.conversation
+                .push(AgentPanelEntry::Error(format!("{err:#}")));
+        }
+    }
+
+    /// Stage one `write_file` call of a multi-file edit for the review
+    /// overlay, diffed against the open buffer (or disk). Returns the
+    /// result string reported back to the model.
+    fn stage_review_file(
+        &mut self,
+        call: &crate::agent::tools::ToolCall,
+        staged: &mut Vec<crate::ui::overlay::ReviewFile>,
+    ) -> std::result::Result<String, String> {
+        let path = call.args.get("path").and_then(|v| v.as_str());
+        let content = call.args.get("content").and_then(|v| v.as_str());
+        let (Some(path), Some(content)) = (path, content) else {
+            return Err("error: write_file requires `path` and `content`".to_string());
+        };
+        let abs = crate::agent::tools::resolve(&self.root, path)?;
+        let path = PathBuf::from(path);
+        let before = self
+            .editor
+            .buffer_for_path(&abs)
+            .map(|idx| self.editor.buffers[idx].rope.to_string())
+            .or_else(|| fs::read_to_string(&abs).ok())
+            .unwrap_or_default();
+        staged.push(crate::ui::overlay::ReviewFile {
+            path,
+            content: content.to_string(),
+            diff: unified_diff(&before, content),
+            accepted: true,
+        });
+        Ok(format!(
+            "staged {} for multi-file review; the user will apply or reject it",
+            abs.display()
+        ))
+    }
+
+    /// Apply the outcome of the multi-file review: accepted files go
+    /// through the usual tool-write path (snapshot, diff entry, reload),
+    /// rejected ones count against the profile's patch stats.
+    pub fn finish_multi_file_review(&mut self, files: Vec<crate::ui::overlay::ReviewFile>) {
+        let mut applied = 0;
+        let mut rejected = 0;
+        for file in files {
+            if file.accepted {
+                match self.apply_tool_write(file.path, file.content) {
+                    Ok(_) => applied += 1,
+                    Err(message) => self
+                        .conversation
+                        .push(AgentPanelEntry::Error(message)),
+                }
+            } else {
+                rejected += 1;
+                let profile = self
+                    .agent
+                    .active_profile()
+                    .map(|p| p.name.clone())
+                    .unwrap_or_default();
+                self.agent_stats.record(&profile, PatchOutcome::Rejected);
+            }
+        }
+        self.set_status(format!("applied {applied} file(s), rejected {rejected}"));
+    }
+
+    /// Whether a tool call waits for the approval overlay: the mutating
+    /// built-ins, and every MCP tool (a server can do anything).
+    fn tool_needs_approval(&self, name: &str) -> bool {
+        crate::agent::tools::is_mutating(name) || self.agent.mcp.owns(name)
+    }
+
+    /// Dispatch one approved tool call. `write_file` routes through
+    /// [`Self::apply_tool_write`] so the guard and review trail apply;
+    /// qualified MCP names go to the server that owns them.
+    fn run_tool_call(&mut self, call: &crate::agent::tools::ToolCall) -> String {
+        if self.agent.mcp.owns(&call.name) {
+            return match self.agent.mcp.call(&call.name, &call.args) {
+                Ok(output) => output,
+                Err(err) => format!("error: {err:#}"),
+            };
+        }
+        if call.name == "write_file" {
+            let path = call.args.get("path").and_then(|v| v.as_str());
+            let content = call.args.get("content").and_then(|v| v.as_str());
+            let (Some(path), Some(content)) = (path, content) else {
+                return "error: write_file requires `path` and `content`".to_string();
+            };
+            // The model supplies the path: confine it to the workspace
+            // just like the read-only tools do.
+            let abs = match crate::agent::tools::resolve(&self.root, path) {
+                Ok(abs) => abs,
+                Err(err) => return err,
+            };
+            return match self.apply_tool_write(abs, content.to_string()) {
+                Ok(summary) => summary,
+                Err(message) => format!("error: {message}"),
+            };
+        }
+        crate::agent::tools::execute(&self.root, call)
+    }
+
+    /// Undo the most recent tool write from its snapshot.
+    pub fn revert_last_tool_write(&mut self) {
+        let Some(record) = self.tool_writes.pop() else {
+            self.set_status("no tool writes to revert");
+            return;
+        };
+        let result = match &record.before {
+            Some(before) => fs::write(&record.path, before),
+            None => fs::remove_file(&record.path),
+        };
+        match result {
+            Ok(()) => {
+                self.agent_stats
+                    .reclassify_accepted(&record.profile, PatchOutcome::Rejected);
+                self.conversation.push(AgentPanelEntry::Info(format!(
+                    "reverted tool write to {}",
+                    record.path.display()
+                )));
+                self.refresh_tree();
+                self.refresh_git();
+            }
+            Err(err) => self.set_error(format!("revert failed: {err}")),
+        }
+    }
+
+    fn on_lsp_event(&mut self, event: LspEvent) {
+        match event {
+            LspEvent::Diagnostics { path, diagnostics } => {
+                self.diagnostics.insert(path, diagnostics);
+            }
+            LspEvent::Definition(locations) => {
+                let targets: Vec<(PathBuf, Position)> = locations
+                    .iter()
+                    .filter_map(|location| {
+                        uri_to_path(&location.uri).map(|path| {
+                            (
+                                path,
+                                Position {
+                                    line: location.range.start.line as usize,
+                                    col: location.range.start.character as usize,
+                                },
+                            )
+                        })
+                    })
+                    .collect();
+                match targets.len() {
+                    0 => self.set_error("definition location could not be resolved"),
+                    1 => {
+                        let (path, pos) = targets.into_iter().next().expect("len checked");
+                        self.push_jump();
+                        self.goto_location(&path, pos);
+                    }
+                    _ => {
+                        let items = targets
+                            .into_iter()
+                            .map(|(path, pos)| {
+                                let preview = preview_line(&path, pos.line);
+                                (path, pos, preview)
+                            })
+                            .collect();
+                        self.overlay = Some(Overlay::DefinitionPicker { items, selected: 0 });
+                    }
+                }
+            }
+            LspEvent::Hover(text) => {
+                self.overlay = Some(Overlay::Hover { text });
+            }
+            LspEvent::RenameEdit(edit) => self.preview_workspace_edit("Rename Symbol", edit),
+            LspEvent::DocumentSymbols { path, symbols } => {
+                self.symbols.insert(path, symbols);
+            }
+            LspEvent::Indexing { active, message } => {
+                let was_indexing = self.lsp_indexing;
+                self.lsp_indexing = active;
+                if active && !was_indexing {
+                    if let Some(message) = message {
+                        self.set_status(format!("LSP: {message}…"));
+                    }
+                } else if !active && was_indexing {
+                    let queued = self.lsp_deferred.len();
+                    if queued > 0 {
+                        self.set_status(format!(
+                            "LSP indexing finished; running {queued} queued request(s)"
+                        ));
+                    }
+                    self.flush_deferred_lsp();
+                }
+            }
+            LspEvent::ServerExited => {
+                self.lsp = None;
+                crate::logging::log(LogLevel::Warn, "language server exited");
+                self.set_warning("language server exited");
+            }
+        }
+    }
+
+    /// Open a file into the editor and tell the language server about it.
+    pub fn open_path(&mut self, path: &Path) -> Result<()> {
+        let already_open = self.editor.buffer_for_path(path).is_some();
+        if !already_open {
+            if let Some(kind) = CryptKind::from_path(path) {
+                self.pending_decrypt = Some((path.to_path_buf(), kind));
+                self.overlay = Some(Overlay::Prompt {
+                    action: PromptAction::DecryptSecret,
+                    input: String::new(),
+                });
+                self.set_status(format!("encrypted file: enter {}", kind.secret_label()));
+                return Ok(());
+            }
+            let size = fs::metadata(path).map(|meta| meta.len()).unwrap_or(0);
+            if size >= ASYNC_OPEN_BYTES {
+                self.spawn_file_load(path, size);
+                return Ok(());
+            }
+        }
+        self.editor.open_file(path)?;
+        if !already_open {
+            if let Some(&encoding) = self.encoding_overrides.get(path) {
+                if self
+                    .editor
+                    .active_buffer()
+                    .is_some_and(|buffer| buffer.encoding != encoding)
+                {
+                    self.reopen_with_encoding(encoding);
+                }
+            }
+            if let (Some(lsp), Some(buffer)) = (&mut self.lsp, self.editor.active_buffer()) {
+                let language = buffer.language.clone().unwrap_or_default();
+                let _ = lsp.did_open(path, &language, &buffer.rope.to_string());
+                let _ = lsp.document_symbols(path);
+            }
+            if let Some(buffer) = self.editor.active_buffer() {
+                if crate::ui::ansi::contains_ansi(&buffer.rope.to_string()) {
+                    self.overlay = Some(Overlay::AnsiDetected);
+                }
+            }
+        }
+        if !already_open && file_not_writable(path) {
+            if let Some(buffer) = self.editor.active_buffer_mut() {
+                buffer.read_only = true;
+            }
+            self.set_status(format!(
+                "{} is not writable; opened read-only",
+                path.display()
+            ));
+        }
+        self.focus = Focus::Editor;
+        self.refresh_language_prefs();
+        Ok(())
+    }
+
+    /// Read and decode a file on a background thread so a multi-hundred-MB
+    /// open never freezes the UI; progress and the result come back as
+    /// app events.
+    fn spawn_file_load(&mut self, path: &Path, total: u64) {
+        if !self.loading_files.insert(path.to_path_buf()) {
+            return;
+        }
+        self.set_status(format!("loading {}…", path.display()));
+        let path = path.to_path_buf();
+        let name = path
+            .file_name()
+            .map(|name| name.to_string_lossy().into_owned())
+            .unwrap_or_else(|| path.display().to_string());
+        self.tasks.spawn(
+            TaskKind::FileLoad,
+            format!("loading {name}"),
+            &self.events_tx,
+            move |ctx| {
+                let result = load_file(&path, total, ctx);
+                TaskOutcome::FileLoaded { path, result }
+            },
+        );
+    }
+
+    /// Adopt a finished background load. Large files skip LSP
+    /// registration, and those past the huge threshold open read-only.
+    fn on_file_loaded(&mut self, path: PathBuf, result: Result<Option<(String, Encoding)>>) {
+        self.loading_files.remove(&path);
+        match result {
+            Ok(None) => self.set_status(format!("cancelled loading {}", path.display())),
+            Ok(Some((contents, encoding))) => {
+                let huge = contents.len() as u64 >= HUGE_FILE_BYTES;
+                self.editor.open_loaded(&path, &contents, encoding);
+                if let Some(buffer) = self.editor.active_buffer_mut() {
+                    buffer.huge = huge;
+                    buffer.read_only = file_not_writable(&path);
+                }
+                self.focus = Focus::Editor;
+                self.refresh_language_prefs();
+                self.set_status(if huge {
+                    format!("opened {} (huge file, read-only)", path.display())
+                } else {
+                    format!("opened {}", path.display())
+                });
+            }
+            Err(err) => self.set_error(format!("open failed: {err:#}")),
+        }
+    }
+
+    pub fn save_active(&mut self) {
+        if self.read_only {
+            self.set_status("read-only mode: not saving");
+            return;
+        }
+        if self
+            .editor
+            .active_buffer()
+            .is_some_and(|buffer| buffer.read_only)
+        {
+            self.set_status("buffer is read-only (Editor: Toggle Read-Only to unlock)");
+            return;
+        }
+        // Buffers past the async-open threshold write on a background
+        // task so the UI stays responsive; encrypted buffers keep the
+        // synchronous path since they carry their secret.
+        let big = self.editor.active_buffer().is_some_and(|buffer| {
+            buffer.path.is_some()
+                && buffer.crypt.is_none()
+                && buffer.rope.len_bytes() as u64 >= ASYNC_OPEN_BYTES
+        });
+        if big {
+            let cleanup = self.editor.save_cleanup();
+            let (path, contents, encoding) = {
+                let buffer = self.editor.active_buffer_mut().unwrap();
+                buffer.apply_save_cleanup(cleanup);
+                buffer.dirty = false;
+                (
+                    buffer.path.clone().unwrap(),
+                    buffer.contents_for_disk(),
+                    buffer.encoding,
+                )
+            };
+            self.set_status(format!("saving {}…", path.display()));
+            let name = path
+                .file_name()
+                .map(|name| name.to_string_lossy().into_owned())
+                .unwrap_or_else(|| path.display().to_string());
+            self.tasks.spawn(
+                TaskKind::FileSave,
+                format!("saving {name}"),
+                &self.events_tx,
+                move |_ctx| {
+                    let result = write_contents(&path, &contents, encoding);
+                    TaskOutcome::FileSaved {
+                        path: path.clone(),
+                        result,
+                    }
+                },
+            );
+            return;
+        }
+        match self.editor.save_active() {
+            Ok(Some(path)) => {
+                self.after_save(&path);
+                self.set_status(format!("saved {}", path.display()));
+            }
+            Ok(None) => self.set_status("buffer has no file name"),
+            Err(err) => self.set_error(format!("save failed: {err:#}")),
+        }
+    }
+
+    /// Bookkeeping shared by the sync and background save paths.
+    fn after_save(&mut self, path: &Path) {
+        // Saving over a tool-written file counts the patch as
+        // hand-edited rather than accepted as-is.
+        if let Some(record) = self
+            .tool_writes
+            .iter_mut()
+            .find(|r| r.path == *path && !r.edited)
+        {
+            record.edited = true;
+            let profile = record.profile.clone();
+            self.agent_stats
+                .reclassify_accepted(&profile, PatchOutcome::Edited);
+        }
+        if let Some(lsp) = &mut self.lsp {
+            let _ = lsp.did_save(path);
+            let _ = lsp.document_symbols(path);
+        }
+        self.refresh_git();
+    }
+
+    /// Adopt a finished background save: a failure marks the buffer
+    /// dirty again so the unsaved state is visible.
+    fn on_file_saved(&mut self, path: PathBuf, result: Result<()>) {
+        match result {
+            Ok(()) => {
+                self.after_save(&path);
+                self.set_status(format!("saved {}", path.display()));
+            }
+            Err(err) => {
+                if let Some(buffer) = self
+                    .editor
+                    .buffers
+                    .iter_mut()
+                    .find(|buffer| buffer.path.as_deref() == Some(path.as_path()))
+                {
+                    buffer.dirty = true;
+                }
+                self.set_error(format!("save failed: {err:#}"));
+            }
+        }
+    }
+
+    /// Paste the clipboard into the active buffer, diverting through a
+    /// confirmation overlay when the content is suspiciously large.
+    pub fn request_paste(&mut self) {
+        let text = self.clipboard.clone();
+        if text.is_empty() {
+            return;
+        }
+        let lines = text.lines().count();
+        let bytes = text.len();
+        if lines > PASTE_CONFIRM_LINES || bytes > PASTE_CONFIRM_BYTES {
+            let target = match self.editor.active_buffer() {
+                Some(buffer) => format!(
+                    "{} at Ln {}, Col {}",
+                    buffer
+                        .path
+                        .as_deref()
+                        .map(|p| p.display().to_string())
+                        .unwrap_or_else(|| "untitled".to_string()),
+                    buffer.cursor.line + 1,
+                    buffer.cursor.col + 1
+                ),
+                None => return,
+            };
+            let preview: Vec<String> = text
+                .lines()
+                .take(8)
+                .map(|l| l.chars().take(76).collect())
+                .collect();
+            self.overlay = Some(Overlay::ConfirmPaste {
+                text,
+                preview,
+                lines,
+                bytes,
+                target,
+            });
+            return;
+        }
+        self.insert_paste(&text);
+    }
+
+    /// A bracketed paste from the terminal: the whole block arrives as
+    /// one event, so it becomes a single insert (one undo step, one LSP
+    /// notification) instead of thousands of key events.
+    pub fn handle_paste(&mut self, text: &str) {
+        match &mut self.overlay {
+            Some(Overlay::CommandPalette { input, .. }) | Some(Overlay::Prompt { input, .. }) => {
+                // Overlay inputs are single-line.
+                input.extend(text.chars().filter(|c| *c != '\n' && *c != '\r'));
+                return;
+            }
+            Some(_) => return,
+            None => {}
+        }
+        match self.focus {
+            Focus::Editor => {
+                if self.read_only {
+                    self.set_status("read-only mode");
+                    return;
+                }
+                match self.editor.active_buffer() {
+                    None => {}
+                    Some(b) if b.is_read_only() => {
+                        self.set_status("buffer is read-only");
+                    }
+                    Some(_) => self.insert_paste(text),
+                }
+            }
+            Focus::Terminal => {
+                // The terminal input runs on Enter; keep the paste on one
+                // line rather than firing intermediate commands.
+                self.terminal
+                    .input
+                    .push_str(text.trim_end_matches('\n').replace('\n', " ").as_str());
+            }
+            Focus::Agent => self.composer.push_str(text),
+            _ => {}
+        }
+    }
+
+    /// The unguarded half of a paste: used directly for small content and
+    /// from the confirmation overlay.
+    pub fn insert_paste(&mut self, text: &str) {
+        let Some(buffer) = self.editor.active_buffer_mut() else {
+            return;
+        };
+        buffer.insert_str(text);
+        self.notify_buffer_changed();
+    }
+
+    /// Push the active buffer's contents to the language server.
+    pub fn notify_buffer_changed(&mut self) {
+        let Some(buffer) = self.editor.active_buffer() else {
+            return;
+        };
+        let Some(path) = buffer.path.clone() else { return };
+        if let Some(lsp) = &mut self.lsp {
+            let _ = lsp.did_change(&path, buffer.version, &buffer.rope.to_string());
+        }
+    }
+
+    pub fn cursor_lsp_position(&self) -> Option<(PathBuf, LspPosition)> {
+        let buffer = self.editor.active_buffer()?;
+        let path = buffer.path.clone()?;
+        Some((
+            path,
+            LspPosition {
+                line: buffer.cursor.line as u32,
+                character: buffer.cursor.col as u32,
+            },
+        ))
+    }
+
+    pub fn send_agent_prompt(&mut self) {
+        let mut prompt = self.composer.trim().to_string();
+        if prompt.is_empty() {
+            return;
+        }
+        // `/name trailing words` expands a prompt template in place; an
+        // unknown name goes through literally.
+        if let Some((name, extra)) = crate::agent::templates::parse_slash(&prompt) {
+            if let Some(body) = self.config.agent.templates.get(name).cloned() {
+                let expanded = crate::agent::templates::expand(&body, &self.template_vars());
+                prompt = if extra.is_empty() {
+                    expanded
+                } else {
+                    format!("{expanded}\n\n{extra}")
+                };
+            }
+        }
+        self.composer.clear();
+        self.conversation.push(AgentPanelEntry::User(prompt.clone()));
+        // A dropped tree entry overrides the active buffer as context
+        // for this one prompt.
+        let (context, context_path) = match self.agent_attachment.take() {
+            Some(path) => match std::fs::read_to_string(&path) {
+                Ok(text) => {
+                    let language = crate::editor::detect_language(&path);
+                    (
+                        Some(crate::agent::context::abridge(&text, language.as_deref(), 0)),
+                        Some(path),
+                    )
+                }
+                Err(err) => {
+                    self.conversation.push(AgentPanelEntry::Error(format!(
+                        "failed to read attached {}: {err}",
+                        path.display()
+                    )));
+                    (None, None)
+                }
+            },
+            None => (
+                // Oversized buffers are abridged along symbol boundaries
+                // so the cursor region survives instead of a head
+                // truncation.
+                self.editor.active_buffer().map(|b| {
+                    crate::agent::context::abridge(
+                        &b.rope.to_string(),
+                        b.language.as_deref(),
+                        b.cursor.line,
+                    )
+                }),
+                self.editor.active_buffer().and_then(|b| b.path.clone()),
+            ),
+        };
+        let (prompt, mut context) = self.redact_outgoing(prompt, context);
+        // Attach the top matching workspace chunks so questions can
+        // reach beyond the attached file, skipping the file itself.
+        if let Some(index) = &self.rag {
+            let hits: Vec<_> = index
+                .retrieve(&prompt, 4)
+                .into_iter()
+                .filter(|chunk| {
+                    context_path
+                        .as_deref()
+                        .map(|p| !p.ends_with(&chunk.path))
+                        .unwrap_or(true)
+                })
+                .collect();
+            if !hits.is_empty() {
+                let mut block = String::from("Relevant workspace context:\n");
+                for chunk in hits {
+                    block.push_str(&format!(
+                        "--- {} (line {})\n{}\n",
+                        chunk.path.display(),
+                        chunk.start_line,
+                        chunk.text
+                    ));
+                }
+                context = Some(match context {
+                    Some(existing) => format!("{existing}\n\n{block}"),
+                    None => block,
+                });
+            }
+        }
+        // Fit the context into the model window and record the estimate
+        // for the footer/status display.
+        let spec = self.active_model_spec();
+        let prompt_tokens = crate::agent::tokens::estimate(&prompt);
+        let context = match context {
+            Some(context) => {
+                let (kept, truncated) =
+                    crate::agent::tokens::budget_context(context, prompt_tokens, &spec);
+                if truncated {
+                    self.conversation.push(AgentPanelEntry::Info(
+                        "context truncated to fit the model window".to_string(),
+                    ));
+                }
+                Some(kept)
+            }
+            None => None,
+        };
+        let sent = prompt_tokens
+            + context
+                .as_deref()
+                .map(crate::agent::tokens::estimate)
+                .unwrap_or(0);
+        self.last_prompt_tokens = sent;
+        self.agent_tokens_in += sent;
+        let request = AgentRequest {
+            prompt,
+            context,
+            context_path,
+        };
+        self.agent.instructions = crate::agent::instructions::load(&self.root);
+        match self.agent.send(request) {
+            Ok(()) => self
+                .conversation
+                .push(AgentPanelEntry::Info("request sent".to_string())),
+            Err(err) => self
+                .conversation
+                .push(AgentPanelEntry::Error(format!("{err:#}"))),
+        }
+    }
+
+    /// Window/pricing spec for the active profile's model.
+    fn active_model_spec(&self) -> crate::agent::tokens::ModelSpec {
+        use crate::agent::profile::BackendConfig;
+        let model = match self.agent.active_profile().map(|p| &p.backend) {
+            Some(BackendConfig::HttpApi(http)) => http.model.as_str(),
+            _ => "",
+        };
+        crate::agent::tokens::spec_for(model)
+    }
+
+    /// One-line session token/cost summary, or `None` before the first
+    /// request.
+    pub fn token_usage_summary(&self) -> Option<String> {
+        if self.agent_tokens_in == 0 && self.agent_tokens_out == 0 {
+            return None;
+        }
+        let spec = self.active_model_spec();
+        let cost =
+            crate::agent::tokens::cost(self.agent_tokens_in, self.agent_tokens_out, &spec);
+        let mut line = format!(
+            "≈{} in / {} out tok",
+            self.agent_tokens_in, self.agent_tokens_out
+        );
+        if cost > 0.0 {
+            line.push_str(&format!(" · ${cost:.4}"));
+        }
+        Some(line)
+    }
+
+    /// The most recent fenced code block in the conversation, if any.
+    fn last_code_block(&self) -> Option<String> {
+        self.conversation.entries.iter().rev().find_map(|entry| {
+            if let AgentPanelEntry::Response(text) = entry {
+                crate::ui::markdown::code_blocks(text).into_iter().next_back()
+            } else {
+                None
+            }
+        })
+    }
+
+    /// The effective redaction rules from config; redaction only
+    /// applies when the active profile is a cloud provider.
+    fn redaction_rules(&self) -> crate::agent::redact::RedactionRules {
+        let section = &self.config.redaction;
+        crate::agent::redact::RedactionRules {
+            enabled: section.enabled.unwrap_or(true),
+            emails: section.emails.unwrap_or(true),
+            api_keys: section.api_keys.unwrap_or(true),
+            patterns: section.patterns.clone(),
+        }
+    }
+
+    /// Whether the active profile sends content off the machine.
+    fn agent_is_cloud(&self) -> bool {
+        use crate::agent::profile::{BackendConfig, HttpProvider};
+        match self.agent.active_profile().map(|p| &p.backend) {
+            Some(BackendConfig::HttpApi(http)) => matches!(
+                http.provider,
+                HttpProvider::Openai
+                    | HttpProvider::Anthropic
+                    | HttpProvider::Gemini
+                    | HttpProvider::Openrouter
+                    | HttpProvider::Mistral
+                    | HttpProvider::Groq
+                    | HttpProvider::Deepseek
+                    | HttpProvider::Bedrock
+                    | HttpProvider::Custom
+            ),
+            _ => false,
+        }
+    }
+
+    /// Redact an outgoing prompt and context for cloud providers,
+    /// recording an audit entry when anything was stripped.
+    fn redact_outgoing(
+        &mut self,
+        prompt: String,
+        context: Option<String>,
+    ) -> (String, Option<String>) {
+        if !self.agent_is_cloud() {
+            return (prompt, context);
+        }
+        let rules = self.redaction_rules();
+        let (prompt, mut hits) = crate::agent::redact::redact(&prompt, &rules);
+        let context = context.map(|text| {
+            let (text, more) = crate::agent::redact::redact(&text, &rules);
+            hits.extend(more);
+            text
+        });
+        if !hits.is_empty() {
+            self.conversation.push(AgentPanelEntry::Info(format!(
+                "redacted {} item(s) before sending to the cloud provider",
+                hits.len()
+            )));
+        }
+        (prompt, context)
+    }
+
+    /// Send the next queued batch item once nothing is in flight and the
+    /// rate-limit window has passed. Called from the prompt completion
+    /// and every tick.
+    fn pump_batch(&mut self) {
+        if self.agent.busy {
+            return;
+        }
+        let Some(run) = &self.batch else {
+            return;
+        };
+        let Some(idx) = run.ready_to_send() else {
+            return;
+        };
+        let path = run.items[idx].path.clone();
+        let template = run.template.clone();
+        let text = match fs::read_to_string(&path) {
+            Ok(text) => text,
+            Err(err) => {
+                if let Some(run) = &mut self.batch {
+                    run.items[idx].state = ItemState::Failed;
+                }
+                self.conversation.push(AgentPanelEntry::Error(format!(
+                    "batch: failed to read {}: {err}",
+                    path.display()
+                )));
+                return;
+            }
+        };
+        let language = crate::editor::detect_language(&path);
+        let prompt = format!(
+            "{template}\n\nReturn the complete updated file contents in one code block."
+        );
+        let context = Some(crate::agent::context::abridge(&text, language.as_deref(), 0));
+        let (prompt, context) = self.redact_outgoing(prompt, context);
+        let request = AgentRequest {
+            prompt,
+            context,
+            context_path: Some(path.clone()),
+        };
+        self.agent.instructions = crate::agent::instructions::load(&self.root);
+        match self.agent.send(request) {
+            Ok(()) => {
+                if let Some(run) = &mut self.batch {
+                    run.mark_sent(idx);
+                    let summary = run.summary();
+                    self.set_status(summary);
+                }
+            }
+            Err(err) => {
+                if let Some(run) = &mut self.batch {
+                    run.items[idx].state = ItemState::Failed;
+                }
+                self.conversation.push(AgentPanelEntry::Error(format!(
+                    "batch {}: {err:#}",
+                    path.display()
+                )));
+            }
+        }
+    }
+
+    /// Write every reviewed-and-ready batch result to disk, skipping
+    /// files open in the editor. Each write is recorded like a tool
+    /// write so it can be reverted.
+    fn apply_batch_results(&mut self) {
+        let Some(run) = &self.batch else {
+            self.set_status("no batch run");
+            return;
+        };
+        let ready: Vec<(usize, PathBuf, String)> = run
+            .items
+            .iter()
+            .enumerate()
+            .filter_map(|(idx, item)| match &item.state {
+                ItemState::Ready { proposed, .. } => {
+                    Some((idx, item.path.clone(), proposed.clone()))
+                }
+                _ => None,
+            })
+            .collect();
+        if ready.is_empty() {
+            self.set_status("no batch results ready to apply");
+            return;
+        }
+        let profile = self
+            .agent
+            .active_profile()
+            .map(|p| p.name.clone())
+            .unwrap_or_default();
+        let mut applied = 0;
+        for (idx, path, proposed) in ready {
+            if self.editor.buffer_for_path(&path).is_some() {
+                self.conversation.push(AgentPanelEntry::Error(format!(
+                    "batch: {} skipped, file is open in the editor",
+                    path.display()
+                )));
+                continue;
+            }
+            let before = fs::read_to_string(&path).ok();
+            if let Err(err) = fs::write(&path, &proposed) {
+                self.conversation.push(AgentPanelEntry::Error(format!(
+                    "batch: write to {} failed: {err}",
+                    path.display()
+                )));
+                continue;
+            }
+            self.agent_stats.record(&profile, PatchOutcome::Accepted);
+            self.tool_writes.push(ToolWriteRecord {
+                path: path.clone(),
+                before,
+                profile: profile.clone(),
+                edited: false,
+            });
+            if let Some(run) = &mut self.batch {
+                run.items[idx].state = ItemState::Applied;
+            }
+            applied += 1;
+        }
+        let summary = self
+            .batch
+            .as_ref()
+            .map(|run| run.summary())
+            .unwrap_or_default();
+        self.set_status(format!("applied {applied} batch result(s); {summary}"));
+    }
+
+    pub fn execute_command(&mut self, id: CommandId) {
+        let edits_buffer = matches!(
+            id,
+            CommandId::MoveLineUp
+                | CommandId::MoveLineDown
+                | CommandId::DuplicateLine
+                | CommandId::JoinLines
+                | CommandId::DeleteLine
+        );
+        if self.read_only && edits_buffer {
+            self.set_status("read-only mode");
+            return;
+        }
+        match id {
+            CommandId::SaveFile => self.save_active(),
+            CommandId::CloseBuffer => {
+                self.editor.close_active();
+                self.refresh_language_prefs();
+            }
+            CommandId::NextBuffer => {
+                self.editor.next_buffer();
+                self.refresh_language_prefs();
+            }
+            CommandId::PrevBuffer => {
+                self.editor.prev_buffer();
+                self.refresh_language_prefs();
+            }
+            CommandId::ToggleTree => self.layout.show_tree = !self.layout.show_tree,
+            CommandId::ToggleTerminal => self.layout.show_terminal = !self.layout.show_terminal,
+            CommandId::TerminalCopyMode => {
+                if self.terminal.lines.is_empty() {
+                    self.set_status("terminal scrollback is empty");
+                } else {
+                    self.layout.show_terminal = true;
+                    self.focus = Focus::Terminal;
+                    self.terminal.enter_copy_mode();
+                }
+            }
+            CommandId::ToggleAgent => self.layout.show_agent = !self.layout.show_agent,
+            CommandId::ToggleGit => {
+                self.layout.show_git = !self.layout.show_git;
+                if self.layout.show_git {
+                    self.layout.show_tree = true;
+                    self.refresh_git();
+                }
+            }
+            CommandId::ToggleHidden => self.tree.toggle_hidden(),
+            CommandId::RestoreLastDeleted => {
+                let Some(path) = self.last_trashed.clone() else {
+                    self.set_status("nothing was deleted this session");
+                    return;
+                };
+                match FileTree::restore_from_trash(&path) {
+                    Ok(()) => {
+                        self.last_trashed = None;
+                        self.refresh_tree();
+                        self.set_status(format!("restored {}", path.display()));
+                    }
+                    Err(err) => self.set_error(format!("restore failed: {err:#}")),
+                }
+            }
+            CommandId::ToggleLineNumbers => {
+                self.editor.prefs.show_line_numbers = !self.editor.prefs.show_line_numbers;
+            }
+            CommandId::ToggleRelativeNumbers => {
+                self.editor.prefs.relative_line_numbers =
+                    !self.editor.prefs.relative_line_numbers;
+                self.set_status(if self.editor.prefs.relative_line_numbers {
+                    "relative line numbers on"
+                } else {
+                    "relative line numbers off"
+                });
+            }
+            CommandId::ToggleWrap => {
+                self.editor.prefs.wrap_mode = match self.editor.prefs.wrap_mode {
+                    WrapMode::NoWrap => WrapMode::WordWrap,
+                    WrapMode::WordWrap => WrapMode::CharWrap,
+                    WrapMode::CharWrap => WrapMode::NoWrap,
+                };
+                self.set_status(self.editor.prefs.wrap_mode.label());
+            }
+            CommandId::ToggleLineEnding => {
+                if let Some(buffer) = self.editor.active_buffer_mut() {
+                    buffer.line_ending = match buffer.line_ending {
+                        LineEnding::Lf => LineEnding::Crlf,
+                        LineEnding::Crlf => LineEnding::Lf,
+                    };
+                    buffer.dirty = true;
+                }
+            }
+            CommandId::ToggleEncoding => {
+                if let Some(buffer) = self.editor.active_buffer_mut() {
+                    buffer.encoding = match buffer.encoding {
+                        Encoding::Utf8 => Encoding::Utf8Bom,
+                        Encoding::Utf8Bom => Encoding::Utf16Le,
+                        Encoding::Utf16Le => Encoding::Utf16Be,
+                        // The legacy encodings are only reachable via
+                        // the picker, so the quick toggle wraps to UTF-8.
+                        _ => Encoding::Utf8,
+                    };
+                    buffer.dirty = true;
+                }
+            }
+            CommandId::ReopenWithEncoding => {
+                if let Some(buffer) = self.editor.active_buffer() {
+                    if buffer.path.is_none() {
+                        self.set_status("buffer has no file name");
+                    } else {
+                        let selected = Encoding::ALL
+                            .iter()
+                            .position(|&e| e == buffer.encoding)
+                            .unwrap_or(0);
+                        self.overlay = Some(Overlay::EncodingPicker { selected });
+                    }
+                }
+            }
+            CommandId::ToggleVim => {
+                self.editor.prefs.vim_mode = !self.editor.prefs.vim_mode;
+                self.vim = crate::vim::VimState::default();
+                self.set_status(if self.editor.prefs.vim_mode {
+                    "vim mode on"
+                } else {
+                    "vim mode off"
+                });
+            }
+            CommandId::ToggleStats => {
+                self.editor.prefs.show_stats = !self.editor.prefs.show_stats;
+                self.set_status(if self.editor.prefs.show_stats {
+                    "document stats on"
+                } else {
+                    "document stats off"
+                });
+            }
+            CommandId::ToggleWhitespace => {
+                self.editor.prefs.show_whitespace = !self.editor.prefs.show_whitespace;
+                self.set_status(if self.editor.prefs.show_whitespace {
+                    "whitespace markers on"
+                } else {
+                    "whitespace markers off"
+                });
+            }
+            CommandId::CycleIndent => {
+                let next = match self.editor.indent() {
+                    IndentKind::Spaces(2) => IndentKind::Spaces(4),
+                    IndentKind::Spaces(_) => IndentKind::Tabs,
+                    IndentKind::Tabs => IndentKind::Spaces(2),
+                };
+                // Cycling adjusts the buffer under the cursor, not every
+                // open file; without a buffer it falls back to the
+                // editor-wide preference.
+                match self.editor.active_buffer_mut() {
+                    Some(buffer) => buffer.indent = Some(next),
+                    None => self.editor.prefs.indent = next,
+                }
+                self.set_status(format!("indent: {}", next.label()));
+            }
+            CommandId::MoveLineUp | CommandId::MoveLineDown => {
+                let dy = if id == CommandId::MoveLineUp { -1 } else { 1 };
+                if self
+                    .editor
+                    .active_buffer_mut()
+                    .is_some_and(|buffer| buffer.move_line(dy))
+                {
+                    self.notify_buffer_changed();
+                }
+            }
+            CommandId::DuplicateLine => {
+                if let Some(buffer) = self.editor.active_buffer_mut() {
+                    buffer.duplicate_line();
+                    self.notify_buffer_changed();
+                }
+            }
+            CommandId::JoinLines => {
+                if self
+                    .editor
+                    .active_buffer_mut()
+                    .is_some_and(|buffer| buffer.join_lines())
+                {
+                    self.notify_buffer_changed();
+                }
+            }
+            CommandId::DeleteLine => {
+                if let Some(buffer) = self.editor.active_buffer_mut() {
+                    buffer.delete_line();
+                    self.notify_buffer_changed();
+                }
+            }
+            CommandId::EvaluateSelection => {
+                let Some(text) = self
+                    .editor
+                    .active_buffer()
+                    .and_then(|buffer| buffer.selected_text())
+                else {
+                    self.set_status("nothing selected to evaluate");
+                    return;
+                };
+                match crate::editor::eval::evaluate(&text) {
+                    Ok(result) => {
+                        self.clipboard = result.clone();
+                        self.set_status(format!("= {result} (copied)"));
+                    }
+                    Err(err) if self.agent.active_profile().is_some() => {
+                        // Not simple arithmetic: let the agent have a go.
+                        self.composer = format!(
+                            "Evaluate this expression and reply with only the result: {}",
+                            text.trim()
+                        );
+                        self.send_agent_prompt();
+                        self.set_status(format!("{err:#}; asked the agent"));
+                    }
+                    Err(err) => self.set_error(format!("cannot evaluate: {err:#}")),
+                }
+            }
+            CommandId::ToggleReadOnly => {
+                let Some(buffer) = self.editor.active_buffer_mut() else {
+                    return;
+                };
+                buffer.read_only = !buffer.read_only;
+                let message = if buffer.read_only {
+                    "buffer locked (read-only)"
+                } else if buffer
+                    .path
+                    .as_deref()
+                    .is_some_and(file_not_writable)
+                {
+                    "buffer unlocked, but the file on disk is not writable"
+                } else {
+                    "buffer unlocked"
+                };
+                self.set_status(message);
+            }
+            CommandId::FollowFile => {
+                let Some(buffer) = self.editor.active_buffer_mut() else {
+                    return;
+                };
+                if buffer.path.is_none() {
+                    self.set_error("cannot follow an unsaved buffer");
+                    return;
+                }
+                buffer.follow = !buffer.follow;
+                buffer.follow_paused = false;
+                let message = if buffer.follow {
+                    buffer.move_to_end();
+                    "following file (scroll up to pause)"
+                } else {
+                    "follow mode off"
+                };
+                self.set_status(message);
+            }
+            CommandId::CycleIconSet => {
+                self.tree.icon_set = match self.tree.icon_set {
+                    IconSet::Emoji => IconSet::NerdFont,
+                    IconSet::NerdFont => IconSet::Unicode,
+                    IconSet::Unicode => IconSet::Emoji,
+                };
+                self.set_status(format!("icons: {}", self.tree.icon_set.label()));
+            }
+            CommandId::SearchReplace => {
+                self.overlay = Some(Overlay::SearchReplace(SearchReplaceState::new()));
+            }
+            CommandId::GotoLine => {
+                self.overlay = Some(Overlay::Prompt {
+                    action: PromptAction::GotoLine,
+                    input: String::new(),
+                });
+            }
+            CommandId::RelatedFile => {
+                let Some(path) = self
+                    .editor
+                    .active_buffer()
+                    .and_then(|buffer| buffer.path.clone())
+                else {
+                    self.set_status("buffer has no file name");
+                    return;
+                };
+                let groups = self.config.related_files.ext_groups.clone();
+                let found = crate::workspace::related_candidates(&path, &groups)
+                    .into_iter()
+                    .find(|candidate| candidate.is_file());
+                match found {
+                    Some(related) => {
+                        if let Err(err) = self.open_path(&related) {
+                            self.set_error(format!("open failed: {err:#}"));
+                        }
+                    }
+                    None => self.set_status("no related file found"),
+                }
+            }
+            CommandId::RenameSymbol => {
+                self.overlay = Some(Overlay::Prompt {
+                    action: PromptAction::RenameSymbol,
+                    input: String::new(),
+                });
+            }
+            CommandId::ToggleBookmark => {
+                if let Some(buffer) = self.editor.active_buffer_mut() {
+                    let set = buffer.toggle_bookmark();
+                    self.set_status(if set { "bookmark set" } else { "bookmark removed" });
+                }
+            }
+            CommandId::NextBookmark => self.goto_bookmark(true),
+            CommandId::PrevBookmark => self.goto_bookmark(false),
+            CommandId::JumpBack => self.jump_back(),
+            CommandId::JumpForward => self.jump_forward(),
+            CommandId::GotoDefinition => self.goto_definition(),
+            CommandId::HoverInfo => self.request_hover(),
+            CommandId::GitRefresh => {
+                self.refresh_git();
+                self.set_status("git status refreshed");
+            }
+            CommandId::GitStage => {
+                if let Err(err) = self.git.toggle_stage_selected() {
+                    self.set_status(format!("git: {err:#}"));
+                }
+            }
+            CommandId::GitCommit => {
+                self.overlay = Some(Overlay::Prompt {
+                    action: PromptAction::CommitMessage,
+                    input: String::new(),
+                });
+            }
+            CommandId::GenerateCommitMessage => self.generate_commit_message(),
+            CommandId::AgentSetApiKey => {
+                self.overlay = Some(Overlay::Prompt {
+                    action: PromptAction::AgentApiKey,
+                    input: String::new(),
+                });
+            }
+            CommandId::AgentBatch => {
+                self.overlay = Some(Overlay::Prompt {
+                    action: PromptAction::AgentBatchFiles,
+                    input: String::new(),
+                });
+            }
+            CommandId::AgentBatchApply => self.apply_batch_results(),
+            CommandId::AgentBatchDiscard => match self.batch.take() {
+                Some(run) => {
+                    self.set_status(format!("batch discarded ({} pending)", run.remaining()))
+                }
+                None => self.set_status("no batch run"),
+            },
+            CommandId::AgentRevertToolWrite => self.revert_last_tool_write(),
+            CommandId::AgentShowStats => self.overlay = Some(Overlay::AgentStats),
+            CommandId::AgentCycleProfile => {
+                let previous = self.agent.active_profile().map(|p| p.name.clone());
+                self.agent.cycle_profile();
+                self.agent.profile_source = "manual";
+                self.swap_agent_tab(previous.as_deref());
+                let name = self
+                    .agent
+                    .active_profile()
+                    .map(|p| p.name.clone())
+                    .unwrap_or_else(|| "none".to_string());
+                self.set_status(format!("agent profile: {name}"));
+            }
+            CommandId::CommandPalette => {
+                self.overlay = Some(Overlay::CommandPalette {
+                    input: String::new(),
+                    selected: 0,
+                });
+            }
+            CommandId::KeyboardHelp => {
+                let bindings = self.keymap.effective_bindings();
+                let bound: Vec<CommandId> =
+                    bindings.iter().map(|(_, _, command)| *command).collect();
+                let mut rows: Vec<(String, String, String)> = bindings
+                    .into_iter()
+                    .map(|(scope, keys, command)| {
+                        (
+                            scope.label().to_string(),
+                            keys,
+                            command_label(command).to_string(),
+                        )
+                    })
+                    .collect();
+                // Unbound commands are still reachable by name, so list
+                // them under a palette group to keep the overlay complete.
+                for (name, command) in COMMAND_NAMES {
+                    if !bound.contains(command) {
+                        rows.push((
+                            "palette".to_string(),
+                            (*name).to_string(),
+                            command_label(*command).to_string(),
+                        ));
+                    }
+                }
+                self.overlay = Some(Overlay::KeyboardHelp {
+                    rows,
+                    scroll: 0,
+                    filter: String::new(),
+                });
+            }
+            CommandId::AgentCopyCode => match self.last_code_block() {
+                Some(block) => self.copy_to_clipboard(block),
+                None => self.set_status("no code block in the conversation"),
+            },
+            CommandId::AgentInsertCode => match self.last_code_block() {
+                Some(block) => {
+                    if self.read_only {
+                        self.set_status("read-only mode");
+                        return;
+                    }
+                    match self.editor.active_buffer() {
+                        None => self.set_status("no buffer to insert into"),
+                        Some(b) if b.is_read_only() => {
+                            self.set_status("buffer is read-only");
+                        }
+                        Some(_) => {
+                            self.insert_paste(&block);
+                            self.set_status("code block inserted");
+                        }
+                    }
+                }
+                None => self.set_status("no code block in the conversation"),
+            },
+            CommandId::AgentPreviewRedactions => {
+                let Some(buffer) = self.editor.active_buffer() else {
+                    self.set_status("no buffer to preview");
+                    return;
+                };
+                let rules = self.redaction_rules();
+                let (_, hits) = crate::agent::redact::redact(&buffer.rope.to_string(), &rules);
+                if hits.is_empty() {
+                    self.set_status("nothing would be redacted");
+                    return;
+                }
+                let mut report = format!("{} region(s) would be redacted:", hits.len());
+                for hit in hits.iter().take(20) {
+                    report.push_str(&format!("\n  line {}: {}", hit.line + 1, hit.kind));
+                }
+                if hits.len() > 20 {
+                    report.push_str(&format!("\n  … {} more", hits.len() - 20));
+                }
+                self.conversation.push(AgentPanelEntry::Info(report));
+                self.set_status(format!("{} redaction(s) previewed", hits.len()));
+            }
+            CommandId::AgentToggleInfo => {
+                self.conversation.show_info = !self.conversation.show_info;
+                self.set_status(if self.conversation.show_info {
+                    "agent info entries shown"
+                } else {
+                    "agent info entries hidden"
+                });
+            }
+            CommandId::AgentToggleDiffs => {
+                self.conversation.show_diffs = !self.conversation.show_diffs;
+                self.set_status(if self.conversation.show_diffs {
+                    "agent diff bodies shown"
+                } else {
+                    "agent diff bodies hidden"
+                });
+            }
+            CommandId::AgentExpandInfo => {
+                self.conversation.expand_info_groups = !self.conversation.expand_info_groups;
+                self.set_status(if self.conversation.expand_info_groups {
+                    "info groups expanded"
+                } else {
+                    "info groups collapsed"
+                });
+            }
+            CommandId::InsertFileHeader => self.insert_file_header(),
+            CommandId::InspectCharacter => {
+                let Some(buffer) = self.editor.active_buffer() else {
+                    return;
+                };
+                let idx = buffer.char_index(buffer.cursor);
+                if idx >= buffer.rope.len_chars() {
+                    self.set_status("no character under the cursor");
+                } else {
+                    let c = buffer.rope.char(idx);
+                    self.overlay = Some(Overlay::CharInspector {
+                        text: crate::editor::unicode::inspect(c),
+                    });
+                }
+            }
+            CommandId::InsertUnicode => {
+                self.overlay = Some(Overlay::UnicodePicker {
+                    input: String::new(),
+                    selected: 0,
+                });
+            }
+            CommandId::SelectLayoutPreset => {
+                self.overlay = Some(Overlay::LayoutPicker {
+                    names: self.layout_preset_names(),
+                    selected: 0,
+                });
+            }
+            CommandId::NotificationHistory => {
+                self.overlay = Some(Overlay::Notifications { scroll: 0 });
+            }
+            CommandId::SelectTheme => {
+                let names = crate::ui::theme::available_themes();
+                let selected = names
+                    .iter()
+                    .position(|n| n == &self.theme_name)
+                    .unwrap_or(0);
+                self.overlay = Some(Overlay::ThemePicker {
+                    names,
+                    selected,
+                    previous: crate::ui::theme::current(),
+                    previous_name: self.theme_name.clone(),
+                });
+            }
+            CommandId::ReloadTheme => {
+                let name = self.theme_name.clone();
+                if self.set_theme(&name) {
+                    self.set_status(format!("theme {name} reloaded"));
+                }
+            }
+            CommandId::FocusTree => {
+                self.layout.show_tree = true;
+                self.focus = Focus::Tree;
+            }
+            CommandId::FocusEditor => self.focus = Focus::Editor,
+            CommandId::FocusTerminal => {
+                self.layout.show_terminal = true;
+                self.focus = Focus::Terminal;
+            }
+            CommandId::FocusAgent => {
+                self.layout.show_agent = true;
+                self.focus = Focus::Agent;
+            }
+            CommandId::FocusGit => {
+                self.layout.show_git = true;
+                self.layout.show_tree = true;
+                self.refresh_git();
+                self.focus = Focus::Git;
+            }
+            CommandId::ConversationHistory => self.open_conversation_history(),
+            CommandId::NewConversation => self.new_conversation(),
+            CommandId::SearchAgentHistory => self.open_agent_history_search(),
+            CommandId::McpBrowser => self.open_mcp_browser(),
+            CommandId::ReviewToolConflict => self.review_tool_conflict(),
+            CommandId::BuildRagIndex => self.build_rag_index(),
+            CommandId::SelectModel => match self.agent.fetch_models() {
+                Ok(()) => self.set_status("fetching model list…"),
+                Err(err) => self.set_error(format!("model list: {err:#}")),
+            },
+            CommandId::ToggleTranscriptLog => {
+                let on = crate::agent::transcript::toggle();
+                self.set_status(if on {
+                    "transcript log on (.clide/agent-transcript.jsonl)"
+                } else {
+                    "transcript log off"
+                });
+            }
+            CommandId::TranscriptViewer => self.open_transcript_viewer(),
+            CommandId::EditInstructions => self.edit_instructions(),
+            CommandId::PromptTemplates => self.open_template_picker(),
+            CommandId::AgentAskSelection => self.agent_ask_selection(),
+            CommandId::AgentExplainError => self.agent_explain_error(),
+            CommandId::AgentGenerateTests => self.agent_generate_tests(),
+            CommandId::AgentEntryActions => self.open_entry_actions(),
+            CommandId::ExportConversation => self.open_export_prompt(),
+            CommandId::RunTask => self.open_task_picker(),
+            CommandId::CargoCheck => self.run_cargo_diagnostics("check"),
+            CommandId::CargoTest => self.run_cargo_diagnostics("test"),
+            CommandId::ExportBundle => self.export_bundle(),
+            CommandId::ImportBundle => {
+                self.overlay = Some(Overlay::Prompt {
+                    action: PromptAction::ImportBundle,
+                    input: String::new(),
+                });
+            }
+            CommandId::CancelTasks => {
+                let cancelled = self.tasks.cancel_all();
+                self.set_status(if cancelled == 0 {
+                    "no background tasks running".to_string()
+                } else {
+                    format!("cancelling {cancelled} task(s)")
+                });
+            }
+            CommandId::Quit => self.should_quit = true,
+        }
+    }
+
+    /// The cursor location of the active named buffer, for the jump list.
+    fn current_location(&self) -> Option<(PathBuf, Position)> {
+        let buffer = self.editor.active_buffer()?;
+        Some((buffer.path.clone()?, buffer.cursor))
+    }
+
+    /// Remember the current location before a jump, so Ctrl+O can return
+    /// to it. Truncates any forward history, like an undo stack.
+    pub fn push_jump(&mut self) {
+        let Some(location) = self.current_location() else {
+            return;
+        };
+        self.jump_list.truncate(self.jump_index);
+        if self.jump_list.last() != Some(&location) {
+            self.jump_list.push(location);
+        }
+        self.jump_index = self.jump_list.len();
+    }
+
+    fn jump_back(&mut self) {
+        if self.jump_index == 0 {
+            self.set_status("at oldest jump");
+            return;
+        }
+        // Save the live position once so Ctrl+I can come back to it.
+        if self.jump_index == self.jump_list.len() {
+            if let Some(location) = self.current_location() {
+                if self.jump_list.last() != Some(&location) {
+                    self.jump_list.push(location);
+                }
+            }
+        }
+        self.jump_index -= 1;
+        let (path, pos) = self.jump_list[self.jump_index].clone();
+        self.goto_location(&path, pos);
+    }
+
+    fn jump_forward(&mut self) {
+        if self.jump_index + 1 >= self.jump_list.len() {
+            self.set_status("at newest jump");
+            return;
+        }
+        self.jump_index += 1;
+        let (path, pos) = self.jump_list[self.jump_index].clone();
+        self.goto_location(&path, pos);
+    }
+
+    pub fn goto_location(&mut self, path: &Path, pos: Position) {
+        if self.open_path(path).is_ok() {
+            if let Some(buffer) = self.editor.active_buffer_mut() {
+                buffer.cursor = pos;
+                buffer.anchor = None;
+                buffer.extra_cursors.clear();
+                buffer.clamp_cursor();
+            }
+        }
+    }
+
+    /// Open the `path:line` location in one terminal output line (by
+    /// absolute index), or the most recent one when `row` is `None`.
+    pub fn open_terminal_location(&mut self, row: Option<usize>) {
+        let loc = match row {
+            Some(row) => self
+                .terminal
+                .lines
+                .get(row)
+                .and_then(|line| crate::terminal::find_location(line)),
+            None => self
+                .terminal
+                .lines
+                .iter()
+                .rev()
+                .find_map(|line| crate::terminal::find_location(line)),
+        };
+        let Some(loc) = loc else {
+            if row.is_none() {
+                self.set_status("no file:line in the terminal output");
+            }
+            return;
+        };
+        let path = PathBuf::from(&loc.path);
+        let path = if path.is_absolute() {
+            path
+        } else {
+            self.root.join(path)
+        };
+        if !path.is_file() {
+            self.set_status(format!("{} does not exist", loc.path));
+            return;
+        }
+        self.push_jump();
+        self.goto_location(
+            &path,
+            Position {
+                line: loc.line - 1,
+                col: loc.col - 1,
+            },
+        );
+        self.focus = Focus::Editor;
+    }
+
+    /// A click on a terminal output row: open its file:line if it has
+    /// one.
+    pub fn terminal_click(&mut self, row: u16) {
+        let inner_top = self.layout.terminal_area.y + 1;
+        if row < inner_top {
+            return;
+        }
+        let line = self.terminal.scroll + (row - inner_top) as usize;
+        self.open_terminal_location(Some(line));
+    }
+
+    fn goto_bookmark(&mut self, forward: bool) {
+        let target = self.editor.active_buffer().and_then(|buffer| {
+            if forward {
+                buffer.next_bookmark()
+            } else {
+                buffer.prev_bookmark()
+            }
+        });
+        match target {
+            Some(line) => {
+                self.push_jump();
+                if let Some(buffer) = self.editor.active_buffer_mut() {
+                    buffer.goto_line(line);
+                }
+            }
+            None => self.set_status("no bookmarks in this buffer"),
+        }
+    }
+
+    pub fn goto_definition(&mut self) {
+        if let Some((path, pos)) = self.cursor_lsp_position() {
+            if self.lsp.is_none() {
+                self.set_status("no language server running");
+            } else if self.lsp_indexing {
+                self.lsp_deferred
+                    .push(DeferredLspRequest::Definition(path, pos));
+                self.set_status("LSP indexing… request queued");
+            } else if let Some(lsp) = &mut self.lsp {
+                let _ = lsp.goto_definition(&path, pos);
+            }
+        }
+    }
+
+    pub fn request_hover(&mut self) {
+        if let Some((path, pos)) = self.cursor_lsp_position() {
+            if self.lsp.is_none() {
+                self.set_status("no language server running");
+            } else if self.lsp_indexing {
+                self.lsp_deferred.push(DeferredLspRequest::Hover(path, pos));
+                self.set_status("LSP indexing… request queued");
+            } else if let Some(lsp) = &mut self.lsp {
+                let _ = lsp.hover(&path, pos);
+            }
+        }
+    }
+
+    /// Replay requests deferred during indexing, oldest first.
+    fn flush_deferred_lsp(&mut self) {
+        let Some(lsp) = &mut self.lsp else {
+            self.lsp_deferred.clear();
+            return;
+        };
+        for request in self.lsp_deferred.drain(..) {
+            match request {
+                DeferredLspRequest::Definition(path, pos) => {
+                    let _ = lsp.goto_definition(&path, pos);
+                }
+                DeferredLspRequest::Hover(path, pos) => {
+                    let _ = lsp.hover(&path, pos);
+                }
+            }
+        }
+    }
+
+    /// Persist the current conversation to `.clide/conversations/`.
+    /// No-op when the panel only holds startup info lines.
+    pub fn save_conversation(&mut self) {
+        if !self
+            .conversation
+            .entries
+            .iter()
+            .any(|entry| matches!(entry, AgentPanelEntry::User(_)))
+        {
+            return;
+        }
+        let id = self
+            .conversation_id
+            .get_or_insert_with(crate::agent::history::new_id)
+            .clone();
+        let title = self
+            .conversation_title
+            .clone()
+            .unwrap_or_else(|| crate::agent::history::derive_title(&self.conversation.entries));
+        let saved = crate::agent::history::SavedConversation {
+            id,
+            title,
+            saved_at: chrono::Local::now().format("%Y-%m-%d %H:%M").to_string(),
+            entries: self.conversation.entries.clone(),
+        };
+        if let Err(err) = crate::agent::history::save(&self.root, &saved) {
+            self.set_warning(format!("conversation not saved: {err:#}"));
+        }
+    }
+
+    /// Reload the most recently saved conversation on startup so chats
+    /// survive restarts.
+    fn restore_conversation(&mut self) {
+        let Some(meta) = crate::agent::history::list(&self.root).into_iter().next() else {
+            return;
+        };
+        let Some(saved) = crate::agent::history::load(&self.root, &meta.id) else {
+            return;
+        };
+        self.conversation.entries = saved.entries;
+        self.conversation.scroll = 0;
+        self.conversation_id = Some(saved.id);
+        self.conversation_title = Some(saved.title);
+    }
+
+    /// Swap a saved conversation into the panel, persisting the current
+    /// one first so nothing is lost.
+    pub fn open_conversation(&mut self, id: &str) {
+        if self.conversation_id.as_deref() != Some(id) {
+            self.save_conversation();
+        }
+        let Some(saved) = crate::agent::history::load(&self.root, id) else {
+            self.set_status("conversation is gone");
+            return;
+        };
+        self.conversation.entries = saved.entries;
+        self.conversation.scroll = 0;
+        self.conversation_id = Some(saved.id);
+        self.conversation_title = Some(saved.title.clone());
+        self.focus = Focus::Agent;
+        self.set_status(format!("opened conversation {}", saved.title));
+    }
+
+    /// Start a fresh conversation, saving the current one away.
+    pub fn new_conversation(&mut self) {
+        self.save_conversation();
+        self.conversation.entries.clear();
+        self.conversation.scroll = 0;
+        self.conversation_id = None;
+        self.conversation_title = None;
+        self.focus = Focus::Agent;
+        self.set_status("new conversation");
+    }
+
+    /// Open (or refresh) the conversation history overlay.
+    pub fn open_conversation_history(&mut self) {
+        self.save_conversation();
+        let items = crate::agent::history::list(&self.root);
+        if items.is_empty() {
+            self.set_status("no saved conversations");
+            return;
+        }
+        self.overlay = Some(Overlay::ConversationHistory { items, selected: 0 });
+    }
+
+    /// Package the current working context into a `.clide-bundle` next
+    /// to the workspace root, with secrets redacted from the
+    /// conversation. File contents stay out; only paths travel.
+    pub fn export_bundle(&mut self) {
+        let rules = self.redaction_rules();
+        let scrub = |text: &str| crate::agent::redact::redact(text, &rules).0;
+        let conversation = self
+            .conversation
+            .entries
+            .iter()
+            .map(|entry| match entry {
+                AgentPanelEntry::Info(t) => AgentPanelEntry::Info(scrub(t)),
+                AgentPanelEntry::User(t) => AgentPanelEntry::User(scrub(t)),
+                AgentPanelEntry::Response(t) => AgentPanelEntry::Response(scrub(t)),
+                AgentPanelEntry::Error(t) => AgentPanelEntry::Error(scrub(t)),
+                AgentPanelEntry::Diff { path, diff } => AgentPanelEntry::Diff {
+                    path: path.clone(),
+                    diff: scrub(diff),
+                },
+                AgentPanelEntry::ToolOutput { name, output } => AgentPanelEntry::ToolOutput {
+                    name: name.clone(),
+                    output: scrub(output),
+                },
+                AgentPanelEntry::Image(path) => AgentPanelEntry::Image(path.clone()),
+            })
+            .collect();
+        let bundle = crate::bundle::Bundle {
+            version: crate::bundle::VERSION,
+            exported_at: chrono::Local::now().format("%Y-%m-%d %H:%M").to_string(),
+            workspace: self
+                .root
+                .file_name()
+                .map(|name| name.to_string_lossy().into_owned())
+                .unwrap_or_else(|| "workspace".to_string()),
+            session: self.current_session(),
+            conversation_title: self.conversation_title.clone(),
+            conversation,
+        };
+        match crate::bundle::export(&self.root, &bundle) {
+            Ok(path) => self.set_status(format!("bundle exported to {}", path.display())),
+            Err(err) => self.set_error(format!("export failed: {err:#}")),
+        }
+    }
+
+    /// Import a bundle: adopt its layout and open files and swap its
+    /// conversation in (the current one is saved away first).
+    pub fn import_bundle(&mut self, input: &str) {
+        let path = Path::new(input);
+        let path = if path.is_absolute() {
+            path.to_path_buf()
+        } else {
+            self.root.join(path)
+        };
+        match crate::bundle::import(&path) {
+            Ok(bundle) => {
+                self.apply_session(&bundle.session);
+                self.save_conversation();
+                self.conversation.entries = bundle.conversation;
+                self.conversation.scroll = 0;
+                self.conversation_id = None;
+                self.conversation_title = bundle.conversation_title;
+                self.set_status(format!("imported bundle from {}", bundle.workspace));
+            }
+            Err(err) => self.set_error(format!("import failed: {err:#}")),
+        }
+    }
+
+    /// Open the browser over connected MCP servers.
+    pub fn open_mcp_browser(&mut self) {
+        let mut rows = Vec::new();
+        for client in &self.agent.mcp.clients {
+            rows.push(McpRow::Server(client.name.clone()));
+            for tool in &client.tools {
+                rows.push(McpRow::Tool {
+                    name: tool.name.clone(),
+                    description: tool.description.clone(),
+                });
+            }
+            for resource in &client.resources {
+                rows.push(McpRow::Resource {
+                    server: client.name.clone(),
+                    uri: resource.uri.clone(),
+                    name: resource.name.clone(),
+                });
+            }
+            for prompt in &client.prompts {
+                rows.push(McpRow::Prompt {
+                    server: client.name.clone(),
+                    name: prompt.name.clone(),
+                    description: prompt.description.clone(),
+                });
+            }
+        }
+        if rows.is_empty() {
+            self.set_status("no MCP servers connected");
+            return;
+        }
+        self.overlay = Some(Overlay::McpBrowser { rows, selected: 0 });
+    }
+
+    /// Run approved agent-proposed shell commands in the terminal pane,
+    /// mirroring their output into a `ToolOutput` conversation entry.
+    pub fn run_agent_commands(&mut self, commands: Vec<String>) {
+        let script = commands
+            .iter()
+            .map(|c| c.trim())
+            .collect::<Vec<_>>()
+            .join("\n");
+        if self.terminal.is_running() {
+            self.set_error("terminal is busy; command not run");
+            return;
+        }
+        self.layout.show_terminal = true;
+        match self.terminal.run_command(&script) {
+            Ok(()) => {
+                self.conversation.push(AgentPanelEntry::ToolOutput {
+                    name: "terminal".to_string(),
+                    output: String::new(),
+                });
+                self.agent_terminal_capture = Some(self.conversation.entries.len() - 1);
+                self.set_status("running agent command in terminal");
+            }
+            Err(err) => self.set_error(format!("terminal: {err:#}")),
+        }
+    }
+
+    /// Ask the active agent to draft a commit message from the staged
+    /// diff; the reply pre-fills the commit prompt for editing. Running
+    /// the command again (or Ctrl+G in the prompt) redrafts.
+    pub fn generate_commit_message(&mut self) {
+        let diff = match self.git.staged_diff() {
+            Ok(diff) => diff,
+            Err(err) => {
+                self.set_error(format!("staged diff: {err:#}"));
+                return;
+            }
+        };
+        if diff.trim().is_empty() {
+            self.set_status("nothing staged to describe");
+            return;
+        }
+        // Huge diffs get truncated rather than blowing the context.
+        let mut diff = diff;
+        if diff.len() > 16_000 {
+            diff.truncate(16_000);
+            diff.push_str("\n[diff truncated]");
+        }
+        let request = AgentRequest {
+            prompt: format!(
+                "Write a git commit message for the staged diff below: one \
+                 imperative summary line under 72 characters, then an optional \
+                 short body. Reply with the message only.\n\n```diff\n{diff}\n```"
+            ),
+            context: None,
+            context_path: None,
+        };
+        self.agent.instructions = crate::agent::instructions::load(&self.root);
+        match self.agent.send(request) {
+            Ok(()) => {
+                self.awaiting_commit_message = true;
+                self.set_status("drafting commit message…");
+            }
+            Err(err) => self.set_error(format!("agent: {err:#}")),
+        }
+    }
+
+    /// Park the outgoing profile's conversation and bring in the new
+    /// active profile's, so each agent keeps its own tab of history.
+    fn swap_agent_tab(&mut self, previous: Option<&str>) {
+        let current = self.agent.active_profile().map(|p| p.name.clone());
+        if previous == current.as_deref() {
+            return;
+        }
+        let incoming = current
+            .and_then(|name| self.parked_conversations.remove(&name))
+            .unwrap_or_default();
+        let outgoing = std::mem::replace(&mut self.conversation, incoming);
+        if let Some(previous) = previous {
+            self.parked_conversations
+                .insert(previous.to_string(), outgoing);
+        }
+        self.streaming_entry = None;
+    }
+
+    /// Open the model picker once the provider's list has arrived.
+    fn on_model_list(&mut self, result: std::result::Result<Vec<String>, String>) {
+        match result {
+            Ok(names) => {
+                use crate::agent::profile::BackendConfig;
+                let current = match self.agent.active_profile().map(|p| &p.backend) {
+                    Some(BackendConfig::HttpApi(http)) => Some(http.model.clone()),
+                    _ => None,
+                };
+                let selected = names
+                    .iter()
+                    .position(|name| Some(name) == current.as_ref())
+                    .unwrap_or(0);
+                self.overlay = Some(Overlay::ModelPicker { names, selected });
+            }
+            Err(err) => self.set_error(format!("model list: {err}")),
+        }
+    }
+
+    /// Switch the active profile to `model`, in memory and in
+    /// `agents.toml`.
+    pub fn select_model(&mut self, model: &str) {
+        use crate::agent::profile::BackendConfig;
+        let Some(profile) = self.agent.active_profile() else {
+            return;
+        };
+        let name = profile.name.clone();
+        if let Some(profile) = self.agent.config.profiles.get_mut(self.agent.active) {
+            if let BackendConfig::HttpApi(http) = &mut profile.backend {
+                http.model = model.to_string();
+            }
+        }
+        match crate::agent::profile::apply_agent_model(&name, model) {
+            Ok(()) => self.set_status(format!("{name} now uses {model}")),
+            Err(err) => self.set_error(format!("model not saved: {err:#}")),
+        }
+    }
+
+    /// Open the viewer over the last transcript log records.
+    pub fn open_transcript_viewer(&mut self) {
+        let records = crate::agent::transcript::tail(&self.root, 50);
+        if records.is_empty() {
+            self.set_status(if crate::agent::transcript::enabled() {
+                "transcript log is empty"
+            } else {
+                "transcript log is off (agent.transcript-log enables it)"
+            });
+            return;
+        }
+        let selected = records.len() - 1;
+        self.overlay = Some(Overlay::TranscriptLog { records, selected });
+    }
+
+    /// Open the project instructions file (`CLIDE.md`), creating it with
+    /// a short template on first use. Edits apply to the next request.
+    pub fn edit_instructions(&mut self) {
+        let path = crate::agent::instructions::path(&self.root);
+        if !path.is_file() {
+            if let Some(parent) = path.parent() {
+                let _ = fs::create_dir_all(parent);
+            }
+            if let Err(err) = fs::write(
+                &path,
+                "# Project instructions\n\nGuidance prepended to every agent request in this workspace.\n",
+            ) {
+                self.set_status(format!("instructions: {err}"));
+                return;
+            }
+        }
+        if let Err(err) = self.open_path(&path) {
+            self.set_status(format!("instructions: {err}"));
+        }
+    }
+
+    /// Act on the selected MCP browser row: resources are read into the
+    /// composer as a fenced block, prompts are expanded into it.
+    pub fn activate_mcp_row(&mut self, row: &McpRow) {
+        match row {
+            McpRow::Server(_) | McpRow::Tool { .. } => {}
+            McpRow::Resource { server, uri, .. } => {
+                let Some(client) = self
+                    .agent
+                    .mcp
+                    .clients
+                    .iter_mut()
+                    .find(|c| &c.name == server)
+                else {
+                    return;
+                };
+                match client.read_resource(uri) {
+                    Ok(text) => {
+                        self.composer
+                            .push_str(&format!("Resource `{uri}`:\n```\n{text}\n```\n"));
+                        self.focus = Focus::Agent;
+                        self.set_status(format!("read {uri} into the composer"));
+                    }
+                    Err(err) => self.set_error(format!("resource read failed: {err:#}")),
+                }
+            }
+            McpRow::Prompt { server, name, .. } => {
+                let Some(client) = self
+                    .agent
+                    .mcp
+                    .clients
+                    .iter_mut()
+                    .find(|c| &c.name == server)
+                else {
+                    return;
+                };
+                match client.get_prompt(name) {
+                    Ok(text) => {
+                        self.composer.push_str(&text);
+                        self.focus = Focus::Agent;
+                        self.set_status(format!("prompt {name} expanded into the composer"));
+                    }
+                    Err(err) => self.set_error(format!("prompt failed: {err:#}")),
+                }
+            }
+        }
+    }
+
+    /// Open the picker over the discovered project tasks.
+    pub fn open_task_picker(&mut self) {
+        let tasks = crate::tasks::discover(&self.root);
+        if tasks.is_empty() {
+            self.set_status("no tasks found (Cargo.toml, package.json, Makefile, .clide/tasks.toml)");
+            return;
+        }
+        self.overlay = Some(Overlay::TaskPicker { tasks, selected: 0 });
+    }
+
+    /// Run one discovered task in the terminal pane; the exit status
+    /// lands in the status bar when it finishes.
+    pub fn run_task(&mut self, task: &crate::tasks::Task) {
+        self.layout.show_terminal = true;
+        match self.terminal.run_command(&task.command) {
+            Ok(()) => {
+                self.running_task = Some(task.name.clone());
+                self.set_status(format!("task {} started", task.name));
+            }
+            Err(err) => self.set_error(format!("task: {err:#}")),
+        }
+    }
+
+    /// Open the per-entry action list over the conversation, landing on
+    /// the newest entry.
+    pub fn open_entry_actions(&mut self) {
+        if self.conversation.entries.is_empty() {
+            self.set_status("conversation is empty");
+            return;
+        }
+        self.overlay = Some(Overlay::EntryActions {
+            selected: self.conversation.entries.len() - 1,
+        });
+    }
+
+    /// Copy the selected conversation entry's text.
+    pub fn copy_entry(&mut self, idx: usize) {
+        if let Some(entry) = self.conversation.entries.get(idx) {
+            let text = entry.text();
+            self.copy_to_clipboard(text);
+        }
+    }
+
+    /// Insert the selected entry's fenced code blocks (or, when it has
+    /// none, the whole body) at the editor cursor.
+    pub fn insert_entry_code(&mut self, idx: usize) {
+        let Some(entry) = self.conversation.entries.get(idx) else {
+            return;
+        };
+        let text = entry.text();
+        let blocks = crate::ui::markdown::code_blocks(&text);
+        let snippet = if blocks.is_empty() {
+            text
+        } else {
+            blocks.join("\n")
+        };
+        self.insert_paste(&snippet);
+        self.set_status("entry inserted at the cursor");
+    }
+
+    /// Load a previous user prompt back into the composer for editing
+    /// and resending.
+    pub fn edit_resend_entry(&mut self, idx: usize) {
+        match self.conversation.entries.get(idx) {
+            Some(AgentPanelEntry::User(text)) => {
+                self.composer = text.clone();
+                self.focus = Focus::Agent;
+                self.set_status("prompt loaded into the composer (Ctrl+Enter resends)");
+            }
+            Some(_) => self.set_status("only user prompts can be resent"),
+            None => {}
+        }
+    }
+
+    /// Remove one entry from the conversation, keeping the live capture
+    /// indices (streamed reply, terminal mirror) pointed at their rows.
+    pub fn delete_entry(&mut self, idx: usize) {
+        if idx >= self.conversation.entries.len() {
+            return;
+        }
+        self.conversation.entries.remove(idx);
+        for slot in [&mut self.streaming_entry, &mut self.agent_terminal_capture] {
+            *slot = match *slot {
+                Some(i) if i == idx => None,
+                Some(i) if i > idx => Some(i - 1),
+                other => other,
+            };
+        }
+    }
+
+    /// Route a pre-built context-action prompt through the composer so
+    /// it gets the usual redaction, RAG, and token accounting.
+    fn ask_agent(&mut self, prompt: String) {
+        self.composer = prompt;
+        self.layout.show_agent = true;
+        self.send_agent_prompt();
+    }
+
+    /// Ask the agent about the current selection, quoted in the prompt
+    /// with the buffer attached as usual.
+    pub fn agent_ask_selection(&mut self) {
+        let Some(buffer) = self.editor.active_buffer() else {
+            return;
+        };
+        let Some(selection) = buffer.selected_text() else {
+            self.set_status("no selection");
+            return;
+        };
+        let language = buffer.language.clone().unwrap_or_default();
+        self.ask_agent(format!(
+            "Explain this selection:\n```{language}\n{selection}\n```"
+        ));
+    }
+
+    /// Ask the agent about the diagnostic under the cursor, sending its
+    /// message and a few surrounding lines of code.
+    pub fn agent_explain_error(&mut self) {
+        let Some(buffer) = self.editor.active_buffer() else {
+            return;
+        };
+        let Some(path) = buffer.path.clone() else {
+            self.set_status("buffer has no file name");
+            return;
+        };
+        let line = buffer.cursor.line;
+        let Some(diag) = self.diagnostics.get(&path).and_then(|diags| {
+            diags.iter().find(|d| {
+                d.range.start.line as usize <= line && line <= d.range.end.line as usize
+            })
+        }) else {
+            self.set_status("no diagnostic under the cursor");
+            return;
+        };
+        let diag = diag.clone();
+        let text = buffer.rope.to_string();
+        let lines: Vec<&str> = text.lines().collect();
+        let start = (diag.range.start.line as usize).saturating_sub(5);
+        let end = (diag.range.end.line as usize + 6).min(lines.len());
+        let snippet = lines[start..end.max(start)].join("\n");
+        let code = diag
+            .code
+            .as_ref()
+            .map(|c| format!(" [{c}]"))
+            .unwrap_or_default();
+        let language = buffer.language.clone().unwrap_or_default();
+        self.ask_agent(format!(
+            "Explain this diagnostic{code} and how to fix it:\n{}\n\nThe code around it \
+             (line {}):\n```{language}\n{snippet}\n```",
+            diag.message,
+            diag.range.start.line + 1,
+        ));
+    }
+
+    /// Ask the agent to write tests for the selection, or for the
+    /// function enclosing the cursor.
+    pub fn agent_generate_tests(&mut self) {
+        let Some(buffer) = self.editor.active_buffer() else {
+            return;
+        };
+        if let Some(selection) = buffer.selected_text() {
+            let language = buffer.language.clone().unwrap_or_default();
+            self.ask_agent(format!(
+                "Write tests for this code, in the project's existing test \
+                 style:\n```{language}\n{selection}\n```"
+            ));
+            return;
+        }
+        let Some((signature, line)) = self.enclosing_symbol() else {
+            self.set_status("no enclosing function (select code to test instead)");
+            return;
+        };
+        self.ask_agent(format!(
+            "Write tests for `{signature}` (line {}) in the attached file, in the \
+             project's existing test style.",
+            line + 1
+        ));
+    }
+
+    /// Open the picker over the `[agent.templates]` library.
+    pub fn open_template_picker(&mut self) {
+        let mut names: Vec<String> = self.config.agent.templates.keys().cloned().collect();
+        if names.is_empty() {
+            self.set_status("no prompt templates ([agent.templates] in config.toml)");
+            return;
+        }
+        names.sort();
+        self.overlay = Some(Overlay::TemplatePicker { names, selected: 0 });
+    }
+
+    /// Current values for the template variables, from the active buffer
+    /// and its diagnostics.
+    fn template_vars(&self) -> crate::agent::templates::TemplateVars {
+        let buffer = self.editor.active_buffer();
+        crate::agent::templates::TemplateVars {
+            selection: buffer.and_then(|b| b.selected_text()),
+            file: buffer
+                .and_then(|b| b.path.as_ref())
+                .map(|p| p.display().to_string()),
+            diagnostics: buffer
+                .and_then(|b| b.path.as_ref())
+                .and_then(|path| self.diagnostics.get(path))
+                .filter(|diags| !diags.is_empty())
+                .map(|diags| {
+                    diags
+                        .iter()
+                        .map(|d| format!("line {}: {}", d.range.start.line + 1, d.message))
+                        .collect::<Vec<_>>()
+                        .join("\n")
+                }),
+        }
+    }
+
+    /// Expand the named template into the composer, variables filled in.
+    pub fn insert_template(&mut self, name: &str) {
+        let Some(body) = self.config.agent.templates.get(name).cloned() else {
+            self.set_status(format!("no template named {name}"));
+            return;
+        };
+        let text = crate::agent::templates::expand(&body, &self.template_vars());
+        if !self.composer.is_empty() && !self.composer.ends_with(char::is_whitespace) {
+            self.composer.push(' ');
+        }
+        self.composer.push_str(&text);
+        self.focus = Focus::Agent;
+        self.set_status(format!("template {name} expanded into the composer"));
+    }
+
+    /// Prompt for the export file name, pre-filled with a dated default.
+    pub fn open_export_prompt(&mut self) {
+        if self.conversation.entries.is_empty() {
+            self.set_status("conversation is empty");
+            return;
+        }
+        let default = format!(
+            "conversation-{}.md",
+            chrono::Local::now().format("%Y%m%d-%H%M%S")
+        );
+        self.overlay = Some(Overlay::Prompt {
+            action: PromptAction::ExportConversation,
+            input: default,
+        });
+    }
+
+    /// Write the conversation to the named workspace file — JSON when
+    /// the name ends in `.json`, Markdown otherwise — and open the
+    /// result for review.
+    pub fn export_conversation(&mut self, name: &str) {
+        let name = name.trim();
+        if name.is_empty() {
+            self.set_status("export needs a file name");
+            return;
+        }
+        let path = self.root.join(name);
+        let title = self
+            .conversation_title
+            .clone()
+            .unwrap_or_else(|| crate::agent::history::derive_title(&self.conversation.entries));
+        let body = if name.ends_with(".json") {
+            match serde_json::to_string_pretty(&self.conversation.entries) {
+                Ok(json) => json,
+                Err(err) => {
+                    self.set_error(format!("export failed: {err:#}"));
+                    return;
+                }
+            }
+        } else {
+            crate::agent::history::export_markdown(&title, &self.conversation.entries)
+        };
+        if let Some(parent) = path.parent() {
+            let _ = fs::create_dir_all(parent);
+        }
+        if let Err(err) = fs::write(&path, body) {
+            self.set_error(format!("export failed: {err:#}"));
+            return;
+        }
+        self.set_status(format!(
+            "exported {} entries to {name}",
+            self.conversation.entries.len()
+        ));
+        if let Err(err) = self.open_path(&path) {
+            self.set_error(format!("open failed: {err:#}"));
+        }
+    }
+
+    /// Open the keyword search over all saved conversations, with the
+    /// live one persisted first so it is searchable too.
+    pub fn open_agent_history_search(&mut self) {
+        self.save_conversation();
+        self.overlay = Some(Overlay::AgentHistorySearch {
+            input: String::new(),
+            hits: Vec::new(),
+            selected: 0,
+        });
+    }
+
+    /// Reopen the conversation a search hit belongs to and scroll the
+    /// panel near the matching entry.
+    pub fn open_history_hit(&mut self, hit: &crate::agent::history::SearchHit) {
+        self.open_conversation(&hit.id);
+        // Entries render as one or more lines; the entry index is a
+        // lower bound, clamped against the real line count at render.
+        // Scroll 0 means "pinned to bottom", so land on 1 at worst.
+        self.conversation.scroll = hit.entry.max(1);
+    }
+
+    /// Queue a rename prompt for the conversation selected in the
+    /// history overlay.
+    pub fn prompt_rename_conversation(&mut self, id: String) {
+        self.pending_conversation_rename = Some(id);
+        self.overlay = Some(Overlay::Prompt {
+            action: PromptAction::RenameConversation,
+            input: String::new(),
+        });
+    }
+
+    /// Delete one saved conversation; clears the live id when it was the
+    /// one loaded so a later save re-creates it fresh.
+    pub fn delete_conversation(&mut self, id: &str) {
+        match crate::agent::history::delete(&self.root, id) {
+            Ok(()) => {
+                if self.conversation_id.as_deref() == Some(id) {
+                    self.conversation_id = None;
+                }
+                self.set_status("conversation deleted");
+            }
+            Err(err) => self.set_error(format!("delete failed: {err:#}")),
+        }
+    }
+
+    pub fn request_rename(&mut self, new_name: &str) {
+        if let Some((path, pos)) = self.cursor_lsp_position() {
+            if let Some(lsp) = &mut self.lsp {
+                let _ = lsp.rename(&path, pos, new_name);
+                self.set_status("rename requested…");
+            } else {
+                self.set_status("no language server running");
+            }
+        }
+    }
+
+    /// Handle the input of a completed prompt overlay.
+    pub fn complete_prompt(&mut self, action: PromptAction, input: &str) {
+        let input = input.trim();
+        if input.is_empty() {
+            return;
+        }
+        match action {
+            PromptAction::NewFile => match self.tree.create_file(input) {
+                Ok(path) => {
+                    let _ = self.open_path(&path);
+                    if self.header_auto_applies(&path) {
+                        self.insert_file_header();
+                    }
+                    self.set_status(format!("created {}", path.display()));
+                }
+                Err(err) => self.set_error(format!("create failed: {err:#}")),
+            },
+            PromptAction::NewDirectory => match self.tree.create_dir(input) {
+                Ok(path) => self.set_status(format!("created {}", path.display())),
+                Err(err) => self.set_error(format!("create failed: {err:#}")),
+            },
+            PromptAction::RenameEntry => match self.tree.rename_selected(input) {
+                Ok(path) => self.set_status(format!("renamed to {}", path.display())),
+                Err(err) => self.set_error(format!("rename failed: {err:#}")),
+            },
+            PromptAction::GotoLine => {
+                if let Ok(line) = input.parse::<usize>() {
+                    self.push_jump();
+                    if let Some(buffer) = self.editor.active_buffer_mut() {
+                        buffer.goto_line(line.saturating_sub(1));
+                    }
+                }
+            }
+            PromptAction::RenameSymbol => self.request_rename(input),
+            PromptAction::AgentApiKey => {
+                let Some(profile) = self.agent.active_profile() else {
+                    self.set_status("no active agent profile");
+                    return;
+                };
+                let name = profile.name.clone();
+                match crate::agent::profile::apply_agent_api_key(&name, input) {
+                    Ok(place) => self.set_status(format!("API key for {name} stored in {place}")),
+                    Err(err) => self.set_error(format!("key not stored: {err:#}")),
+                }
+            }
+            PromptAction::RenameConversation => {
+                let Some(id) = self.pending_conversation_rename.take() else {
+                    return;
+                };
+                match crate::agent::history::rename(&self.root, &id, input) {
+                    Ok(()) => {
+                        if self.conversation_id.as_deref() == Some(id.as_str()) {
+                            self.conversation_title = Some(input.to_string());
+                        }
+                        self.open_conversation_history();
+                    }
+                    Err(err) => self.set_error(format!("rename failed: {err:#}")),
+                }
+            }
+            PromptAction::ImportBundle => self.import_bundle(input),
+            PromptAction::ExportConversation => self.export_conversation(input),
+            PromptAction::CommitMessage => match self.git.commit(input) {
+                Ok(()) => self.set_status("committed"),
+                Err(err) => self.set_error(format!("commit failed: {err:#}")),
+            },
+            PromptAction::AgentBatchFiles => {
+                let prefix = self.root.join(input);
+                let files: Vec<PathBuf> = walk_files(&self.root, 10_000)
+                    .into_iter()
+                    .filter(|path| path.starts_with(&prefix))
+                    .collect();
+                if files.is_empty() {
+                    self.set_status(format!("no files under {input}"));
+                    return;
+                }
+                self.pending_batch_files = files;
+                self.overlay = Some(Overlay::Prompt {
+                    action: PromptAction::AgentBatchPrompt,
+                    input: String::new(),
+                });
+            }
+            PromptAction::AgentBatchPrompt => {
+                let files = std::mem::take(&mut self.pending_batch_files);
+                let count = files.len();
+                self.batch = Some(BatchRun::new(input.to_string(), files));
+                self.conversation.push(AgentPanelEntry::Info(format!(
+                    "batch started: {count} file(s), one request every {}s",
+                    crate::agent::batch::SEND_INTERVAL.as_secs()
+                )));
+                self.pump_batch();
+            }
+            PromptAction::DecryptSecret => {
+                let Some((path, kind)) = self.pending_decrypt.take() else {
+                    return;
+                };
+                match self.editor.open_decrypted(&path, kind, input) {
+                    Ok(_) => {
+                        // Deliberately no LSP didOpen: the plaintext stays
+                        // inside this process.
+                        self.focus = Focus::Editor;
+                        self.set_status(format!("decrypted {}", path.display()));
+                    }
+                    Err(err) => self.set_error(format!("decrypt failed: {err:#}")),
+                }
+            }
+        }
+    }
+
+    /// Build the preview overlay for a multi-file workspace edit instead of
+    /// applying it blindly.
+    pub fn preview_workspace_edit(&mut self, title: &str, edit: WorkspaceEdit) {
+        let mut files: Vec<PendingFileEdit> = Vec::new();
+        for (uri, edits) in edit.changes {
+            let Some(path) = uri_to_path(&uri) else { continue };
+            if edits.is_empty() {
+                continue;
+            }
+            let preview = self.edit_preview_lines(&path, &edits);
+            files.push(PendingFileEdit {
+                path,
+                edits,
+                included: true,
+                preview,
+            });
+        }
+        if files.is_empty() {
+            self.set_status("workspace edit contained no changes");
+            return;
+        }
+        files.sort_by(|a, b| a.path.cmp(&b.path));
+        self.overlay = Some(Overlay::WorkspaceEditPreview(WorkspaceEditPreviewState {
+            title: title.to_string(),
+            files,
+            selected: 0,
+        }));
+    }
+
+    /// "line N: before -> after" strings for the preview list.
+    fn edit_preview_lines(&self, path: &Path, edits: &[TextEdit]) -> Vec<String> {
+        let text = match self.editor.buffer_for_path(path) {
+            Some(idx) => self.editor.buffers[idx].rope.to_string(),
+            None => fs::read_to_string(path).unwrap_or_default(),
+        };
+        let lines: Vec<&str> = text.lines().collect();
+        edits
+            .iter()
+            .take(8)
+            .map(|edit| {
+                let line_no = edit.range.start.line as usize;
+                let before = lines.get(line_no).map(|l| l.trim()).unwrap_or("");
+                format!(
+                    "  {}: {} -> `{}`",
+                    line_no + 1,
+                    truncate(before, 40),
+                    truncate(&edit.new_text, 20)
+                )
+            })
+            .collect()
+    }
+
+    /// Apply the included files of a previewed workspace edit and report
+    /// how many files were touched.
+    pub fn apply_workspace_edit(&mut self, state: WorkspaceEditPreviewState) {
+        let mut applied = 0usize;
+        let mut failed = 0usize;
+        for file in state.files.iter().filter(|f| f.included) {
+            match self.apply_edits_to_file(&file.path, &file.edits) {
+                Ok(()) => applied += 1,
+                Err(_) => failed += 1,
+            }
+        }
+        if failed > 0 {
+            self.set_status(format!(
+                "{}: modified {applied} file(s), {failed} failed",
+                state.title
+            ));
+        } else {
+            self.set_status(format!("{}: modified {applied} file(s)", state.title));
+        }
+        self.refresh_git();
+    }
+
+    /// Apply LSP text edits to a file, through the open buffer when there
+    /// is one, otherwise directly on disk.
+    fn apply_edits_to_file(&mut self, path: &Path, edits: &[TextEdit]) -> Result<()> {
+        let mut sorted: Vec<&TextEdit> = edits.iter().collect();
+        sorted.sort_by_key(|e| (e.range.start.line, e.range.start.character));
+        sorted.reverse();
+        if let Some(idx) = self.editor.buffer_for_path(path) {
+            let buffer = &mut self.editor.buffers[idx];
+            for edit in sorted {
+                let start = buffer.char_index(Position {
+                    line: edit.range.start.line as usize,
+                    col: edit.range.start.character as usize,
+                });
+                let end = buffer.char_index(Position {
+                    line: edit.range.end.line as usize,
+                    col: edit.range.end.character as usize,
+                });
+                buffer.replace_range(start, end, &edit.new_text);
+            }
+            let version = buffer.version;
+            let text = buffer.rope.to_string();
+            if let Some(lsp) = &mut self.lsp {
+                let _ = lsp.did_change(path, version, &text);
+            }
+        } else {
+            let text = fs::read_to_string(path)?;
+            let updated = apply_edits_to_text(&text, &sorted);
+            fs::write(path, updated)?;
+        }
+        Ok(())
+    }
+
+    /// Run the workspace search for the replace-in-files overlay.
+    pub fn run_search(&self, state: &mut SearchReplaceState) {
+        state.files.clear();
+        state.selected = 0;
+        state.searched = true;
+        if state.query.is_empty() {
+            return;
+        }
+        for path in walk_files(&self.root, 5_000) {
+            let Ok(text) = fs::read_to_string(&path) else {
+                continue;
+            };
+            let matches: Vec<(usize, String)> = text
+                .lines()
+                .enumerate()
+                .filter(|(_, line)| line.contains(&state.query))
+                .map(|(i, line)| (i + 1, truncate(line.trim(), 80).to_string()))
+                .collect();
+            if !matches.is_empty() {
+                state.files.push(FileMatches {
+                    path,
+                    matches,
+                    included: true,
+                });
+            }
+        }
+    }
+
+    /// Apply the replacement to every included file of a finished search.
+    pub fn apply_replace(&mut self, state: &SearchReplaceState) {
+        let mut applied = 0usize;
+        for file in state.files.iter().filter(|f| f.included) {
+            let Ok(text) = fs::read_to_string(&file.path) else {
+                continue;
+            };
+            let updated = text.replace(&state.query, &state.replacement);
+            if updated != text && fs::write(&file.path, updated).is_ok() {
+                applied += 1;
+                if let Some(idx) = self.editor.buffer_for_path(&file.path) {
+                    // Reload the open buffer from disk so it doesn't diverge.
+                    let buffer = &mut self.editor.buffers[idx];
+                    if let Ok(new_text) = fs::read_to_string(&file.path) {
+                        buffer.replace_range(
+                            0,
+                            buffer.rope.len_chars(),
+                            &new_text.replace("\r\n", "\n"),
+                        );
+                        buffer.dirty = false;
+                    }
+                }
+            }
+        }
+        self.set_status(format!("replaced in {applied} file(s)"));
+        self.refresh_git();
+    }
+
+    /// Move the editor cursor to the clicked cell. Wrapped rows are not
+    /// accounted for; the click maps to (row, column) in buffer space.
+    /// The buffer position under a screen coordinate inside the editor
+    /// pane.
+    fn editor_point(&self, x: u16, y: u16) -> Option<Position> {
+        let area = self.layout.editor_area;
+        let show_line_numbers = self.editor.prefs.show_line_numbers;
+        let buffer = self.editor.active_buffer()?;
+        let inner_x = x.saturating_sub(area.x + 1) as usize;
+        let inner_y = y.saturating_sub(area.y + 1) as usize;
+        let gutter = if show_line_numbers {
+            (buffer.line_count().max(1).ilog10() as usize + 2).max(4)
+        } else {
+            0
+        };
+        Some(Position {
+            line: (buffer.scroll_line + inner_y).min(buffer.line_count().saturating_sub(1)),
+            col: inner_x.saturating_sub(gutter) + buffer.scroll_col,
+        })
+    }
+
+    pub fn editor_click(&mut self, x: u16, y: u16, add_caret: bool) {
+        let Some(clicked) = self.editor_point(x, y) else {
+            return;
+        };
+        // Successive clicks on the same spot widen the selection:
+        // click, word, line, then back to a plain click.
+        let now = Instant::now();
+        let count = match self.last_editor_click {
+            Some((at, pos, n)) if now.duration_since(at) < MULTI_CLICK_WINDOW && pos == clicked => {
+                n % 3 + 1
+            }
+            _ => 1,
+        };
+        self.last_editor_click = Some((now, clicked, count));
+        let buffer = self.editor.active_buffer_mut().expect("checked above");
+        if add_caret {
+            buffer.add_caret(clicked);
+            return;
+        }
+        buffer.cursor = clicked;
+        buffer.extra_cursors.clear();
+        buffer.clamp_cursor();
+        match count {
+            2 => buffer.select_word(),
+            3 => buffer.select_line(),
+            // Anchor at the press point so a subsequent drag selects;
+            // an anchor equal to the cursor is no selection.
+            _ => buffer.anchor = Some(buffer.cursor),
+        }
+        self.editor_drag = count == 1;
+    }
+
+    /// Extend the drag selection toward the pointer, scrolling when it
+    /// rests on the pane's top or bottom border.
+    pub fn editor_drag_move(&mut self, x: u16, y: u16) {
+        if !self.editor_drag {
+            return;
+        }
+        let area = self.layout.editor_area;
+        if let Some(buffer) = self.editor.active_buffer_mut() {
+            if y <= area.y {
+                buffer.scroll_line = buffer.scroll_line.saturating_sub(1);
+            } else if area.height >= 1 && y >= area.y + area.height - 1 {
+                buffer.scroll_line =
+                    (buffer.scroll_line + 1).min(buffer.line_count().saturating_sub(1));
+            }
+        }
+        let Some(point) = self.editor_point(x, y) else {
+            return;
+        };
+        if let Some(buffer) = self.editor.active_buffer_mut() {
+            buffer.cursor = point;
+            buffer.clamp_cursor();
+        }
+    }
+
+    pub fn editor_drag_end(&mut self) {
+        self.editor_drag = false;
+    }
+
+    /// Begin a scrollbar drag and jump the pane to the clicked row.
+    pub fn scrollbar_click(&mut self, sb: crate::layout::Scrollbar, y: u16) {
+        self.scrollbar_drag = Some(sb);
+        self.scroll_to_offset(sb, y);
+    }
+
+    pub fn scrollbar_drag_move(&mut self, y: u16) {
+        if let Some(sb) = self.scrollbar_drag {
+            self.scroll_to_offset(sb, y);
+        }
+    }
+
+    pub fn scrollbar_drag_end(&mut self) {
+        self.scrollbar_drag = None;
+    }
+
+    fn scroll_to_offset(&mut self, sb: crate::layout::Scrollbar, y: u16) {
+        let offset = sb.offset_for(y);
+        let at_bottom = offset + sb.viewport >= sb.total;
+        match sb.pane {
+            Focus::Editor => {
+                if let Some(buffer) = self.editor.active_buffer_mut() {
+                    buffer.scroll_line = offset;
+                    if buffer.follow {
+                        buffer.follow_paused = !at_bottom;
+                    }
+                }
+            }
+            Focus::Terminal => {
+                self.terminal.scroll = offset;
+                self.terminal.follow = at_bottom;
+            }
+            Focus::Agent => {
+                // Zero means "follow the bottom" for the conversation, so
+                // dragging to the very top lands one row below it.
+                self.conversation.scroll = if at_bottom { 0 } else { offset.max(1) };
+            }
+            _ => {}
+        }
+    }
+
+    pub fn tree_click(&mut self, y: u16) {
+        let inner_y = y.saturating_sub(self.layout.tree_area.y + 1) as usize;
+        let idx = self.tree.scroll + inner_y;
+        if idx < self.tree.entries.len() {
+            if self.tree.selected == idx {
+                if let Some(path) = self.tree.activate_selected() {
+                    if let Err(err) = self.open_path(&path) {
+                        self.set_error(format!("open failed: {err:#}"));
+                    }
+                }
+            } else {
+                self.tree.selected = idx;
+            }
+        }
+    }
+
+    /// Remember the file under a tree-pane press as a drag candidate.
+    /// Directories stay click-only.
+    pub fn tree_drag_start(&mut self, y: u16) {
+        let inner_y = y.saturating_sub(self.layout.tree_area.y + 1) as usize;
+        let idx = self.tree.scroll + inner_y;
+        self.tree_drag = self
+            .tree
+            .entries
+            .get(idx)
+            .filter(|entry| !entry.is_dir)
+            .map(|entry| TreeDrag {
+                path: entry.path.clone(),
+                armed: false,
+            });
+    }
+
+    /// Track pointer movement while the button is held; the drag arms
+    /// once the pointer leaves the tree pane.
+    pub fn tree_drag_move(&mut self, x: u16, y: u16) {
+        let pane = self.layout.pane_at(x, y);
+        let Some(drag) = self.tree_drag.as_mut() else {
+            return;
+        };
+        if !drag.armed && pane != Some(Focus::Tree) {
+            drag.armed = true;
+        }
+        if drag.armed {
+            let name = drag
+                .path
+                .file_name()
+                .map(|n| n.to_string_lossy().into_owned())
+                .unwrap_or_default();
+            let target = match pane {
+                Some(Focus::Editor) => "drop to open in editor",
+                Some(Focus::Terminal) => "drop to insert path in terminal",
+                Some(Focus::Agent) => "drop to attach as agent context",
+                _ => "drag to editor, terminal, or agent",
+            };
+            self.set_status(format!("dragging {name} — {target}"));
+        }
+    }
+
+    /// Complete (or abandon) a tree drag on button release.
+    pub fn tree_drag_drop(&mut self, x: u16, y: u16) {
+        let Some(drag) = self.tree_drag.take() else {
+            return;
+        };
+        if !drag.armed {
+            return;
+        }
+        match self.layout.pane_at(x, y) {
+            Some(Focus::Editor) => {
+                self.focus = Focus::Editor;
+                if let Err(err) = self.open_path(&drag.path) {
+                    self.set_error(format!("open failed: {err:#}"));
+                }
+            }
+            Some(Focus::Terminal) => {
+                self.focus = Focus::Terminal;
+                let quoted = shell_quote(&drag.path.to_string_lossy());
+                if !self.terminal.input.is_empty() && !self.terminal.input.ends_with(' ') {
+                    self.terminal.input.push(' ');
+                }
+                self.terminal.input.push_str(&quoted);
+                self.set_status(format!("inserted {quoted}"));
+            }
+            Some(Focus::Agent) => {
+                self.focus = Focus::Agent;
+                let display = self
+                    .workspace_relative(&drag.path)
+                    .to_string_lossy()
+                    .into_owned();
+                self.agent_attachment = Some(drag.path);
+                self.conversation.push(AgentPanelEntry::Info(format!(
+                    "attached {display} as context for the next prompt"
+                )));
+                self.set_status(format!("attached {display}"));
+            }
+            _ => self.set_status("drag cancelled"),
+        }
+    }
+
+    /// Preset names offered by the layout picker: built-ins first, then
+    /// config-defined ones not shadowing a built-in.
+    pub fn layout_preset_names(&self) -> Vec<String> {
+        let mut names: Vec<String> = builtin_layout_presets()
+            .iter()
+            .map(|(name, _)| (*name).to_string())
+            .collect();
+        for name in self.config.layout_presets.keys() {
+            if !names.iter().any(|n| n == name) {
+                names.push(name.clone());
+            }
+        }
+        names
+    }
+
+    /// Apply a named layout preset; config presets override built-ins
+    /// of the same name, and unset fields keep their current value.
+    pub fn apply_layout_preset(&mut self, name: &str) {
+        let preset = self.config.layout_presets.get(name).copied().or_else(|| {
+            builtin_layout_presets()
+                .iter()
+                .find(|(n, _)| *n == name)
+                .map(|(_, preset)| *preset)
+        });
+        let Some(preset) = preset else {
+            self.set_status(format!("unknown layout preset {name:?}"));
+            return;
+        };
+        let layout = &mut self.layout;
+        if let Some(v) = preset.tree_ratio {
+            layout.tree_ratio = v;
+        }
+        if let Some(v) = preset.agent_ratio {
+            layout.agent_ratio = v;
+        }
+        if let Some(v) = preset.terminal_ratio {
+            layout.terminal_ratio = v;
+        }
+        if let Some(v) = preset.show_tree {
+            layout.show_tree = v;
+        }
+        if let Some(v) = preset.show_terminal {
+            layout.show_terminal = v;
+        }
+        if let Some(v) = preset.show_agent {
+            layout.show_agent = v;
+        }
+        if let Some(v) = preset.show_git {
+            layout.show_git = v;
+        }
+        self.set_status(format!("layout: {name}"));
+    }
+
+    /// The breadcrumb segments for the active buffer: directory, file
+    /// name, and (when known) the symbol enclosing the cursor.
+    pub fn breadcrumb_segments(&self) -> Vec<(String, BreadcrumbTarget)> {
+        let Some(buffer) = self.editor.active_buffer() else {
+            return Vec::new();
+        };
+        let mut segments = Vec::new();
+        match &buffer.path {
+            Some(path) => {
+                let rel = self.workspace_relative(path);
+                if let Some(dir) = rel.parent().filter(|d| !d.as_os_str().is_empty()) {
+                    segments.push((dir.display().to_string(), BreadcrumbTarget::Tree));
+                }
+                let name = rel
+                    .file_name()
+                    .map(|n| n.to_string_lossy().into_owned())
+                    .unwrap_or_else(|| rel.display().to_string());
+                segments.push((name, BreadcrumbTarget::Editor));
+            }
+            None => segments.push(("[scratch]".to_string(), BreadcrumbTarget::Editor)),
+        }
+        if let Some((name, line)) = self.enclosing_symbol() {
+            segments.push((name, BreadcrumbTarget::Line(line)));
+        }
+        segments
+    }
+
+    /// The innermost symbol containing the cursor, from the LSP cache
+    /// when the server has answered, else the heuristic outline.
+    fn enclosing_symbol(&self) -> Option<(String, usize)> {
+        let buffer = self.editor.active_buffer()?;
+        let line = buffer.cursor.line;
+        if let Some(symbols) = buffer.path.as_ref().and_then(|p| self.symbols.get(p)) {
+            return symbols
+                .iter()
+                .filter(|(_, range)| {
+                    range.start.line as usize <= line && line <= range.end.line as usize
+                })
+                .max_by_key(|(_, range)| range.start.line)
+                .map(|(name, range)| (name.clone(), range.start.line as usize));
+        }
+        let text = buffer.rope.to_string();
+        let lines: Vec<&str> = text.lines().collect();
+        crate::agent::context::outline(&lines, buffer.language.as_deref())
+            .into_iter()
+            .rev()
+            .find(|symbol| symbol.line <= line)
+            .map(|symbol| (symbol.signature, symbol.line))
+    }
+
+    /// A click on the breadcrumb strip: the directory segment reveals
+    /// the tree, the file segment focuses the editor, and the symbol
+    /// segment jumps to its definition line.
+    pub fn breadcrumb_click(&mut self, x: u16) {
+        let area = self.layout.breadcrumb_area;
+        let mut col = area.x + 1;
+        for (text, target) in self.breadcrumb_segments() {
+            let width = text.chars().count() as u16;
+            if x >= col && x < col + width {
+                match target {
+                    BreadcrumbTarget::Tree => self.focus = Focus::Tree,
+                    BreadcrumbTarget::Editor => self.focus = Focus::Editor,
+                    BreadcrumbTarget::Line(line) => {
+                        self.push_jump();
+                        if let Some(buffer) = self.editor.active_buffer_mut() {
+                            buffer.cursor = Position { line, col: 0 };
+                            buffer.anchor = None;
+                        }
+                        self.focus = Focus::Editor;
+                    }
+                }
+                return;
+            }
+            col += width + 3;
+        }
+    }
+
+    /// A path relative to the workspace root when it lives inside it.
+    pub fn workspace_relative<'a>(&self, path: &'a Path) -> &'a Path {
+        path.strip_prefix(&self.root).unwrap_or(path)
+    }
+
+    pub fn git_click(&mut self, y: u16) {
+        let inner_y = y.saturating_sub(self.layout.git_area.y + 1) as usize;
+        if inner_y < self.git.entries.len() {
+            self.git.selected = inner_y;
+        }
+    }
+
+    pub fn scroll_pane_at(&mut self, x: u16, y: u16, delta: isize) {
+        match self.layout.pane_at(x, y) {
+            Some(Focus::Editor) => {
+                let viewport = self.layout.editor_area.height.saturating_sub(2) as usize;
+                if let Some(buffer) = self.editor.active_buffer_mut() {
+                    let line = buffer.scroll_line as isize + delta;
+                    buffer.scroll_line =
+                        line.clamp(0, buffer.line_count() as isize - 1) as usize;
+                    if buffer.follow {
+                        if delta < 0 {
+                            // Scrolling up pauses follow; park the cursor on
+                            // the top visible line so the viewport stays put.
+                            buffer.follow_paused = true;
+                            buffer.cursor.line = buffer.scroll_line;
+                            buffer.clamp_cursor();
+                        } else if buffer.scroll_line + viewport >= buffer.line_count() {
+                            buffer.follow_paused = false;
+                            buffer.move_to_end();
+                        }
+                    }
+                }
+            }
+            Some(Focus::Terminal) => {
+                if delta < 0 {
+                    self.terminal.scroll_up(delta.unsigned_abs());
+                } else {
+                    let viewport = self.layout.terminal_area.height.saturating_sub(3) as usize;
+                    self.terminal.scroll_down(delta as usize, viewport);
+                }
+            }
+            Some(Focus::Tree) => self.tree.move_selection(delta),
+            Some(Focus::Git) => self.git.move_selection(delta),
+            Some(Focus::Agent) => {
+                if delta < 0 {
+                    self.conversation.scroll =
+                        self.conversation.scroll.saturating_sub(delta.unsigned_abs());
+                } else {
+                    self.conversation.scroll += delta as usize;
+                }
+            }
+            None => {}
+        }
+    }
+
+    pub fn shutdown(&mut self) {
+        self.save_conversation();
+        self.save_session();
+        crate::recovery::discard_all(&self.root);
+        if let Some(lsp) = &mut self.lsp {
+            lsp.shutdown();
+        }
+        self.terminal.kill();
+    }
+}
+
+/// Apply pre-sorted (reverse document order) edits to plain text.
+fn apply_edits_to_text(text: &str, sorted_desc: &[&TextEdit]) -> String {
+    let mut lines: Vec<String> = text.split('\n').map(str::to_string).collect();
+    for edit in sorted_desc {
+        let start_line = edit.range.start.line as usize;
+        let end_line = edit.range.end.line as usize;
+        if start_line >= lines.len() || end_line >= lines.len() {
+            continue;
+        }
+        let start_col = (edit.range.start.character as usize).min(lines[start_line].chars().count());
+        let end_col = (edit.range.end.character as usize).min(lines[end_line].chars().count());
+        let prefix: String = lines[start_line].chars().take(start_col).collect();
+        let suffix: String = lines[end_line].chars().skip(end_col).collect();
+        let replacement = format!("{prefix}{}{suffix}", edit.new_text);
+        lines.splice(start_line..=end_line, replacement.split('\n').map(str::to_string));
+    }
+    lines.join("\n")
+}
+
+fn truncate(s: &str, max: usize) -> &str {
+    match s.char_indices().nth(max) {
+        Some((idx, _)) => &s[..idx],
+        None => s,
+    }
+}
+
+/// Drop a wrapping ``` fence pair if the reply arrived as a code block.
+fn strip_code_fences(text: &str) -> &str {
+    let trimmed = text.trim();
+    let Some(rest) = trimmed.strip_prefix("```") else {
+        return trimmed;
+    };
+    let rest = rest.split_once('\n').map_or("", |(_, body)| body);
+    rest.strip_suffix("```").unwrap_or(rest)
+}
+
+/// Apply the set fields of an `[editor]` (or `[language.<name>]`)
+/// config table onto live preferences.
+fn apply_editor_section(prefs: &mut EditorPreferences, section: &EditorSection) {
+    match (section.use_tabs, section.tab_width) {
+        (Some(true), _) => prefs.indent = IndentKind::Tabs,
+        (_, Some(width)) if width > 0 => prefs.indent = IndentKind::Spaces(width),
+        _ => {}
+    }
+    if let Some(wrap) = section.wrap {
+        prefs.wrap_mode = if wrap {
+            WrapMode::WordWrap
+        } else {
+            WrapMode::NoWrap
+        };
+    }
+    if let Some(show) = section.line_numbers {
+        prefs.show_line_numbers = show;
+    }
+    if let Some(relative) = section.relative_line_numbers {
+        prefs.relative_line_numbers = relative;
+    }
+    if let Some(auto) = section.auto_indent {
+        prefs.auto_indent = auto;
+    }
+    if let Some(auto) = section.auto_close {
+        prefs.auto_close = auto;
+    }
+    if let Some(show) = section.show_stats {
+        prefs.show_stats = show;
+    }
+    if let Some(vim) = section.vim {
+        prefs.vim_mode = vim;
+    }
+    if let Some(show) = section.show_whitespace {
+        prefs.show_whitespace = show;
+    }
+    if let Some(trim) = section.trim_trailing_whitespace {
+        prefs.trim_trailing_whitespace = trim;
+    }
+    if let Some(newline) = section.final_newline {
+        prefs.final_newline = newline;
+    }
+}
+
+/// Chunked read plus decode for [`App::spawn_file_load`], reporting
+/// progress after every chunk. `Ok(None)` means the load was cancelled.
+fn load_file(
+    path: &Path,
+    total: u64,
+    ctx: &crate::task::TaskContext,
+) -> Result<Option<(String, Encoding)>> {
+    use std::io::Read as _;
+
+    let mut file = fs::File::open(path)
+        .with_context(|| format!("failed to open {}", path.display()))?;
+    let mut bytes = Vec::with_capacity(total as usize);
+    let mut chunk = vec![0u8; 4 * 1024 * 1024];
+    loop {
+        if ctx.is_cancelled() {
+            return Ok(None);
+        }
+        let n = file.read(&mut chunk)?;
+        if n == 0 {
+            break;
+        }
+        bytes.extend_from_slice(&chunk[..n]);
+        ctx.progress(bytes.len() as u64, total);
+    }
+    crate::editor::decode_file(&bytes).map(Some)
+}
+
+/// Background half of a large save: encode and write a snapshot of the
+/// buffer contents.
+fn write_contents(path: &Path, contents: &str, encoding: Encoding) -> Result<()> {
+    use std::io::Write as _;
+
+    let mut file = fs::File::create(path)
+        .with_context(|| format!("failed to create {}", path.display()))?;
+    file.write_all(&crate::editor::encode_contents(contents, encoding))?;
+    Ok(())
+}
+
+/// Whether the file on disk refuses writes; missing files (new
+/// buffers) count as writable.
+fn file_not_writable(path: &Path) -> bool {
+    fs::metadata(path).is_ok_and(|meta| meta.permissions().readonly())
+}
+
+/// The trimmed text of one line of a file, for the definition picker's
+/// previews. Errors degrade to an empty preview.
+fn preview_line(path: &Path, line: usize) -> String {
+    fs::read_to_string(path)
+        .ok()
+        .and_then(|text| text.lines().nth(line).map(|l| l.trim().to_string()))
+        .unwrap_or_default()
+}
+
+/// The layout presets that ship with the binary.
+fn builtin_layout_presets() -> [(&'static str, LayoutPreset); 3] {
+    [
+        (
+            "coding",
+            LayoutPreset {
+                tree_ratio: Some(20),
+                terminal_ratio: Some(25),
+                show_tree: Some(true),
+                show_terminal: Some(true),
+                show_agent: Some(false),
+                ..Default::default()
+            },
+        ),
+        (
+            "agent chat",
+            LayoutPreset {
+                agent_ratio: Some(45),
+                show_tree: Some(false),
+                show_terminal: Some(false),
+                show_agent: Some(true),
+                ..Default::default()
+            },
+        ),
+        (
+            "terminal heavy",
+            LayoutPreset {
+                terminal_ratio: Some(60),
+                show_tree: Some(false),
+                show_terminal: Some(true),
+                show_agent: Some(false),
+                ..Default::default()
+            },
+        ),
+    ]
+}
+
+/// Quote a path for insertion into the terminal input: single-quoted
+/// (with embedded quotes escaped) only when it contains characters the
+/// shell would interpret.
+fn shell_quote(path: &str) -> String {
+    let plain = !path.is_empty()
+        && path
+            .chars()
+            .all(|c| c.is_alphanumeric() || matches!(c, '/' | '.' | '-' | '_' | '+' | ':' | '@'));
+    if plain {
+        path.to_string()
+    } else {
+        format!("'{}'", path.replace('\'', r"'\''"))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::lsp::types::{LspPosition as P, LspRange};
+
+    fn edit(sl: u32, sc: u32, el: u32, ec: u32, text: &str) -> TextEdit {
+        TextEdit {
+            range: LspRange {
+                start: P { line: sl, character: sc },
+                end: P { line: el, character: ec },
+            },
+            new_text: text.to_string(),
+        }
+    }
+
+    #[test]
+    fn apply_edits_to_text_replaces_in_reverse_order() {
+        let text = "fn old() {\n    old();\n}\n";
+        let e1 = edit(0, 3, 0, 6, "new");
+        let e2 = edit(1, 4, 1, 7, "new");
+        let sorted: Vec<&TextEdit> = vec![&e2, &e1];
+        assert_eq!(
+            apply_edits_to_text(text, &sorted),
+            "fn new() {\n    new();\n}\n"
+        );
+    }
+
+    #[test]
+    fn apply_edits_to_text_spanning_lines() {
+        let text = "abc\ndef\nghi";
+        let e = edit(0, 1, 2, 1, "X");
+        let sorted: Vec<&TextEdit> = vec![&e];
+        assert_eq!(apply_edits_to_text(text, &sorted), "aXhi");
+    }
+
+    #[test]
+    fn shell_quote_wraps_only_when_needed() {
+        assert_eq!(shell_quote("src/main.rs"), "src/main.rs");
+        assert_eq!(shell_quote("my file.txt"), "'my file.txt'");
+        assert_eq!(shell_quote("it's.txt"), r"'it'\''s.txt'");
+    }
+}
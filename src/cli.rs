@@ -0,0 +1,196 @@
+//! Hand-rolled command-line parsing.
+//!
+//! The surface is small enough that a dependency is not worth it:
+//! one optional positional target (`dir/`, `file.rs`, or `file.rs:42`)
+//! plus a few flags.
+
+use std::path::{Path, PathBuf};
+
+use anyhow::{bail, Context, Result};
+
+pub const USAGE: &str = "\
+usage: clide [options] [path[:line] | -]
+
+  path            workspace directory, or a file to open (file.rs:42
+                  jumps to that line)
+  -               read stdin (a pipe or FIFO) into an unnamed buffer
+
+options:
+  --read-only     open buffers without allowing edits
+  --config <path> use this config file instead of the global one
+  --log-level <error|warn|info|debug>
+                  verbosity of .clide/clide.log (default: warn)
+  --record <path> append key/mouse events to this file for later replay
+  --replay <path> feed a recorded event file back instead of real input
+  -h, --help      print this help
+";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum LogLevel {
+    Error,
+    Warn,
+    Info,
+    Debug,
+}
+
+impl LogLevel {
+    fn parse(text: &str) -> Result<Self> {
+        Ok(match text {
+            "error" => LogLevel::Error,
+            "warn" => LogLevel::Warn,
+            "info" => LogLevel::Info,
+            "debug" => LogLevel::Debug,
+            other => bail!("unknown log level {other:?}"),
+        })
+    }
+
+    pub fn label(self) -> &'static str {
+        match self {
+            LogLevel::Error => "error",
+            LogLevel::Warn => "warn",
+            LogLevel::Info => "info",
+            LogLevel::Debug => "debug",
+        }
+    }
+}
+
+/// What the positional argument referred to.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Target {
+    Dir(PathBuf),
+    File { path: PathBuf, line: Option<usize> },
+    /// `clide -`: read stdin into an unnamed buffer.
+    Stdin,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CliArgs {
+    pub target: Option<Target>,
+    pub read_only: bool,
+    pub config: Option<PathBuf>,
+    pub log_level: LogLevel,
+    /// Record input events to this file (`--record`).
+    pub record: Option<PathBuf>,
+    /// Replay a recorded event file instead of waiting for real input
+    /// (`--replay`).
+    pub replay: Option<PathBuf>,
+    pub help: bool,
+}
+
+impl Default for CliArgs {
+    fn default() -> Self {
+        Self {
+            target: None,
+            read_only: false,
+            config: None,
+            log_level: LogLevel::Warn,
+            record: None,
+            replay: None,
+            help: false,
+        }
+    }
+}
+
+/// Parse the arguments after the program name.
+pub fn parse(args: impl Iterator<Item = String>) -> Result<CliArgs> {
+    let mut out = CliArgs::default();
+    let mut args = args.peekable();
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "-h" | "--help" => out.help = true,
+            "--read-only" => out.read_only = true,
+            "--config" => {
+                let value = args.next().context("--config needs a path")?;
+                out.config = Some(PathBuf::from(value));
+            }
+            "-" => {
+                if out.target.is_some() {
+                    bail!("more than one path given");
+                }
+                out.target = Some(Target::Stdin);
+            }
+            "--log-level" => {
+                let value = args.next().context("--log-level needs a value")?;
+                out.log_level = LogLevel::parse(&value)?;
+            }
+            "--record" => {
+                let value = args.next().context("--record needs a path")?;
+                out.record = Some(PathBuf::from(value));
+            }
+            "--replay" => {
+                let value = args.next().context("--replay needs a path")?;
+                out.replay = Some(PathBuf::from(value));
+            }
+            flag if flag.starts_with('-') => bail!("unknown flag {flag:?}"),
+            positional => {
+                if out.target.is_some() {
+                    bail!("more than one path given");
+                }
+                out.target = Some(parse_target(positional));
+            }
+        }
+    }
+    Ok(out)
+}
+
+/// Resolve a positional argument. An existing path wins as-is; otherwise
+/// a trailing `:line` suffix is split off, so `file.rs:42` works even
+/// though `file.rs:42` is itself a valid file name.
+fn parse_target(text: &str) -> Target {
+    let path = Path::new(text);
+    if path.is_dir() {
+        return Target::Dir(path.to_path_buf());
+    }
+    if path.is_file() {
+        return Target::File {
+            path: path.to_path_buf(),
+            line: None,
+        };
+    }
+    if let Some((front, digits)) = text.rsplit_once(':') {
+        if !front.is_empty() && !digits.is_empty() && digits.bytes().all(|b| b.is_ascii_digit()) {
+            return Target::File {
+                path: PathBuf::from(front),
+                line: digits.parse().ok(),
+            };
+        }
+    }
+    Target::File {
+        path: path.to_path_buf(),
+        line: None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn args(list: &[&str]) -> impl Iterator<Item = String> {
+        list.iter()
+            .map(|s| s.to_string())
+            .collect::<Vec<_>>()
+            .into_iter()
+    }
+
+    #[test]
+    fn parses_flags_and_file_line_target() {
+        let cli = parse(args(&[
+            "--read-only",
+            "--log-level",
+            "debug",
+            "src/lib.rs:42",
+        ]))
+        .unwrap();
+        assert!(cli.read_only);
+        assert_eq!(cli.log_level, LogLevel::Debug);
+        assert_eq!(
+            cli.target,
+            Some(Target::File {
+                path: PathBuf::from("src/lib.rs"),
+                line: Some(42),
+            })
+        );
+        assert!(parse(args(&["--bogus"])).is_err());
+        assert!(parse(args(&["a", "b"])).is_err());
+    }
+}
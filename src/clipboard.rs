@@ -0,0 +1,86 @@
+//! System clipboard integration.
+//!
+//! There is no display-server clipboard to talk to from a headless or
+//! SSH session, so copies are mirrored to the client terminal through
+//! OSC 52 escape sequences, which most terminals forward to the local
+//! clipboard. When OSC 52 is unavailable (no terminal on stdout, or the
+//! payload exceeds the ~100 KiB cap terminals commonly apply) a local
+//! clipboard tool is tried instead, and failing that the text stays in
+//! the internal register only.
+
+use std::io::{IsTerminal, Write};
+use std::process::{Command, Stdio};
+
+use base64::Engine;
+
+/// Raw byte limit before base64 expansion pushes the sequence past
+/// common terminal caps.
+const OSC52_MAX: usize = 74_994;
+
+/// Which mechanism actually received a copy, so commands can report it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CopyMechanism {
+    /// Sent to the client terminal's clipboard via OSC 52.
+    Osc52,
+    /// Piped into a local clipboard tool (wl-copy, xclip, pbcopy).
+    LocalTool,
+    /// Kept only in the in-process clipboard register.
+    Internal,
+}
+
+impl CopyMechanism {
+    pub fn label(self) -> &'static str {
+        match self {
+            CopyMechanism::Osc52 => "terminal clipboard (OSC 52)",
+            CopyMechanism::LocalTool => "system clipboard",
+            CopyMechanism::Internal => "internal register only",
+        }
+    }
+}
+
+/// Mirror `text` to the system clipboard if a mechanism is available,
+/// returning which one was used.
+pub fn copy_to_system(text: &str) -> CopyMechanism {
+    let mut out = std::io::stdout();
+    if out.is_terminal() && text.len() <= OSC52_MAX {
+        let payload = base64::engine::general_purpose::STANDARD.encode(text);
+        if write!(out, "\x1b]52;c;{payload}\x07").is_ok() && out.flush().is_ok() {
+            return CopyMechanism::Osc52;
+        }
+    }
+    if copy_via_tool(text) {
+        return CopyMechanism::LocalTool;
+    }
+    CopyMechanism::Internal
+}
+
+/// Pipe the text into the first clipboard tool that accepts it, covering
+/// Wayland, X11, and macOS desktops where OSC 52 is unavailable or the
+/// payload is too large for it.
+fn copy_via_tool(text: &str) -> bool {
+    const TOOLS: &[(&str, &[&str])] = &[
+        ("wl-copy", &[]),
+        ("xclip", &["-selection", "clipboard"]),
+        ("pbcopy", &[]),
+    ];
+    for (tool, args) in TOOLS {
+        let Ok(mut child) = Command::new(tool)
+            .args(*args)
+            .stdin(Stdio::piped())
+            .stdout(Stdio::null())
+            .stderr(Stdio::null())
+            .spawn()
+        else {
+            continue;
+        };
+        let wrote = child
+            .stdin
+            .take()
+            .map(|mut stdin| stdin.write_all(text.as_bytes()).is_ok())
+            .unwrap_or(false);
+        if child.wait().map(|s| s.success()).unwrap_or(false) && wrote {
+            return true;
+        }
+    }
+    false
+}
@@ -0,0 +1,453 @@
+//! User configuration loaded from `config.toml`.
+//!
+//! Two files are consulted: the global `~/.config/clide/config.toml` and
+//! the per-workspace `.clide/config.toml`, with workspace entries applied
+//! after (and therefore on top of) the global ones.
+
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+
+use anyhow::{Context, Result};
+use serde::Deserialize;
+
+/// One action run after the workspace has loaded, declared as a
+/// `[[startup-hook]]` table in `config.toml`.
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+#[serde(tag = "action", rename_all = "kebab-case")]
+pub enum StartupHook {
+    /// Open a file in the editor; relative paths resolve against the
+    /// workspace root.
+    Open { path: PathBuf },
+    /// Run a shell command in the terminal pane.
+    Run { command: String },
+    /// Show and focus a pane: `tree`, `editor`, `terminal`, `agent`, `git`.
+    Show { pane: String },
+}
+
+/// Settings for the optional focus-follows-mouse mode, declared as a
+/// `[focus-follows-mouse]` table. Disabled unless the table is present.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub struct FocusFollowsMouse {
+    /// Focus the editor when the pointer dwells over it.
+    #[serde(default = "default_true")]
+    pub editor: bool,
+    /// Focus the tree/terminal/agent/git panes when the pointer dwells
+    /// over them.
+    #[serde(default = "default_true")]
+    pub side_panes: bool,
+    /// How long the pointer must rest over a pane before it takes focus.
+    #[serde(default = "default_dwell_ms")]
+    pub delay_ms: u64,
+}
+
+fn default_true() -> bool {
+    true
+}
+
+fn default_dwell_ms() -> u64 {
+    150
+}
+
+/// Editor preferences from the `[editor]` table. Unset fields keep the
+/// built-in defaults.
+#[derive(Debug, Clone, Copy, Default, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub struct EditorSection {
+    pub tab_width: Option<u8>,
+    pub use_tabs: Option<bool>,
+    pub wrap: Option<bool>,
+    pub line_numbers: Option<bool>,
+    pub relative_line_numbers: Option<bool>,
+    pub auto_indent: Option<bool>,
+    pub auto_close: Option<bool>,
+    pub show_stats: Option<bool>,
+    /// Vim-style modal editing.
+    pub vim: Option<bool>,
+    /// Save dirty named buffers every this many seconds; 0 or unset
+    /// disables autosave.
+    pub autosave_secs: Option<u64>,
+    /// Render spaces, tabs, and zero-width characters visibly.
+    pub show_whitespace: Option<bool>,
+    /// Strip trailing whitespace from every line when saving.
+    pub trim_trailing_whitespace: Option<bool>,
+    /// Guarantee a trailing newline when saving.
+    pub final_newline: Option<bool>,
+}
+
+/// UI preferences from the `[ui]` table.
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub struct UiSection {
+    /// Theme name: a built-in (`dark`, `light`) or a file in
+    /// `~/.config/clide/themes/`.
+    pub theme: Option<String>,
+    /// Icon set for the file tree: `emoji`, `unicode`, or `nerd-font`;
+    /// overrides terminal detection.
+    pub icons: Option<String>,
+    pub show_hidden: Option<bool>,
+    /// Tree sort mode: `name`, `natural`, `mtime`, or `size`.
+    pub tree_sort: Option<String>,
+    /// Status bar segment names, in display order; unknown names are
+    /// reported and skipped.
+    pub status_segments: Option<Vec<String>>,
+}
+
+/// File header templates from the `[header]` table. Placeholders
+/// `{file}`, `{author}`, `{license}`, and `{date}` are expanded when a
+/// header is inserted; the comment prefix comes from the extension.
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub struct HeaderSection {
+    pub license: Option<String>,
+    pub author: Option<String>,
+    /// Template lines without comment markers; empty keeps the built-in
+    /// file/author/license/date block.
+    #[serde(default)]
+    pub template: Vec<String>,
+    /// Extensions whose new files get the header automatically.
+    #[serde(default)]
+    pub auto_extensions: Vec<String>,
+}
+
+/// Redaction settings from the `[redaction]` table: what is stripped
+/// from content before it is sent to a cloud agent provider.
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub struct RedactionSection {
+    /// Master switch; unset means enabled.
+    pub enabled: Option<bool>,
+    pub emails: Option<bool>,
+    pub api_keys: Option<bool>,
+    /// Literal substrings to strip wherever they appear. These are
+    /// matched verbatim, not as regular expressions.
+    #[serde(default)]
+    pub patterns: Vec<String>,
+}
+
+/// A named pane layout from a `[layout-preset.<name>]` table; unset
+/// fields keep their current value when the preset is applied.
+#[derive(Debug, Clone, Copy, Default, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub struct LayoutPreset {
+    pub tree_ratio: Option<u16>,
+    pub agent_ratio: Option<u16>,
+    pub terminal_ratio: Option<u16>,
+    pub show_tree: Option<bool>,
+    pub show_terminal: Option<bool>,
+    pub show_agent: Option<bool>,
+    pub show_git: Option<bool>,
+}
+
+/// Agent preferences from the `[agent]` table.
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub struct AgentSection {
+    /// Profile to activate at startup, overriding the default-profile
+    /// entry in agents.toml.
+    pub default_profile: Option<String>,
+    /// Log every provider request/response to
+    /// `.clide/agent-transcript.jsonl` (keys scrubbed). Off by default.
+    pub transcript_log: Option<bool>,
+    /// Prompt templates from `[agent.templates]`, one `name = "body"`
+    /// pair each; bodies may use `{selection}`, `{file}`, and
+    /// `{diagnostics}`.
+    #[serde(default)]
+    pub templates: HashMap<String, String>,
+}
+
+/// Terminal pane settings from the `[terminal]` table.
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub struct TerminalSection {
+    /// Shell binary; unset picks the per-OS default (`cmd` on Windows,
+    /// `$SHELL` falling back to `/bin/sh` elsewhere).
+    pub shell: Option<String>,
+    /// Arguments placed before the command string; unset derives them
+    /// from the shell (`/C` for cmd, `-Command` for PowerShell, `-c`
+    /// otherwise).
+    pub args: Option<Vec<String>>,
+    /// Starting directory for commands; relative paths resolve against
+    /// the workspace root.
+    pub cwd: Option<PathBuf>,
+    /// Extra environment variables set for every spawned command.
+    #[serde(default)]
+    pub env: HashMap<String, String>,
+    /// Restore the terminal's scrollback, input history, and working
+    /// directory when reopening this workspace. Off by default.
+    pub persist: Option<bool>,
+}
+
+/// Patterns highlighted in follow/log views, from the `[log-highlight]`
+/// table. Matching is a plain substring check per line.
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub struct LogHighlight {
+    #[serde(default = "default_error_patterns")]
+    pub error: Vec<String>,
+    #[serde(default = "default_warn_patterns")]
+    pub warn: Vec<String>,
+}
+
+impl Default for LogHighlight {
+    fn default() -> Self {
+        Self {
+            error: default_error_patterns(),
+            warn: default_warn_patterns(),
+        }
+    }
+}
+
+fn default_error_patterns() -> Vec<String> {
+    vec!["ERROR".to_string(), "error[".to_string()]
+}
+
+fn default_warn_patterns() -> Vec<String> {
+    vec!["WARN".to_string(), "warning:".to_string()]
+}
+
+/// Extra extension groups for "Switch to Related File", from the
+/// `[related-files]` table; every extension in a group is related to
+/// the others in it.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub struct RelatedFiles {
+    #[serde(default)]
+    pub ext_groups: Vec<Vec<String>>,
+}
+
+/// Keybinding overrides from the `[keys.*]` tables, one per scope, each
+/// mapping a chord sequence (`"ctrl+k ctrl+s"`) to a command name.
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct KeysSection {
+    #[serde(default)]
+    pub global: HashMap<String, String>,
+    #[serde(default)]
+    pub tree: HashMap<String, String>,
+    #[serde(default)]
+    pub editor: HashMap<String, String>,
+    #[serde(default)]
+    pub terminal: HashMap<String, String>,
+    #[serde(default)]
+    pub agent: HashMap<String, String>,
+    #[serde(default)]
+    pub git: HashMap<String, String>,
+}
+
+/// Merge `src` onto `dst` field by field, used both for the `[editor]`
+/// table and the per-language overrides.
+fn merge_editor_section(dst: &mut EditorSection, src: EditorSection) {
+    merge_field(&mut dst.tab_width, src.tab_width);
+    merge_field(&mut dst.use_tabs, src.use_tabs);
+    merge_field(&mut dst.wrap, src.wrap);
+    merge_field(&mut dst.line_numbers, src.line_numbers);
+    merge_field(&mut dst.relative_line_numbers, src.relative_line_numbers);
+    merge_field(&mut dst.auto_indent, src.auto_indent);
+    merge_field(&mut dst.auto_close, src.auto_close);
+    merge_field(&mut dst.show_stats, src.show_stats);
+    merge_field(&mut dst.vim, src.vim);
+    merge_field(&mut dst.autosave_secs, src.autosave_secs);
+    merge_field(&mut dst.show_whitespace, src.show_whitespace);
+    merge_field(
+        &mut dst.trim_trailing_whitespace,
+        src.trim_trailing_whitespace,
+    );
+    merge_field(&mut dst.final_newline, src.final_newline);
+}
+
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct ClideConfig {
+    #[serde(default, rename = "startup-hook")]
+    pub startup_hooks: Vec<StartupHook>,
+    #[serde(rename = "focus-follows-mouse")]
+    pub focus_follows_mouse: Option<FocusFollowsMouse>,
+    #[serde(rename = "log-highlight")]
+    pub log_highlight: Option<LogHighlight>,
+    #[serde(default)]
+    pub editor: EditorSection,
+    #[serde(default, rename = "related-files")]
+    pub related_files: RelatedFiles,
+    /// Per-language `[language.<name>]` overrides applied on top of
+    /// `[editor]` when a buffer with that language is active.
+    #[serde(default, rename = "language")]
+    pub languages: HashMap<String, EditorSection>,
+    #[serde(default)]
+    pub ui: UiSection,
+    #[serde(default)]
+    pub agent: AgentSection,
+    #[serde(default)]
+    pub header: HeaderSection,
+    /// Named pane layouts selectable from the command palette, on top
+    /// of the built-in ones.
+    #[serde(default, rename = "layout-preset")]
+    pub layout_presets: HashMap<String, LayoutPreset>,
+    #[serde(default)]
+    pub terminal: TerminalSection,
+    #[serde(default)]
+    pub redaction: RedactionSection,
+    #[serde(default)]
+    pub keys: KeysSection,
+}
+
+fn merge_field<T>(dst: &mut Option<T>, src: Option<T>) {
+    if src.is_some() {
+        *dst = src;
+    }
+}
+
+fn global_config_path() -> Option<PathBuf> {
+    dirs::config_dir().map(|dir| dir.join("clide").join("config.toml"))
+}
+
+/// Load the global config merged with the workspace `.clide/config.toml`.
+/// Missing files are fine; a file that exists but does not parse is an
+/// error, and an explicit `--config` override must exist.
+pub fn load_config(root: &Path, global_override: Option<&Path>) -> Result<ClideConfig> {
+    let mut config = ClideConfig::default();
+    let mut paths = Vec::new();
+    match global_override {
+        Some(path) => {
+            if !path.is_file() {
+                anyhow::bail!("config file {} not found", path.display());
+            }
+            paths.push(path.to_path_buf());
+        }
+        None => {
+            if let Some(path) = global_config_path() {
+                paths.push(path);
+            }
+        }
+    }
+    paths.push(root.join(".clide").join("config.toml"));
+    for path in paths {
+        if !path.is_file() {
+            continue;
+        }
+        let text = std::fs::read_to_string(&path)
+            .with_context(|| format!("failed to read {}", path.display()))?;
+        let parsed: ClideConfig = toml::from_str(&text)
+            .with_context(|| format!("failed to parse {}", path.display()))?;
+        config.startup_hooks.extend(parsed.startup_hooks);
+        if parsed.focus_follows_mouse.is_some() {
+            config.focus_follows_mouse = parsed.focus_follows_mouse;
+        }
+        if parsed.log_highlight.is_some() {
+            config.log_highlight = parsed.log_highlight;
+        }
+        merge_editor_section(&mut config.editor, parsed.editor);
+        config
+            .related_files
+            .ext_groups
+            .extend(parsed.related_files.ext_groups);
+        for (name, section) in parsed.languages {
+            merge_editor_section(config.languages.entry(name).or_default(), section);
+        }
+        merge_field(&mut config.ui.theme, parsed.ui.theme);
+        merge_field(&mut config.ui.icons, parsed.ui.icons);
+        merge_field(&mut config.ui.show_hidden, parsed.ui.show_hidden);
+        merge_field(&mut config.ui.tree_sort, parsed.ui.tree_sort);
+        merge_field(&mut config.ui.status_segments, parsed.ui.status_segments);
+        merge_field(&mut config.agent.default_profile, parsed.agent.default_profile);
+        merge_field(&mut config.agent.transcript_log, parsed.agent.transcript_log);
+        config.agent.templates.extend(parsed.agent.templates);
+        merge_field(&mut config.header.license, parsed.header.license);
+        merge_field(&mut config.header.author, parsed.header.author);
+        if !parsed.header.template.is_empty() {
+            config.header.template = parsed.header.template;
+        }
+        if !parsed.header.auto_extensions.is_empty() {
+            config.header.auto_extensions = parsed.header.auto_extensions;
+        }
+        config.layout_presets.extend(parsed.layout_presets);
+        merge_field(&mut config.terminal.shell, parsed.terminal.shell);
+        merge_field(&mut config.terminal.args, parsed.terminal.args);
+        merge_field(&mut config.terminal.cwd, parsed.terminal.cwd);
+        config.terminal.env.extend(parsed.terminal.env);
+        merge_field(&mut config.terminal.persist, parsed.terminal.persist);
+        merge_field(&mut config.redaction.enabled, parsed.redaction.enabled);
+        merge_field(&mut config.redaction.emails, parsed.redaction.emails);
+        merge_field(&mut config.redaction.api_keys, parsed.redaction.api_keys);
+        config.redaction.patterns.extend(parsed.redaction.patterns);
+        config.keys.global.extend(parsed.keys.global);
+        config.keys.tree.extend(parsed.keys.tree);
+        config.keys.editor.extend(parsed.keys.editor);
+        config.keys.terminal.extend(parsed.keys.terminal);
+        config.keys.agent.extend(parsed.keys.agent);
+        config.keys.git.extend(parsed.keys.git);
+    }
+    Ok(config)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_startup_hooks() {
+        let text = r#"
+            [[startup-hook]]
+            action = "open"
+            path = "src/main.rs"
+
+            [[startup-hook]]
+            action = "run"
+            command = "cargo check"
+
+            [[startup-hook]]
+            action = "show"
+            pane = "git"
+        "#;
+        let config: ClideConfig = toml::from_str(text).unwrap();
+        assert_eq!(config.startup_hooks.len(), 3);
+        assert_eq!(
+            config.startup_hooks[0],
+            StartupHook::Open {
+                path: PathBuf::from("src/main.rs")
+            }
+        );
+        assert_eq!(
+            config.startup_hooks[2],
+            StartupHook::Show {
+                pane: "git".to_string()
+            }
+        );
+    }
+
+    #[test]
+    fn parses_per_language_overrides() {
+        let config: ClideConfig = toml::from_str(
+            r#"
+            [editor]
+            tab-width = 4
+
+            [language.python]
+            tab-width = 2
+            wrap = false
+            "#,
+        )
+        .unwrap();
+        assert_eq!(config.editor.tab_width, Some(4));
+        let python = &config.languages["python"];
+        assert_eq!(python.tab_width, Some(2));
+        assert_eq!(python.wrap, Some(false));
+        assert_eq!(python.use_tabs, None);
+    }
+
+    #[test]
+    fn focus_follows_mouse_defaults_apply_per_field() {
+        let config: ClideConfig = toml::from_str(
+            r#"
+            [focus-follows-mouse]
+            editor = false
+            "#,
+        )
+        .unwrap();
+        let ffm = config.focus_follows_mouse.unwrap();
+        assert!(!ffm.editor);
+        assert!(ffm.side_panes);
+        assert_eq!(ffm.delay_ms, 150);
+
+        let config: ClideConfig = toml::from_str("").unwrap();
+        assert!(config.focus_follows_mouse.is_none());
+    }
+}
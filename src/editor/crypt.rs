@@ -0,0 +1,146 @@
+//! Transparent encryption for `.age` and `.gpg` buffers.
+//!
+//! Both formats are handled by shelling out to the `age` and `gpg`
+//! binaries so no key material is persisted by clide itself: the
+//! decrypted text lives only in the buffer rope and the secret only in
+//! the buffer, and saves re-encrypt through the same tool. For `age`
+//! the secret is the path to an identity file (the CLI has no
+//! non-interactive passphrase mode); for `gpg` it is the symmetric
+//! passphrase.
+
+use std::io::Write;
+use std::path::Path;
+use std::process::{Command, Stdio};
+
+use anyhow::{Context, Result};
+
+/// Which encryption tool a file belongs to, from its extension.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CryptKind {
+    Age,
+    Gpg,
+}
+
+impl CryptKind {
+    pub fn from_path(path: &Path) -> Option<Self> {
+        match path.extension().and_then(|e| e.to_str()) {
+            Some("age") => Some(CryptKind::Age),
+            Some("gpg") => Some(CryptKind::Gpg),
+            _ => None,
+        }
+    }
+
+    /// What the passphrase prompt should ask for.
+    pub fn secret_label(self) -> &'static str {
+        match self {
+            CryptKind::Age => "identity file",
+            CryptKind::Gpg => "passphrase",
+        }
+    }
+}
+
+/// Decrypt `path` and return the plaintext. The plaintext never touches
+/// disk: it is read straight from the tool's stdout.
+pub fn decrypt(kind: CryptKind, path: &Path, secret: &str) -> Result<String> {
+    let output = match kind {
+        CryptKind::Age => Command::new("age")
+            .args(["--decrypt", "-i", secret])
+            .arg(path)
+            .stdin(Stdio::null())
+            .output()
+            .context("failed to run age (is it installed?)")?,
+        CryptKind::Gpg => Command::new("gpg")
+            .args([
+                "--batch",
+                "--quiet",
+                "--pinentry-mode",
+                "loopback",
+                "--passphrase-fd",
+                "0",
+                "--decrypt",
+            ])
+            .arg(path)
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .spawn()
+            .and_then(|mut child| {
+                if let Some(stdin) = child.stdin.as_mut() {
+                    stdin.write_all(secret.as_bytes())?;
+                }
+                child.wait_with_output()
+            })
+            .context("failed to run gpg (is it installed?)")?,
+    };
+    if !output.status.success() {
+        anyhow::bail!(
+            "decryption failed: {}",
+            String::from_utf8_lossy(&output.stderr).trim()
+        );
+    }
+    String::from_utf8(output.stdout).context("decrypted content is not valid UTF-8")
+}
+
+/// Re-encrypt `plaintext` to `path`, overwriting it. The plaintext is
+/// fed to the tool over stdin.
+pub fn encrypt(kind: CryptKind, path: &Path, secret: &str, plaintext: &[u8]) -> Result<()> {
+    let mut child = match kind {
+        CryptKind::Age => Command::new("age")
+            .args(["--encrypt", "-i", secret, "-o"])
+            .arg(path)
+            .stdin(Stdio::piped())
+            .stdout(Stdio::null())
+            .stderr(Stdio::piped())
+            .spawn()
+            .context("failed to run age (is it installed?)")?,
+        CryptKind::Gpg => Command::new("gpg")
+            .args([
+                "--batch",
+                "--yes",
+                "--quiet",
+                "--pinentry-mode",
+                "loopback",
+                "--passphrase",
+                secret,
+                "--symmetric",
+                "-o",
+            ])
+            .arg(path)
+            .stdin(Stdio::piped())
+            .stdout(Stdio::null())
+            .stderr(Stdio::piped())
+            .spawn()
+            .context("failed to run gpg (is it installed?)")?,
+    };
+    if let Some(stdin) = child.stdin.as_mut() {
+        stdin
+            .write_all(plaintext)
+            .context("failed to feed plaintext to the encryption tool")?;
+    }
+    let output = child.wait_with_output()?;
+    if !output.status.success() {
+        anyhow::bail!(
+            "encryption failed: {}",
+            String::from_utf8_lossy(&output.stderr).trim()
+        );
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn detects_kind_from_extension() {
+        assert_eq!(
+            CryptKind::from_path(Path::new("secrets.env.age")),
+            Some(CryptKind::Age)
+        );
+        assert_eq!(
+            CryptKind::from_path(Path::new("notes.md.gpg")),
+            Some(CryptKind::Gpg)
+        );
+        assert_eq!(CryptKind::from_path(Path::new("notes.md")), None);
+    }
+}
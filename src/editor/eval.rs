@@ -0,0 +1,160 @@
+//! A tiny arithmetic evaluator for the "Evaluate Selection" command.
+//!
+//! Recursive descent over `+ - * / % ^` with parentheses and unary
+//! minus; numbers are f64 with `0x`/`0b` integer literals accepted.
+//! Anything beyond that is an error — the caller can hand the
+//! expression to the agent instead.
+
+use anyhow::{bail, Result};
+
+/// Evaluate an expression and format the result, trimming a trailing
+/// `.0` for whole numbers.
+pub fn evaluate(text: &str) -> Result<String> {
+    let tokens = tokenize(text)?;
+    let mut parser = Parser { tokens, pos: 0 };
+    let value = parser.expression(0)?;
+    if parser.pos != parser.tokens.len() {
+        bail!("unexpected trailing input");
+    }
+    if value.fract() == 0.0 && value.abs() < 1e15 {
+        Ok(format!("{}", value as i64))
+    } else {
+        Ok(format!("{value}"))
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Token {
+    Number(f64),
+    Op(char),
+    Open,
+    Close,
+}
+
+fn tokenize(text: &str) -> Result<Vec<Token>> {
+    let mut tokens = Vec::new();
+    let mut chars = text.chars().peekable();
+    while let Some(&c) = chars.peek() {
+        match c {
+            ' ' | '\t' | '\n' | '_' | ',' => {
+                chars.next();
+            }
+            '(' => {
+                chars.next();
+                tokens.push(Token::Open);
+            }
+            ')' => {
+                chars.next();
+                tokens.push(Token::Close);
+            }
+            '+' | '-' | '*' | '/' | '%' | '^' => {
+                chars.next();
+                tokens.push(Token::Op(c));
+            }
+            '0'..='9' | '.' => {
+                let mut num = String::new();
+                while let Some(&c) = chars.peek() {
+                    if c.is_ascii_alphanumeric() || c == '.' {
+                        num.push(c);
+                        chars.next();
+                    } else {
+                        break;
+                    }
+                }
+                let value = if let Some(hex) = num.strip_prefix("0x") {
+                    i64::from_str_radix(hex, 16).map(|v| v as f64)
+                } else if let Some(bin) = num.strip_prefix("0b") {
+                    i64::from_str_radix(bin, 2).map(|v| v as f64)
+                } else {
+                    num.parse::<f64>().map_err(|_| "".parse::<i64>().unwrap_err())
+                };
+                match value {
+                    Ok(value) => tokens.push(Token::Number(value)),
+                    Err(_) => bail!("bad number {num:?}"),
+                }
+            }
+            other => bail!("unexpected character {other:?}"),
+        }
+    }
+    if tokens.is_empty() {
+        bail!("empty expression");
+    }
+    Ok(tokens)
+}
+
+struct Parser {
+    tokens: Vec<Token>,
+    pos: usize,
+}
+
+impl Parser {
+    fn expression(&mut self, min_prec: u8) -> Result<f64> {
+        let mut left = self.atom()?;
+        while let Some(Token::Op(op)) = self.tokens.get(self.pos).copied() {
+            let prec = match op {
+                '+' | '-' => 1,
+                '*' | '/' | '%' => 2,
+                '^' => 3,
+                _ => break,
+            };
+            if prec < min_prec {
+                break;
+            }
+            self.pos += 1;
+            // `^` is right-associative, the rest are left-associative.
+            let right = self.expression(if op == '^' { prec } else { prec + 1 })?;
+            left = match op {
+                '+' => left + right,
+                '-' => left - right,
+                '*' => left * right,
+                '/' => left / right,
+                '%' => left % right,
+                _ => left.powf(right),
+            };
+        }
+        Ok(left)
+    }
+
+    fn atom(&mut self) -> Result<f64> {
+        match self.tokens.get(self.pos).copied() {
+            Some(Token::Number(value)) => {
+                self.pos += 1;
+                Ok(value)
+            }
+            Some(Token::Op('-')) => {
+                self.pos += 1;
+                Ok(-self.atom()?)
+            }
+            Some(Token::Op('+')) => {
+                self.pos += 1;
+                self.atom()
+            }
+            Some(Token::Open) => {
+                self.pos += 1;
+                let value = self.expression(0)?;
+                if self.tokens.get(self.pos) != Some(&Token::Close) {
+                    bail!("missing closing parenthesis");
+                }
+                self.pos += 1;
+                Ok(value)
+            }
+            _ => bail!("expected a number"),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn evaluates_arithmetic_with_precedence() {
+        assert_eq!(evaluate("1 + 2 * 3").unwrap(), "7");
+        assert_eq!(evaluate("(1 + 2) * 3").unwrap(), "9");
+        assert_eq!(evaluate("2 ^ 3 ^ 2").unwrap(), "512");
+        assert_eq!(evaluate("-4 + 0x10").unwrap(), "12");
+        assert_eq!(evaluate("7 / 2").unwrap(), "3.5");
+        assert!(evaluate("let x = 1").is_err());
+        assert!(evaluate("").is_err());
+    }
+}
@@ -0,0 +1,127 @@
+//! File header templates: comment-wrapped license/author/date blocks
+//! inserted at the top of new files and refreshed on demand.
+
+use std::path::Path;
+
+use crate::config::HeaderSection;
+
+/// Used when `[header]` sets no `template` lines.
+const DEFAULT_TEMPLATE: &[&str] = &[
+    "File: {file}",
+    "Author: {author}",
+    "License: {license}",
+    "Date: {date}",
+];
+
+/// The line-comment prefix for a file extension; extensions without a
+/// known prefix get no header.
+pub fn comment_prefix(ext: &str) -> Option<&'static str> {
+    Some(match ext {
+        "rs" | "c" | "h" | "cpp" | "cc" | "hpp" | "go" | "java" | "js" | "mjs" | "cjs" | "ts"
+        | "tsx" | "css" => "//",
+        "py" | "sh" | "bash" | "toml" | "yaml" | "yml" => "#",
+        _ => return None,
+    })
+}
+
+fn template_lines(section: &HeaderSection) -> Vec<&str> {
+    if section.template.is_empty() {
+        DEFAULT_TEMPLATE.to_vec()
+    } else {
+        section.template.iter().map(String::as_str).collect()
+    }
+}
+
+/// Render the commented header block for `path`, with a blank separator
+/// line after it. `None` when the extension has no comment syntax.
+pub fn render(section: &HeaderSection, path: &Path) -> Option<String> {
+    let ext = path.extension()?.to_str()?;
+    let prefix = comment_prefix(ext)?;
+    let file = path
+        .file_name()
+        .map(|n| n.to_string_lossy().into_owned())
+        .unwrap_or_default();
+    let date = chrono::Local::now().format("%Y-%m-%d").to_string();
+    let mut out = String::new();
+    for line in template_lines(section) {
+        let line = line
+            .replace("{file}", &file)
+            .replace("{author}", section.author.as_deref().unwrap_or(""))
+            .replace("{license}", section.license.as_deref().unwrap_or(""))
+            .replace("{date}", &date);
+        let line = line.trim_end();
+        if line.is_empty() {
+            out.push_str(prefix);
+        } else {
+            out.push_str(prefix);
+            out.push(' ');
+            out.push_str(line);
+        }
+        out.push('\n');
+    }
+    out.push('\n');
+    Some(out)
+}
+
+/// Character length of the existing header block at the top of `text`,
+/// or `None` when the file does not start with this template's header.
+///
+/// Each leading line must start with the comment prefix plus the static
+/// text of the matching template line (everything before its first
+/// placeholder), so doc comments and shebangs are not mistaken for a
+/// stale header.
+pub fn existing_header_len(text: &str, prefix: &str, section: &HeaderSection) -> Option<usize> {
+    let mut chars = 0;
+    let mut lines = text.split_inclusive('\n');
+    for template in template_lines(section) {
+        let line = lines.next()?;
+        let statics = template.split('{').next().unwrap_or("").trim_end();
+        let want = if statics.is_empty() {
+            prefix.to_string()
+        } else {
+            format!("{prefix} {statics}")
+        };
+        if !line.starts_with(&want) {
+            return None;
+        }
+        chars += line.chars().count();
+    }
+    // Swallow the blank separator so a refresh does not grow the file.
+    if let Some(line) = lines.next() {
+        if line.trim().is_empty() {
+            chars += line.chars().count();
+        }
+    }
+    Some(chars)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn section() -> HeaderSection {
+        HeaderSection {
+            license: Some("MIT".to_string()),
+            author: Some("jane".to_string()),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn render_expands_placeholders_with_comment_prefix() {
+        let header = render(&section(), Path::new("src/lib.rs")).unwrap();
+        assert!(header.starts_with("// File: lib.rs\n"));
+        assert!(header.contains("// License: MIT\n"));
+        assert!(header.ends_with("\n\n"));
+    }
+
+    #[test]
+    fn existing_header_is_detected_but_doc_comments_are_not() {
+        let section = section();
+        let header = render(&section, Path::new("lib.rs")).unwrap();
+        let text = format!("{header}fn main() {{}}\n");
+        let len = existing_header_len(&text, "//", &section).unwrap();
+        assert_eq!(len, header.chars().count());
+        assert!(existing_header_len("//! A crate doc.\n", "//", &section).is_none());
+    }
+}
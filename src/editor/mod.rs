@@ -0,0 +1,1879 @@
+//! The rope-backed text editor: buffers, cursors, undo, preferences.
+
+pub mod crypt;
+pub mod eval;
+pub mod header;
+pub mod unicode;
+
+use std::cell::Cell;
+use std::collections::{BTreeSet, HashSet};
+use std::fs;
+use std::io::Write as _;
+use std::path::{Path, PathBuf};
+
+use anyhow::{Context, Result};
+use ropey::Rope;
+
+/// How the Tab key indents.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum IndentKind {
+    Spaces(u8),
+    Tabs,
+}
+
+impl IndentKind {
+    pub fn label(self) -> String {
+        match self {
+            IndentKind::Spaces(n) => format!("Spaces: {n}"),
+            IndentKind::Tabs => "Tabs".to_string(),
+        }
+    }
+
+    /// One level of indentation as the literal text to insert.
+    pub fn unit(self) -> String {
+        match self {
+            IndentKind::Spaces(n) => " ".repeat(n as usize),
+            IndentKind::Tabs => "\t".to_string(),
+        }
+    }
+}
+
+/// Guess the indentation style from file contents: tabs win if any line
+/// starts with one, otherwise the smallest leading-space count seen.
+/// `None` when the file has no indented lines.
+pub fn detect_indent(text: &str) -> Option<IndentKind> {
+    let mut smallest: Option<usize> = None;
+    for line in text.lines() {
+        if line.starts_with('\t') {
+            return Some(IndentKind::Tabs);
+        }
+        let spaces = line.len() - line.trim_start_matches(' ').len();
+        if spaces > 0 && spaces < line.len() {
+            smallest = Some(smallest.map_or(spaces, |s| s.min(spaces)));
+        }
+    }
+    match smallest {
+        Some(width) if width <= 8 => Some(IndentKind::Spaces(width as u8)),
+        _ => None,
+    }
+}
+
+/// Counts shown by the optional status-bar statistics segment.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct DocStats {
+    pub lines: usize,
+    pub words: usize,
+    pub chars: usize,
+}
+
+/// Whitespace-separated word count across rope chunks; a word may span
+/// a chunk boundary.
+fn count_words<'a>(chunks: impl Iterator<Item = &'a str>) -> usize {
+    let mut words = 0;
+    let mut in_word = false;
+    for chunk in chunks {
+        for c in chunk.chars() {
+            if c.is_whitespace() {
+                in_word = false;
+            } else if !in_word {
+                in_word = true;
+                words += 1;
+            }
+        }
+    }
+    words
+}
+
+/// Line wrapping behavior for the editor viewport.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+// The shared suffix is the point: these name wrapping strategies.
+#[allow(clippy::enum_variant_names)]
+pub enum WrapMode {
+    NoWrap,
+    /// Break at word boundaries, with a hanging indent on continuations.
+    WordWrap,
+    /// Char-exact fallback: break at exactly the viewport width.
+    CharWrap,
+}
+
+impl WrapMode {
+    pub fn label(self) -> &'static str {
+        match self {
+            WrapMode::NoWrap => "no wrap",
+            WrapMode::WordWrap => "word wrap",
+            WrapMode::CharWrap => "char wrap",
+        }
+    }
+}
+
+/// Line ending written on save.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LineEnding {
+    Lf,
+    Crlf,
+}
+
+impl LineEnding {
+    pub fn label(self) -> &'static str {
+        match self {
+            LineEnding::Lf => "LF",
+            LineEnding::Crlf => "CRLF",
+        }
+    }
+}
+
+/// Text encoding detected on open (or chosen via "Reopen with
+/// Encoding…") and written on save. The UTF-16 variants always carry a
+/// BOM; pick LE or BE to choose the byte order. The legacy encodings
+/// can only be detected by the user spotting mojibake, so they are
+/// never auto-detected.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Encoding {
+    Utf8,
+    Utf8Bom,
+    Utf16Le,
+    Utf16Be,
+    Gbk,
+    Big5,
+    ShiftJis,
+    Latin1,
+}
+
+impl Encoding {
+    /// Everything offered by the "Reopen with Encoding…" picker.
+    pub const ALL: &'static [Encoding] = &[
+        Encoding::Utf8,
+        Encoding::Utf8Bom,
+        Encoding::Utf16Le,
+        Encoding::Utf16Be,
+        Encoding::Gbk,
+        Encoding::Big5,
+        Encoding::ShiftJis,
+        Encoding::Latin1,
+    ];
+
+    pub fn label(self) -> &'static str {
+        match self {
+            Encoding::Utf8 => "UTF-8",
+            Encoding::Utf8Bom => "UTF-8 BOM",
+            Encoding::Utf16Le => "UTF-16 LE",
+            Encoding::Utf16Be => "UTF-16 BE",
+            Encoding::Gbk => "GBK",
+            Encoding::Big5 => "Big5",
+            Encoding::ShiftJis => "Shift-JIS",
+            Encoding::Latin1 => "Latin-1",
+        }
+    }
+
+    /// Stable name used to remember the choice in the session file.
+    pub fn name(self) -> &'static str {
+        match self {
+            Encoding::Utf8 => "utf-8",
+            Encoding::Utf8Bom => "utf-8-bom",
+            Encoding::Utf16Le => "utf-16-le",
+            Encoding::Utf16Be => "utf-16-be",
+            Encoding::Gbk => "gbk",
+            Encoding::Big5 => "big5",
+            Encoding::ShiftJis => "shift-jis",
+            Encoding::Latin1 => "latin-1",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<Encoding> {
+        Encoding::ALL.iter().copied().find(|e| e.name() == name)
+    }
+}
+
+/// Decode raw file bytes, honoring a BOM when one is present. Files
+/// without a BOM must be valid UTF-8.
+pub fn decode_file(bytes: &[u8]) -> Result<(String, Encoding)> {
+    match bytes {
+        [0xef, 0xbb, 0xbf, rest @ ..] => Ok((
+            String::from_utf8(rest.to_vec()).context("file is not valid UTF-8")?,
+            Encoding::Utf8Bom,
+        )),
+        [0xff, 0xfe, rest @ ..] => {
+            Ok((decode_utf16(rest, u16::from_le_bytes)?, Encoding::Utf16Le))
+        }
+        [0xfe, 0xff, rest @ ..] => {
+            Ok((decode_utf16(rest, u16::from_be_bytes)?, Encoding::Utf16Be))
+        }
+        _ => Ok((
+            String::from_utf8(bytes.to_vec()).context("file is not valid UTF-8")?,
+            Encoding::Utf8,
+        )),
+    }
+}
+
+/// Re-decode raw bytes in an explicitly chosen encoding, tolerating (and
+/// stripping) a matching BOM.
+pub fn decode_with(bytes: &[u8], encoding: Encoding) -> Result<String> {
+    match encoding {
+        Encoding::Utf8 | Encoding::Utf8Bom => {
+            let bytes = bytes.strip_prefix(b"\xef\xbb\xbf" as &[u8]).unwrap_or(bytes);
+            String::from_utf8(bytes.to_vec()).context("file is not valid UTF-8")
+        }
+        Encoding::Utf16Le => decode_utf16(
+            bytes.strip_prefix(b"\xff\xfe" as &[u8]).unwrap_or(bytes),
+            u16::from_le_bytes,
+        ),
+        Encoding::Utf16Be => decode_utf16(
+            bytes.strip_prefix(b"\xfe\xff" as &[u8]).unwrap_or(bytes),
+            u16::from_be_bytes,
+        ),
+        Encoding::Latin1 => Ok(bytes.iter().map(|&b| b as char).collect()),
+        Encoding::Gbk | Encoding::Big5 | Encoding::ShiftJis => {
+            let (text, _, had_errors) = legacy_codec(encoding).decode(bytes);
+            if had_errors {
+                anyhow::bail!("file is not valid {}", encoding.label());
+            }
+            Ok(text.into_owned())
+        }
+    }
+}
+
+fn legacy_codec(encoding: Encoding) -> &'static encoding_rs::Encoding {
+    match encoding {
+        Encoding::Big5 => encoding_rs::BIG5,
+        Encoding::ShiftJis => encoding_rs::SHIFT_JIS,
+        _ => encoding_rs::GBK,
+    }
+}
+
+fn decode_utf16(bytes: &[u8], unit: fn([u8; 2]) -> u16) -> Result<String> {
+    if !bytes.len().is_multiple_of(2) {
+        anyhow::bail!("truncated UTF-16 file");
+    }
+    let units: Vec<u16> = bytes
+        .chunks_exact(2)
+        .map(|pair| unit([pair[0], pair[1]]))
+        .collect();
+    String::from_utf16(&units).context("file is not valid UTF-16")
+}
+
+/// Encode text for disk in the given encoding.
+pub fn encode_contents(text: &str, encoding: Encoding) -> Vec<u8> {
+    match encoding {
+        Encoding::Utf8 => text.as_bytes().to_vec(),
+        Encoding::Utf8Bom => {
+            let mut out = vec![0xef, 0xbb, 0xbf];
+            out.extend_from_slice(text.as_bytes());
+            out
+        }
+        Encoding::Utf16Le => {
+            let mut out = vec![0xff, 0xfe];
+            for unit in text.encode_utf16() {
+                out.extend_from_slice(&unit.to_le_bytes());
+            }
+            out
+        }
+        Encoding::Utf16Be => {
+            let mut out = vec![0xfe, 0xff];
+            for unit in text.encode_utf16() {
+                out.extend_from_slice(&unit.to_be_bytes());
+            }
+            out
+        }
+        Encoding::Latin1 => text
+            .chars()
+            .map(|c| if (c as u32) <= 0xff { c as u8 } else { b'?' })
+            .collect(),
+        Encoding::Gbk | Encoding::Big5 | Encoding::ShiftJis => {
+            legacy_codec(encoding).encode(text).0.into_owned()
+        }
+    }
+}
+
+/// User preferences that shape editing behavior.
+#[derive(Debug, Clone)]
+pub struct EditorPreferences {
+    pub indent: IndentKind,
+    pub wrap_mode: WrapMode,
+    pub show_line_numbers: bool,
+    /// Gutter shows each line's distance from the cursor line instead of
+    /// its absolute number; the cursor line keeps the absolute number.
+    pub relative_line_numbers: bool,
+    /// Enter inherits (and extends after `{`, `:` etc.) the previous
+    /// line's indentation.
+    pub auto_indent: bool,
+    /// Typing an opening bracket or quote inserts the matching closer.
+    pub auto_close: bool,
+    /// Show document statistics (lines/words/chars) in the status bar.
+    pub show_stats: bool,
+    /// Vim-style modal editing (`vim = true` in the `[editor]` table).
+    pub vim_mode: bool,
+    /// Render spaces as faint dots, tabs as arrows, and zero-width or
+    /// control characters as visible boxes.
+    pub show_whitespace: bool,
+    /// Strip trailing whitespace from every line when saving.
+    pub trim_trailing_whitespace: bool,
+    /// Guarantee the file ends with a newline when saving.
+    pub final_newline: bool,
+}
+
+impl Default for EditorPreferences {
+    fn default() -> Self {
+        Self {
+            indent: IndentKind::Spaces(4),
+            wrap_mode: WrapMode::WordWrap,
+            show_line_numbers: true,
+            relative_line_numbers: false,
+            auto_indent: true,
+            auto_close: true,
+            show_stats: false,
+            vim_mode: false,
+            show_whitespace: false,
+            trim_trailing_whitespace: false,
+            final_newline: false,
+        }
+    }
+}
+
+/// Save-time cleanup switches, copied out of [`EditorPreferences`] so
+/// [`Editor::write_to`] stays callable without the whole editor.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct SaveCleanup {
+    pub trim_trailing_whitespace: bool,
+    pub final_newline: bool,
+}
+
+/// A cursor position as (line, column) in character terms.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct Position {
+    pub line: usize,
+    pub col: usize,
+}
+
+/// One undo step: the rope before the edit plus the cursor to restore.
+struct UndoState {
+    rope: Rope,
+    cursor: Position,
+}
+
+/// A single open file.
+pub struct Buffer {
+    pub path: Option<PathBuf>,
+    pub rope: Rope,
+    pub cursor: Position,
+    /// Selection anchor; `Some` while a selection is active.
+    pub anchor: Option<Position>,
+    pub scroll_line: usize,
+    /// Horizontal scroll offset in display columns, used only in no-wrap
+    /// mode.
+    pub scroll_col: usize,
+    pub dirty: bool,
+    pub line_ending: LineEnding,
+    pub encoding: Encoding,
+    pub language: Option<String>,
+    /// Indent style detected from the file at open (or picked via
+    /// "Cycle Indent"); overrides the editor-wide preference while this
+    /// buffer is active.
+    pub indent: Option<IndentKind>,
+    undo_stack: Vec<UndoState>,
+    redo_stack: Vec<UndoState>,
+    /// Bumped on every edit; used to version LSP didChange notifications.
+    pub version: i64,
+    /// Set after a successful decrypt; saves re-encrypt through the same
+    /// tool and the buffer is excluded from history and recovery files.
+    pub crypt: Option<crypt::CryptKind>,
+    crypt_secret: Option<String>,
+    /// Additional carets beyond `cursor`; insert and delete operations
+    /// apply at every caret. Cleared by plain cursor movement.
+    pub extra_cursors: Vec<Position>,
+    /// Render ANSI color codes instead of editing them; the buffer is
+    /// read-only while set.
+    pub log_view: bool,
+    /// Tail mode: the buffer mirrors the file on disk and the viewport
+    /// stays pinned to the end as it grows.
+    pub follow: bool,
+    /// Follow is suspended because the user scrolled up; resumes when
+    /// the end of the buffer is back in view.
+    pub follow_paused: bool,
+    /// Word count cached against `version`, so the status bar segment
+    /// recounts at most once per edit instead of every frame.
+    words_cache: Cell<Option<(i64, usize)>>,
+    /// Set for files past the huge-file threshold: the buffer is
+    /// read-only and skips LSP registration.
+    pub huge: bool,
+    /// Explicit lock, set by "Toggle Read-Only" or at open time when
+    /// the file on disk is not writable.
+    pub read_only: bool,
+    /// Bookmarked lines, marked in the gutter. Lines are not remapped as
+    /// edits shift them; navigation skips any past the end.
+    pub bookmarks: BTreeSet<usize>,
+    /// Lines that already carried trailing whitespace when the buffer
+    /// was loaded or last saved; the trim cleanup leaves them alone so
+    /// saving a small edit does not rewrite unrelated lines.
+    disk_ws_lines: HashSet<String>,
+}
+
+/// The lines of `text` that end in whitespace, collected so save-time
+/// trimming can tell pre-existing trailing whitespace from edits.
+fn lines_with_trailing_ws(text: &str) -> HashSet<String> {
+    text.split('\n')
+        .filter(|line| line.len() != line.trim_end().len())
+        .map(str::to_string)
+        .collect()
+}
+
+impl Buffer {
+    pub fn new(path: Option<PathBuf>, text: &str) -> Self {
+        let line_ending = if text.contains("\r\n") {
+            LineEnding::Crlf
+        } else {
+            LineEnding::Lf
+        };
+        let normalized = text.replace("\r\n", "\n");
+        let language = path.as_deref().and_then(detect_language);
+        Self {
+            path,
+            rope: Rope::from_str(&normalized),
+            cursor: Position::default(),
+            anchor: None,
+            scroll_line: 0,
+            scroll_col: 0,
+            dirty: false,
+            line_ending,
+            encoding: Encoding::Utf8,
+            language,
+            indent: None,
+            undo_stack: Vec::new(),
+            redo_stack: Vec::new(),
+            version: 0,
+            crypt: None,
+            crypt_secret: None,
+            extra_cursors: Vec::new(),
+            log_view: false,
+            follow: false,
+            follow_paused: false,
+            words_cache: Cell::new(None),
+            huge: false,
+            read_only: false,
+            bookmarks: BTreeSet::new(),
+            disk_ws_lines: lines_with_trailing_ws(&normalized),
+        }
+    }
+
+    /// Whether edits are blocked: the explicit read-only lock, a log
+    /// view, follow mode, or a huge file.
+    pub fn is_read_only(&self) -> bool {
+        self.read_only || self.log_view || self.follow || self.huge
+    }
+
+    /// Refresh a followed buffer from disk, appending new content in
+    /// place. Not an undo step and does not mark the buffer dirty: a
+    /// followed buffer mirrors the file. Returns whether it changed.
+    pub fn refresh_from_disk(&mut self) -> Result<bool> {
+        let Some(path) = self.path.clone() else {
+            return Ok(false);
+        };
+        let contents = fs::read_to_string(&path)
+            .with_context(|| format!("failed to read {}", path.display()))?;
+        let normalized = contents.replace("\r\n", "\n");
+        let old = self.rope.to_string();
+        if normalized == old {
+            return Ok(false);
+        }
+        if normalized.len() > old.len() && normalized.starts_with(&old) {
+            let end = self.rope.len_chars();
+            self.rope.insert(end, &normalized[old.len()..]);
+        } else {
+            // Truncated or rewritten (e.g. log rotation): reload fully.
+            self.rope = Rope::from_str(&normalized);
+            self.clamp_cursor();
+        }
+        self.version += 1;
+        Ok(true)
+    }
+
+    /// Move the cursor to the very end of the buffer.
+    pub fn move_to_end(&mut self) {
+        self.cursor = self.position_of(self.rope.len_chars());
+        self.anchor = None;
+        self.extra_cursors.clear();
+    }
+
+    /// Strip ANSI escape sequences from the whole buffer so it can be
+    /// edited as plain text. Undoable like any other edit.
+    pub fn strip_ansi_content(&mut self) {
+        self.push_undo();
+        let stripped = crate::ui::ansi::strip(&self.rope.to_string());
+        self.rope = Rope::from_str(&stripped);
+        self.anchor = None;
+        self.extra_cursors.clear();
+        self.log_view = false;
+        self.clamp_cursor();
+        self.mark_edited();
+    }
+
+    /// Apply the save-time cleanups: strip trailing whitespace from
+    /// lines changed since load and/or append a final newline. Lines
+    /// whose trailing whitespace was already on disk keep it, so a
+    /// one-line edit does not pollute the diff with unrelated trims.
+    /// The change is undoable like any other edit; returns whether the
+    /// buffer changed.
+    pub fn apply_save_cleanup(&mut self, cleanup: SaveCleanup) -> bool {
+        let text = self.rope.to_string();
+        let mut cleaned = if cleanup.trim_trailing_whitespace {
+            let lines: Vec<&str> = text
+                .split('\n')
+                .map(|line| {
+                    if self.disk_ws_lines.contains(line) {
+                        line
+                    } else {
+                        line.trim_end()
+                    }
+                })
+                .collect();
+            lines.join("\n")
+        } else {
+            text.clone()
+        };
+        if cleanup.final_newline && !cleaned.is_empty() && !cleaned.ends_with('\n') {
+            cleaned.push('\n');
+        }
+        if cleaned == text {
+            return false;
+        }
+        self.push_undo();
+        self.rope = Rope::from_str(&cleaned);
+        self.clamp_cursor();
+        self.version += 1;
+        true
+    }
+
+    pub fn display_name(&self) -> String {
+        self.path
+            .as_deref()
+            .and_then(|p| p.file_name())
+            .map(|n| n.to_string_lossy().into_owned())
+            .unwrap_or_else(|| "[untitled]".to_string())
+    }
+
+    pub fn line_count(&self) -> usize {
+        self.rope.len_lines()
+    }
+
+    /// Character length of the given line, excluding the trailing newline.
+    pub fn line_len(&self, line: usize) -> usize {
+        if line >= self.rope.len_lines() {
+            return 0;
+        }
+        let l = self.rope.line(line);
+        let mut len = l.len_chars();
+        if len > 0 && l.char(len - 1) == '\n' {
+            len -= 1;
+        }
+        len
+    }
+
+    pub fn char_index(&self, pos: Position) -> usize {
+        let line = pos.line.min(self.rope.len_lines().saturating_sub(1));
+        self.rope.line_to_char(line) + pos.col.min(self.line_len(line))
+    }
+
+    pub fn position_of(&self, char_idx: usize) -> Position {
+        let idx = char_idx.min(self.rope.len_chars());
+        let line = self.rope.char_to_line(idx);
+        Position {
+            line,
+            col: idx - self.rope.line_to_char(line),
+        }
+    }
+
+    pub fn clamp_cursor(&mut self) {
+        let max_line = self.rope.len_lines().saturating_sub(1);
+        self.cursor.line = self.cursor.line.min(max_line);
+        self.cursor.col = self.cursor.col.min(self.line_len(self.cursor.line));
+    }
+
+    /// The active selection as an ordered (start, end) char range.
+    pub fn selection_range(&self) -> Option<(usize, usize)> {
+        let anchor = self.anchor?;
+        let a = self.char_index(anchor);
+        let b = self.char_index(self.cursor);
+        if a == b {
+            None
+        } else {
+            Some((a.min(b), a.max(b)))
+        }
+    }
+
+    pub fn selected_text(&self) -> Option<String> {
+        let (start, end) = self.selection_range()?;
+        Some(self.rope.slice(start..end).to_string())
+    }
+
+    /// The cursor line including its trailing newline, used as the
+    /// copy/cut fallback when nothing is selected.
+    pub fn current_line_text(&self) -> String {
+        self.rope.line(self.cursor.line).to_string()
+    }
+
+    fn push_undo(&mut self) {
+        self.undo_stack.push(UndoState {
+            rope: self.rope.clone(),
+            cursor: self.cursor,
+        });
+        if self.undo_stack.len() > 200 {
+            self.undo_stack.remove(0);
+        }
+        self.redo_stack.clear();
+    }
+
+    fn mark_edited(&mut self) {
+        self.dirty = true;
+        self.version += 1;
+    }
+
+    pub fn undo(&mut self) -> bool {
+        if let Some(state) = self.undo_stack.pop() {
+            self.redo_stack.push(UndoState {
+                rope: self.rope.clone(),
+                cursor: self.cursor,
+            });
+            self.rope = state.rope;
+            self.cursor = state.cursor;
+            self.anchor = None;
+            self.extra_cursors.clear();
+            self.mark_edited();
+            self.clamp_cursor();
+            true
+        } else {
+            false
+        }
+    }
+
+    pub fn redo(&mut self) -> bool {
+        if let Some(state) = self.redo_stack.pop() {
+            self.undo_stack.push(UndoState {
+                rope: self.rope.clone(),
+                cursor: self.cursor,
+            });
+            self.rope = state.rope;
+            self.cursor = state.cursor;
+            self.anchor = None;
+            self.extra_cursors.clear();
+            self.mark_edited();
+            self.clamp_cursor();
+            true
+        } else {
+            false
+        }
+    }
+
+    /// Char indices of every caret (primary plus extras), ascending and
+    /// deduplicated, with the position of the primary in the list.
+    fn caret_indices(&self) -> (Vec<usize>, usize) {
+        let primary = self.char_index(self.cursor);
+        let mut indices: Vec<usize> = self
+            .extra_cursors
+            .iter()
+            .map(|p| self.char_index(*p))
+            .chain([primary])
+            .collect();
+        indices.sort_unstable();
+        indices.dedup();
+        let primary_pos = indices.iter().position(|&i| i == primary).unwrap_or(0);
+        (indices, primary_pos)
+    }
+
+    /// Apply `edit` at every caret, back to front so earlier indices stay
+    /// valid. `edit` returns the caret's new index and how much text after
+    /// it shifted, or `None` for a no-op at that caret. Afterwards the
+    /// carets are rebuilt from the adjusted indices.
+    fn edit_at_carets(&mut self, edit: impl Fn(&mut Rope, usize) -> Option<(usize, isize)>) {
+        let (indices, primary_pos) = self.caret_indices();
+        let mut results: Vec<(usize, isize)> = indices
+            .iter()
+            .rev()
+            .map(|&idx| edit(&mut self.rope, idx).unwrap_or((idx, 0)))
+            .collect();
+        results.reverse();
+        // Shift each caret by the deltas of all edits before it.
+        let mut shift = 0isize;
+        let mut finals = Vec::with_capacity(results.len());
+        for (new_idx, delta) in results {
+            finals.push((new_idx as isize + shift).max(0) as usize);
+            shift += delta;
+        }
+        self.cursor = self.position_of(finals[primary_pos]);
+        self.extra_cursors = finals
+            .iter()
+            .enumerate()
+            .filter(|&(i, _)| i != primary_pos)
+            .map(|(_, &idx)| self.position_of(idx))
+            .collect();
+        self.extra_cursors.dedup();
+        self.extra_cursors.retain(|p| *p != self.cursor);
+    }
+
+    pub fn insert_char(&mut self, c: char) {
+        self.push_undo();
+        self.delete_selection_inner();
+        if !self.extra_cursors.is_empty() {
+            self.edit_at_carets(|rope, idx| {
+                rope.insert_char(idx, c);
+                Some((idx + 1, 1))
+            });
+            self.mark_edited();
+            return;
+        }
+        let idx = self.char_index(self.cursor);
+        self.rope.insert_char(idx, c);
+        if c == '\n' {
+            self.cursor.line += 1;
+            self.cursor.col = 0;
+        } else {
+            self.cursor.col += 1;
+        }
+        self.mark_edited();
+    }
+
+    /// Insert in overwrite mode: replace the character under the cursor,
+    /// except at line end (or with a newline), where it inserts.
+    pub fn overwrite_char(&mut self, c: char) {
+        let idx = self.char_index(self.cursor);
+        let at_line_end = idx >= self.rope.len_chars() || self.rope.char(idx) == '\n';
+        if c == '\n' || at_line_end || self.selection_range().is_some() {
+            self.insert_char(c);
+            return;
+        }
+        self.push_undo();
+        self.rope.remove(idx..idx + 1);
+        self.rope.insert_char(idx, c);
+        self.cursor.col += 1;
+        self.mark_edited();
+    }
+
+    pub fn insert_str(&mut self, text: &str) {
+        self.push_undo();
+        self.delete_selection_inner();
+        self.extra_cursors.clear();
+        let idx = self.char_index(self.cursor);
+        self.rope.insert(idx, text);
+        let new_idx = idx + text.chars().count();
+        self.cursor = self.position_of(new_idx);
+        self.mark_edited();
+    }
+
+    /// Tab honoring the configured indent: inserts one level, or indents
+    /// every line of a multi-line selection.
+    pub fn insert_tab(&mut self, unit: &str) {
+        if let Some((first, last)) = self.selected_line_span() {
+            if first != last {
+                self.indent_lines(first, last, unit);
+                return;
+            }
+        }
+        self.insert_str(unit);
+    }
+
+    /// Inclusive line span of the selection, if any.
+    fn selected_line_span(&self) -> Option<(usize, usize)> {
+        let (start, end) = self.selection_range()?;
+        let first = self.rope.char_to_line(start);
+        // A selection ending at column 0 does not include that line.
+        let last_pos = self.position_of(end);
+        let last = if last_pos.col == 0 && last_pos.line > first {
+            last_pos.line - 1
+        } else {
+            last_pos.line
+        };
+        Some((first, last))
+    }
+
+    fn indent_lines(&mut self, first: usize, last: usize, unit: &str) {
+        self.push_undo();
+        for line in (first..=last).rev() {
+            if self.line_len(line) == 0 {
+                continue;
+            }
+            let idx = self.rope.line_to_char(line);
+            self.rope.insert(idx, unit);
+        }
+        let width = unit.chars().count();
+        self.cursor.col += width;
+        if let Some(anchor) = &mut self.anchor {
+            anchor.col += width;
+        }
+        self.clamp_cursor();
+        self.mark_edited();
+    }
+
+    /// Shift+Tab: remove one level of indentation from the cursor line or
+    /// every selected line. A leading tab counts as a level regardless of
+    /// the configured width. Returns whether anything changed.
+    pub fn dedent(&mut self, width: usize) -> bool {
+        let (first, last) = self
+            .selected_line_span()
+            .unwrap_or((self.cursor.line, self.cursor.line));
+        let mut removals = Vec::new();
+        for line in first..=last {
+            let start = self.rope.line_to_char(line);
+            let len = self.line_len(line);
+            let count = if len > 0 && self.rope.char(start) == '\t' {
+                1
+            } else {
+                (0..width.min(len))
+                    .take_while(|&i| self.rope.char(start + i) == ' ')
+                    .count()
+            };
+            if count > 0 {
+                removals.push((start, count));
+            }
+        }
+        if removals.is_empty() {
+            return false;
+        }
+        self.push_undo();
+        for &(start, count) in removals.iter().rev() {
+            self.rope.remove(start..start + count);
+        }
+        self.clamp_cursor();
+        if let Some(anchor) = self.anchor {
+            let mut anchor = anchor;
+            anchor.col = anchor.col.min(self.line_len(anchor.line));
+            self.anchor = Some(anchor);
+        }
+        self.mark_edited();
+        true
+    }
+
+    pub fn insert_newline(&mut self) {
+        self.insert_char('\n');
+    }
+
+    /// Enter with auto-indent: the new line inherits the current line's
+    /// indentation, one `unit` deeper after `{`, `(`, `[` or a trailing
+    /// `:`. Pressing Enter between `{` and `}` also drops the closer onto
+    /// its own line.
+    pub fn insert_newline_indented(&mut self, unit: &str) {
+        if !self.extra_cursors.is_empty() {
+            self.insert_newline();
+            return;
+        }
+        self.push_undo();
+        self.delete_selection_inner();
+        let idx = self.char_index(self.cursor);
+        let line_text = self.rope.line(self.cursor.line).to_string();
+        let base: String = line_text
+            .chars()
+            .take_while(|c| *c == ' ' || *c == '\t')
+            .take(self.cursor.col)
+            .collect();
+        let before_cursor: String = line_text.chars().take(self.cursor.col).collect();
+        let before = (idx > 0).then(|| self.rope.char(idx - 1));
+        let next = (idx < self.rope.len_chars()).then(|| self.rope.char(idx));
+        let deepen = matches!(before, Some('{' | '(' | '['))
+            || before_cursor.trim_end().ends_with(':');
+        let mut indent = base.clone();
+        if deepen {
+            indent.push_str(unit);
+        }
+        if before == Some('{') && next == Some('}') {
+            self.rope.insert(idx, &format!("\n{indent}\n{base}"));
+        } else {
+            self.rope.insert(idx, &format!("\n{indent}"));
+        }
+        self.cursor = self.position_of(idx + 1 + indent.chars().count());
+        self.mark_edited();
+    }
+
+    /// Auto-closing for brackets and quotes: openers insert the matching
+    /// closer with the cursor in between, and typing a closer that is
+    /// already next just steps over it. Returns false when the key should
+    /// be inserted normally.
+    pub fn insert_char_autoclose(&mut self, c: char) -> bool {
+        if !self.extra_cursors.is_empty() || self.selection_range().is_some() {
+            return false;
+        }
+        let idx = self.char_index(self.cursor);
+        let next = (idx < self.rope.len_chars()).then(|| self.rope.char(idx));
+        let step_over = |buf: &mut Self| {
+            buf.cursor = buf.position_of(idx + 1);
+        };
+        match c {
+            ')' | ']' | '}' if next == Some(c) => {
+                step_over(self);
+                true
+            }
+            ')' | ']' | '}' => false,
+            '(' | '[' | '{' => {
+                let closer = match c {
+                    '(' => ')',
+                    '[' => ']',
+                    _ => '}',
+                };
+                self.push_undo();
+                self.rope.insert(idx, &format!("{c}{closer}"));
+                self.cursor = self.position_of(idx + 1);
+                self.mark_edited();
+                true
+            }
+            '"' | '\'' | '`' => {
+                if next == Some(c) {
+                    step_over(self);
+                    return true;
+                }
+                // An apostrophe inside a word is punctuation, not a quote.
+                let before = (idx > 0).then(|| self.rope.char(idx - 1));
+                if before.is_some_and(|b| b.is_alphanumeric()) {
+                    return false;
+                }
+                self.push_undo();
+                self.rope.insert(idx, &format!("{c}{c}"));
+                self.cursor = self.position_of(idx + 1);
+                self.mark_edited();
+                true
+            }
+            _ => false,
+        }
+    }
+
+    pub fn backspace(&mut self) {
+        if self.selection_range().is_some() {
+            self.delete_selection();
+            return;
+        }
+        if !self.extra_cursors.is_empty() {
+            self.push_undo();
+            self.edit_at_carets(|rope, idx| {
+                if idx == 0 {
+                    return None;
+                }
+                rope.remove(idx - 1..idx);
+                Some((idx - 1, -1))
+            });
+            self.mark_edited();
+            return;
+        }
+        let idx = self.char_index(self.cursor);
+        if idx == 0 {
+            return;
+        }
+        self.push_undo();
+        self.rope.remove(idx - 1..idx);
+        self.cursor = self.position_of(idx - 1);
+        self.mark_edited();
+    }
+
+    pub fn delete_forward(&mut self) {
+        if self.selection_range().is_some() {
+            self.delete_selection();
+            return;
+        }
+        if !self.extra_cursors.is_empty() {
+            self.push_undo();
+            self.edit_at_carets(|rope, idx| {
+                if idx >= rope.len_chars() {
+                    return None;
+                }
+                rope.remove(idx..idx + 1);
+                Some((idx, -1))
+            });
+            self.mark_edited();
+            return;
+        }
+        let idx = self.char_index(self.cursor);
+        if idx >= self.rope.len_chars() {
+            return;
+        }
+        self.push_undo();
+        self.rope.remove(idx..idx + 1);
+        self.mark_edited();
+    }
+
+    pub fn delete_selection(&mut self) {
+        if self.selection_range().is_none() {
+            return;
+        }
+        self.push_undo();
+        self.delete_selection_inner();
+        self.mark_edited();
+    }
+
+    fn delete_selection_inner(&mut self) {
+        if let Some((start, end)) = self.selection_range() {
+            self.rope.remove(start..end);
+            self.cursor = self.position_of(start);
+        }
+        self.anchor = None;
+    }
+
+    /// Replace an arbitrary char range, used by LSP text edits and
+    /// replace-in-files. Restores a sensible cursor afterwards.
+    pub fn replace_range(&mut self, start: usize, end: usize, text: &str) {
+        self.push_undo();
+        let end = end.min(self.rope.len_chars());
+        let start = start.min(end);
+        self.rope.remove(start..end);
+        self.rope.insert(start, text);
+        self.cursor = self.position_of(start + text.chars().count());
+        self.anchor = None;
+        self.mark_edited();
+    }
+
+    pub fn move_cursor(&mut self, dx: isize, dy: isize, select: bool) {
+        self.extra_cursors.clear();
+        if select {
+            if self.anchor.is_none() {
+                self.anchor = Some(self.cursor);
+            }
+        } else {
+            self.anchor = None;
+        }
+        if dy != 0 {
+            let line = self.cursor.line as isize + dy;
+            self.cursor.line = line.clamp(0, self.rope.len_lines() as isize - 1) as usize;
+            self.cursor.col = self.cursor.col.min(self.line_len(self.cursor.line));
+        }
+        if dx < 0 {
+            let idx = self.char_index(self.cursor);
+            self.cursor = self.position_of(idx.saturating_sub(dx.unsigned_abs()));
+        } else if dx > 0 {
+            let idx = self.char_index(self.cursor);
+            self.cursor = self.position_of(idx + dx as usize);
+        }
+    }
+
+    pub fn move_home(&mut self, select: bool) {
+        self.extra_cursors.clear();
+        if select && self.anchor.is_none() {
+            self.anchor = Some(self.cursor);
+        } else if !select {
+            self.anchor = None;
+        }
+        self.cursor.col = 0;
+    }
+
+    pub fn move_end(&mut self, select: bool) {
+        self.extra_cursors.clear();
+        if select && self.anchor.is_none() {
+            self.anchor = Some(self.cursor);
+        } else if !select {
+            self.anchor = None;
+        }
+        self.cursor.col = self.line_len(self.cursor.line);
+    }
+
+    pub fn select_all(&mut self) {
+        self.anchor = Some(Position { line: 0, col: 0 });
+        self.cursor = self.position_of(self.rope.len_chars());
+        self.extra_cursors.clear();
+    }
+
+    pub fn goto_line(&mut self, line: usize) {
+        self.cursor.line = line.min(self.rope.len_lines().saturating_sub(1));
+        self.cursor.col = 0;
+        self.anchor = None;
+        self.extra_cursors.clear();
+    }
+
+    /// Toggle a bookmark on the cursor line; returns whether it is now
+    /// set.
+    pub fn toggle_bookmark(&mut self) -> bool {
+        let line = self.cursor.line;
+        if self.bookmarks.remove(&line) {
+            false
+        } else {
+            self.bookmarks.insert(line);
+            true
+        }
+    }
+
+    /// The nearest bookmarked line after the cursor, wrapping to the
+    /// first; bookmarks past the end of the buffer are ignored.
+    pub fn next_bookmark(&self) -> Option<usize> {
+        let last = self.rope.len_lines().saturating_sub(1);
+        let mut valid = self.bookmarks.iter().copied().filter(|&line| line <= last);
+        valid
+            .clone()
+            .find(|&line| line > self.cursor.line)
+            .or_else(|| valid.next())
+    }
+
+    /// The nearest bookmarked line before the cursor, wrapping to the
+    /// last in-range bookmark.
+    pub fn prev_bookmark(&self) -> Option<usize> {
+        let last = self.rope.len_lines().saturating_sub(1);
+        let valid = || self.bookmarks.iter().copied().filter(|&line| line <= last);
+        valid()
+            .rfind(|&line| line < self.cursor.line)
+            .or_else(|| valid().next_back())
+    }
+
+    /// Swap the cursor line with its neighbour above (`-1`) or below
+    /// (`1`), as a single undo step.
+    pub fn move_line(&mut self, dy: isize) -> bool {
+        let line = self.cursor.line;
+        let target = line as isize + dy;
+        if dy == 0 || target < 0 || target as usize >= self.rope.len_lines() {
+            return false;
+        }
+        self.push_undo();
+        let a = line.min(target as usize);
+        let b = a + 1;
+        let a_start = self.rope.line_to_char(a);
+        let a_end = self.rope.line_to_char(b);
+        let b_end = if b + 1 < self.rope.len_lines() {
+            self.rope.line_to_char(b + 1)
+        } else {
+            self.rope.len_chars()
+        };
+        let mut text_a = self.rope.slice(a_start..a_end).to_string();
+        let mut text_b = self.rope.slice(a_end..b_end).to_string();
+        // When the lower line lacks a trailing newline the swap moves it
+        // to the new last line instead.
+        if !text_b.ends_with('\n') {
+            text_b.push('\n');
+            text_a.pop();
+        }
+        self.rope.remove(a_start..b_end);
+        self.rope.insert(a_start, &format!("{text_b}{text_a}"));
+        self.cursor.line = target as usize;
+        self.anchor = None;
+        self.extra_cursors.clear();
+        self.clamp_cursor();
+        self.mark_edited();
+        true
+    }
+
+    /// Duplicate the cursor line below itself and move onto the copy.
+    pub fn duplicate_line(&mut self) {
+        self.push_undo();
+        let line = self.cursor.line;
+        let start = self.rope.line_to_char(line);
+        let end = if line + 1 < self.rope.len_lines() {
+            self.rope.line_to_char(line + 1)
+        } else {
+            self.rope.len_chars()
+        };
+        let mut text = self.rope.slice(start..end).to_string();
+        if !text.ends_with('\n') {
+            text.insert(0, '\n');
+        }
+        self.rope.insert(end, &text);
+        self.cursor.line += 1;
+        self.anchor = None;
+        self.extra_cursors.clear();
+        self.mark_edited();
+    }
+
+    /// Join the next line onto the cursor line, collapsing the newline and
+    /// the next line's indentation into a single space.
+    pub fn join_lines(&mut self) -> bool {
+        let line = self.cursor.line;
+        if line + 1 >= self.rope.len_lines() {
+            return false;
+        }
+        self.push_undo();
+        let nl_idx = self.rope.line_to_char(line) + self.line_len(line);
+        let mut end = nl_idx + 1;
+        while end < self.rope.len_chars() && matches!(self.rope.char(end), ' ' | '\t') {
+            end += 1;
+        }
+        self.rope.remove(nl_idx..end);
+        let joined_empty = nl_idx == self.rope.len_chars() || self.rope.char(nl_idx) == '\n';
+        if !joined_empty && nl_idx > self.rope.line_to_char(line) {
+            self.rope.insert_char(nl_idx, ' ');
+        }
+        self.cursor = self.position_of(nl_idx);
+        self.anchor = None;
+        self.extra_cursors.clear();
+        self.mark_edited();
+        true
+    }
+
+    /// Replace the first `replace_chars` characters with `header`; used
+    /// by the file-header command so a refresh swaps the stale block
+    /// instead of stacking a second one.
+    pub fn apply_header(&mut self, header: &str, replace_chars: usize) {
+        self.push_undo();
+        self.rope.remove(0..replace_chars.min(self.rope.len_chars()));
+        self.rope.insert(0, header);
+        self.anchor = None;
+        self.extra_cursors.clear();
+        self.clamp_cursor();
+        self.mark_edited();
+    }
+
+    /// Delete the whole cursor line.
+    pub fn delete_line(&mut self) {
+        self.push_undo();
+        let line = self.cursor.line;
+        let start = self.rope.line_to_char(line);
+        let end = if line + 1 < self.rope.len_lines() {
+            self.rope.line_to_char(line + 1)
+        } else {
+            self.rope.len_chars()
+        };
+        if start == end && start > 0 {
+            // Empty last line: remove the newline that created it.
+            self.rope.remove(start - 1..start);
+        } else {
+            self.rope.remove(start..end);
+        }
+        self.anchor = None;
+        self.extra_cursors.clear();
+        self.clamp_cursor();
+        self.mark_edited();
+    }
+
+    /// Add a caret at `pos` (Alt+Click); adding one on an existing caret
+    /// removes it instead.
+    pub fn add_caret(&mut self, pos: Position) {
+        let idx = self.char_index(pos);
+        let pos = self.position_of(idx);
+        if pos == self.cursor {
+            return;
+        }
+        if let Some(existing) = self.extra_cursors.iter().position(|p| *p == pos) {
+            self.extra_cursors.remove(existing);
+        } else {
+            self.extra_cursors.push(pos);
+        }
+    }
+
+    /// Extend a column (block) selection: spawn a caret one line above or
+    /// below the outermost caret, keeping the current column.
+    pub fn add_caret_line(&mut self, dy: isize) {
+        let col = self.cursor.col;
+        let line = if dy < 0 {
+            self.extra_cursors
+                .iter()
+                .map(|p| p.line)
+                .chain([self.cursor.line])
+                .min()
+                .unwrap_or(0)
+                .checked_sub(1)
+        } else {
+            let max = self
+                .extra_cursors
+                .iter()
+                .map(|p| p.line)
+                .chain([self.cursor.line])
+                .max()
+                .unwrap_or(0);
+            (max + 1 < self.rope.len_lines()).then_some(max + 1)
+        };
+        if let Some(line) = line {
+            let pos = Position {
+                line,
+                col: col.min(self.line_len(line)),
+            };
+            if pos != self.cursor && !self.extra_cursors.contains(&pos) {
+                self.extra_cursors.push(pos);
+            }
+        }
+    }
+
+    /// Add a caret after the next occurrence of the selected text (or the
+    /// word under the cursor when nothing is selected). Ctrl+D.
+    pub fn select_next_occurrence(&mut self) -> bool {
+        if self.selection_range().is_none() {
+            self.select_word();
+        }
+        let Some(needle) = self.selected_text().filter(|t| !t.is_empty()) else {
+            return false;
+        };
+        let text = self.rope.to_string();
+        let from = self
+            .extra_cursors
+            .iter()
+            .map(|p| self.char_index(*p))
+            .chain([self.char_index(self.cursor)])
+            .max()
+            .unwrap_or(0);
+        let from_byte = text
+            .char_indices()
+            .nth(from)
+            .map(|(b, _)| b)
+            .unwrap_or(text.len());
+        let Some(found) = text[from_byte..].find(&needle) else {
+            return false;
+        };
+        let match_char = text[..from_byte + found].chars().count() + needle.chars().count();
+        let pos = self.position_of(match_char);
+        if pos != self.cursor && !self.extra_cursors.contains(&pos) {
+            self.extra_cursors.push(pos);
+            return true;
+        }
+        false
+    }
+
+    /// Select the alphanumeric/underscore word around the cursor.
+    pub fn select_word(&mut self) {
+        let is_word = |c: char| c.is_alphanumeric() || c == '_';
+        let idx = self.char_index(self.cursor);
+        let mut start = idx;
+        while start > 0 && is_word(self.rope.char(start - 1)) {
+            start -= 1;
+        }
+        let mut end = idx;
+        while end < self.rope.len_chars() && is_word(self.rope.char(end)) {
+            end += 1;
+        }
+        if start < end {
+            self.anchor = Some(self.position_of(start));
+            self.cursor = self.position_of(end);
+        }
+    }
+
+    /// Select the whole cursor line including its trailing newline.
+    pub fn select_line(&mut self) {
+        let line = self.cursor.line;
+        self.anchor = Some(Position { line, col: 0 });
+        if line + 1 < self.rope.len_lines() {
+            self.cursor = Position {
+                line: line + 1,
+                col: 0,
+            };
+        } else {
+            self.move_end(true);
+        }
+    }
+
+    /// Swap in freshly re-decoded text as if the file had just been
+    /// opened: undo history cleared, buffer left clean.
+    pub fn reload_with(&mut self, text: &str, encoding: Encoding) {
+        self.line_ending = if text.contains("\r\n") {
+            LineEnding::Crlf
+        } else {
+            LineEnding::Lf
+        };
+        self.rope = Rope::from_str(&text.replace("\r\n", "\n"));
+        self.encoding = encoding;
+        self.undo_stack.clear();
+        self.redo_stack.clear();
+        self.extra_cursors.clear();
+        self.anchor = None;
+        self.dirty = false;
+        self.version += 1;
+        self.clamp_cursor();
+    }
+
+    /// Replace the whole buffer text as a single undo step, used when
+    /// restoring a crash-recovery snapshot. Leaves the buffer dirty.
+    pub fn replace_contents(&mut self, text: &str) {
+        self.push_undo();
+        self.rope = Rope::from_str(&text.replace("\r\n", "\n"));
+        self.extra_cursors.clear();
+        self.anchor = None;
+        self.clamp_cursor();
+        self.mark_edited();
+    }
+
+    /// Document statistics: lines and chars come straight from the rope;
+    /// the word count is cached against the buffer version.
+    pub fn stats(&self) -> DocStats {
+        let words = match self.words_cache.get() {
+            Some((version, words)) if version == self.version => words,
+            _ => {
+                let words = count_words(self.rope.chunks());
+                self.words_cache.set(Some((self.version, words)));
+                words
+            }
+        };
+        DocStats {
+            lines: self.rope.len_lines(),
+            words,
+            chars: self.rope.len_chars(),
+        }
+    }
+
+    /// Statistics for the active selection, scanning only the selected
+    /// slice.
+    pub fn selection_stats(&self) -> Option<DocStats> {
+        let (start, end) = self.selection_range()?;
+        let slice = self.rope.slice(start..end);
+        Some(DocStats {
+            lines: slice.len_lines(),
+            words: count_words(slice.chunks()),
+            chars: slice.len_chars(),
+        })
+    }
+
+    /// Serialize the buffer with its configured line endings.
+    pub fn contents_for_disk(&self) -> String {
+        let text = self.rope.to_string();
+        match self.line_ending {
+            LineEnding::Lf => text,
+            LineEnding::Crlf => text.replace('\n', "\r\n"),
+        }
+    }
+}
+
+/// The editor pane: a set of open buffers plus the active one.
+pub struct Editor {
+    pub buffers: Vec<Buffer>,
+    pub active: usize,
+    pub prefs: EditorPreferences,
+    /// Insert-key toggled overwrite mode: typed characters replace the
+    /// one under the cursor instead of pushing it right.
+    pub overwrite: bool,
+}
+
+impl Editor {
+    pub fn new() -> Self {
+        Self {
+            buffers: Vec::new(),
+            active: 0,
+            prefs: EditorPreferences::default(),
+            overwrite: false,
+        }
+    }
+
+    pub fn active_buffer(&self) -> Option<&Buffer> {
+        self.buffers.get(self.active)
+    }
+
+    pub fn active_buffer_mut(&mut self) -> Option<&mut Buffer> {
+        self.buffers.get_mut(self.active)
+    }
+
+    /// The effective indent: the active buffer's detected (or cycled)
+    /// style when it has one, otherwise the editor-wide preference.
+    pub fn indent(&self) -> IndentKind {
+        self.active_buffer()
+            .and_then(|buffer| buffer.indent)
+            .unwrap_or(self.prefs.indent)
+    }
+
+    pub fn buffer_for_path(&self, path: &Path) -> Option<usize> {
+        self.buffers
+            .iter()
+            .position(|b| b.path.as_deref() == Some(path))
+    }
+
+    /// Open an empty unnamed buffer and make it active.
+    pub fn open_untitled(&mut self) -> usize {
+        self.buffers.push(Buffer::new(None, ""));
+        self.active = self.buffers.len() - 1;
+        self.active
+    }
+
+    /// Open a file, reusing an existing buffer when the file is already open.
+    pub fn open_file(&mut self, path: &Path) -> Result<usize> {
+        if let Some(idx) = self.buffer_for_path(path) {
+            self.active = idx;
+            return Ok(idx);
+        }
+        let bytes =
+            fs::read(path).with_context(|| format!("failed to read {}", path.display()))?;
+        let (contents, encoding) = decode_file(&bytes)?;
+        Ok(self.open_loaded(path, &contents, encoding))
+    }
+
+    /// Adopt already-decoded text as a new buffer, or activate the
+    /// existing one. Used both by [`open_file`](Self::open_file) and for
+    /// loads that ran on a background thread.
+    pub fn open_loaded(&mut self, path: &Path, contents: &str, encoding: Encoding) -> usize {
+        if let Some(idx) = self.buffer_for_path(path) {
+            self.active = idx;
+            return idx;
+        }
+        let mut buffer = Buffer::new(Some(path.to_path_buf()), contents);
+        buffer.encoding = encoding;
+        buffer.indent = detect_indent(contents);
+        self.buffers.push(buffer);
+        self.active = self.buffers.len() - 1;
+        self.active
+    }
+
+    pub fn close_active(&mut self) {
+        if self.buffers.is_empty() {
+            return;
+        }
+        self.buffers.remove(self.active);
+        if self.active >= self.buffers.len() && self.active > 0 {
+            self.active -= 1;
+        }
+    }
+
+    pub fn next_buffer(&mut self) {
+        if !self.buffers.is_empty() {
+            self.active = (self.active + 1) % self.buffers.len();
+        }
+    }
+
+    pub fn prev_buffer(&mut self) {
+        if !self.buffers.is_empty() {
+            self.active = (self.active + self.buffers.len() - 1) % self.buffers.len();
+        }
+    }
+
+    /// Open an encrypted file: decrypt via the matching tool and keep the
+    /// plaintext (and the secret, for re-encrypting saves) only in memory.
+    pub fn open_decrypted(
+        &mut self,
+        path: &Path,
+        kind: crypt::CryptKind,
+        secret: &str,
+    ) -> Result<usize> {
+        if let Some(idx) = self.buffer_for_path(path) {
+            self.active = idx;
+            return Ok(idx);
+        }
+        let contents = crypt::decrypt(kind, path, secret)?;
+        let mut buffer = Buffer::new(Some(path.to_path_buf()), &contents);
+        buffer.crypt = Some(kind);
+        buffer.crypt_secret = Some(secret.to_string());
+        self.buffers.push(buffer);
+        self.active = self.buffers.len() - 1;
+        Ok(self.active)
+    }
+
+    /// The save-time cleanups the current preferences ask for.
+    pub fn save_cleanup(&self) -> SaveCleanup {
+        SaveCleanup {
+            trim_trailing_whitespace: self.prefs.trim_trailing_whitespace,
+            final_newline: self.prefs.final_newline,
+        }
+    }
+
+    /// Write a buffer to the given path, applying the save-time
+    /// cleanups first. All save paths funnel through here.
+    pub fn write_to(buffer: &mut Buffer, path: &Path, cleanup: SaveCleanup) -> Result<()> {
+        buffer.apply_save_cleanup(cleanup);
+        let contents = buffer.contents_for_disk();
+        if let (Some(kind), Some(secret)) = (buffer.crypt, buffer.crypt_secret.clone()) {
+            crypt::encrypt(kind, path, &secret, contents.as_bytes())?;
+            buffer.dirty = false;
+            buffer.path = Some(path.to_path_buf());
+            buffer.disk_ws_lines = lines_with_trailing_ws(&buffer.rope.to_string());
+            return Ok(());
+        }
+        let mut file = fs::File::create(path)
+            .with_context(|| format!("failed to create {}", path.display()))?;
+        file.write_all(&encode_contents(&contents, buffer.encoding))?;
+        buffer.dirty = false;
+        buffer.path = Some(path.to_path_buf());
+        // The disk now matches the buffer: any trailing whitespace that
+        // survived the cleanup counts as pre-existing from here on.
+        buffer.disk_ws_lines = lines_with_trailing_ws(&buffer.rope.to_string());
+        Ok(())
+    }
+
+    pub fn save_active(&mut self) -> Result<Option<PathBuf>> {
+        let cleanup = self.save_cleanup();
+        let Some(buffer) = self.buffers.get_mut(self.active) else {
+            return Ok(None);
+        };
+        let Some(path) = buffer.path.clone() else {
+            return Ok(None);
+        };
+        Self::write_to(buffer, &path, cleanup)?;
+        Ok(Some(path))
+    }
+}
+
+/// Map a file extension to a language identifier used for LSP and display.
+pub fn detect_language(path: &Path) -> Option<String> {
+    let ext = path.extension()?.to_str()?;
+    let lang = match ext {
+        "rs" => "rust",
+        "py" => "python",
+        "js" | "mjs" | "cjs" => "javascript",
+        "ts" | "tsx" => "typescript",
+        "c" | "h" => "c",
+        "cpp" | "cc" | "hpp" => "cpp",
+        "go" => "go",
+        "java" => "java",
+        "toml" => "toml",
+        "json" => "json",
+        "yaml" | "yml" => "yaml",
+        "md" => "markdown",
+        "sh" | "bash" => "shell",
+        "html" => "html",
+        "css" => "css",
+        _ => return None,
+    };
+    Some(lang.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn save_cleanup_trims_only_modified_lines() {
+        let mut buf = Buffer::new(None, "stale  \nfn main() {\n}");
+        buf.cursor = Position { line: 1, col: 11 };
+        buf.insert_str("  ");
+        let cleanup = SaveCleanup {
+            trim_trailing_whitespace: true,
+            final_newline: true,
+        };
+        assert!(buf.apply_save_cleanup(cleanup));
+        // The edited line is trimmed; line 0's whitespace predates the
+        // load and stays put.
+        assert_eq!(buf.rope.to_string(), "stale  \nfn main() {\n}\n");
+        // Already clean: no change and no undo step.
+        assert!(!buf.apply_save_cleanup(cleanup));
+        assert!(buf.undo());
+        assert_eq!(buf.rope.to_string(), "stale  \nfn main() {  \n}");
+    }
+
+    #[test]
+    fn insert_and_undo_roundtrip() {
+        let mut buf = Buffer::new(None, "hello\n");
+        buf.cursor = Position { line: 0, col: 5 };
+        buf.insert_str(" world");
+        assert_eq!(buf.rope.to_string(), "hello world\n");
+        assert!(buf.undo());
+        assert_eq!(buf.rope.to_string(), "hello\n");
+        assert!(buf.redo());
+        assert_eq!(buf.rope.to_string(), "hello world\n");
+    }
+
+    #[test]
+    fn overwrite_char_replaces_until_line_end() {
+        let mut buf = Buffer::new(None, "abc\n");
+        buf.cursor = Position { line: 0, col: 1 };
+        buf.overwrite_char('X');
+        assert_eq!(buf.rope.to_string(), "aXc\n");
+        buf.overwrite_char('Y');
+        assert_eq!(buf.rope.to_string(), "aXY\n");
+        // At line end overwrite falls back to inserting.
+        buf.overwrite_char('Z');
+        assert_eq!(buf.rope.to_string(), "aXYZ\n");
+        assert_eq!(buf.cursor.col, 4);
+    }
+
+    #[test]
+    fn bookmarks_toggle_and_navigation_wraps() {
+        let mut buf = Buffer::new(None, "a\nb\nc\nd\n");
+        buf.cursor.line = 1;
+        assert!(buf.toggle_bookmark());
+        buf.cursor.line = 3;
+        assert!(buf.toggle_bookmark());
+        buf.cursor.line = 0;
+        assert_eq!(buf.next_bookmark(), Some(1));
+        buf.cursor.line = 3;
+        assert_eq!(buf.next_bookmark(), Some(1));
+        assert_eq!(buf.prev_bookmark(), Some(1));
+        buf.cursor.line = 0;
+        assert_eq!(buf.prev_bookmark(), Some(3));
+        buf.cursor.line = 1;
+        assert!(!buf.toggle_bookmark());
+        buf.cursor.line = 3;
+        assert!(!buf.toggle_bookmark());
+        assert_eq!(buf.next_bookmark(), None);
+    }
+
+    #[test]
+    fn multi_caret_edits_apply_at_every_caret() {
+        let mut buf = Buffer::new(None, "aaa\nbbb\nccc\n");
+        buf.add_caret_line(1);
+        buf.add_caret_line(1);
+        buf.insert_char('x');
+        assert_eq!(buf.rope.to_string(), "xaaa\nxbbb\nxccc\n");
+        assert_eq!(buf.cursor, Position { line: 0, col: 1 });
+        buf.backspace();
+        assert_eq!(buf.rope.to_string(), "aaa\nbbb\nccc\n");
+        buf.move_cursor(1, 0, false);
+        assert!(buf.extra_cursors.is_empty());
+    }
+
+    #[test]
+    fn ctrl_d_adds_caret_at_next_occurrence() {
+        let mut buf = Buffer::new(None, "foo bar foo baz foo\n");
+        assert!(buf.select_next_occurrence());
+        assert_eq!(buf.selected_text().unwrap(), "foo");
+        assert_eq!(buf.extra_cursors, vec![Position { line: 0, col: 11 }]);
+        assert!(buf.select_next_occurrence());
+        assert_eq!(buf.extra_cursors.len(), 2);
+        assert!(!buf.select_next_occurrence());
+    }
+
+    #[test]
+    fn enter_inherits_and_deepens_indentation() {
+        let mut buf = Buffer::new(None, "    foo {}\n");
+        buf.cursor = Position { line: 0, col: 9 };
+        buf.insert_newline_indented("    ");
+        assert_eq!(buf.rope.to_string(), "    foo {\n        \n    }\n");
+        assert_eq!(buf.cursor, Position { line: 1, col: 8 });
+
+        let mut buf = Buffer::new(None, "  bar:\n");
+        buf.cursor = Position { line: 0, col: 6 };
+        buf.insert_newline_indented("  ");
+        assert_eq!(buf.rope.to_string(), "  bar:\n    \n");
+    }
+
+    #[test]
+    fn autoclose_pairs_and_steps_over_closers() {
+        let mut buf = Buffer::new(None, "");
+        assert!(buf.insert_char_autoclose('('));
+        assert_eq!(buf.rope.to_string(), "()");
+        assert_eq!(buf.cursor.col, 1);
+        assert!(buf.insert_char_autoclose(')'));
+        assert_eq!(buf.rope.to_string(), "()");
+        assert_eq!(buf.cursor.col, 2);
+
+        // Apostrophe after a word character is left alone.
+        let mut buf = Buffer::new(None, "it");
+        buf.cursor = Position { line: 0, col: 2 };
+        assert!(!buf.insert_char_autoclose('\''));
+    }
+
+    #[test]
+    fn tab_honors_indent_preference_and_dedents() {
+        let mut buf = Buffer::new(None, "fn main() {\nbody\n}\n");
+        buf.cursor = Position { line: 1, col: 0 };
+        buf.insert_tab("  ");
+        assert_eq!(buf.rope.line(1).to_string(), "  body\n");
+        buf.insert_tab("\t");
+        assert_eq!(buf.rope.line(1).to_string(), "  \tbody\n");
+
+        // Multi-line selection indents every line as one undo step.
+        let mut buf = Buffer::new(None, "one\ntwo\nthree\n");
+        buf.anchor = Some(Position { line: 0, col: 0 });
+        buf.cursor = Position { line: 2, col: 0 };
+        buf.insert_tab("  ");
+        assert_eq!(buf.rope.to_string(), "  one\n  two\nthree\n");
+        assert!(buf.dedent(2));
+        assert_eq!(buf.rope.to_string(), "one\ntwo\nthree\n");
+        assert!(!buf.dedent(2));
+
+        // A leading tab is one level regardless of the configured width.
+        let mut buf = Buffer::new(None, "\tindented\n");
+        assert!(buf.dedent(4));
+        assert_eq!(buf.rope.to_string(), "indented\n");
+    }
+
+    #[test]
+    fn stats_count_words_and_selection() {
+        let mut buf = Buffer::new(None, "one two\nthree four five\n");
+        let stats = buf.stats();
+        assert_eq!((stats.lines, stats.words, stats.chars), (3, 5, 24));
+        // Cached count is reused while the version is unchanged.
+        assert_eq!(buf.stats().words, 5);
+
+        buf.insert_str("six ");
+        assert_eq!(buf.stats().words, 6);
+
+        buf.anchor = Some(Position { line: 1, col: 0 });
+        buf.cursor = Position { line: 1, col: 10 };
+        let sel = buf.selection_stats().unwrap();
+        assert_eq!((sel.words, sel.chars), (2, 10));
+    }
+
+    #[test]
+    fn decodes_and_encodes_boms() {
+        let (text, encoding) = decode_file(b"\xef\xbb\xbfhi\n").unwrap();
+        assert_eq!((text.as_str(), encoding), ("hi\n", Encoding::Utf8Bom));
+
+        let bytes = encode_contents("héllo\n", Encoding::Utf16Le);
+        assert_eq!(&bytes[..2], [0xff, 0xfe]);
+        let (text, encoding) = decode_file(&bytes).unwrap();
+        assert_eq!((text.as_str(), encoding), ("héllo\n", Encoding::Utf16Le));
+
+        let bytes = encode_contents("héllo\n", Encoding::Utf16Be);
+        let (text, encoding) = decode_file(&bytes).unwrap();
+        assert_eq!((text.as_str(), encoding), ("héllo\n", Encoding::Utf16Be));
+
+        assert!(decode_file(b"\xff\xfe\x00").is_err());
+        assert!(decode_file(b"\xff\xff plain").is_err());
+    }
+
+    #[test]
+    fn reopens_with_explicit_encodings() {
+        // 0xC4 0xE3 is "你" in GBK but invalid UTF-8.
+        assert_eq!(decode_with(b"\xc4\xe3", Encoding::Gbk).unwrap(), "你");
+        assert!(decode_with(b"\xc4\xe3", Encoding::Utf8).is_err());
+        assert_eq!(decode_with(b"caf\xe9", Encoding::Latin1).unwrap(), "café");
+        assert_eq!(encode_contents("café", Encoding::Latin1), b"caf\xe9");
+        assert_eq!(encode_contents("你", Encoding::Gbk), b"\xc4\xe3");
+        assert_eq!(Encoding::from_name("shift-jis"), Some(Encoding::ShiftJis));
+
+        let mut buf = Buffer::new(None, "old");
+        buf.insert_char('x');
+        buf.reload_with("néw\r\n", Encoding::Latin1);
+        assert_eq!(buf.rope.to_string(), "néw\n");
+        assert_eq!(buf.line_ending, LineEnding::Crlf);
+        assert!(!buf.dirty);
+        assert!(!buf.undo());
+    }
+
+    #[test]
+    fn detects_indentation_from_contents() {
+        assert_eq!(
+            detect_indent("fn f() {\n  two\n    four\n}\n"),
+            Some(IndentKind::Spaces(2))
+        );
+        assert_eq!(detect_indent("a\n\ttabbed\n"), Some(IndentKind::Tabs));
+        assert_eq!(detect_indent("flat\nlines\n"), None);
+    }
+
+    #[test]
+    fn detected_indent_stays_per_buffer() {
+        let mut editor = Editor::new();
+        editor.open_loaded(Path::new("two.py"), "def f():\n  pass\n", Encoding::Utf8);
+        editor.open_loaded(Path::new("tabs.go"), "func f() {\n\tgo()\n}\n", Encoding::Utf8);
+        assert_eq!(editor.indent(), IndentKind::Tabs);
+        // Opening the tabs file must not bleed into the 2-space buffer.
+        editor.prev_buffer();
+        assert_eq!(editor.indent(), IndentKind::Spaces(2));
+        assert_eq!(editor.prefs.indent, IndentKind::Spaces(4));
+    }
+
+    #[test]
+    fn line_commands_are_single_undo_steps() {
+        let mut buf = Buffer::new(None, "one\ntwo\nthree");
+        assert!(buf.move_line(1));
+        assert_eq!(buf.rope.to_string(), "two\none\nthree");
+        assert!(buf.undo());
+        assert_eq!(buf.rope.to_string(), "one\ntwo\nthree");
+
+        buf.duplicate_line();
+        assert_eq!(buf.rope.to_string(), "one\none\ntwo\nthree");
+        assert_eq!(buf.cursor.line, 1);
+        assert!(buf.undo());
+
+        buf.cursor = Position { line: 0, col: 0 };
+        assert!(buf.join_lines());
+        assert_eq!(buf.rope.to_string(), "one two\nthree");
+        assert!(buf.undo());
+
+        buf.delete_line();
+        assert_eq!(buf.rope.to_string(), "two\nthree");
+        assert!(buf.undo());
+        assert_eq!(buf.rope.to_string(), "one\ntwo\nthree");
+    }
+
+    #[test]
+    fn move_line_keeps_missing_trailing_newline_on_last_line() {
+        let mut buf = Buffer::new(None, "a\nb");
+        buf.cursor = Position { line: 1, col: 0 };
+        assert!(buf.move_line(-1));
+        assert_eq!(buf.rope.to_string(), "b\na");
+        assert!(!buf.move_line(-1));
+    }
+
+    #[test]
+    fn selection_range_orders_endpoints() {
+        let mut buf = Buffer::new(None, "abc\ndef\n");
+        buf.cursor = Position { line: 0, col: 1 };
+        buf.anchor = Some(Position { line: 1, col: 2 });
+        let (start, end) = buf.selection_range().unwrap();
+        assert_eq!((start, end), (1, 6));
+        assert_eq!(buf.selected_text().unwrap(), "bc\nde");
+    }
+
+    #[test]
+    fn crlf_detected_and_restored_on_serialize() {
+        let buf = Buffer::new(None, "a\r\nb\r\n");
+        assert_eq!(buf.line_ending, LineEnding::Crlf);
+        assert_eq!(buf.rope.to_string(), "a\nb\n");
+        assert_eq!(buf.contents_for_disk(), "a\r\nb\r\n");
+    }
+
+    #[test]
+    fn replace_range_moves_cursor_past_insertion() {
+        let mut buf = Buffer::new(None, "fn old_name() {}\n");
+        buf.replace_range(3, 11, "new");
+        assert_eq!(buf.rope.to_string(), "fn new() {}\n");
+        assert_eq!(buf.cursor, Position { line: 0, col: 6 });
+    }
+}
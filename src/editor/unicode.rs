@@ -0,0 +1,154 @@
+//! Character inspection and a curated, searchable Unicode table.
+//!
+//! The table is deliberately small: invisible characters that cause
+//! rendering bugs, typographic punctuation, and common symbols. The
+//! picker also accepts a raw codepoint (`U+00A0` or `00a0`), so any
+//! character can still be inserted by number.
+
+use unicode_width::UnicodeWidthChar;
+
+/// Characters the picker offers by name.
+pub const NAMED: &[(char, &str)] = &[
+    ('\u{00A0}', "no-break space"),
+    ('\u{200B}', "zero width space"),
+    ('\u{200C}', "zero width non-joiner"),
+    ('\u{200D}', "zero width joiner"),
+    ('\u{2060}', "word joiner"),
+    ('\u{FEFF}', "byte order mark"),
+    ('\u{2028}', "line separator"),
+    ('\u{00AD}', "soft hyphen"),
+    ('–', "en dash"),
+    ('—', "em dash"),
+    ('‘', "left single quotation mark"),
+    ('’', "right single quotation mark"),
+    ('“', "left double quotation mark"),
+    ('”', "right double quotation mark"),
+    ('…', "horizontal ellipsis"),
+    ('•', "bullet"),
+    ('·', "middle dot"),
+    ('¶', "pilcrow sign"),
+    ('§', "section sign"),
+    ('°', "degree sign"),
+    ('µ', "micro sign"),
+    ('±', "plus-minus sign"),
+    ('×', "multiplication sign"),
+    ('÷', "division sign"),
+    ('≈', "almost equal to"),
+    ('≠', "not equal to"),
+    ('≤', "less-than or equal to"),
+    ('≥', "greater-than or equal to"),
+    ('∞', "infinity"),
+    ('←', "leftwards arrow"),
+    ('→', "rightwards arrow"),
+    ('↑', "upwards arrow"),
+    ('↓', "downwards arrow"),
+    ('⇒', "rightwards double arrow"),
+    ('✓', "check mark"),
+    ('✗', "ballot x"),
+    ('©', "copyright sign"),
+    ('®', "registered sign"),
+    ('™', "trade mark sign"),
+    ('€', "euro sign"),
+    ('£', "pound sign"),
+    ('¥', "yen sign"),
+    ('λ', "greek small letter lambda"),
+    ('π', "greek small letter pi"),
+];
+
+/// The visible stand-in for `c` when whitespace markers are on:
+/// spaces become middle dots, tabs arrows, no-break spaces a shouldered
+/// open box, and other zero-width or control characters a white square.
+/// The bool reports whether the character was rewritten.
+pub fn reveal(c: char) -> (char, bool) {
+    match c {
+        ' ' => ('·', true),
+        '\t' => ('→', true),
+        '\u{00A0}' => ('⍽', true),
+        c if c.is_control() || c.width().unwrap_or(0) == 0 => ('□', true),
+        c => (c, false),
+    }
+}
+
+/// The curated name for `c`, if it has one.
+pub fn name(c: char) -> Option<&'static str> {
+    match c {
+        ' ' => Some("space"),
+        '\t' => Some("character tabulation"),
+        '\n' => Some("line feed"),
+        '\r' => Some("carriage return"),
+        _ => NAMED.iter().find(|(ch, _)| *ch == c).map(|(_, n)| *n),
+    }
+}
+
+/// A multi-line report on `c`: codepoint, UTF-8 bytes, display width,
+/// and name when known.
+pub fn inspect(c: char) -> String {
+    let mut utf8 = [0u8; 4];
+    let bytes = c.encode_utf8(&mut utf8).as_bytes();
+    let hex: Vec<String> = bytes.iter().map(|b| format!("{b:02X}")).collect();
+    let shown = if c.is_control() {
+        format!("U+{:04X}", c as u32)
+    } else {
+        format!("'{c}'")
+    };
+    let mut out = format!(
+        "{shown}\ncodepoint: U+{:04X}\nutf-8: {}\ndisplay width: {}",
+        c as u32,
+        hex.join(" "),
+        c.width().unwrap_or(0),
+    );
+    if let Some(name) = name(c) {
+        out.push_str(&format!("\nname: {name}"));
+    }
+    out
+}
+
+/// Matches for a picker query: a substring of a curated name, or a
+/// codepoint written as `U+XXXX` or bare hex.
+pub fn search(query: &str) -> Vec<(char, String)> {
+    let query = query.trim();
+    let hex = query
+        .strip_prefix("U+")
+        .or_else(|| query.strip_prefix("u+"))
+        .unwrap_or(query);
+    if let Some(c) = u32::from_str_radix(hex, 16).ok().and_then(char::from_u32) {
+        let label = name(c).map(str::to_string).unwrap_or_else(|| {
+            format!("U+{:04X}", c as u32)
+        });
+        return vec![(c, label)];
+    }
+    let needle = query.to_lowercase();
+    NAMED
+        .iter()
+        .filter(|(_, n)| needle.is_empty() || n.contains(&needle))
+        .map(|(c, n)| (*c, (*n).to_string()))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn inspect_reports_codepoint_bytes_and_width() {
+        let report = inspect('\u{00A0}');
+        assert!(report.contains("U+00A0"));
+        assert!(report.contains("utf-8: C2 A0"));
+        assert!(report.contains("name: no-break space"));
+    }
+
+    #[test]
+    fn reveal_rewrites_invisible_characters() {
+        assert_eq!(reveal(' '), ('·', true));
+        assert_eq!(reveal('\t'), ('→', true));
+        assert_eq!(reveal('\u{200B}'), ('□', true));
+        assert_eq!(reveal('x'), ('x', false));
+    }
+
+    #[test]
+    fn search_matches_names_and_codepoints() {
+        assert!(search("dash").iter().any(|(c, _)| *c == '—'));
+        assert_eq!(search("U+2014")[0].0, '—');
+        assert_eq!(search("2014")[0].0, '—');
+    }
+}
@@ -0,0 +1,36 @@
+//! Cross-thread events delivered back to the main loop.
+//!
+//! Background subsystems (agent backends, the LSP client, the terminal
+//! pane's reader threads) hold a clone of the [`AppEventSender`] and push
+//! events that the main loop drains once per frame.
+
+use std::sync::mpsc;
+
+use crate::agent::AgentEvent;
+use crate::lsp::LspEvent;
+use crate::task::TaskEvent;
+
+/// One event produced by a background subsystem.
+pub enum AppEvent {
+    Agent(AgentEvent),
+    Lsp(LspEvent),
+    /// A line of output from the terminal pane's child process.
+    TerminalOutput(String),
+    /// A line read from stdin when launched as `clide -`; FIFOs keep
+    /// delivering lines for as long as the writer holds them open.
+    StdinLine(String),
+    /// Model IDs fetched from the active provider for the model picker,
+    /// or the failure rendered as a message.
+    ModelList(Result<Vec<String>, String>),
+    /// Progress or completion from a [`TaskManager`] background task.
+    ///
+    /// [`TaskManager`]: crate::task::TaskManager
+    Task(TaskEvent),
+}
+
+pub type AppEventSender = mpsc::Sender<AppEvent>;
+pub type AppEventReceiver = mpsc::Receiver<AppEvent>;
+
+pub fn channel() -> (AppEventSender, AppEventReceiver) {
+    mpsc::channel()
+}
@@ -0,0 +1,138 @@
+//! Git integration backed by the `git` CLI.
+
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+use anyhow::{Context, Result};
+
+/// Status of one path reported by `git status --porcelain`.
+#[derive(Debug, Clone)]
+pub struct GitEntry {
+    pub path: PathBuf,
+    /// Two-character porcelain status code, e.g. ` M`, `??`, `A `.
+    pub status: String,
+}
+
+impl GitEntry {
+    pub fn is_staged(&self) -> bool {
+        !self.status.starts_with(' ') && !self.status.starts_with('?')
+    }
+}
+
+/// State for the git side panel.
+pub struct GitPanel {
+    pub root: PathBuf,
+    pub branch: Option<String>,
+    pub entries: Vec<GitEntry>,
+    pub selected: usize,
+    pub available: bool,
+}
+
+impl GitPanel {
+    pub fn new(root: PathBuf) -> Self {
+        let mut panel = Self {
+            root,
+            branch: None,
+            entries: Vec::new(),
+            selected: 0,
+            available: false,
+        };
+        panel.refresh();
+        panel
+    }
+
+    fn git(&self, args: &[&str]) -> Result<String> {
+        run_git(&self.root, args)
+    }
+
+    /// Read the branch and porcelain status for `root`. Free of panel
+    /// state so background refreshes can run it off-thread.
+    pub fn scan(root: &Path) -> (Option<String>, Vec<GitEntry>) {
+        let branch = run_git(root, &["rev-parse", "--abbrev-ref", "HEAD"])
+            .ok()
+            .map(|s| s.trim().to_string());
+        let mut entries = Vec::new();
+        if branch.is_some() {
+            if let Ok(status) = run_git(root, &["status", "--porcelain"]) {
+                for line in status.lines() {
+                    if line.len() < 4 {
+                        continue;
+                    }
+                    entries.push(GitEntry {
+                        status: line[..2].to_string(),
+                        path: PathBuf::from(line[3..].trim()),
+                    });
+                }
+            }
+        }
+        (branch, entries)
+    }
+
+    /// Replace the panel contents with a finished scan.
+    pub fn adopt(&mut self, branch: Option<String>, entries: Vec<GitEntry>) {
+        self.available = branch.is_some();
+        self.branch = branch;
+        self.entries = entries;
+        self.selected = self.selected.min(self.entries.len().saturating_sub(1));
+    }
+
+    pub fn refresh(&mut self) {
+        let (branch, entries) = Self::scan(&self.root);
+        self.adopt(branch, entries);
+    }
+
+    pub fn selected_entry(&self) -> Option<&GitEntry> {
+        self.entries.get(self.selected)
+    }
+
+    pub fn move_selection(&mut self, delta: isize) {
+        if self.entries.is_empty() {
+            return;
+        }
+        let len = self.entries.len() as isize;
+        self.selected = (self.selected as isize + delta).clamp(0, len - 1) as usize;
+    }
+
+    pub fn toggle_stage_selected(&mut self) -> Result<()> {
+        let Some(entry) = self.selected_entry().cloned() else {
+            return Ok(());
+        };
+        let path = entry.path.to_string_lossy().into_owned();
+        if entry.is_staged() {
+            self.git(&["restore", "--staged", &path])?;
+        } else {
+            self.git(&["add", &path])?;
+        }
+        self.refresh();
+        Ok(())
+    }
+
+    /// Unified diff of everything staged, for agent-drafted commit
+    /// messages.
+    pub fn staged_diff(&self) -> Result<String> {
+        self.git(&["diff", "--cached"])
+    }
+
+    pub fn commit(&mut self, message: &str) -> Result<()> {
+        self.git(&["commit", "-m", message])?;
+        self.refresh();
+        Ok(())
+    }
+}
+
+/// Run one git subcommand in `root`, returning stdout.
+fn run_git(root: &Path, args: &[&str]) -> Result<String> {
+    let output = Command::new("git")
+        .args(args)
+        .current_dir(root)
+        .output()
+        .context("failed to run git")?;
+    if !output.status.success() {
+        anyhow::bail!(
+            "git {} failed: {}",
+            args.first().unwrap_or(&""),
+            String::from_utf8_lossy(&output.stderr).trim()
+        );
+    }
+    Ok(String::from_utf8_lossy(&output.stdout).into_owned())
+}
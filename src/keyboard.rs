@@ -0,0 +1,1157 @@
+//! Keyboard dispatch: overlays first, then global chords, then the
+//! focused pane.
+
+use crossterm::event::{KeyCode, KeyEvent, KeyEventKind, KeyModifiers};
+
+use crate::app::{App, CommandId, PALETTE_COMMANDS};
+use crate::editor::Encoding;
+use crate::keymap::{sequence_label, KeyChord, KeyScope, Lookup};
+use crate::ui::overlay::{Overlay, PromptAction, SearchField};
+
+pub fn handle_key(app: &mut App, key: KeyEvent) {
+    if key.kind == KeyEventKind::Release {
+        return;
+    }
+    if app.overlay.is_some() {
+        handle_overlay_key(app, key);
+        return;
+    }
+    if handle_keymap(app, key) {
+        return;
+    }
+    crate::ui::pane::view_for(app.focus).handle_key(app, key);
+}
+
+/// Resolve the key through the user keymap. Returns true when the event
+/// was consumed (a command ran, or a chord sequence is in progress).
+fn handle_keymap(app: &mut App, key: KeyEvent) -> bool {
+    let chord = KeyChord::from_event(&key);
+    let pending = app.pending_chord.take();
+    match app.keymap.lookup(KeyScope::of(app.focus), pending, chord) {
+        Lookup::Command(command) => {
+            app.execute_command(command);
+            true
+        }
+        Lookup::Prefix => {
+            app.pending_chord = Some(chord);
+            app.set_status(format!("{} …", chord.label()));
+            true
+        }
+        Lookup::Unbound => {
+            if let Some(first) = pending {
+                app.set_status(format!(
+                    "{} is not bound",
+                    sequence_label(&[first, chord])
+                ));
+                true
+            } else {
+                false
+            }
+        }
+    }
+}
+
+pub(crate) fn handle_editor_key(app: &mut App, key: KeyEvent) {
+    let ctrl = key.modifiers.contains(KeyModifiers::CONTROL);
+    let shift = key.modifiers.contains(KeyModifiers::SHIFT);
+    if app.editor.active_buffer().is_none() {
+        return;
+    }
+    // The optional modal layer consumes normal/visual-mode keys first;
+    // in insert mode everything except Esc falls through.
+    if app.editor.prefs.vim_mode && crate::vim::handle_key(app, key) {
+        return;
+    }
+    if app.read_only && is_edit_key(&key) {
+        app.set_status("read-only mode");
+        return;
+    }
+    if is_edit_key(&key) {
+        if app
+            .editor
+            .active_buffer()
+            .is_some_and(|buffer| buffer.log_view)
+        {
+            app.set_status("log view is read-only (reopen and strip ANSI to edit)");
+            return;
+        }
+        if app
+            .editor
+            .active_buffer()
+            .is_some_and(|buffer| buffer.follow)
+        {
+            app.set_status("buffer is in follow mode (View: Follow File to stop)");
+            return;
+        }
+        if app.editor.active_buffer().is_some_and(|buffer| buffer.huge) {
+            app.set_status("huge file opened read-only");
+            return;
+        }
+        if app
+            .editor
+            .active_buffer()
+            .is_some_and(|buffer| buffer.read_only)
+        {
+            app.set_status("buffer is read-only (Editor: Toggle Read-Only to unlock)");
+            return;
+        }
+    }
+    if key.code == KeyCode::Insert {
+        app.editor.overwrite = !app.editor.overwrite;
+        app.set_status(if app.editor.overwrite {
+            "overwrite mode (Insert to leave)"
+        } else {
+            "insert mode"
+        });
+        return;
+    }
+    let alt = key.modifiers.contains(KeyModifiers::ALT);
+    let auto_indent = app.editor.prefs.auto_indent;
+    let auto_close = app.editor.prefs.auto_close;
+    let overwrite = app.editor.overwrite;
+    let indent_unit = app.editor.indent().unit();
+    let mut edited = false;
+    let mut copied = None;
+    let mut paste = false;
+    let mut caret_count = None;
+    let mut switched = false;
+    {
+        let buffer = app.editor.active_buffer_mut().unwrap();
+        match key.code {
+            KeyCode::Char('z') if ctrl => edited = buffer.undo(),
+            KeyCode::Char('y') if ctrl => edited = buffer.redo(),
+            KeyCode::Char('a') if ctrl => buffer.select_all(),
+            KeyCode::Char('c') if ctrl => {
+                copied = buffer
+                    .selected_text()
+                    .or_else(|| Some(buffer.current_line_text()));
+            }
+            KeyCode::Char('x') if ctrl => {
+                if let Some(text) = buffer.selected_text() {
+                    copied = Some(text);
+                    buffer.delete_selection();
+                } else {
+                    copied = Some(buffer.current_line_text());
+                    buffer.delete_line();
+                }
+                edited = true;
+            }
+            KeyCode::Char('v') if ctrl && !app.clipboard.is_empty() => paste = true,
+            KeyCode::Char('d') if ctrl && buffer.select_next_occurrence() => {
+                caret_count = Some(buffer.extra_cursors.len() + 1);
+            }
+            KeyCode::Char('w') if ctrl => {
+                app.editor.close_active();
+                switched = true;
+            }
+            KeyCode::Tab if ctrl => {
+                app.editor.next_buffer();
+                switched = true;
+            }
+            KeyCode::Char(c) if !ctrl => {
+                if overwrite {
+                    buffer.overwrite_char(c);
+                } else if !auto_close || !buffer.insert_char_autoclose(c) {
+                    buffer.insert_char(c);
+                }
+                edited = true;
+            }
+            KeyCode::Enter => {
+                if auto_indent {
+                    buffer.insert_newline_indented(&indent_unit);
+                } else {
+                    buffer.insert_newline();
+                }
+                edited = true;
+            }
+            KeyCode::Tab => {
+                buffer.insert_tab(&indent_unit);
+                edited = true;
+            }
+            KeyCode::BackTab => edited = buffer.dedent(indent_unit.chars().count()),
+            KeyCode::Backspace => {
+                buffer.backspace();
+                edited = true;
+            }
+            KeyCode::Delete => {
+                buffer.delete_forward();
+                edited = true;
+            }
+            KeyCode::Up if alt && shift => buffer.add_caret_line(-1),
+            KeyCode::Down if alt && shift => buffer.add_caret_line(1),
+            KeyCode::Up if alt => edited = buffer.move_line(-1),
+            KeyCode::Down if alt => edited = buffer.move_line(1),
+            KeyCode::Char('D') if ctrl => {
+                buffer.duplicate_line();
+                edited = true;
+            }
+            KeyCode::Char('j') if ctrl => edited = buffer.join_lines(),
+            KeyCode::Char('K') if ctrl => {
+                buffer.delete_line();
+                edited = true;
+            }
+            KeyCode::Esc => {
+                buffer.anchor = None;
+                buffer.extra_cursors.clear();
+            }
+            KeyCode::Left => buffer.move_cursor(-1, 0, shift),
+            KeyCode::Right => buffer.move_cursor(1, 0, shift),
+            KeyCode::Up => buffer.move_cursor(0, -1, shift),
+            KeyCode::Down => buffer.move_cursor(0, 1, shift),
+            KeyCode::Home => buffer.move_home(shift),
+            KeyCode::End => buffer.move_end(shift),
+            KeyCode::PageUp => buffer.move_cursor(0, -20, shift),
+            KeyCode::PageDown => buffer.move_cursor(0, 20, shift),
+            _ => {}
+        }
+    }
+    if let Some(text) = copied {
+        app.copy_to_clipboard(text);
+    }
+    if let Some(count) = caret_count {
+        app.set_status(format!("{count} carets"));
+    }
+    if paste {
+        app.request_paste();
+    }
+    if switched {
+        app.refresh_language_prefs();
+    }
+    if edited {
+        app.notify_buffer_changed();
+    }
+}
+
+/// Keys that would modify the buffer, blocked in read-only mode.
+fn is_edit_key(key: &KeyEvent) -> bool {
+    let ctrl = key.modifiers.contains(KeyModifiers::CONTROL);
+    let alt = key.modifiers.contains(KeyModifiers::ALT);
+    let shift = key.modifiers.contains(KeyModifiers::SHIFT);
+    match key.code {
+        KeyCode::Char('z' | 'y' | 'x' | 'v' | 'j' | 'D' | 'K') if ctrl => true,
+        KeyCode::Char(_) if !ctrl => true,
+        KeyCode::Enter | KeyCode::Backspace | KeyCode::Delete => true,
+        KeyCode::Tab if !ctrl => true,
+        KeyCode::BackTab => true,
+        KeyCode::Up | KeyCode::Down if alt && !shift => true,
+        _ => false,
+    }
+}
+
+pub(crate) fn handle_tree_key(app: &mut App, key: KeyEvent) {
+    match key.code {
+        KeyCode::Up | KeyCode::Char('k') => app.tree.move_selection(-1),
+        KeyCode::Down | KeyCode::Char('j') => app.tree.move_selection(1),
+        KeyCode::Enter => {
+            if let Some(path) = app.tree.activate_selected() {
+                if let Err(err) = app.open_path(&path) {
+                    app.set_error(format!("open failed: {err:#}"));
+                }
+            }
+        }
+        KeyCode::Char('n') => {
+            app.overlay = Some(Overlay::Prompt {
+                action: PromptAction::NewFile,
+                input: String::new(),
+            });
+        }
+        KeyCode::Char('m') => {
+            app.overlay = Some(Overlay::Prompt {
+                action: PromptAction::NewDirectory,
+                input: String::new(),
+            });
+        }
+        KeyCode::Char('r') => {
+            app.overlay = Some(Overlay::Prompt {
+                action: PromptAction::RenameEntry,
+                input: String::new(),
+            });
+        }
+        KeyCode::Char('d') | KeyCode::Delete => {
+            if let Some(entry) = app.tree.selected_entry() {
+                app.overlay = Some(Overlay::ConfirmDelete {
+                    path: entry.path.clone(),
+                });
+            }
+        }
+        KeyCode::Char('h') => app.tree.toggle_hidden(),
+        KeyCode::Char('s') => {
+            let label = app.tree.cycle_sort_mode();
+            app.set_status(format!("tree sorted by {label}"));
+        }
+        KeyCode::Char('R') => {
+            app.tree.refresh();
+            app.set_status("file tree refreshed");
+        }
+        _ => {}
+    }
+}
+
+pub(crate) fn handle_terminal_key(app: &mut App, key: KeyEvent) {
+    let ctrl = key.modifiers.contains(KeyModifiers::CONTROL);
+    if app.terminal.copy.is_some() {
+        handle_terminal_copy_key(app, key);
+        return;
+    }
+    match key.code {
+        KeyCode::Char('c') if ctrl => {
+            app.terminal.kill();
+            app.set_status("terminal process killed");
+        }
+        KeyCode::Char('y') if ctrl => app.terminal.enter_copy_mode(),
+        KeyCode::Char(c) => app.terminal.input.push(c),
+        KeyCode::Backspace => {
+            app.terminal.input.pop();
+        }
+        KeyCode::Enter => {
+            if app.terminal.input.is_empty() {
+                // Nothing typed: jump to the latest file:line in the output.
+                app.open_terminal_location(None);
+            } else if let Err(err) = app.terminal.run_input() {
+                let message = format!("{err:#}");
+                app.terminal.push_output(message);
+            }
+        }
+        KeyCode::Up => app.terminal.history_prev(),
+        KeyCode::Down => app.terminal.history_next(),
+        KeyCode::PageUp => app.terminal.scroll_up(10),
+        KeyCode::PageDown => {
+            let viewport = app.layout.terminal_area.height.saturating_sub(3) as usize;
+            app.terminal.scroll_down(10, viewport);
+        }
+        _ => {}
+    }
+}
+
+/// Copy-mode keys: vi-style movement, `v` to select, `y`/Enter to
+/// yank, `/` plus `n`/`N` to search, Esc or `q` to leave.
+fn handle_terminal_copy_key(app: &mut App, key: KeyEvent) {
+    let viewport = app.layout.terminal_area.height.saturating_sub(3) as usize;
+    if let Some(input) = app
+        .terminal
+        .copy
+        .as_mut()
+        .and_then(|mode| mode.search_input.as_mut())
+    {
+        match key.code {
+            KeyCode::Char(c) => input.push(c),
+            KeyCode::Backspace => {
+                input.pop();
+            }
+            KeyCode::Esc => {
+                if let Some(mode) = app.terminal.copy.as_mut() {
+                    mode.search_input = None;
+                }
+            }
+            KeyCode::Enter => {
+                if let Some(mode) = app.terminal.copy.as_mut() {
+                    mode.query = mode.search_input.take().unwrap_or_default();
+                }
+                if !app.terminal.copy_search_step(true, viewport) {
+                    app.set_status("no match in scrollback");
+                }
+            }
+            _ => {}
+        }
+        return;
+    }
+    match key.code {
+        KeyCode::Esc | KeyCode::Char('q') => app.terminal.exit_copy_mode(),
+        KeyCode::Up | KeyCode::Char('k') => app.terminal.copy_cursor_move(-1, viewport),
+        KeyCode::Down | KeyCode::Char('j') => app.terminal.copy_cursor_move(1, viewport),
+        KeyCode::PageUp => app.terminal.copy_cursor_move(-(viewport as isize), viewport),
+        KeyCode::PageDown => app.terminal.copy_cursor_move(viewport as isize, viewport),
+        KeyCode::Char('g') => app.terminal.copy_cursor_move(isize::MIN, viewport),
+        KeyCode::Char('G') => app.terminal.copy_cursor_move(isize::MAX, viewport),
+        KeyCode::Char('v') => {
+            if let Some(mode) = app.terminal.copy.as_mut() {
+                mode.anchor = match mode.anchor {
+                    Some(_) => None,
+                    None => Some(mode.cursor),
+                };
+            }
+        }
+        KeyCode::Char('y') | KeyCode::Enter => {
+            if let Some(text) = app.terminal.yank() {
+                app.copy_to_clipboard(text);
+                app.terminal.exit_copy_mode();
+            }
+        }
+        KeyCode::Char('/') => {
+            if let Some(mode) = app.terminal.copy.as_mut() {
+                mode.search_input = Some(String::new());
+            }
+        }
+        KeyCode::Char(c @ ('n' | 'N')) if !app.terminal.copy_search_step(c == 'n', viewport) => {
+            app.set_status("no match in scrollback");
+        }
+        _ => {}
+    }
+}
+
+pub(crate) fn handle_agent_key(app: &mut App, key: KeyEvent) {
+    let ctrl = key.modifiers.contains(KeyModifiers::CONTROL);
+    match key.code {
+        KeyCode::Enter if ctrl => app.send_agent_prompt(),
+        KeyCode::Char('e') if ctrl => app.open_entry_actions(),
+        KeyCode::Char(c) if !ctrl => app.composer.push(c),
+        KeyCode::Enter => app.composer.push('\n'),
+        KeyCode::Backspace => {
+            app.composer.pop();
+        }
+        KeyCode::PageUp => {
+            app.conversation.scroll = app.conversation.scroll.saturating_sub(5);
+        }
+        KeyCode::PageDown => {
+            app.conversation.scroll += 5;
+        }
+        _ => {}
+    }
+}
+
+pub(crate) fn handle_git_key(app: &mut App, key: KeyEvent) {
+    match key.code {
+        KeyCode::Up | KeyCode::Char('k') => app.git.move_selection(-1),
+        KeyCode::Down | KeyCode::Char('j') => app.git.move_selection(1),
+        KeyCode::Enter => {
+            if let Some(entry) = app.git.selected_entry() {
+                let path = app.root.join(&entry.path);
+                if path.is_file() {
+                    if let Err(err) = app.open_path(&path) {
+                        app.set_error(format!("open failed: {err:#}"));
+                    }
+                }
+            }
+        }
+        _ => {}
+    }
+}
+
+/// Case-insensitive subsequence filter used by the command palette.
+pub fn palette_matches(input: &str) -> Vec<(&'static str, CommandId)> {
+    let needle = input.to_lowercase();
+    PALETTE_COMMANDS
+        .iter()
+        .filter(|(label, _)| {
+            let label = label.to_lowercase();
+            let mut chars = label.chars();
+            needle.chars().all(|c| chars.any(|l| l == c))
+        })
+        .copied()
+        .collect()
+}
+
+fn handle_overlay_key(app: &mut App, key: KeyEvent) {
+    let Some(overlay) = app.overlay.take() else { return };
+    match overlay {
+        Overlay::CommandPalette { mut input, mut selected } => match key.code {
+            KeyCode::Esc => {}
+            KeyCode::Enter => {
+                let matches = palette_matches(&input);
+                if let Some((_, id)) = matches.get(selected) {
+                    app.execute_command(*id);
+                }
+            }
+            KeyCode::Up => {
+                selected = selected.saturating_sub(1);
+                app.overlay = Some(Overlay::CommandPalette { input, selected });
+            }
+            KeyCode::Down => {
+                let len = palette_matches(&input).len();
+                if selected + 1 < len {
+                    selected += 1;
+                }
+                app.overlay = Some(Overlay::CommandPalette { input, selected });
+            }
+            KeyCode::Backspace => {
+                input.pop();
+                app.overlay = Some(Overlay::CommandPalette { input, selected: 0 });
+            }
+            KeyCode::Char(c) => {
+                input.push(c);
+                app.overlay = Some(Overlay::CommandPalette { input, selected: 0 });
+            }
+            _ => app.overlay = Some(Overlay::CommandPalette { input, selected }),
+        },
+        Overlay::Prompt { action, mut input } => match key.code {
+            KeyCode::Esc => {}
+            KeyCode::Enter => app.complete_prompt(action, &input),
+            // Redraft a generated commit message without retyping.
+            KeyCode::Char('g')
+                if key.modifiers.contains(KeyModifiers::CONTROL)
+                    && action == PromptAction::CommitMessage =>
+            {
+                app.generate_commit_message();
+            }
+            KeyCode::Backspace => {
+                input.pop();
+                app.overlay = Some(Overlay::Prompt { action, input });
+            }
+            KeyCode::Char(c) => {
+                input.push(c);
+                app.overlay = Some(Overlay::Prompt { action, input });
+            }
+            _ => app.overlay = Some(Overlay::Prompt { action, input }),
+        },
+        Overlay::AnsiDetected => match key.code {
+            KeyCode::Char('s') | KeyCode::Char('S') => {
+                if let Some(buffer) = app.editor.active_buffer_mut() {
+                    buffer.strip_ansi_content();
+                }
+                app.set_status("ANSI escape codes stripped");
+            }
+            KeyCode::Char('v') | KeyCode::Char('V') => {
+                if let Some(buffer) = app.editor.active_buffer_mut() {
+                    buffer.log_view = true;
+                }
+                app.set_status("log view: colorized, read-only");
+            }
+            KeyCode::Esc | KeyCode::Enter => {}
+            _ => app.overlay = Some(Overlay::AnsiDetected),
+        },
+        Overlay::EncodingPicker { mut selected } => match key.code {
+            KeyCode::Up => {
+                selected = selected.saturating_sub(1);
+                app.overlay = Some(Overlay::EncodingPicker { selected });
+            }
+            KeyCode::Down => {
+                selected = (selected + 1).min(Encoding::ALL.len() - 1);
+                app.overlay = Some(Overlay::EncodingPicker { selected });
+            }
+            KeyCode::Enter => app.reopen_with_encoding(Encoding::ALL[selected]),
+            KeyCode::Esc => {}
+            _ => app.overlay = Some(Overlay::EncodingPicker { selected }),
+        },
+        Overlay::DefinitionPicker { items, mut selected } => match key.code {
+            KeyCode::Up => {
+                selected = selected.saturating_sub(1);
+                app.overlay = Some(Overlay::DefinitionPicker { items, selected });
+            }
+            KeyCode::Down => {
+                selected = (selected + 1).min(items.len().saturating_sub(1));
+                app.overlay = Some(Overlay::DefinitionPicker { items, selected });
+            }
+            KeyCode::Enter => {
+                if let Some((path, pos, _)) = items.get(selected) {
+                    let (path, pos) = (path.clone(), *pos);
+                    app.push_jump();
+                    app.goto_location(&path, pos);
+                }
+            }
+            KeyCode::Esc => {}
+            _ => app.overlay = Some(Overlay::DefinitionPicker { items, selected }),
+        },
+        Overlay::RecoveryFound { files } => match key.code {
+            KeyCode::Char('r') | KeyCode::Char('R') | KeyCode::Enter => {
+                app.restore_recovery(&files);
+            }
+            KeyCode::Char('d') | KeyCode::Char('D') => {
+                crate::recovery::discard_all(&app.root);
+                app.set_status("recovery snapshots discarded");
+            }
+            KeyCode::Esc => app.set_status("recovery snapshots kept"),
+            _ => app.overlay = Some(Overlay::RecoveryFound { files }),
+        },
+        Overlay::ConfirmPaste {
+            text,
+            preview,
+            lines,
+            bytes,
+            target,
+        } => match key.code {
+            KeyCode::Char('y') | KeyCode::Char('Y') | KeyCode::Enter => {
+                app.insert_paste(&text);
+            }
+            KeyCode::Esc | KeyCode::Char('n') | KeyCode::Char('N') => {
+                app.set_status("paste cancelled");
+            }
+            _ => {
+                app.overlay = Some(Overlay::ConfirmPaste {
+                    text,
+                    preview,
+                    lines,
+                    bytes,
+                    target,
+                });
+            }
+        },
+        Overlay::ConfirmDelete { path } => match key.code {
+            KeyCode::Char('y') | KeyCode::Char('Y') | KeyCode::Enter => {
+                match app.tree.trash_selected() {
+                    Ok(path) => {
+                        app.last_trashed = Some(path.clone());
+                        app.set_status(format!("moved {} to trash", path.display()));
+                    }
+                    Err(err) => app.set_error(format!("trash failed: {err:#}")),
+                }
+            }
+            KeyCode::Char('p') | KeyCode::Char('P') => match app.tree.delete_selected() {
+                Ok(path) => app.set_status(format!("permanently deleted {}", path.display())),
+                Err(err) => app.set_error(format!("delete failed: {err:#}")),
+            },
+            KeyCode::Esc | KeyCode::Char('n') | KeyCode::Char('N') => {}
+            _ => app.overlay = Some(Overlay::ConfirmDelete { path }),
+        },
+        Overlay::ToolWriteConflict { path, diff } => match key.code {
+            KeyCode::Char('a') | KeyCode::Char('A') | KeyCode::Enter => {
+                app.resolve_tool_conflict(&path, true);
+            }
+            KeyCode::Char('b') | KeyCode::Char('B') => {
+                app.resolve_tool_conflict(&path, false);
+            }
+            KeyCode::Esc => {
+                app.set_status("patch left staged (agent.review-conflict reopens it)");
+            }
+            _ => app.overlay = Some(Overlay::ToolWriteConflict { path, diff }),
+        },
+        Overlay::ToolApproval {
+            transcript,
+            calls,
+            round,
+        } => match key.code {
+            KeyCode::Char('y') | KeyCode::Char('Y') | KeyCode::Enter => {
+                app.finish_tool_calls(transcript, calls, round, true);
+            }
+            KeyCode::Esc | KeyCode::Char('n') | KeyCode::Char('N') => {
+                app.finish_tool_calls(transcript, calls, round, false);
+            }
+            _ => {
+                app.overlay = Some(Overlay::ToolApproval {
+                    transcript,
+                    calls,
+                    round,
+                })
+            }
+        },
+        Overlay::SearchReplace(mut state) => match key.code {
+            KeyCode::Esc => {}
+            KeyCode::Tab => {
+                state.field = match state.field {
+                    SearchField::Query => SearchField::Replacement,
+                    SearchField::Replacement => SearchField::Query,
+                };
+                app.overlay = Some(Overlay::SearchReplace(state));
+            }
+            KeyCode::Enter => {
+                if state.searched && !state.files.is_empty() {
+                    app.apply_replace(&state);
+                } else {
+                    app.run_search(&mut state);
+                    app.overlay = Some(Overlay::SearchReplace(state));
+                }
+            }
+            KeyCode::Up => {
+                state.selected = state.selected.saturating_sub(1);
+                app.overlay = Some(Overlay::SearchReplace(state));
+            }
+            KeyCode::Down => {
+                if state.selected + 1 < state.files.len() {
+                    state.selected += 1;
+                }
+                app.overlay = Some(Overlay::SearchReplace(state));
+            }
+            KeyCode::Char(' ') if state.searched => {
+                if let Some(file) = state.files.get_mut(state.selected) {
+                    file.included = !file.included;
+                }
+                app.overlay = Some(Overlay::SearchReplace(state));
+            }
+            KeyCode::Backspace => {
+                match state.field {
+                    SearchField::Query => state.query.pop(),
+                    SearchField::Replacement => state.replacement.pop(),
+                };
+                state.searched = false;
+                app.overlay = Some(Overlay::SearchReplace(state));
+            }
+            KeyCode::Char(c) => {
+                match state.field {
+                    SearchField::Query => state.query.push(c),
+                    SearchField::Replacement => state.replacement.push(c),
+                }
+                state.searched = false;
+                app.overlay = Some(Overlay::SearchReplace(state));
+            }
+            _ => app.overlay = Some(Overlay::SearchReplace(state)),
+        },
+        Overlay::WorkspaceEditPreview(mut state) => match key.code {
+            KeyCode::Esc => app.set_status("workspace edit discarded"),
+            KeyCode::Enter => app.apply_workspace_edit(state),
+            KeyCode::Up => {
+                state.selected = state.selected.saturating_sub(1);
+                app.overlay = Some(Overlay::WorkspaceEditPreview(state));
+            }
+            KeyCode::Down => {
+                if state.selected + 1 < state.files.len() {
+                    state.selected += 1;
+                }
+                app.overlay = Some(Overlay::WorkspaceEditPreview(state));
+            }
+            KeyCode::Char(' ') => {
+                if let Some(file) = state.files.get_mut(state.selected) {
+                    file.included = !file.included;
+                }
+                app.overlay = Some(Overlay::WorkspaceEditPreview(state));
+            }
+            _ => app.overlay = Some(Overlay::WorkspaceEditPreview(state)),
+        },
+        Overlay::Hover { text } => match key.code {
+            KeyCode::Esc | KeyCode::Enter | KeyCode::Char('q') => {}
+            _ => app.overlay = Some(Overlay::Hover { text }),
+        },
+        Overlay::CharInspector { text } => match key.code {
+            KeyCode::Esc | KeyCode::Enter | KeyCode::Char('q') => {}
+            _ => app.overlay = Some(Overlay::CharInspector { text }),
+        },
+        Overlay::UnicodePicker {
+            mut input,
+            mut selected,
+        } => match key.code {
+            KeyCode::Esc => {}
+            KeyCode::Enter => {
+                let matches = crate::editor::unicode::search(&input);
+                if let Some((c, name)) = matches.get(selected) {
+                    if app.read_only {
+                        app.set_status("read-only mode");
+                    } else {
+                        match app.editor.active_buffer() {
+                            None => {}
+                            Some(b) if b.is_read_only() => {
+                                app.set_status("buffer is read-only");
+                            }
+                            Some(_) => {
+                                app.insert_paste(&c.to_string());
+                                app.set_status(format!("inserted {name}"));
+                            }
+                        }
+                    }
+                }
+            }
+            KeyCode::Up => {
+                selected = selected.saturating_sub(1);
+                app.overlay = Some(Overlay::UnicodePicker { input, selected });
+            }
+            KeyCode::Down => {
+                if selected + 1 < crate::editor::unicode::search(&input).len() {
+                    selected += 1;
+                }
+                app.overlay = Some(Overlay::UnicodePicker { input, selected });
+            }
+            KeyCode::Backspace => {
+                input.pop();
+                selected = 0;
+                app.overlay = Some(Overlay::UnicodePicker { input, selected });
+            }
+            KeyCode::Char(c) => {
+                input.push(c);
+                selected = 0;
+                app.overlay = Some(Overlay::UnicodePicker { input, selected });
+            }
+            _ => app.overlay = Some(Overlay::UnicodePicker { input, selected }),
+        },
+        Overlay::AgentStats => match key.code {
+            KeyCode::Esc | KeyCode::Enter | KeyCode::Char('q') => {}
+            _ => app.overlay = Some(Overlay::AgentStats),
+        },
+        Overlay::ThemePicker {
+            names,
+            mut selected,
+            previous,
+            previous_name,
+        } => match key.code {
+            KeyCode::Esc => {
+                crate::ui::theme::set_current(previous);
+                app.theme_name = previous_name;
+            }
+            KeyCode::Enter => {
+                let name = app.theme_name.clone();
+                app.set_status(format!("theme: {name}"));
+            }
+            KeyCode::Up | KeyCode::Down => {
+                if key.code == KeyCode::Up {
+                    selected = selected.saturating_sub(1);
+                } else if selected + 1 < names.len() {
+                    selected += 1;
+                }
+                if let Some(name) = names.get(selected) {
+                    let name = name.clone();
+                    app.set_theme(&name);
+                }
+                app.overlay = Some(Overlay::ThemePicker {
+                    names,
+                    selected,
+                    previous,
+                    previous_name,
+                });
+            }
+            _ => {
+                app.overlay = Some(Overlay::ThemePicker {
+                    names,
+                    selected,
+                    previous,
+                    previous_name,
+                })
+            }
+        },
+        Overlay::KeyboardHelp {
+            rows,
+            mut scroll,
+            mut filter,
+        } => match key.code {
+            KeyCode::Esc | KeyCode::Enter | KeyCode::F(1) => {}
+            KeyCode::Up => {
+                scroll = scroll.saturating_sub(1);
+                app.overlay = Some(Overlay::KeyboardHelp {
+                    rows,
+                    scroll,
+                    filter,
+                });
+            }
+            KeyCode::Down => {
+                if scroll + 1 < rows.len() {
+                    scroll += 1;
+                }
+                app.overlay = Some(Overlay::KeyboardHelp {
+                    rows,
+                    scroll,
+                    filter,
+                });
+            }
+            KeyCode::Char(c) => {
+                filter.push(c);
+                scroll = 0;
+                app.overlay = Some(Overlay::KeyboardHelp {
+                    rows,
+                    scroll,
+                    filter,
+                });
+            }
+            KeyCode::Backspace => {
+                filter.pop();
+                scroll = 0;
+                app.overlay = Some(Overlay::KeyboardHelp {
+                    rows,
+                    scroll,
+                    filter,
+                });
+            }
+            _ => {
+                app.overlay = Some(Overlay::KeyboardHelp {
+                    rows,
+                    scroll,
+                    filter,
+                })
+            }
+        },
+        Overlay::LayoutPicker {
+            names,
+            mut selected,
+        } => match key.code {
+            KeyCode::Esc => {}
+            KeyCode::Enter => {
+                if let Some(name) = names.get(selected) {
+                    let name = name.clone();
+                    app.apply_layout_preset(&name);
+                }
+            }
+            KeyCode::Up => {
+                selected = selected.saturating_sub(1);
+                app.overlay = Some(Overlay::LayoutPicker { names, selected });
+            }
+            KeyCode::Down => {
+                if selected + 1 < names.len() {
+                    selected += 1;
+                }
+                app.overlay = Some(Overlay::LayoutPicker { names, selected });
+            }
+            _ => app.overlay = Some(Overlay::LayoutPicker { names, selected }),
+        },
+        Overlay::ConversationHistory {
+            items,
+            mut selected,
+        } => match key.code {
+            KeyCode::Esc => {}
+            KeyCode::Enter => {
+                if let Some(item) = items.get(selected) {
+                    let id = item.id.clone();
+                    app.open_conversation(&id);
+                }
+            }
+            KeyCode::Char('r') => {
+                if let Some(item) = items.get(selected) {
+                    app.prompt_rename_conversation(item.id.clone());
+                }
+            }
+            KeyCode::Char('d') | KeyCode::Delete => {
+                if let Some(item) = items.get(selected) {
+                    let id = item.id.clone();
+                    app.delete_conversation(&id);
+                    let mut items = items;
+                    items.remove(selected);
+                    if !items.is_empty() {
+                        selected = selected.min(items.len() - 1);
+                        app.overlay = Some(Overlay::ConversationHistory { items, selected });
+                    }
+                }
+            }
+            KeyCode::Up => {
+                selected = selected.saturating_sub(1);
+                app.overlay = Some(Overlay::ConversationHistory { items, selected });
+            }
+            KeyCode::Down => {
+                if selected + 1 < items.len() {
+                    selected += 1;
+                }
+                app.overlay = Some(Overlay::ConversationHistory { items, selected });
+            }
+            _ => app.overlay = Some(Overlay::ConversationHistory { items, selected }),
+        },
+        Overlay::McpBrowser { rows, mut selected } => match key.code {
+            KeyCode::Esc => {}
+            KeyCode::Enter => {
+                if let Some(row) = rows.get(selected) {
+                    let row = row.clone();
+                    app.activate_mcp_row(&row);
+                }
+            }
+            KeyCode::Up => {
+                selected = selected.saturating_sub(1);
+                app.overlay = Some(Overlay::McpBrowser { rows, selected });
+            }
+            KeyCode::Down => {
+                if selected + 1 < rows.len() {
+                    selected += 1;
+                }
+                app.overlay = Some(Overlay::McpBrowser { rows, selected });
+            }
+            _ => app.overlay = Some(Overlay::McpBrowser { rows, selected }),
+        },
+        Overlay::CommandApproval { commands } => match key.code {
+            KeyCode::Char('y') | KeyCode::Char('Y') | KeyCode::Enter => {
+                app.run_agent_commands(commands);
+            }
+            KeyCode::Char('n') | KeyCode::Char('N') | KeyCode::Esc => {
+                app.set_status("command not run");
+            }
+            _ => app.overlay = Some(Overlay::CommandApproval { commands }),
+        },
+        Overlay::ModelPicker { names, mut selected } => match key.code {
+            KeyCode::Esc => {}
+            KeyCode::Enter => {
+                if let Some(name) = names.get(selected) {
+                    let name = name.clone();
+                    app.select_model(&name);
+                }
+            }
+            KeyCode::Up => {
+                selected = selected.saturating_sub(1);
+                app.overlay = Some(Overlay::ModelPicker { names, selected });
+            }
+            KeyCode::Down => {
+                if selected + 1 < names.len() {
+                    selected += 1;
+                }
+                app.overlay = Some(Overlay::ModelPicker { names, selected });
+            }
+            _ => app.overlay = Some(Overlay::ModelPicker { names, selected }),
+        },
+        Overlay::TaskPicker {
+            tasks,
+            mut selected,
+        } => match key.code {
+            KeyCode::Esc => {}
+            KeyCode::Enter => {
+                if let Some(task) = tasks.get(selected) {
+                    let task = task.clone();
+                    app.run_task(&task);
+                }
+            }
+            KeyCode::Up => {
+                selected = selected.saturating_sub(1);
+                app.overlay = Some(Overlay::TaskPicker { tasks, selected });
+            }
+            KeyCode::Down => {
+                if selected + 1 < tasks.len() {
+                    selected += 1;
+                }
+                app.overlay = Some(Overlay::TaskPicker { tasks, selected });
+            }
+            _ => app.overlay = Some(Overlay::TaskPicker { tasks, selected }),
+        },
+        Overlay::MultiFileReview {
+            mut files,
+            mut selected,
+        } => match key.code {
+            KeyCode::Esc => {
+                for file in &mut files {
+                    file.accepted = false;
+                }
+                app.finish_multi_file_review(files);
+            }
+            KeyCode::Enter => app.finish_multi_file_review(files),
+            KeyCode::Up => {
+                selected = selected.saturating_sub(1);
+                app.overlay = Some(Overlay::MultiFileReview { files, selected });
+            }
+            KeyCode::Down => {
+                if selected + 1 < files.len() {
+                    selected += 1;
+                }
+                app.overlay = Some(Overlay::MultiFileReview { files, selected });
+            }
+            KeyCode::Char(' ') => {
+                if let Some(file) = files.get_mut(selected) {
+                    file.accepted = !file.accepted;
+                }
+                app.overlay = Some(Overlay::MultiFileReview { files, selected });
+            }
+            KeyCode::Char('a') => {
+                for file in &mut files {
+                    file.accepted = true;
+                }
+                app.overlay = Some(Overlay::MultiFileReview { files, selected });
+            }
+            KeyCode::Char('r') => {
+                for file in &mut files {
+                    file.accepted = false;
+                }
+                app.overlay = Some(Overlay::MultiFileReview { files, selected });
+            }
+            _ => app.overlay = Some(Overlay::MultiFileReview { files, selected }),
+        },
+        Overlay::EntryActions { mut selected } => match key.code {
+            KeyCode::Esc => {}
+            KeyCode::Up => {
+                selected = selected.saturating_sub(1);
+                app.overlay = Some(Overlay::EntryActions { selected });
+            }
+            KeyCode::Down => {
+                if selected + 1 < app.conversation.entries.len() {
+                    selected += 1;
+                }
+                app.overlay = Some(Overlay::EntryActions { selected });
+            }
+            KeyCode::Char('c') => app.copy_entry(selected),
+            KeyCode::Char('i') => app.insert_entry_code(selected),
+            KeyCode::Char('e') | KeyCode::Enter => app.edit_resend_entry(selected),
+            KeyCode::Char('d') => {
+                app.delete_entry(selected);
+                if !app.conversation.entries.is_empty() {
+                    app.overlay = Some(Overlay::EntryActions {
+                        selected: selected.min(app.conversation.entries.len() - 1),
+                    });
+                }
+            }
+            _ => app.overlay = Some(Overlay::EntryActions { selected }),
+        },
+        Overlay::TemplatePicker { names, mut selected } => match key.code {
+            KeyCode::Esc => {}
+            KeyCode::Enter => {
+                if let Some(name) = names.get(selected) {
+                    let name = name.clone();
+                    app.insert_template(&name);
+                }
+            }
+            KeyCode::Up => {
+                selected = selected.saturating_sub(1);
+                app.overlay = Some(Overlay::TemplatePicker { names, selected });
+            }
+            KeyCode::Down => {
+                if selected + 1 < names.len() {
+                    selected += 1;
+                }
+                app.overlay = Some(Overlay::TemplatePicker { names, selected });
+            }
+            _ => app.overlay = Some(Overlay::TemplatePicker { names, selected }),
+        },
+        Overlay::TranscriptLog {
+            records,
+            mut selected,
+        } => match key.code {
+            KeyCode::Esc | KeyCode::Enter => {}
+            KeyCode::Up => {
+                selected = selected.saturating_sub(1);
+                app.overlay = Some(Overlay::TranscriptLog { records, selected });
+            }
+            KeyCode::Down => {
+                if selected + 1 < records.len() {
+                    selected += 1;
+                }
+                app.overlay = Some(Overlay::TranscriptLog { records, selected });
+            }
+            _ => app.overlay = Some(Overlay::TranscriptLog { records, selected }),
+        },
+        Overlay::AgentHistorySearch {
+            mut input,
+            mut hits,
+            mut selected,
+        } => match key.code {
+            KeyCode::Esc => {}
+            KeyCode::Enter => {
+                if let Some(hit) = hits.get(selected) {
+                    let hit = hit.clone();
+                    app.open_history_hit(&hit);
+                }
+            }
+            KeyCode::Up => {
+                selected = selected.saturating_sub(1);
+                app.overlay = Some(Overlay::AgentHistorySearch {
+                    input,
+                    hits,
+                    selected,
+                });
+            }
+            KeyCode::Down => {
+                if selected + 1 < hits.len() {
+                    selected += 1;
+                }
+                app.overlay = Some(Overlay::AgentHistorySearch {
+                    input,
+                    hits,
+                    selected,
+                });
+            }
+            KeyCode::Char(c) => {
+                input.push(c);
+                hits = crate::agent::history::search(&app.root, &input, 100);
+                selected = 0;
+                app.overlay = Some(Overlay::AgentHistorySearch {
+                    input,
+                    hits,
+                    selected,
+                });
+            }
+            KeyCode::Backspace => {
+                input.pop();
+                hits = crate::agent::history::search(&app.root, &input, 100);
+                selected = 0;
+                app.overlay = Some(Overlay::AgentHistorySearch {
+                    input,
+                    hits,
+                    selected,
+                });
+            }
+            _ => {
+                app.overlay = Some(Overlay::AgentHistorySearch {
+                    input,
+                    hits,
+                    selected,
+                })
+            }
+        },
+        Overlay::Notifications { mut scroll } => match key.code {
+            KeyCode::Esc | KeyCode::Enter | KeyCode::Char('q') => {}
+            KeyCode::Up => {
+                scroll = scroll.saturating_sub(1);
+                app.overlay = Some(Overlay::Notifications { scroll });
+            }
+            KeyCode::Down => {
+                if scroll + 1 < app.notify.history().len() {
+                    scroll += 1;
+                }
+                app.overlay = Some(Overlay::Notifications { scroll });
+            }
+            _ => app.overlay = Some(Overlay::Notifications { scroll }),
+        },
+    }
+}
@@ -0,0 +1,374 @@
+//! User-remappable keybindings.
+//!
+//! A [`Keymap`] resolves key chords to [`CommandId`]s, scoped either
+//! globally or to the focused pane. The built-in defaults mirror the
+//! traditional bindings (Ctrl+S, Ctrl+Q, Alt+1..5, ...) and `[keys.*]`
+//! tables in config.toml override or extend them, including two-chord
+//! sequences like `ctrl+k ctrl+s`.
+
+use std::collections::HashMap;
+
+use anyhow::{bail, Context, Result};
+use crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
+
+use crate::app::CommandId;
+use crate::layout::Focus;
+
+/// A single key press with modifiers, e.g. `ctrl+shift+p`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct KeyChord {
+    pub code: KeyCode,
+    pub mods: KeyModifiers,
+}
+
+impl KeyChord {
+    /// Normalize an incoming event: shift is already folded into the
+    /// character for `Char` keys, so it is dropped there to keep lookups
+    /// predictable.
+    pub fn from_event(key: &KeyEvent) -> Self {
+        let mut mods = key.modifiers & (KeyModifiers::CONTROL | KeyModifiers::ALT | KeyModifiers::SHIFT);
+        if matches!(key.code, KeyCode::Char(_)) {
+            mods -= KeyModifiers::SHIFT;
+        }
+        Self {
+            code: key.code,
+            mods,
+        }
+    }
+
+    /// Parse a chord like `ctrl+s`, `alt+1`, `f12`, or `ctrl+shift+end`.
+    pub fn parse(text: &str) -> Result<Self> {
+        let mut mods = KeyModifiers::empty();
+        let mut code = None;
+        for part in text.split('+') {
+            let part = part.trim().to_ascii_lowercase();
+            match part.as_str() {
+                "ctrl" | "control" => mods |= KeyModifiers::CONTROL,
+                "alt" => mods |= KeyModifiers::ALT,
+                "shift" => mods |= KeyModifiers::SHIFT,
+                "" => bail!("empty key in {text:?}"),
+                key => {
+                    let parsed = match key {
+                        "enter" => KeyCode::Enter,
+                        "tab" => KeyCode::Tab,
+                        "space" => KeyCode::Char(' '),
+                        "esc" | "escape" => KeyCode::Esc,
+                        "backspace" => KeyCode::Backspace,
+                        "delete" | "del" => KeyCode::Delete,
+                        "insert" => KeyCode::Insert,
+                        "home" => KeyCode::Home,
+                        "end" => KeyCode::End,
+                        "pageup" => KeyCode::PageUp,
+                        "pagedown" => KeyCode::PageDown,
+                        "up" => KeyCode::Up,
+                        "down" => KeyCode::Down,
+                        "left" => KeyCode::Left,
+                        "right" => KeyCode::Right,
+                        key if key.len() == 1 => KeyCode::Char(key.chars().next().unwrap()),
+                        key if key.starts_with('f') => {
+                            let n: u8 = key[1..]
+                                .parse()
+                                .with_context(|| format!("bad function key {key:?}"))?;
+                            KeyCode::F(n)
+                        }
+                        other => bail!("unknown key {other:?}"),
+                    };
+                    if code.replace(parsed).is_some() {
+                        bail!("more than one key in chord {text:?}");
+                    }
+                }
+            }
+        }
+        let code = code.with_context(|| format!("no key in chord {text:?}"))?;
+        // Char chords never carry shift; the shifted character is spelled
+        // out instead (e.g. `ctrl+?`).
+        let mods = if matches!(code, KeyCode::Char(_)) {
+            mods - KeyModifiers::SHIFT
+        } else {
+            mods
+        };
+        Ok(Self { code, mods })
+    }
+
+    pub fn label(&self) -> String {
+        let mut out = String::new();
+        if self.mods.contains(KeyModifiers::CONTROL) {
+            out.push_str("Ctrl+");
+        }
+        if self.mods.contains(KeyModifiers::ALT) {
+            out.push_str("Alt+");
+        }
+        if self.mods.contains(KeyModifiers::SHIFT) {
+            out.push_str("Shift+");
+        }
+        match self.code {
+            KeyCode::Char(' ') => out.push_str("Space"),
+            KeyCode::Char(c) => out.push(c.to_ascii_uppercase()),
+            KeyCode::F(n) => out.push_str(&format!("F{n}")),
+            KeyCode::Enter => out.push_str("Enter"),
+            KeyCode::Tab => out.push_str("Tab"),
+            KeyCode::Esc => out.push_str("Esc"),
+            KeyCode::Backspace => out.push_str("Backspace"),
+            KeyCode::Delete => out.push_str("Delete"),
+            KeyCode::Insert => out.push_str("Insert"),
+            KeyCode::Home => out.push_str("Home"),
+            KeyCode::End => out.push_str("End"),
+            KeyCode::PageUp => out.push_str("PageUp"),
+            KeyCode::PageDown => out.push_str("PageDown"),
+            KeyCode::Up => out.push_str("Up"),
+            KeyCode::Down => out.push_str("Down"),
+            KeyCode::Left => out.push_str("Left"),
+            KeyCode::Right => out.push_str("Right"),
+            other => out.push_str(&format!("{other:?}")),
+        }
+        out
+    }
+}
+
+/// A binding is one chord or a two-chord sequence.
+pub type ChordSeq = Vec<KeyChord>;
+
+/// Parse `ctrl+k ctrl+s` style sequences (whitespace separated, max 2).
+pub fn parse_sequence(text: &str) -> Result<ChordSeq> {
+    let chords: Vec<KeyChord> = text
+        .split_whitespace()
+        .map(KeyChord::parse)
+        .collect::<Result<_>>()?;
+    if chords.is_empty() || chords.len() > 2 {
+        bail!("bindings must be one or two chords: {text:?}");
+    }
+    Ok(chords)
+}
+
+pub fn sequence_label(seq: &[KeyChord]) -> String {
+    seq.iter()
+        .map(KeyChord::label)
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+/// Where a binding applies: everywhere, or only while a pane is focused.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum KeyScope {
+    Global,
+    Tree,
+    Editor,
+    Terminal,
+    Agent,
+    Git,
+}
+
+impl KeyScope {
+    pub fn of(focus: Focus) -> Self {
+        match focus {
+            Focus::Tree => KeyScope::Tree,
+            Focus::Editor => KeyScope::Editor,
+            Focus::Terminal => KeyScope::Terminal,
+            Focus::Agent => KeyScope::Agent,
+            Focus::Git => KeyScope::Git,
+        }
+    }
+
+    pub fn label(self) -> &'static str {
+        match self {
+            KeyScope::Global => "global",
+            KeyScope::Tree => "tree",
+            KeyScope::Editor => "editor",
+            KeyScope::Terminal => "terminal",
+            KeyScope::Agent => "agent",
+            KeyScope::Git => "git",
+        }
+    }
+}
+
+/// Outcome of feeding one chord into the keymap.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Lookup {
+    Command(CommandId),
+    /// First chord of a bound two-chord sequence.
+    Prefix,
+    Unbound,
+}
+
+pub struct Keymap {
+    bindings: HashMap<KeyScope, Vec<(ChordSeq, CommandId)>>,
+}
+
+impl Keymap {
+    /// The built-in bindings.
+    pub fn with_defaults() -> Self {
+        let mut map = Self {
+            bindings: HashMap::new(),
+        };
+        let defaults: &[(KeyScope, &str, CommandId)] = &[
+            (KeyScope::Global, "ctrl+q", CommandId::Quit),
+            (KeyScope::Global, "ctrl+s", CommandId::SaveFile),
+            (KeyScope::Global, "ctrl+p", CommandId::CommandPalette),
+            (KeyScope::Global, "ctrl+f", CommandId::SearchReplace),
+            (KeyScope::Global, "f1", CommandId::KeyboardHelp),
+            (KeyScope::Global, "alt+1", CommandId::FocusTree),
+            (KeyScope::Global, "alt+2", CommandId::FocusEditor),
+            (KeyScope::Global, "alt+3", CommandId::FocusTerminal),
+            (KeyScope::Global, "alt+4", CommandId::FocusAgent),
+            (KeyScope::Global, "alt+5", CommandId::FocusGit),
+            (KeyScope::Editor, "f2", CommandId::RenameSymbol),
+            (KeyScope::Editor, "f12", CommandId::GotoDefinition),
+            (KeyScope::Editor, "ctrl+b", CommandId::ToggleBookmark),
+            (KeyScope::Editor, "alt+n", CommandId::NextBookmark),
+            (KeyScope::Editor, "alt+p", CommandId::PrevBookmark),
+            (KeyScope::Editor, "ctrl+o", CommandId::JumpBack),
+            (KeyScope::Editor, "ctrl+i", CommandId::JumpForward),
+            (KeyScope::Agent, "ctrl+r", CommandId::AgentCycleProfile),
+            (KeyScope::Git, "space", CommandId::GitStage),
+            (KeyScope::Git, "c", CommandId::GitCommit),
+            (KeyScope::Git, "r", CommandId::GitRefresh),
+            (KeyScope::Git, "R", CommandId::GitRefresh),
+        ];
+        for (scope, seq, command) in defaults {
+            let seq = parse_sequence(seq).expect("default binding parses");
+            map.bind(*scope, seq, *command);
+        }
+        map
+    }
+
+    /// Bind a sequence, replacing any existing binding for the same
+    /// sequence in the same scope.
+    pub fn bind(&mut self, scope: KeyScope, seq: ChordSeq, command: CommandId) {
+        let entries = self.bindings.entry(scope).or_default();
+        entries.retain(|(existing, _)| existing != &seq);
+        entries.push((seq, command));
+    }
+
+    /// Apply the `[keys.*]` config tables; returns human-readable errors
+    /// for entries that do not parse.
+    pub fn apply_config(&mut self, keys: &crate::config::KeysSection) -> Vec<String> {
+        let mut errors = Vec::new();
+        let sections: &[(KeyScope, &HashMap<String, String>)] = &[
+            (KeyScope::Global, &keys.global),
+            (KeyScope::Tree, &keys.tree),
+            (KeyScope::Editor, &keys.editor),
+            (KeyScope::Terminal, &keys.terminal),
+            (KeyScope::Agent, &keys.agent),
+            (KeyScope::Git, &keys.git),
+        ];
+        for (scope, table) in sections {
+            for (seq_text, command_name) in table.iter() {
+                let seq = match parse_sequence(seq_text) {
+                    Ok(seq) => seq,
+                    Err(err) => {
+                        errors.push(format!("bad binding {seq_text:?}: {err}"));
+                        continue;
+                    }
+                };
+                let Some(command) = crate::app::command_by_name(command_name) else {
+                    errors.push(format!("unknown command {command_name:?}"));
+                    continue;
+                };
+                self.bind(*scope, seq, command);
+            }
+        }
+        errors
+    }
+
+    /// Feed one chord, with the previous chord of a pending sequence if
+    /// any. The focused scope shadows global bindings.
+    pub fn lookup(&self, scope: KeyScope, pending: Option<KeyChord>, chord: KeyChord) -> Lookup {
+        for scope in [scope, KeyScope::Global] {
+            let Some(entries) = self.bindings.get(&scope) else {
+                continue;
+            };
+            match pending {
+                None => {
+                    if let Some((_, command)) = entries
+                        .iter()
+                        .find(|(seq, _)| seq.len() == 1 && seq[0] == chord)
+                    {
+                        return Lookup::Command(*command);
+                    }
+                    if entries
+                        .iter()
+                        .any(|(seq, _)| seq.len() == 2 && seq[0] == chord)
+                    {
+                        return Lookup::Prefix;
+                    }
+                }
+                Some(first) => {
+                    if let Some((_, command)) = entries
+                        .iter()
+                        .find(|(seq, _)| seq.as_slice() == [first, chord])
+                    {
+                        return Lookup::Command(*command);
+                    }
+                }
+            }
+        }
+        Lookup::Unbound
+    }
+
+    /// Every effective binding, for the help overlay: scope, key
+    /// sequence, command label. Global bindings come first.
+    pub fn effective_bindings(&self) -> Vec<(KeyScope, String, CommandId)> {
+        let order = [
+            KeyScope::Global,
+            KeyScope::Tree,
+            KeyScope::Editor,
+            KeyScope::Terminal,
+            KeyScope::Agent,
+            KeyScope::Git,
+        ];
+        let mut out = Vec::new();
+        for scope in order {
+            let Some(entries) = self.bindings.get(&scope) else {
+                continue;
+            };
+            let mut rows: Vec<(KeyScope, String, CommandId)> = entries
+                .iter()
+                .map(|(seq, command)| (scope, sequence_label(seq), *command))
+                .collect();
+            rows.sort_by(|a, b| a.1.cmp(&b.1));
+            out.extend(rows);
+        }
+        out
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_chords_and_sequences() {
+        let seq = parse_sequence("ctrl+k ctrl+s").unwrap();
+        assert_eq!(seq.len(), 2);
+        assert_eq!(seq[0].code, KeyCode::Char('k'));
+        assert!(seq[0].mods.contains(KeyModifiers::CONTROL));
+        assert_eq!(sequence_label(&seq), "Ctrl+K Ctrl+S");
+        assert!(parse_sequence("ctrl+a ctrl+b ctrl+c").is_err());
+        assert!(parse_sequence("ctrl+").is_err());
+        assert_eq!(KeyChord::parse("f12").unwrap().code, KeyCode::F(12));
+    }
+
+    #[test]
+    fn config_overrides_and_chord_prefixes() {
+        let mut map = Keymap::with_defaults();
+        let mut keys = crate::config::KeysSection::default();
+        keys.global
+            .insert("ctrl+k ctrl+s".into(), "help.keyboard".into());
+        keys.global.insert("ctrl+q".into(), "buffer.close".into());
+        let errors = map.apply_config(&keys);
+        assert!(errors.is_empty(), "{errors:?}");
+
+        let ctrl_k = KeyChord::parse("ctrl+k").unwrap();
+        let ctrl_s = KeyChord::parse("ctrl+s").unwrap();
+        let ctrl_q = KeyChord::parse("ctrl+q").unwrap();
+        assert_eq!(map.lookup(KeyScope::Editor, None, ctrl_k), Lookup::Prefix);
+        assert_eq!(
+            map.lookup(KeyScope::Editor, Some(ctrl_k), ctrl_s),
+            Lookup::Command(CommandId::KeyboardHelp)
+        );
+        assert_eq!(
+            map.lookup(KeyScope::Editor, None, ctrl_q),
+            Lookup::Command(CommandId::CloseBuffer)
+        );
+    }
+}
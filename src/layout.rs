@@ -0,0 +1,143 @@
+//! Pane layout: split ratios, visibility, and mouse hit-testing.
+
+use ratatui::layout::Rect;
+
+/// Which pane currently receives keyboard input.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Focus {
+    Tree,
+    Editor,
+    Terminal,
+    Agent,
+    Git,
+}
+
+/// One scrollbar track registered during the last render, so mouse
+/// events on it can be mapped back to a scroll offset.
+#[derive(Debug, Clone, Copy)]
+pub struct Scrollbar {
+    pub pane: Focus,
+    /// The one-cell-wide track column.
+    pub track: Rect,
+    /// Total content rows and the viewport height.
+    pub total: usize,
+    pub viewport: usize,
+}
+
+impl Scrollbar {
+    /// Map a click row on the track to a content offset, clamped to the
+    /// scrollable range.
+    pub fn offset_for(&self, y: u16) -> usize {
+        let max = self.total.saturating_sub(self.viewport);
+        if self.track.height <= 1 {
+            return 0;
+        }
+        let row = y.saturating_sub(self.track.y) as usize;
+        (row * max / (self.track.height as usize - 1)).min(max)
+    }
+}
+
+/// Split ratios and pane visibility, plus the rects computed during the
+/// last render so mouse events can be routed to the pane under the cursor.
+pub struct LayoutState {
+    /// Width of the file tree as a percentage of the full frame.
+    pub tree_ratio: u16,
+    /// Width of the agent panel as a percentage of the full frame.
+    pub agent_ratio: u16,
+    /// Height of the terminal as a percentage of the center column.
+    pub terminal_ratio: u16,
+    pub show_tree: bool,
+    pub show_terminal: bool,
+    pub show_agent: bool,
+    /// The git panel shares the left column with the file tree.
+    pub show_git: bool,
+    pub tree_area: Rect,
+    /// One-line breadcrumb strip above the editor; zero when hidden.
+    pub breadcrumb_area: Rect,
+    pub editor_area: Rect,
+    pub terminal_area: Rect,
+    pub agent_area: Rect,
+    /// Cell rectangle reserved for the inline agent image, if any.
+    pub agent_image_area: Rect,
+    pub git_area: Rect,
+    /// Scrollbar tracks drawn during the last render.
+    pub scrollbars: Vec<Scrollbar>,
+    /// The one-line status bar at the bottom.
+    pub status_area: Rect,
+    /// Clickable status segment spans from the last render.
+    pub status_hits: Vec<StatusHit>,
+}
+
+/// The column span of one clickable status bar segment.
+#[derive(Debug, Clone, Copy)]
+pub struct StatusHit {
+    pub start: u16,
+    pub end: u16,
+    pub command: crate::app::CommandId,
+}
+
+impl Default for LayoutState {
+    fn default() -> Self {
+        Self {
+            tree_ratio: 20,
+            agent_ratio: 30,
+            terminal_ratio: 30,
+            show_tree: true,
+            show_terminal: true,
+            show_agent: true,
+            show_git: false,
+            tree_area: Rect::default(),
+            breadcrumb_area: Rect::default(),
+            editor_area: Rect::default(),
+            terminal_area: Rect::default(),
+            agent_area: Rect::default(),
+            agent_image_area: Rect::default(),
+            git_area: Rect::default(),
+            scrollbars: Vec::new(),
+            status_area: Rect::default(),
+            status_hits: Vec::new(),
+        }
+    }
+}
+
+impl LayoutState {
+    /// The command of the clickable status segment at column `x`, if
+    /// any.
+    pub fn status_hit(&self, x: u16) -> Option<crate::app::CommandId> {
+        self.status_hits
+            .iter()
+            .find(|hit| x >= hit.start && x < hit.end)
+            .map(|hit| hit.command)
+    }
+
+    /// The scrollbar track containing the given cell, if any.
+    pub fn scrollbar_at(&self, x: u16, y: u16) -> Option<Scrollbar> {
+        self.scrollbars
+            .iter()
+            .find(|sb| {
+                let r = sb.track;
+                x >= r.x && x < r.x + r.width && y >= r.y && y < r.y + r.height
+            })
+            .copied()
+    }
+
+    /// The pane whose last-rendered rect contains the given cell.
+    pub fn pane_at(&self, x: u16, y: u16) -> Option<Focus> {
+        let contains = |r: Rect| {
+            x >= r.x && x < r.x + r.width && y >= r.y && y < r.y + r.height
+        };
+        if self.show_tree && contains(self.tree_area) {
+            Some(Focus::Tree)
+        } else if self.show_git && contains(self.git_area) {
+            Some(Focus::Git)
+        } else if contains(self.editor_area) {
+            Some(Focus::Editor)
+        } else if self.show_terminal && contains(self.terminal_area) {
+            Some(Focus::Terminal)
+        } else if self.show_agent && contains(self.agent_area) {
+            Some(Focus::Agent)
+        } else {
+            None
+        }
+    }
+}
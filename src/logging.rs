@@ -0,0 +1,39 @@
+//! Minimal leveled logging to `.clide/clide.log`.
+//!
+//! The TUI owns the terminal, so diagnostics go to a workspace-local
+//! file instead of stderr. The level comes from `--log-level`.
+
+use std::fs::OpenOptions;
+use std::io::Write;
+use std::path::PathBuf;
+use std::sync::RwLock;
+
+use crate::cli::LogLevel;
+
+static STATE: RwLock<Option<(LogLevel, PathBuf)>> = RwLock::new(None);
+
+/// Set the active level and the workspace the log file lives in.
+pub fn init(level: LogLevel, root: &std::path::Path) {
+    if let Ok(mut guard) = STATE.write() {
+        *guard = Some((level, root.join(".clide").join("clide.log")));
+    }
+}
+
+/// Append one line if `level` is enabled; failures are silently dropped
+/// (logging must never take the editor down).
+pub fn log(level: LogLevel, message: &str) {
+    let Ok(guard) = STATE.read() else { return };
+    let Some((active, path)) = guard.as_ref() else {
+        return;
+    };
+    if level > *active {
+        return;
+    }
+    if let Some(dir) = path.parent() {
+        let _ = std::fs::create_dir_all(dir);
+    }
+    if let Ok(mut file) = OpenOptions::new().create(true).append(true).open(path) {
+        let now = chrono::Local::now().format("%Y-%m-%d %H:%M:%S");
+        let _ = writeln!(file, "{now} [{}] {message}", level.label());
+    }
+}
@@ -0,0 +1,432 @@
+//! A small LSP client speaking JSON-RPC over the child process's stdio.
+//!
+//! The client is deliberately minimal: it supports the requests Clide
+//! actually issues (definition, hover, rename, document symbols) plus
+//! `publishDiagnostics`. Responses are correlated through a pending-request
+//! map and surfaced to the main loop as [`LspEvent`]s.
+
+pub mod types;
+
+use std::collections::{HashMap, HashSet};
+use std::io::{BufRead, BufReader, Write};
+use std::path::{Path, PathBuf};
+use std::process::{Child, Command, Stdio};
+use std::sync::{Arc, Mutex};
+
+use anyhow::{Context, Result};
+use serde_json::{json, Value};
+
+use crate::event::{AppEvent, AppEventSender};
+use types::{
+    Diagnostic, DiagnosticSeverity, GotoDefinitionResponse, Location, LspPosition, WorkspaceEdit,
+};
+
+/// What kind of answer an outstanding request expects.
+#[derive(Debug, Clone)]
+enum PendingKind {
+    Definition,
+    Hover,
+    Rename,
+    /// Symbols for this document, cached for the breadcrumb bar.
+    DocumentSymbol(PathBuf),
+}
+
+/// Events the LSP reader thread sends back to the main loop.
+pub enum LspEvent {
+    Diagnostics {
+        path: PathBuf,
+        diagnostics: Vec<Diagnostic>,
+    },
+    /// Go-to-definition candidates; one jumps directly, several go
+    /// through a picker.
+    Definition(Vec<Location>),
+    Hover(String),
+    /// A rename produced this workspace edit; the UI decides how to apply it.
+    RenameEdit(WorkspaceEdit),
+    /// Flattened symbol outline of a document, as (name, range) pairs.
+    DocumentSymbols {
+        path: PathBuf,
+        symbols: Vec<(String, types::LspRange)>,
+    },
+    /// The server's work-done progress (initial indexing, cargo check)
+    /// started or finished; `active` is false once every token has ended.
+    Indexing {
+        active: bool,
+        message: Option<String>,
+    },
+    ServerExited,
+}
+
+pub struct LspClient {
+    child: Child,
+    /// Shared with the reader thread, which acks the server's
+    /// `window/workDoneProgress/create` requests.
+    stdin: Arc<Mutex<std::process::ChildStdin>>,
+    next_id: i64,
+    pending: Arc<Mutex<HashMap<i64, PendingKind>>>,
+}
+
+impl LspClient {
+    /// Spawn `rust-analyzer` for the workspace, if it is on PATH and the
+    /// workspace looks like a Rust project.
+    pub fn spawn(root: &Path, events: AppEventSender) -> Option<Self> {
+        if !root.join("Cargo.toml").exists() {
+            return None;
+        }
+        let mut child = Command::new("rust-analyzer")
+            .current_dir(root)
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::null())
+            .spawn()
+            .ok()?;
+        let stdin = Arc::new(Mutex::new(child.stdin.take()?));
+        let stdout = child.stdout.take()?;
+        let pending = Arc::new(Mutex::new(HashMap::new()));
+        let reader_pending = Arc::clone(&pending);
+        let reader_stdin = Arc::clone(&stdin);
+        std::thread::spawn(move || {
+            reader_loop(stdout, reader_stdin, reader_pending, events);
+        });
+        let mut client = Self {
+            child,
+            stdin,
+            next_id: 1,
+            pending,
+        };
+        client.initialize(root).ok()?;
+        Some(client)
+    }
+
+    fn initialize(&mut self, root: &Path) -> Result<()> {
+        let id = self.next_id;
+        self.next_id += 1;
+        self.send(&json!({
+            "jsonrpc": "2.0",
+            "id": id,
+            "method": "initialize",
+            "params": {
+                "processId": std::process::id(),
+                "rootUri": types::path_to_uri(root),
+                "capabilities": {
+                    "textDocument": {
+                        "publishDiagnostics": {},
+                        "hover": { "contentFormat": ["plaintext"] },
+                        "rename": {},
+                        "documentSymbol": { "hierarchicalDocumentSymbolSupport": true }
+                    },
+                    "workspace": {
+                        "workspaceEdit": { "documentChanges": false }
+                    },
+                    "window": {
+                        "workDoneProgress": true
+                    }
+                }
+            }
+        }))?;
+        self.notify("initialized", json!({}))
+    }
+
+    fn send(&mut self, msg: &Value) -> Result<()> {
+        write_message(&mut self.stdin.lock().unwrap(), msg)
+    }
+
+    fn notify(&mut self, method: &str, params: Value) -> Result<()> {
+        self.send(&json!({ "jsonrpc": "2.0", "method": method, "params": params }))
+    }
+
+    fn request(&mut self, kind: PendingKind, method: &str, params: Value) -> Result<()> {
+        let id = self.next_id;
+        self.next_id += 1;
+        self.pending.lock().unwrap().insert(id, kind);
+        self.send(&json!({ "jsonrpc": "2.0", "id": id, "method": method, "params": params }))
+    }
+
+    pub fn did_open(&mut self, path: &Path, language: &str, text: &str) -> Result<()> {
+        self.notify(
+            "textDocument/didOpen",
+            json!({
+                "textDocument": {
+                    "uri": types::path_to_uri(path),
+                    "languageId": language,
+                    "version": 0,
+                    "text": text
+                }
+            }),
+        )
+    }
+
+    pub fn did_change(&mut self, path: &Path, version: i64, text: &str) -> Result<()> {
+        self.notify(
+            "textDocument/didChange",
+            json!({
+                "textDocument": { "uri": types::path_to_uri(path), "version": version },
+                "contentChanges": [{ "text": text }]
+            }),
+        )
+    }
+
+    pub fn did_save(&mut self, path: &Path) -> Result<()> {
+        self.notify(
+            "textDocument/didSave",
+            json!({ "textDocument": { "uri": types::path_to_uri(path) } }),
+        )
+    }
+
+    pub fn goto_definition(&mut self, path: &Path, pos: LspPosition) -> Result<()> {
+        self.request(
+            PendingKind::Definition,
+            "textDocument/definition",
+            doc_position_params(path, pos),
+        )
+    }
+
+    pub fn document_symbols(&mut self, path: &Path) -> Result<()> {
+        self.request(
+            PendingKind::DocumentSymbol(path.to_path_buf()),
+            "textDocument/documentSymbol",
+            json!({ "textDocument": { "uri": types::path_to_uri(path) } }),
+        )
+    }
+
+    pub fn hover(&mut self, path: &Path, pos: LspPosition) -> Result<()> {
+        self.request(
+            PendingKind::Hover,
+            "textDocument/hover",
+            doc_position_params(path, pos),
+        )
+    }
+
+    pub fn rename(&mut self, path: &Path, pos: LspPosition, new_name: &str) -> Result<()> {
+        let mut params = doc_position_params(path, pos);
+        params["newName"] = json!(new_name);
+        self.request(PendingKind::Rename, "textDocument/rename", params)
+    }
+
+    pub fn shutdown(&mut self) {
+        let _ = self.notify("exit", json!({}));
+        let _ = self.child.kill();
+        let _ = self.child.wait();
+    }
+}
+
+fn write_message(stdin: &mut std::process::ChildStdin, msg: &Value) -> Result<()> {
+    let body = serde_json::to_string(msg)?;
+    write!(stdin, "Content-Length: {}\r\n\r\n{}", body.len(), body)
+        .context("lsp server stdin closed")?;
+    stdin.flush()?;
+    Ok(())
+}
+
+fn doc_position_params(path: &Path, pos: LspPosition) -> Value {
+    json!({
+        "textDocument": { "uri": types::path_to_uri(path) },
+        "position": { "line": pos.line, "character": pos.character }
+    })
+}
+
+fn reader_loop(
+    stdout: std::process::ChildStdout,
+    stdin: Arc<Mutex<std::process::ChildStdin>>,
+    pending: Arc<Mutex<HashMap<i64, PendingKind>>>,
+    events: AppEventSender,
+) {
+    let mut reader = BufReader::new(stdout);
+    // Progress tokens the server has begun but not yet ended.
+    let mut progress_tokens: HashSet<String> = HashSet::new();
+    loop {
+        let Some(msg) = read_message(&mut reader) else {
+            let _ = events.send(AppEvent::Lsp(LspEvent::ServerExited));
+            return;
+        };
+        // The only server-to-client request we handle: progress-token
+        // creation just needs an empty ack.
+        if msg.get("method").and_then(Value::as_str) == Some("window/workDoneProgress/create") {
+            if let Some(id) = msg.get("id") {
+                let reply = json!({ "jsonrpc": "2.0", "id": id, "result": null });
+                let _ = write_message(&mut stdin.lock().unwrap(), &reply);
+            }
+            continue;
+        }
+        if let Some(event) = dispatch_message(&msg, &pending, &mut progress_tokens) {
+            if events.send(AppEvent::Lsp(event)).is_err() {
+                return;
+            }
+        }
+    }
+}
+
+/// Read one `Content-Length`-framed JSON-RPC message.
+fn read_message(reader: &mut impl BufRead) -> Option<Value> {
+    let mut content_length = 0usize;
+    loop {
+        let mut line = String::new();
+        if reader.read_line(&mut line).ok()? == 0 {
+            return None;
+        }
+        let line = line.trim();
+        if line.is_empty() {
+            break;
+        }
+        if let Some(len) = line.strip_prefix("Content-Length:") {
+            content_length = len.trim().parse().ok()?;
+        }
+    }
+    let mut body = vec![0u8; content_length];
+    reader.read_exact(&mut body).ok()?;
+    serde_json::from_slice(&body).ok()
+}
+
+fn dispatch_message(
+    msg: &Value,
+    pending: &Arc<Mutex<HashMap<i64, PendingKind>>>,
+    progress_tokens: &mut HashSet<String>,
+) -> Option<LspEvent> {
+    if let Some(method) = msg.get("method").and_then(Value::as_str) {
+        if method == "textDocument/publishDiagnostics" {
+            return parse_diagnostics(msg.get("params")?);
+        }
+        if method == "$/progress" {
+            return parse_progress(msg.get("params")?, progress_tokens);
+        }
+        return None;
+    }
+    let id = msg.get("id")?.as_i64()?;
+    let kind = pending.lock().unwrap().remove(&id)?;
+    let result = msg.get("result")?;
+    match kind {
+        PendingKind::Definition => {
+            let response: GotoDefinitionResponse =
+                serde_json::from_value(result.clone()).ok()?;
+            let locations = match response {
+                GotoDefinitionResponse::Scalar(location) => vec![location],
+                GotoDefinitionResponse::Array(locations) => locations,
+                GotoDefinitionResponse::Link(links) => links
+                    .into_iter()
+                    .map(|link| Location {
+                        uri: link.target_uri,
+                        range: link.target_selection_range,
+                    })
+                    .collect(),
+            };
+            if locations.is_empty() {
+                None
+            } else {
+                Some(LspEvent::Definition(locations))
+            }
+        }
+        PendingKind::Hover => {
+            let text = result
+                .get("contents")
+                .map(hover_contents_to_text)
+                .unwrap_or_default();
+            if text.is_empty() {
+                None
+            } else {
+                Some(LspEvent::Hover(text))
+            }
+        }
+        PendingKind::Rename => {
+            let edit: WorkspaceEdit = serde_json::from_value(result.clone()).ok()?;
+            Some(LspEvent::RenameEdit(edit))
+        }
+        PendingKind::DocumentSymbol(path) => {
+            let symbols: Vec<types::DocumentSymbol> =
+                serde_json::from_value(result.clone()).ok()?;
+            let mut flat = Vec::new();
+            flatten_symbols(symbols, &mut flat);
+            Some(LspEvent::DocumentSymbols {
+                path,
+                symbols: flat,
+            })
+        }
+    }
+}
+
+/// Depth-first flatten of a hierarchical symbol tree; inner symbols
+/// follow their parent so "innermost containing" lookups can pick the
+/// later match.
+fn flatten_symbols(symbols: Vec<types::DocumentSymbol>, out: &mut Vec<(String, types::LspRange)>) {
+    for symbol in symbols {
+        let range = symbol
+            .range
+            .or_else(|| symbol.location.as_ref().map(|l| l.range));
+        if let Some(range) = range {
+            out.push((symbol.name, range));
+        }
+        flatten_symbols(symbol.children, out);
+    }
+}
+
+fn parse_diagnostics(params: &Value) -> Option<LspEvent> {
+    let path = types::uri_to_path(params.get("uri")?.as_str()?)?;
+    let diagnostics = params
+        .get("diagnostics")?
+        .as_array()?
+        .iter()
+        .filter_map(|d| {
+            Some(Diagnostic {
+                range: serde_json::from_value(d.get("range")?.clone()).ok()?,
+                severity: DiagnosticSeverity::from_lsp(
+                    d.get("severity").and_then(Value::as_u64).map(|v| v as u8),
+                ),
+                message: d.get("message")?.as_str()?.to_string(),
+                code: d.get("code").map(|c| match c {
+                    Value::String(s) => s.clone(),
+                    other => other.to_string(),
+                }),
+            })
+        })
+        .collect();
+    Some(LspEvent::Diagnostics { path, diagnostics })
+}
+
+/// Track `$/progress` begin/end pairs; the emitted event's `active` is
+/// true while any token is outstanding, so the UI can flag the indexing
+/// phase and defer requests until it ends.
+fn parse_progress(params: &Value, progress_tokens: &mut HashSet<String>) -> Option<LspEvent> {
+    let token = match params.get("token")? {
+        Value::String(s) => s.clone(),
+        other => other.to_string(),
+    };
+    let value = params.get("value")?;
+    match value.get("kind").and_then(Value::as_str)? {
+        "begin" => {
+            progress_tokens.insert(token);
+            let message = value
+                .get("title")
+                .and_then(Value::as_str)
+                .map(str::to_string);
+            Some(LspEvent::Indexing {
+                active: true,
+                message,
+            })
+        }
+        "end" => {
+            progress_tokens.remove(&token);
+            Some(LspEvent::Indexing {
+                active: !progress_tokens.is_empty(),
+                message: None,
+            })
+        }
+        _ => None,
+    }
+}
+
+fn hover_contents_to_text(contents: &Value) -> String {
+    match contents {
+        Value::String(s) => s.clone(),
+        Value::Object(map) => map
+            .get("value")
+            .and_then(Value::as_str)
+            .unwrap_or_default()
+            .to_string(),
+        Value::Array(items) => items
+            .iter()
+            .map(hover_contents_to_text)
+            .collect::<Vec<_>>()
+            .join("\n"),
+        _ => String::new(),
+    }
+}
+
@@ -0,0 +1,137 @@
+//! Minimal serde models for the subset of LSP the client speaks.
+
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, Default)]
+pub struct LspPosition {
+    pub line: u32,
+    pub character: u32,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, Default)]
+pub struct LspRange {
+    pub start: LspPosition,
+    pub end: LspPosition,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Location {
+    pub uri: String,
+    pub range: LspRange,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct LocationLink {
+    #[serde(rename = "targetUri")]
+    pub target_uri: String,
+    #[serde(rename = "targetSelectionRange")]
+    pub target_selection_range: LspRange,
+}
+
+/// One entry of a `textDocument/documentSymbol` response. Hierarchical
+/// servers nest `children` under a `range`; flat servers send a
+/// `location` per symbol instead.
+#[derive(Debug, Clone, Deserialize)]
+pub struct DocumentSymbol {
+    pub name: String,
+    #[serde(default)]
+    pub range: Option<LspRange>,
+    #[serde(default)]
+    pub location: Option<Location>,
+    #[serde(default)]
+    pub children: Vec<DocumentSymbol>,
+}
+
+/// The three shapes a `textDocument/definition` result can take.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(untagged)]
+pub enum GotoDefinitionResponse {
+    Scalar(Location),
+    Array(Vec<Location>),
+    Link(Vec<LocationLink>),
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TextEdit {
+    pub range: LspRange,
+    #[serde(rename = "newText")]
+    pub new_text: String,
+}
+
+/// A multi-file edit returned by rename and code actions. Only the
+/// `changes` form is modeled; servers using `documentChanges` are asked
+/// not to via client capabilities.
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct WorkspaceEdit {
+    #[serde(default)]
+    pub changes: HashMap<String, Vec<TextEdit>>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum DiagnosticSeverity {
+    Error,
+    Warning,
+    Information,
+    Hint,
+}
+
+impl DiagnosticSeverity {
+    pub fn from_lsp(value: Option<u8>) -> Self {
+        match value {
+            Some(1) => DiagnosticSeverity::Error,
+            Some(2) => DiagnosticSeverity::Warning,
+            Some(3) => DiagnosticSeverity::Information,
+            _ => DiagnosticSeverity::Hint,
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct Diagnostic {
+    pub range: LspRange,
+    pub severity: DiagnosticSeverity,
+    pub message: String,
+    pub code: Option<String>,
+}
+
+/// Convert a filesystem path to a `file://` URI.
+pub fn path_to_uri(path: &Path) -> String {
+    format!("file://{}", path.display())
+}
+
+/// Convert a `file://` URI back to a path, tolerating percent-encoding of
+/// spaces which is all rust-analyzer emits in practice.
+pub fn uri_to_path(uri: &str) -> Option<PathBuf> {
+    let raw = uri.strip_prefix("file://")?;
+    Some(PathBuf::from(raw.replace("%20", " ")))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn uri_roundtrip() {
+        let path = Path::new("/tmp/some dir/file.rs");
+        let uri = path_to_uri(path);
+        assert_eq!(uri, "file:///tmp/some dir/file.rs");
+        assert_eq!(uri_to_path("file:///tmp/some%20dir/file.rs").unwrap(), path);
+    }
+
+    #[test]
+    fn definition_response_variants_deserialize() {
+        let scalar: GotoDefinitionResponse = serde_json::from_str(
+            r#"{"uri":"file:///a.rs","range":{"start":{"line":0,"character":0},"end":{"line":0,"character":1}}}"#,
+        )
+        .unwrap();
+        assert!(matches!(scalar, GotoDefinitionResponse::Scalar(_)));
+        let array: GotoDefinitionResponse = serde_json::from_str(
+            r#"[{"uri":"file:///a.rs","range":{"start":{"line":0,"character":0},"end":{"line":0,"character":1}}}]"#,
+        )
+        .unwrap();
+        assert!(matches!(array, GotoDefinitionResponse::Array(_)));
+    }
+}
@@ -0,0 +1,189 @@
+//! Clide: a terminal IDE with integrated agents.
+
+mod agent;
+mod app;
+mod bundle;
+mod cli;
+mod clipboard;
+mod config;
+mod editor;
+mod event;
+mod git;
+mod keyboard;
+mod keymap;
+mod layout;
+mod logging;
+mod lsp;
+mod notify;
+mod recovery;
+mod replay;
+mod session;
+mod task;
+mod tasks;
+mod terminal;
+mod tui;
+mod ui;
+mod vim;
+mod workspace;
+
+use std::collections::VecDeque;
+use std::path::PathBuf;
+use std::time::Duration;
+
+use anyhow::Result;
+use crossterm::event::{Event, MouseEvent};
+
+use app::App;
+
+fn main() -> Result<()> {
+    let args = match cli::parse(std::env::args().skip(1)) {
+        Ok(args) => args,
+        Err(err) => {
+            eprintln!("clide: {err:#}\n\n{}", cli::USAGE);
+            std::process::exit(2);
+        }
+    };
+    if args.help {
+        print!("{}", cli::USAGE);
+        return Ok(());
+    }
+
+    let cwd = std::env::current_dir().unwrap_or_else(|_| PathBuf::from("."));
+    let root = match &args.target {
+        Some(cli::Target::Dir(dir)) => dir.clone(),
+        _ => cwd,
+    };
+    let root = root.canonicalize().unwrap_or(root);
+    logging::init(args.log_level, &root);
+    logging::log(
+        cli::LogLevel::Info,
+        &format!("opening workspace {}", root.display()),
+    );
+
+    let recorder = match &args.record {
+        Some(path) => Some(replay::Recorder::create(path)?),
+        None => None,
+    };
+    let replay_events: VecDeque<Event> = match &args.replay {
+        Some(path) => replay::load(path)?.into(),
+        None => VecDeque::new(),
+    };
+
+    let runtime = tokio::runtime::Builder::new_multi_thread()
+        .enable_all()
+        .build()?;
+    let mut app = App::init(root, runtime.handle().clone(), args);
+
+    let mut terminal = tui::init()?;
+    let result = run(&mut terminal, &mut app, recorder, replay_events);
+    app.shutdown();
+    tui::restore()?;
+    result
+}
+
+fn run(
+    terminal: &mut tui::Tui,
+    app: &mut App,
+    mut recorder: Option<replay::Recorder>,
+    mut replay_events: VecDeque<Event>,
+) -> Result<()> {
+    let mut inline_image: Option<(std::path::PathBuf, ratatui::layout::Rect)> = None;
+    while !app.should_quit {
+        app.drain_events();
+        app.tick();
+        terminal.draw(|frame| ui::render(frame, app))?;
+        // Inline images bypass the cell buffer: emit the protocol escape
+        // sequence directly whenever the image or its placement changes.
+        let current = app
+            .inline_image()
+            .map(|path| (path.clone(), app.layout.agent_image_area))
+            .filter(|(_, area)| area.width > 0 && area.height > 0);
+        if current != inline_image {
+            let mut out = std::io::stdout();
+            let _ = ui::images::clear_inline(&mut out, app.image_protocol);
+            if let Some((path, area)) = &current {
+                let _ = ui::images::emit_inline(&mut out, app.image_protocol, path, *area);
+            }
+            inline_image = current;
+        }
+        // Replayed events are dispatched one per frame without waiting on
+        // real input; once exhausted the session becomes interactive.
+        let event = if let Some(event) = replay_events.pop_front() {
+            Some(event)
+        } else if crossterm::event::poll(Duration::from_millis(50))? {
+            Some(crossterm::event::read()?)
+        } else {
+            None
+        };
+        if let Some(event) = event {
+            if let Some(recorder) = recorder.as_mut() {
+                recorder.record(&event, masked_prompt_open(app));
+            }
+            match event {
+                Event::Key(key) => keyboard::handle_key(app, key),
+                Event::Mouse(mouse) => handle_mouse(app, mouse),
+                Event::Paste(text) => app.handle_paste(&text),
+                _ => {}
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Whether a secret-entry prompt is open, so the recorder can elide the
+/// keystrokes.
+fn masked_prompt_open(app: &App) -> bool {
+    matches!(
+        &app.overlay,
+        Some(ui::overlay::Overlay::Prompt { action, .. }) if action.masked()
+    )
+}
+
+fn handle_mouse(app: &mut App, mouse: MouseEvent) {
+    use crossterm::event::{MouseButton, MouseEventKind};
+    match mouse.kind {
+        MouseEventKind::Down(MouseButton::Left) => {
+            if app.overlay.is_some() {
+                return;
+            }
+            let status = app.layout.status_area;
+            if status.height > 0 && mouse.row == status.y {
+                if let Some(command) = app.layout.status_hit(mouse.column) {
+                    app.execute_command(command);
+                }
+                return;
+            }
+            let crumb = app.layout.breadcrumb_area;
+            if crumb.height > 0
+                && mouse.row == crumb.y
+                && mouse.column >= crumb.x
+                && mouse.column < crumb.x + crumb.width
+            {
+                app.breadcrumb_click(mouse.column);
+                return;
+            }
+            if let Some(sb) = app.layout.scrollbar_at(mouse.column, mouse.row) {
+                app.scrollbar_click(sb, mouse.row);
+                return;
+            }
+            if let Some(pane) = app.layout.pane_at(mouse.column, mouse.row) {
+                app.focus = pane;
+                ui::pane::view_for(pane).handle_mouse(app, mouse);
+            }
+        }
+        MouseEventKind::Drag(MouseButton::Left) => {
+            app.scrollbar_drag_move(mouse.row);
+            app.tree_drag_move(mouse.column, mouse.row);
+            app.editor_drag_move(mouse.column, mouse.row);
+        }
+        MouseEventKind::Up(MouseButton::Left) => {
+            app.scrollbar_drag_end();
+            app.tree_drag_drop(mouse.column, mouse.row);
+            app.editor_drag_end();
+        }
+        MouseEventKind::Moved => app.mouse_moved(mouse.column, mouse.row),
+        MouseEventKind::ScrollUp => app.scroll_pane_at(mouse.column, mouse.row, -3),
+        MouseEventKind::ScrollDown => app.scroll_pane_at(mouse.column, mouse.row, 3),
+        _ => {}
+    }
+}
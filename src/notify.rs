@@ -0,0 +1,127 @@
+//! Toast notifications with severity levels and a session history.
+//!
+//! Everything the app wants to tell the user flows through
+//! [`Notifications::push`]: the newest entry doubles as the status-bar
+//! message, recent entries are drawn as stacked toasts over the
+//! workspace until they expire, and the full session log stays
+//! available in the notification-history overlay.
+
+use std::collections::VecDeque;
+use std::time::{Duration, Instant};
+
+/// How long an info/warning toast stays on screen.
+const TOAST_TTL: Duration = Duration::from_secs(5);
+/// Errors linger longer so they are not missed mid-edit.
+const ERROR_TTL: Duration = Duration::from_secs(10);
+/// At most this many toasts are stacked at once; older ones yield.
+const MAX_VISIBLE: usize = 4;
+/// History is capped so a chatty session cannot grow without bound.
+const MAX_HISTORY: usize = 500;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Severity {
+    Info,
+    Warning,
+    Error,
+}
+
+impl Severity {
+    pub fn label(self) -> &'static str {
+        match self {
+            Severity::Info => "info",
+            Severity::Warning => "warn",
+            Severity::Error => "error",
+        }
+    }
+
+    fn ttl(self) -> Duration {
+        match self {
+            Severity::Error => ERROR_TTL,
+            _ => TOAST_TTL,
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct Notification {
+    pub message: String,
+    pub severity: Severity,
+    pub at: Instant,
+}
+
+impl Notification {
+    fn expired(&self) -> bool {
+        self.at.elapsed() > self.severity.ttl()
+    }
+}
+
+/// The live toast stack plus the persistent session history.
+#[derive(Default)]
+pub struct Notifications {
+    toasts: VecDeque<Notification>,
+    history: VecDeque<Notification>,
+}
+
+impl Notifications {
+    pub fn push(&mut self, severity: Severity, message: impl Into<String>) {
+        let entry = Notification {
+            message: message.into(),
+            severity,
+            at: Instant::now(),
+        };
+        self.history.push_back(entry.clone());
+        if self.history.len() > MAX_HISTORY {
+            self.history.pop_front();
+        }
+        self.toasts.push_back(entry);
+        if self.toasts.len() > MAX_VISIBLE {
+            self.toasts.pop_front();
+        }
+    }
+
+    /// Drop expired toasts; called once per frame.
+    pub fn tick(&mut self) {
+        self.toasts.retain(|n| !n.expired());
+    }
+
+    /// Live toasts, oldest first.
+    pub fn visible(&self) -> impl Iterator<Item = &Notification> {
+        self.toasts.iter()
+    }
+
+    /// The newest live toast, shown in the status bar.
+    pub fn latest(&self) -> Option<&Notification> {
+        self.toasts.back()
+    }
+
+    /// Every notification of the session, oldest first.
+    pub fn history(&self) -> impl ExactSizeIterator<Item = &Notification> {
+        self.history.iter()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn toast_stack_is_capped_but_history_keeps_everything() {
+        let mut notify = Notifications::default();
+        for i in 0..10 {
+            notify.push(Severity::Info, format!("message {i}"));
+        }
+        assert_eq!(notify.visible().count(), MAX_VISIBLE);
+        assert_eq!(notify.history().len(), 10);
+        assert_eq!(notify.latest().unwrap().message, "message 9");
+    }
+
+    #[test]
+    fn expired_toasts_leave_the_stack_not_the_history() {
+        let mut notify = Notifications::default();
+        notify.push(Severity::Info, "old");
+        notify.toasts[0].at = Instant::now() - TOAST_TTL - Duration::from_secs(1);
+        notify.tick();
+        assert_eq!(notify.visible().count(), 0);
+        assert_eq!(notify.history().len(), 1);
+    }
+}
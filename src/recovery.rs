@@ -0,0 +1,81 @@
+//! Crash-recovery swap files.
+//!
+//! Dirty buffers are snapshotted to `.clide/recovery/` on a short
+//! interval and the directory is cleared on a clean exit, so anything
+//! found there at startup is the residue of a crash and is offered for
+//! restore. A crash therefore loses at most one snapshot interval of
+//! typing.
+
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use anyhow::{Context, Result};
+
+fn recovery_dir(root: &Path) -> PathBuf {
+    root.join(".clide").join("recovery")
+}
+
+/// Swap-file name for a buffer path: separators are percent-escaped so
+/// the whole path fits in a single file name.
+fn swap_name(path: &Path) -> String {
+    let escaped = path
+        .to_string_lossy()
+        .replace('%', "%25")
+        .replace(['/', '\\'], "%2F");
+    format!("{escaped}.swp")
+}
+
+fn original_path(name: &str) -> Option<PathBuf> {
+    let escaped = name.strip_suffix(".swp")?;
+    Some(PathBuf::from(escaped.replace("%2F", "/").replace("%25", "%")))
+}
+
+/// Write (or refresh) the snapshot for one buffer.
+pub fn write_snapshot(root: &Path, path: &Path, contents: &str) -> Result<()> {
+    let dir = recovery_dir(root);
+    fs::create_dir_all(&dir).with_context(|| format!("failed to create {}", dir.display()))?;
+    let swap = dir.join(swap_name(path));
+    fs::write(&swap, contents).with_context(|| format!("failed to write {}", swap.display()))
+}
+
+/// Drop the snapshot for a buffer that is clean again.
+pub fn remove_snapshot(root: &Path, path: &Path) {
+    let _ = fs::remove_file(recovery_dir(root).join(swap_name(path)));
+}
+
+/// Remove every snapshot; called on clean shutdown and on discard.
+pub fn discard_all(root: &Path) {
+    let _ = fs::remove_dir_all(recovery_dir(root));
+}
+
+/// Snapshots left behind by a crash, as (original, swap) path pairs.
+pub fn pending(root: &Path) -> Vec<(PathBuf, PathBuf)> {
+    let Ok(entries) = fs::read_dir(recovery_dir(root)) else {
+        return Vec::new();
+    };
+    let mut pending = Vec::new();
+    for entry in entries.flatten() {
+        let swap = entry.path();
+        let Some(name) = swap.file_name().and_then(|name| name.to_str()) else {
+            continue;
+        };
+        if let Some(original) = original_path(name) {
+            pending.push((original, swap));
+        }
+    }
+    pending.sort();
+    pending
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn swap_names_round_trip() {
+        let path = Path::new("/work/src/100%.rs");
+        assert_eq!(original_path(&swap_name(path)), Some(path.to_path_buf()));
+        assert!(!swap_name(path).contains('/'));
+        assert_eq!(original_path("not-a-swap"), None);
+    }
+}
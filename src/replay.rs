@@ -0,0 +1,262 @@
+//! Opt-in input recording and deterministic replay.
+//!
+//! `clide --record file` appends every key, mouse, and resize event to a
+//! plain-text log, one event per line; `clide --replay file` feeds the
+//! same events back through the normal dispatch path as fast as frames
+//! render, so a UI bug can be reproduced from a recording and kept as a
+//! regression script. Keystrokes typed into masked prompts (API keys,
+//! decryption passphrases) are elided from the log.
+
+use std::fs;
+use std::io::Write as _;
+use std::path::Path;
+
+use anyhow::{bail, Context, Result};
+use crossterm::event::{
+    Event, KeyCode, KeyEvent, KeyEventKind, KeyModifiers, MouseButton, MouseEvent, MouseEventKind,
+};
+
+/// Appends events to the recording file as they are dispatched.
+pub struct Recorder {
+    file: fs::File,
+}
+
+impl Recorder {
+    pub fn create(path: &Path) -> Result<Self> {
+        let file = fs::File::create(path)
+            .with_context(|| format!("failed to create {}", path.display()))?;
+        Ok(Self { file })
+    }
+
+    /// Record one event; unsupported events (focus, paste) are skipped.
+    /// With `masked` set (a secret prompt is open) key events are elided
+    /// so passphrases never reach the log.
+    pub fn record(&mut self, event: &Event, masked: bool) {
+        if masked && matches!(event, Event::Key(_)) {
+            let _ = writeln!(self.file, "# masked input elided");
+            return;
+        }
+        if let Some(line) = serialize(event) {
+            let _ = writeln!(self.file, "{line}");
+        }
+    }
+}
+
+/// Load a recording for `--replay`, oldest event first.
+pub fn load(path: &Path) -> Result<Vec<Event>> {
+    let text = fs::read_to_string(path)
+        .with_context(|| format!("failed to read {}", path.display()))?;
+    let mut events = Vec::new();
+    for (idx, line) in text.lines().enumerate() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        let event =
+            parse(line).with_context(|| format!("{}:{}: bad event", path.display(), idx + 1))?;
+        events.push(event);
+    }
+    Ok(events)
+}
+
+fn serialize(event: &Event) -> Option<String> {
+    match event {
+        Event::Key(key) if key.kind == KeyEventKind::Press => Some(format!(
+            "key {} {}",
+            mods_token(key.modifiers),
+            code_token(key.code)?
+        )),
+        Event::Mouse(mouse) => Some(format!(
+            "mouse {} {} {} {}",
+            mouse_kind_token(mouse.kind)?,
+            mouse.column,
+            mouse.row,
+            mods_token(mouse.modifiers)
+        )),
+        Event::Resize(w, h) => Some(format!("resize {w} {h}")),
+        _ => None,
+    }
+}
+
+fn parse(line: &str) -> Result<Event> {
+    let mut parts = line.split_whitespace();
+    let kind = parts.next().context("empty event")?;
+    let event = match kind {
+        "key" => {
+            let mods = parse_mods(parts.next().context("key needs modifiers")?)?;
+            let code = parse_code(parts.next().context("key needs a code")?)?;
+            Event::Key(KeyEvent::new(code, mods))
+        }
+        "mouse" => {
+            let kind = parse_mouse_kind(parts.next().context("mouse needs a kind")?)?;
+            let column = parts.next().context("mouse needs a column")?.parse()?;
+            let row = parts.next().context("mouse needs a row")?.parse()?;
+            let modifiers = parse_mods(parts.next().context("mouse needs modifiers")?)?;
+            Event::Mouse(MouseEvent {
+                kind,
+                column,
+                row,
+                modifiers,
+            })
+        }
+        "resize" => {
+            let w = parts.next().context("resize needs a width")?.parse()?;
+            let h = parts.next().context("resize needs a height")?.parse()?;
+            Event::Resize(w, h)
+        }
+        other => bail!("unknown event kind {other:?}"),
+    };
+    if parts.next().is_some() {
+        bail!("trailing tokens");
+    }
+    Ok(event)
+}
+
+fn mods_token(mods: KeyModifiers) -> String {
+    let mut parts = Vec::new();
+    if mods.contains(KeyModifiers::CONTROL) {
+        parts.push("ctrl");
+    }
+    if mods.contains(KeyModifiers::ALT) {
+        parts.push("alt");
+    }
+    if mods.contains(KeyModifiers::SHIFT) {
+        parts.push("shift");
+    }
+    if parts.is_empty() {
+        "none".to_string()
+    } else {
+        parts.join("+")
+    }
+}
+
+fn parse_mods(text: &str) -> Result<KeyModifiers> {
+    let mut mods = KeyModifiers::empty();
+    if text == "none" {
+        return Ok(mods);
+    }
+    for part in text.split('+') {
+        match part {
+            "ctrl" => mods |= KeyModifiers::CONTROL,
+            "alt" => mods |= KeyModifiers::ALT,
+            "shift" => mods |= KeyModifiers::SHIFT,
+            other => bail!("unknown modifier {other:?}"),
+        }
+    }
+    Ok(mods)
+}
+
+/// Characters are stored as hex codepoints so the format survives
+/// whitespace and non-ASCII input unambiguously.
+fn code_token(code: KeyCode) -> Option<String> {
+    Some(match code {
+        KeyCode::Char(c) => format!("char:{:x}", c as u32),
+        KeyCode::F(n) => format!("f:{n}"),
+        KeyCode::Enter => "enter".to_string(),
+        KeyCode::Tab => "tab".to_string(),
+        KeyCode::BackTab => "backtab".to_string(),
+        KeyCode::Esc => "esc".to_string(),
+        KeyCode::Backspace => "backspace".to_string(),
+        KeyCode::Delete => "delete".to_string(),
+        KeyCode::Insert => "insert".to_string(),
+        KeyCode::Home => "home".to_string(),
+        KeyCode::End => "end".to_string(),
+        KeyCode::PageUp => "pageup".to_string(),
+        KeyCode::PageDown => "pagedown".to_string(),
+        KeyCode::Up => "up".to_string(),
+        KeyCode::Down => "down".to_string(),
+        KeyCode::Left => "left".to_string(),
+        KeyCode::Right => "right".to_string(),
+        _ => return None,
+    })
+}
+
+fn parse_code(text: &str) -> Result<KeyCode> {
+    if let Some(hex) = text.strip_prefix("char:") {
+        let value = u32::from_str_radix(hex, 16).context("bad char codepoint")?;
+        let c = char::from_u32(value).context("invalid char codepoint")?;
+        return Ok(KeyCode::Char(c));
+    }
+    if let Some(n) = text.strip_prefix("f:") {
+        return Ok(KeyCode::F(n.parse().context("bad function key")?));
+    }
+    Ok(match text {
+        "enter" => KeyCode::Enter,
+        "tab" => KeyCode::Tab,
+        "backtab" => KeyCode::BackTab,
+        "esc" => KeyCode::Esc,
+        "backspace" => KeyCode::Backspace,
+        "delete" => KeyCode::Delete,
+        "insert" => KeyCode::Insert,
+        "home" => KeyCode::Home,
+        "end" => KeyCode::End,
+        "pageup" => KeyCode::PageUp,
+        "pagedown" => KeyCode::PageDown,
+        "up" => KeyCode::Up,
+        "down" => KeyCode::Down,
+        "left" => KeyCode::Left,
+        "right" => KeyCode::Right,
+        other => bail!("unknown key code {other:?}"),
+    })
+}
+
+fn mouse_kind_token(kind: MouseEventKind) -> Option<&'static str> {
+    Some(match kind {
+        MouseEventKind::Down(MouseButton::Left) => "down-left",
+        MouseEventKind::Down(MouseButton::Right) => "down-right",
+        MouseEventKind::Down(MouseButton::Middle) => "down-middle",
+        MouseEventKind::Up(MouseButton::Left) => "up-left",
+        MouseEventKind::Up(MouseButton::Right) => "up-right",
+        MouseEventKind::Up(MouseButton::Middle) => "up-middle",
+        MouseEventKind::Drag(MouseButton::Left) => "drag-left",
+        MouseEventKind::Moved => "moved",
+        MouseEventKind::ScrollUp => "scroll-up",
+        MouseEventKind::ScrollDown => "scroll-down",
+        _ => return None,
+    })
+}
+
+fn parse_mouse_kind(text: &str) -> Result<MouseEventKind> {
+    Ok(match text {
+        "down-left" => MouseEventKind::Down(MouseButton::Left),
+        "down-right" => MouseEventKind::Down(MouseButton::Right),
+        "down-middle" => MouseEventKind::Down(MouseButton::Middle),
+        "up-left" => MouseEventKind::Up(MouseButton::Left),
+        "up-right" => MouseEventKind::Up(MouseButton::Right),
+        "up-middle" => MouseEventKind::Up(MouseButton::Middle),
+        "drag-left" => MouseEventKind::Drag(MouseButton::Left),
+        "moved" => MouseEventKind::Moved,
+        "scroll-up" => MouseEventKind::ScrollUp,
+        "scroll-down" => MouseEventKind::ScrollDown,
+        other => bail!("unknown mouse kind {other:?}"),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn events_round_trip_through_the_log_format() {
+        let events = vec![
+            Event::Key(KeyEvent::new(
+                KeyCode::Char('你'),
+                KeyModifiers::CONTROL | KeyModifiers::ALT,
+            )),
+            Event::Key(KeyEvent::new(KeyCode::F(5), KeyModifiers::empty())),
+            Event::Mouse(MouseEvent {
+                kind: MouseEventKind::Down(MouseButton::Left),
+                column: 12,
+                row: 3,
+                modifiers: KeyModifiers::ALT,
+            }),
+            Event::Resize(120, 40),
+        ];
+        for event in &events {
+            let line = serialize(event).unwrap();
+            assert_eq!(&parse(&line).unwrap(), event);
+        }
+        assert!(parse("key none char:110000").is_err());
+        assert!(parse("mouse down-left 1").is_err());
+    }
+}
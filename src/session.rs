@@ -0,0 +1,79 @@
+//! Workspace session persistence.
+//!
+//! On exit the open buffers, cursor positions, pane layout, and tree
+//! expansion state are written to `.clide/session.json` inside the
+//! workspace, and restored on the next launch of the same workspace.
+
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+
+/// One previously open buffer. Paths are stored relative to the
+/// workspace root where possible so the file survives a moved checkout.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SessionFile {
+    pub path: PathBuf,
+    pub cursor_line: usize,
+    pub cursor_col: usize,
+    pub scroll_line: usize,
+    /// "Reopen with Encoding…" choice to re-apply, by stable name.
+    #[serde(default)]
+    pub encoding: Option<String>,
+}
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+pub struct SessionLayout {
+    pub tree_ratio: u16,
+    pub agent_ratio: u16,
+    pub terminal_ratio: u16,
+    pub show_tree: bool,
+    pub show_terminal: bool,
+    pub show_agent: bool,
+    pub show_git: bool,
+}
+
+/// Terminal pane state persisted when `[terminal] persist = true`: the
+/// scrollback tail, input history, and working directory. The running
+/// process itself is not kept alive — only what was on screen.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SessionTerminal {
+    pub lines: Vec<String>,
+    pub history: Vec<String>,
+    pub cwd: PathBuf,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Session {
+    pub open_files: Vec<SessionFile>,
+    /// Index into `open_files` of the buffer that was active.
+    pub active: usize,
+    pub layout: SessionLayout,
+    pub expanded_dirs: Vec<PathBuf>,
+    pub show_hidden: bool,
+    /// Present only when `[terminal] persist = true` was set at save.
+    #[serde(default)]
+    pub terminal: Option<SessionTerminal>,
+}
+
+fn session_path(root: &Path) -> PathBuf {
+    root.join(".clide").join("session.json")
+}
+
+/// Load the session saved for this workspace, if any. A file that exists
+/// but does not parse (e.g. from an older version) is treated as absent.
+pub fn load(root: &Path) -> Option<Session> {
+    let text = fs::read_to_string(session_path(root)).ok()?;
+    serde_json::from_str(&text).ok()
+}
+
+pub fn save(root: &Path, session: &Session) -> Result<()> {
+    let path = session_path(root);
+    if let Some(dir) = path.parent() {
+        fs::create_dir_all(dir)
+            .with_context(|| format!("failed to create {}", dir.display()))?;
+    }
+    let text = serde_json::to_string_pretty(session)?;
+    fs::write(&path, text).with_context(|| format!("failed to write {}", path.display()))
+}
@@ -0,0 +1,214 @@
+//! Background task management: typed handles, cancellation, progress.
+//!
+//! Subsystems used to spawn ad-hoc threads with their own event
+//! variants. The [`TaskManager`] centralizes that: each task gets an id,
+//! a cancellation token, and a [`TaskContext`] for progress reports; the
+//! result comes back to the main loop as one typed [`TaskOutcome`] that
+//! [`crate::app::App`] reduces into state.
+
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+
+use crate::editor::Encoding;
+use crate::event::{AppEvent, AppEventSender};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TaskId(u64);
+
+/// What a task is doing, for coalescing and status display.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TaskKind {
+    FileLoad,
+    FileSave,
+    GitRefresh,
+    TreeScan,
+    RagIndex,
+    CargoDiagnostics,
+}
+
+/// Shared cancellation flag; the task polls it at natural checkpoints.
+#[derive(Clone, Default)]
+pub struct CancelToken(Arc<AtomicBool>);
+
+impl CancelToken {
+    pub fn cancel(&self) {
+        self.0.store(true, Ordering::Relaxed);
+    }
+
+    pub fn is_cancelled(&self) -> bool {
+        self.0.load(Ordering::Relaxed)
+    }
+}
+
+/// The main loop's record of one running task.
+pub struct TaskHandle {
+    pub id: TaskId,
+    pub kind: TaskKind,
+    /// Short human label for progress messages, e.g. a file name.
+    pub label: String,
+    pub token: CancelToken,
+    pub progress: Option<(u64, u64)>,
+}
+
+/// Handed to the task body: progress reporting plus the cancel flag.
+pub struct TaskContext {
+    id: TaskId,
+    token: CancelToken,
+    tx: AppEventSender,
+}
+
+impl TaskContext {
+    pub fn is_cancelled(&self) -> bool {
+        self.token.is_cancelled()
+    }
+
+    pub fn progress(&self, done: u64, total: u64) {
+        let _ = self.tx.send(AppEvent::Task(TaskEvent::Progress {
+            id: self.id,
+            done,
+            total,
+        }));
+    }
+}
+
+/// Cross-thread event from a running task.
+pub enum TaskEvent {
+    Progress { id: TaskId, done: u64, total: u64 },
+    Done { id: TaskId, outcome: TaskOutcome },
+}
+
+/// The typed result of a finished task, reduced into app state by
+/// `App::on_task_event`.
+pub enum TaskOutcome {
+    /// The task observed its cancellation token and stopped early.
+    Cancelled,
+    /// `Ok(None)` means the load observed its token and stopped early.
+    FileLoaded {
+        path: PathBuf,
+        result: anyhow::Result<Option<(String, Encoding)>>,
+    },
+    FileSaved {
+        path: PathBuf,
+        result: anyhow::Result<()>,
+    },
+    GitRefreshed {
+        branch: Option<String>,
+        entries: Vec<crate::git::GitEntry>,
+    },
+    TreeScanned {
+        entries: Vec<crate::workspace::TreeEntry>,
+    },
+    RagIndexed {
+        index: crate::agent::rag::WorkspaceIndex,
+    },
+    /// Diagnostics parsed from a `cargo … --message-format=json` run;
+    /// `Err` means cargo itself could not be started.
+    CargoDiagnostics {
+        command: String,
+        result: anyhow::Result<
+            std::collections::HashMap<PathBuf, Vec<crate::lsp::types::Diagnostic>>,
+        >,
+    },
+}
+
+/// Owns the handles of every running background task.
+#[derive(Default)]
+pub struct TaskManager {
+    next_id: u64,
+    tasks: Vec<TaskHandle>,
+}
+
+impl TaskManager {
+    /// Run `work` on a background thread; its outcome is delivered as an
+    /// [`AppEvent::Task`] and the handle is kept until [`finish`] is
+    /// called for it.
+    ///
+    /// [`finish`]: TaskManager::finish
+    pub fn spawn(
+        &mut self,
+        kind: TaskKind,
+        label: impl Into<String>,
+        tx: &AppEventSender,
+        work: impl FnOnce(&TaskContext) -> TaskOutcome + Send + 'static,
+    ) -> TaskId {
+        self.next_id += 1;
+        let id = TaskId(self.next_id);
+        let token = CancelToken::default();
+        self.tasks.push(TaskHandle {
+            id,
+            kind,
+            label: label.into(),
+            token: token.clone(),
+            progress: None,
+        });
+        let ctx = TaskContext {
+            id,
+            token,
+            tx: tx.clone(),
+        };
+        std::thread::spawn(move || {
+            let outcome = work(&ctx);
+            let _ = ctx.tx.send(AppEvent::Task(TaskEvent::Done { id, outcome }));
+        });
+        id
+    }
+
+    pub fn running(&self) -> &[TaskHandle] {
+        &self.tasks
+    }
+
+    pub fn is_running(&self, kind: TaskKind) -> bool {
+        self.tasks.iter().any(|task| task.kind == kind)
+    }
+
+    pub fn on_progress(&mut self, id: TaskId, done: u64, total: u64) -> Option<&TaskHandle> {
+        let task = self.tasks.iter_mut().find(|task| task.id == id)?;
+        task.progress = Some((done, total));
+        Some(task)
+    }
+
+    /// Drop the handle of a finished task, returning it for the reducer.
+    pub fn finish(&mut self, id: TaskId) -> Option<TaskHandle> {
+        let at = self.tasks.iter().position(|task| task.id == id)?;
+        Some(self.tasks.remove(at))
+    }
+
+    /// Flag every running task as cancelled; returns how many were told.
+    pub fn cancel_all(&mut self) -> usize {
+        for task in &self.tasks {
+            task.token.cancel();
+        }
+        self.tasks.len()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn cancel_token_is_shared() {
+        let token = CancelToken::default();
+        let seen = token.clone();
+        assert!(!seen.is_cancelled());
+        token.cancel();
+        assert!(seen.is_cancelled());
+    }
+
+    #[test]
+    fn spawn_delivers_outcome_and_finish_drops_handle() {
+        let (tx, rx) = crate::event::channel();
+        let mut tasks = TaskManager::default();
+        let id = tasks.spawn(TaskKind::GitRefresh, "git", &tx, |_ctx| {
+            TaskOutcome::Cancelled
+        });
+        assert!(tasks.is_running(TaskKind::GitRefresh));
+        match rx.recv().expect("task event") {
+            AppEvent::Task(TaskEvent::Done { id: done, .. }) => assert_eq!(done, id),
+            _ => panic!("expected task completion"),
+        }
+        assert!(tasks.finish(id).is_some());
+        assert!(!tasks.is_running(TaskKind::GitRefresh));
+    }
+}
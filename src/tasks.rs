@@ -0,0 +1,209 @@
+//! Project task discovery: cargo targets, npm scripts, Makefile
+//! targets, and user-defined entries from `.clide/tasks.toml`.
+//!
+//! Discovered tasks run in the terminal pane; the app reports the exit
+//! status in the status bar when the process finishes.
+
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use crate::lsp::types::{Diagnostic, DiagnosticSeverity, LspPosition, LspRange};
+
+/// One runnable project task.
+#[derive(Debug, Clone)]
+pub struct Task {
+    pub name: String,
+    pub command: String,
+    /// Where the task came from (`cargo`, `npm`, `make`, `tasks.toml`).
+    pub source: &'static str,
+}
+
+/// Everything runnable in the workspace, user-defined entries first.
+pub fn discover(root: &Path) -> Vec<Task> {
+    let mut tasks = Vec::new();
+    if let Ok(text) = fs::read_to_string(root.join(".clide").join("tasks.toml")) {
+        for (name, command) in toml_tasks(&text) {
+            tasks.push(Task {
+                name,
+                command,
+                source: "tasks.toml",
+            });
+        }
+    }
+    if root.join("Cargo.toml").is_file() {
+        for name in ["build", "test", "clippy", "fmt", "run"] {
+            tasks.push(Task {
+                name: name.to_string(),
+                command: format!("cargo {name}"),
+                source: "cargo",
+            });
+        }
+    }
+    if let Ok(text) = fs::read_to_string(root.join("package.json")) {
+        for name in npm_scripts(&text) {
+            tasks.push(Task {
+                command: format!("npm run {name}"),
+                name,
+                source: "npm",
+            });
+        }
+    }
+    if let Ok(text) = fs::read_to_string(root.join("Makefile")) {
+        for name in make_targets(&text) {
+            tasks.push(Task {
+                command: format!("make {name}"),
+                name,
+                source: "make",
+            });
+        }
+    }
+    tasks
+}
+
+/// The `[tasks]` table of `.clide/tasks.toml`: `name = "command"`.
+fn toml_tasks(text: &str) -> Vec<(String, String)> {
+    #[derive(Default, serde::Deserialize)]
+    struct TasksFile {
+        #[serde(default)]
+        tasks: HashMap<String, String>,
+    }
+    let parsed: TasksFile = toml::from_str(text).unwrap_or_default();
+    let mut tasks: Vec<(String, String)> = parsed.tasks.into_iter().collect();
+    tasks.sort();
+    tasks
+}
+
+/// Script names from a `package.json`.
+fn npm_scripts(text: &str) -> Vec<String> {
+    let Ok(value) = serde_json::from_str::<serde_json::Value>(text) else {
+        return Vec::new();
+    };
+    let mut names: Vec<String> = value["scripts"]
+        .as_object()
+        .map(|scripts| scripts.keys().cloned().collect())
+        .unwrap_or_default();
+    names.sort();
+    names
+}
+
+/// Plain targets from a Makefile: `name:` at the start of a line,
+/// skipping pattern rules, variables, and special `.PHONY`-style names.
+fn make_targets(text: &str) -> Vec<String> {
+    let mut targets = Vec::new();
+    for line in text.lines() {
+        if line.starts_with(['\t', ' ', '.', '#']) {
+            continue;
+        }
+        let Some((name, _)) = line.split_once(':') else {
+            continue;
+        };
+        let name = name.trim();
+        if name.is_empty()
+            || name.contains(['%', '$', '=', ' '])
+            || targets.iter().any(|t| t == name)
+        {
+            continue;
+        }
+        targets.push(name.to_string());
+    }
+    targets
+}
+
+/// Parse `cargo … --message-format=json` output into the same
+/// per-file diagnostics the LSP client produces, so compiler errors
+/// reach the Problems panel and gutter without a language server.
+/// Relative span paths are resolved against `root`.
+pub fn cargo_diagnostics(root: &Path, output: &str) -> HashMap<PathBuf, Vec<Diagnostic>> {
+    let mut diagnostics: HashMap<PathBuf, Vec<Diagnostic>> = HashMap::new();
+    for line in output.lines() {
+        let Ok(value) = serde_json::from_str::<serde_json::Value>(line) else {
+            continue;
+        };
+        if value["reason"] != "compiler-message" {
+            continue;
+        }
+        let message = &value["message"];
+        let severity = match message["level"].as_str() {
+            Some("error") => DiagnosticSeverity::Error,
+            Some("warning") => DiagnosticSeverity::Warning,
+            // "note"/"help" arrive as children of the main message;
+            // top-level ones are summaries ("aborting due to…").
+            _ => continue,
+        };
+        let Some(spans) = message["spans"].as_array() else {
+            continue;
+        };
+        let Some(span) = spans
+            .iter()
+            .find(|s| s["is_primary"] == true)
+            .or_else(|| spans.first())
+        else {
+            continue;
+        };
+        let Some(file) = span["file_name"].as_str() else {
+            continue;
+        };
+        let path = if Path::new(file).is_absolute() {
+            PathBuf::from(file)
+        } else {
+            root.join(file)
+        };
+        let position = |line_key: &str, col_key: &str| LspPosition {
+            line: span[line_key].as_u64().unwrap_or(1).saturating_sub(1) as u32,
+            character: span[col_key].as_u64().unwrap_or(1).saturating_sub(1) as u32,
+        };
+        diagnostics.entry(path).or_default().push(Diagnostic {
+            range: LspRange {
+                start: position("line_start", "column_start"),
+                end: position("line_end", "column_end"),
+            },
+            severity,
+            message: message["message"].as_str().unwrap_or_default().to_string(),
+            code: message["code"]["code"].as_str().map(str::to_string),
+        });
+    }
+    diagnostics
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn make_targets_skip_patterns_and_recipes() {
+        let text = "CC = gcc\n.PHONY: all\nall: build\n\tcc main.c\nbuild:\n%.o: %.c\nall:\n";
+        assert_eq!(make_targets(text), vec!["all", "build"]);
+    }
+
+    #[test]
+    fn cargo_diagnostics_read_compiler_messages() {
+        let output = concat!(
+            r#"{"reason":"compiler-artifact","target":{"name":"clide"}}"#,
+            "\n",
+            r#"{"reason":"compiler-message","message":{"level":"error","message":"mismatched types","code":{"code":"E0308"},"spans":[{"file_name":"src/app.rs","is_primary":true,"line_start":10,"line_end":10,"column_start":5,"column_end":9}]}}"#,
+            "\n",
+            r#"{"reason":"compiler-message","message":{"level":"note","message":"aborting due to 1 previous error","spans":[]}}"#,
+            "\n",
+        );
+        let diagnostics = cargo_diagnostics(Path::new("/ws"), output);
+        assert_eq!(diagnostics.len(), 1);
+        let diags = &diagnostics[Path::new("/ws/src/app.rs")];
+        assert_eq!(diags.len(), 1);
+        assert_eq!(diags[0].severity, DiagnosticSeverity::Error);
+        assert_eq!(diags[0].code.as_deref(), Some("E0308"));
+        assert_eq!((diags[0].range.start.line, diags[0].range.start.character), (9, 4));
+    }
+
+    #[test]
+    fn toml_tasks_read_the_tasks_table() {
+        let text = "[tasks]\ncheck = \"cargo clippy\"\nbench = \"cargo bench\"\n";
+        assert_eq!(
+            toml_tasks(text),
+            vec![
+                ("bench".to_string(), "cargo bench".to_string()),
+                ("check".to_string(), "cargo clippy".to_string()),
+            ]
+        );
+    }
+}
@@ -0,0 +1,463 @@
+//! The terminal pane: a command runner streaming child process output.
+//!
+//! This is not a full PTY emulation; commands are run through the shell
+//! one at a time with stdout/stderr streamed into a scrollback buffer,
+//! which covers the build/test/grep workflows an IDE terminal is for.
+
+use std::io::{BufRead, BufReader};
+use std::path::Path;
+use std::process::{Child, Command, Stdio};
+
+use anyhow::{Context, Result};
+
+use crate::event::{AppEvent, AppEventSender};
+
+/// Keyboard-driven scrollback navigation: a cursor over the lines,
+/// line-wise selection, and `/` search with match highlighting.
+pub struct CopyMode {
+    /// Line index of the cursor in the scrollback.
+    pub cursor: usize,
+    /// Selection start set by `v`; yank covers anchor..=cursor.
+    pub anchor: Option<usize>,
+    /// The committed search query; its matches stay highlighted.
+    pub query: String,
+    /// `Some` while the `/` prompt is being typed.
+    pub search_input: Option<String>,
+}
+
+pub struct TerminalPane {
+    /// Scrollback lines, oldest first.
+    pub lines: Vec<String>,
+    pub scroll: usize,
+    /// Pinned to the bottom unless the user scrolled up.
+    pub follow: bool,
+    /// `Some` while the pane is in copy mode.
+    pub copy: Option<CopyMode>,
+    pub input: String,
+    pub history: Vec<String>,
+    history_pos: Option<usize>,
+    child: Option<Child>,
+    events: AppEventSender,
+    cwd: std::path::PathBuf,
+    /// Shell binary commands run through, per-OS default unless the
+    /// `[terminal]` config table overrides it.
+    shell: String,
+    /// Arguments placed before the command string (`-c`, `/C`, …).
+    shell_args: Vec<String>,
+    /// Extra environment variables from config, sorted by name.
+    env: Vec<(String, String)>,
+}
+
+impl TerminalPane {
+    pub fn new(cwd: std::path::PathBuf, events: AppEventSender) -> Self {
+        Self {
+            lines: Vec::new(),
+            scroll: 0,
+            follow: true,
+            copy: None,
+            input: String::new(),
+            history: Vec::new(),
+            history_pos: None,
+            child: None,
+            events,
+            cwd,
+            shell: default_shell(),
+            shell_args: default_shell_args(&default_shell()),
+            env: Vec::new(),
+        }
+    }
+
+    /// Apply the `[terminal]` config table: shell binary, argument
+    /// list, starting directory, and extra environment variables.
+    pub fn configure(&mut self, section: &crate::config::TerminalSection, root: &Path) {
+        if let Some(shell) = &section.shell {
+            self.shell = shell.clone();
+            self.shell_args = default_shell_args(shell);
+        }
+        if let Some(args) = &section.args {
+            self.shell_args = args.clone();
+        }
+        if let Some(cwd) = &section.cwd {
+            self.cwd = if cwd.is_absolute() {
+                cwd.clone()
+            } else {
+                root.join(cwd)
+            };
+        }
+        let mut env: Vec<(String, String)> = section
+            .env
+            .iter()
+            .map(|(name, value)| (name.clone(), value.clone()))
+            .collect();
+        env.sort();
+        self.env = env;
+    }
+
+    pub fn is_running(&self) -> bool {
+        self.child.is_some()
+    }
+
+    /// The directory new commands start in.
+    pub fn cwd(&self) -> &Path {
+        &self.cwd
+    }
+
+    /// Point new commands at a different directory; ignored unless it
+    /// exists (e.g. a persisted session whose directory was deleted).
+    pub fn set_cwd(&mut self, cwd: std::path::PathBuf) {
+        if cwd.is_dir() {
+            self.cwd = cwd;
+        }
+    }
+
+    /// Run the current input line through the shell.
+    pub fn run_input(&mut self) -> Result<()> {
+        let command = self.input.trim().to_string();
+        if command.is_empty() {
+            return Ok(());
+        }
+        self.history.push(command.clone());
+        self.history_pos = None;
+        self.input.clear();
+        self.lines.push(format!("$ {command}"));
+        self.spawn(&command)
+    }
+
+    /// Run a command as if it had been typed into the pane, without
+    /// touching the input line or history.
+    pub fn run_command(&mut self, command: &str) -> Result<()> {
+        let command = command.trim();
+        if command.is_empty() {
+            return Ok(());
+        }
+        self.lines.push(format!("$ {command}"));
+        self.spawn(command)
+    }
+
+    fn spawn(&mut self, command: &str) -> Result<()> {
+        if self.child.is_some() {
+            anyhow::bail!("a command is already running");
+        }
+        let mut child = Command::new(&self.shell)
+            .args(&self.shell_args)
+            .arg(command)
+            .envs(self.env.iter().map(|(name, value)| (name, value)))
+            .current_dir(&self.cwd)
+            .stdin(Stdio::null())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .spawn()
+            .context("failed to spawn shell")?;
+        let stdout = child.stdout.take().context("child has no stdout")?;
+        let stderr = child.stderr.take().context("child has no stderr")?;
+        let pipes: [Box<dyn std::io::Read + Send>; 2] = [Box::new(stdout), Box::new(stderr)];
+        for pipe in pipes {
+            let events = self.events.clone();
+            std::thread::spawn(move || {
+                let reader = BufReader::new(pipe);
+                for line in reader.lines() {
+                    let Ok(line) = line else { break };
+                    if events.send(AppEvent::TerminalOutput(line)).is_err() {
+                        return;
+                    }
+                }
+            });
+        }
+        self.child = Some(child);
+        Ok(())
+    }
+
+    /// Reap the child if it has finished; called once per frame. Returns
+    /// the exit code when a process was reaped this call (`None` inside
+    /// the `Some` means it was killed by a signal).
+    pub fn poll_exit(&mut self) -> Option<Option<i32>> {
+        let child = self.child.as_mut()?;
+        match child.try_wait() {
+            Ok(Some(status)) => {
+                self.child = None;
+                self.lines.push(match status.code() {
+                    Some(0) => "[process exited]".to_string(),
+                    Some(code) => format!("[process exited with code {code}]"),
+                    None => "[process killed]".to_string(),
+                });
+                Some(status.code())
+            }
+            Ok(None) => None,
+            Err(_) => {
+                self.child = None;
+                None
+            }
+        }
+    }
+
+    pub fn push_output(&mut self, line: String) {
+        self.lines.push(line);
+        if self.lines.len() > 10_000 {
+            let excess = self.lines.len() - 10_000;
+            self.lines.drain(..excess);
+        }
+    }
+
+    pub fn kill(&mut self) {
+        if let Some(child) = &mut self.child {
+            let _ = child.kill();
+        }
+    }
+
+    pub fn history_prev(&mut self) {
+        if self.history.is_empty() {
+            return;
+        }
+        let pos = match self.history_pos {
+            Some(0) => 0,
+            Some(p) => p - 1,
+            None => self.history.len() - 1,
+        };
+        self.history_pos = Some(pos);
+        self.input = self.history[pos].clone();
+    }
+
+    pub fn history_next(&mut self) {
+        let Some(pos) = self.history_pos else { return };
+        if pos + 1 < self.history.len() {
+            self.history_pos = Some(pos + 1);
+            self.input = self.history[pos + 1].clone();
+        } else {
+            self.history_pos = None;
+            self.input.clear();
+        }
+    }
+
+    /// Enter copy mode with the cursor on the newest line.
+    pub fn enter_copy_mode(&mut self) {
+        if self.lines.is_empty() {
+            return;
+        }
+        self.follow = false;
+        self.copy = Some(CopyMode {
+            cursor: self.lines.len() - 1,
+            anchor: None,
+            query: String::new(),
+            search_input: None,
+        });
+    }
+
+    /// Leave copy mode; output resumes tailing if the cursor was at
+    /// the bottom of the scrollback.
+    pub fn exit_copy_mode(&mut self) {
+        let at_bottom = self
+            .copy
+            .take()
+            .is_some_and(|mode| mode.cursor + 1 >= self.lines.len());
+        if at_bottom {
+            self.follow = true;
+        }
+    }
+
+    /// Move the copy-mode cursor, clamping to the scrollback and
+    /// scrolling so it stays inside the viewport.
+    pub fn copy_cursor_move(&mut self, delta: isize, viewport: usize) {
+        let last = self.lines.len().saturating_sub(1);
+        let Some(mode) = &mut self.copy else { return };
+        mode.cursor = mode.cursor.saturating_add_signed(delta).min(last);
+        let (cursor, viewport) = (mode.cursor, viewport.max(1));
+        if cursor < self.scroll {
+            self.scroll = cursor;
+        } else if cursor >= self.scroll + viewport {
+            self.scroll = cursor + 1 - viewport;
+        }
+    }
+
+    /// The inclusive line range selected in copy mode, if any.
+    pub fn copy_selection(&self) -> Option<(usize, usize)> {
+        let mode = self.copy.as_ref()?;
+        let anchor = mode.anchor?;
+        Some((anchor.min(mode.cursor), anchor.max(mode.cursor)))
+    }
+
+    /// The selected lines — or just the cursor line — joined with
+    /// newlines, ready for the clipboard.
+    pub fn yank(&self) -> Option<String> {
+        let cursor = self.copy.as_ref()?.cursor;
+        let (start, end) = self.copy_selection().unwrap_or((cursor, cursor));
+        Some(self.lines.get(start..=end)?.join("\n"))
+    }
+
+    /// Jump the cursor to the nearest line matching the active search
+    /// query, wrapping around the scrollback. Returns false when the
+    /// query is empty or matches nothing.
+    pub fn copy_search_step(&mut self, forward: bool, viewport: usize) -> bool {
+        let Some(mode) = &self.copy else { return false };
+        let Some(hit) = find_match(&self.lines, &mode.query, mode.cursor, forward) else {
+            return false;
+        };
+        if let Some(mode) = &mut self.copy {
+            mode.cursor = hit;
+        }
+        self.copy_cursor_move(0, viewport);
+        true
+    }
+
+    pub fn scroll_up(&mut self, amount: usize) {
+        self.follow = false;
+        self.scroll = self.scroll.saturating_sub(amount);
+    }
+
+    pub fn scroll_down(&mut self, amount: usize, viewport: usize) {
+        let max = self.lines.len().saturating_sub(viewport);
+        self.scroll = (self.scroll + amount).min(max);
+        if self.scroll >= max {
+            self.follow = true;
+        }
+    }
+}
+
+/// The per-OS default shell: `cmd` on Windows, `$SHELL` (falling back
+/// to `/bin/sh`) elsewhere.
+fn default_shell() -> String {
+    if cfg!(windows) {
+        "cmd".to_string()
+    } else {
+        std::env::var("SHELL").unwrap_or_else(|_| "/bin/sh".to_string())
+    }
+}
+
+/// The flag that makes a shell run one command string: `/C` for cmd,
+/// `-Command` for PowerShell, `-c` for POSIX shells.
+fn default_shell_args(program: &str) -> Vec<String> {
+    let name = Path::new(program)
+        .file_stem()
+        .and_then(|stem| stem.to_str())
+        .unwrap_or(program)
+        .to_ascii_lowercase();
+    match name.as_str() {
+        "cmd" => vec!["/C".to_string()],
+        "powershell" | "pwsh" => vec!["-Command".to_string()],
+        _ => vec!["-c".to_string()],
+    }
+}
+
+/// Find the nearest line containing `query` strictly after (forward)
+/// or before `from`, wrapping around the buffer.
+fn find_match(lines: &[String], query: &str, from: usize, forward: bool) -> Option<usize> {
+    let len = lines.len();
+    if len == 0 || query.is_empty() {
+        return None;
+    }
+    for step in 1..=len {
+        let idx = if forward {
+            (from + step) % len
+        } else {
+            (from + len - step) % len
+        };
+        if lines[idx].contains(query) {
+            return Some(idx);
+        }
+    }
+    None
+}
+
+/// A `path:line[:col]` location spotted in one output line, with the
+/// byte range the token occupies so it can be underlined.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct OutputLocation {
+    pub path: String,
+    /// 1-based, as compilers print them.
+    pub line: usize,
+    pub col: usize,
+    pub start: usize,
+    pub end: usize,
+}
+
+/// Find the first `path:line[:col]` token in a line of compiler or test
+/// output (`src/app.rs:10:5`, ` --> tests/x.rs:3`). The path part must
+/// look like a file (contain `/` or `.`) so timestamps don't match.
+pub fn find_location(text: &str) -> Option<OutputLocation> {
+    let mut token_start = None;
+    for (i, c) in text.char_indices().chain([(text.len(), ' ')]) {
+        if c.is_whitespace() {
+            if let Some(start) = token_start.take() {
+                let token: &str = &text[start..i];
+                let trimmed = token.trim_matches(|c: char| "()[],;'\"`".contains(c));
+                let offset = start + (token.len() - token.trim_start_matches(|c: char| "()[],;'\"`".contains(c)).len());
+                if let Some(loc) = parse_location(trimmed, offset) {
+                    return Some(loc);
+                }
+            }
+        } else if token_start.is_none() {
+            token_start = Some(i);
+        }
+    }
+    None
+}
+
+/// Parse one whitespace-delimited token as `path:line[:col]`.
+fn parse_location(token: &str, offset: usize) -> Option<OutputLocation> {
+    let parts: Vec<&str> = token.split(':').collect();
+    if parts.len() < 2 {
+        return None;
+    }
+    let path = parts[0];
+    if path.is_empty() || (!path.contains('/') && !path.contains('.')) {
+        return None;
+    }
+    let line: usize = parts[1].parse().ok().filter(|&l| l > 0)?;
+    let col = parts
+        .get(2)
+        .and_then(|c| c.parse().ok())
+        .filter(|&c| c > 0)
+        .unwrap_or(1);
+    let matched_len = if parts.len() >= 3 && parts[2].parse::<usize>().is_ok() {
+        path.len() + 1 + parts[1].len() + 1 + parts[2].len()
+    } else {
+        path.len() + 1 + parts[1].len()
+    };
+    Some(OutputLocation {
+        path: path.to_string(),
+        line,
+        col,
+        start: offset,
+        end: offset + matched_len,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn finds_cargo_style_locations() {
+        let loc = find_location("  --> src/app.rs:10:5").unwrap();
+        assert_eq!(loc.path, "src/app.rs");
+        assert_eq!((loc.line, loc.col), (10, 5));
+        assert_eq!(&"  --> src/app.rs:10:5"[loc.start..loc.end], "src/app.rs:10:5");
+    }
+
+    #[test]
+    fn shell_args_match_the_shell_flavor() {
+        assert_eq!(default_shell_args("/bin/zsh"), vec!["-c"]);
+        assert_eq!(default_shell_args("cmd.exe"), vec!["/C"]);
+        assert_eq!(default_shell_args("pwsh.exe"), vec!["-Command"]);
+    }
+
+    #[test]
+    fn find_match_wraps_in_both_directions() {
+        let lines: Vec<String> = ["error: a", "ok", "error: b"]
+            .iter()
+            .map(|s| s.to_string())
+            .collect();
+        assert_eq!(find_match(&lines, "error", 0, true), Some(2));
+        assert_eq!(find_match(&lines, "error", 2, true), Some(0));
+        assert_eq!(find_match(&lines, "error", 0, false), Some(2));
+        assert_eq!(find_match(&lines, "missing", 0, true), None);
+    }
+
+    #[test]
+    fn ignores_timestamps_and_plain_text() {
+        assert_eq!(find_location("12:30:45 build started"), None);
+        assert_eq!(find_location("no locations here"), None);
+        let loc = find_location("at tests/it.rs:3, assertion failed").unwrap();
+        assert_eq!(loc.path, "tests/it.rs");
+        assert_eq!(loc.line, 3);
+    }
+}
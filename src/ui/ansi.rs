@@ -0,0 +1,171 @@
+//! Minimal ANSI escape handling for log files opened in the editor.
+//!
+//! Only what log output actually contains is supported: CSI sequences
+//! (colors and cursor movement) and OSC sequences are recognized, SGR
+//! foreground colors and bold are mapped to styles, everything else is
+//! dropped.
+
+use ratatui::style::{Color, Modifier, Style};
+use ratatui::text::Span;
+
+/// Whether the text contains any escape sequence worth offering to strip.
+pub fn contains_ansi(text: &str) -> bool {
+    text.contains('\u{1b}')
+}
+
+/// Remove all CSI and OSC escape sequences, keeping the visible text.
+pub fn strip(text: &str) -> String {
+    let mut out = String::with_capacity(text.len());
+    let mut chars = text.chars().peekable();
+    while let Some(c) = chars.next() {
+        if c != '\u{1b}' {
+            out.push(c);
+            continue;
+        }
+        match chars.peek() {
+            Some('[') => {
+                chars.next();
+                // CSI: parameter bytes, then one final byte in `@`..=`~`.
+                for c in chars.by_ref() {
+                    if ('\u{40}'..='\u{7e}').contains(&c) {
+                        break;
+                    }
+                }
+            }
+            Some(']') => {
+                chars.next();
+                // OSC: terminated by BEL or ST.
+                while let Some(c) = chars.next() {
+                    if c == '\u{7}' {
+                        break;
+                    }
+                    if c == '\u{1b}' && chars.peek() == Some(&'\\') {
+                        chars.next();
+                        break;
+                    }
+                }
+            }
+            _ => {
+                chars.next();
+            }
+        }
+    }
+    out
+}
+
+/// Parse a line into styled spans, interpreting SGR color codes and
+/// discarding other escapes. At most `max_chars` visible characters are
+/// emitted.
+pub fn parse_spans(line: &str, max_chars: usize) -> Vec<Span<'static>> {
+    let mut spans = Vec::new();
+    let mut current = String::new();
+    let mut style = Style::default();
+    let mut emitted = 0usize;
+    let mut chars = line.chars().peekable();
+    while let Some(c) = chars.next() {
+        if c != '\u{1b}' {
+            if emitted < max_chars {
+                current.push(c);
+                emitted += 1;
+            }
+            continue;
+        }
+        if chars.peek() != Some(&'[') {
+            // Skip the introducer of a non-CSI sequence.
+            chars.next();
+            continue;
+        }
+        chars.next();
+        let mut params = String::new();
+        let mut terminator = ' ';
+        for c in chars.by_ref() {
+            if ('\u{40}'..='\u{7e}').contains(&c) {
+                terminator = c;
+                break;
+            }
+            params.push(c);
+        }
+        if terminator != 'm' {
+            continue;
+        }
+        let next = apply_sgr(style, &params);
+        if next != style {
+            if !current.is_empty() {
+                spans.push(Span::styled(std::mem::take(&mut current), style));
+            }
+            style = next;
+        }
+    }
+    if !current.is_empty() {
+        spans.push(Span::styled(current, style));
+    }
+    spans
+}
+
+fn apply_sgr(mut style: Style, params: &str) -> Style {
+    for code in params.split(';') {
+        let code: u8 = match code.parse() {
+            Ok(n) => n,
+            Err(_) if code.is_empty() => 0,
+            Err(_) => continue,
+        };
+        style = match code {
+            0 => Style::default(),
+            1 => style.add_modifier(Modifier::BOLD),
+            30..=37 => style.fg(basic_color(code - 30)),
+            39 => style.fg(Color::Reset),
+            90..=97 => style.fg(bright_color(code - 90)),
+            _ => style,
+        };
+    }
+    style
+}
+
+fn basic_color(n: u8) -> Color {
+    [
+        Color::Black,
+        Color::Red,
+        Color::Green,
+        Color::Yellow,
+        Color::Blue,
+        Color::Magenta,
+        Color::Cyan,
+        Color::Gray,
+    ][n as usize]
+}
+
+fn bright_color(n: u8) -> Color {
+    [
+        Color::DarkGray,
+        Color::LightRed,
+        Color::LightGreen,
+        Color::LightYellow,
+        Color::LightBlue,
+        Color::LightMagenta,
+        Color::LightCyan,
+        Color::White,
+    ][n as usize]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn strips_csi_and_osc_sequences() {
+        let text = "\u{1b}[1;31merror\u{1b}[0m: bad \u{1b}]0;title\u{7}thing";
+        assert_eq!(strip(text), "error: bad thing");
+        assert!(contains_ansi(text));
+        assert!(!contains_ansi("plain"));
+    }
+
+    #[test]
+    fn sgr_colors_map_to_styled_spans() {
+        let spans = parse_spans("\u{1b}[31mred\u{1b}[0m plain", 80);
+        assert_eq!(spans.len(), 2);
+        assert_eq!(spans[0].content.as_ref(), "red");
+        assert_eq!(spans[0].style.fg, Some(Color::Red));
+        assert_eq!(spans[1].content.as_ref(), " plain");
+        assert_eq!(spans[1].style.fg, None);
+    }
+}
@@ -0,0 +1,129 @@
+//! OSC 8 hyperlinks in rendered output.
+//!
+//! ratatui has no native hyperlink support, so links are patched into
+//! the cell buffer after a line is rendered: every cell of the link gets
+//! the OSC 8 open/close sequence embedded around its symbol, which the
+//! backend writes through verbatim. Embedding per cell keeps links
+//! intact across partial redraws. Terminals without OSC 8 ignore the
+//! sequence, and the internal Ctrl+Click handling still applies.
+
+use ratatui::buffer::Buffer;
+use ratatui::layout::Rect;
+
+/// A link found in a rendered line: the char offset range and target URL.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Link {
+    pub start: usize,
+    pub end: usize,
+    pub url: String,
+}
+
+/// Detect `http(s)://` URLs and workspace-looking `path/file.ext:line`
+/// references in a line of output.
+pub fn detect_links(text: &str) -> Vec<Link> {
+    let chars: Vec<char> = text.chars().collect();
+    let mut out = Vec::new();
+    for (begin, token) in split_with_offsets(&chars) {
+        let token_str: String = token.iter().collect();
+        let trimmed = token_str.trim_end_matches(['.', ',', ';', ')', ']', '>']);
+        let end = begin + trimmed.chars().count();
+        if trimmed.starts_with("http://") || trimmed.starts_with("https://") {
+            out.push(Link {
+                start: begin,
+                end,
+                url: trimmed.to_string(),
+            });
+        } else if let Some(url) = file_line_link(trimmed) {
+            out.push(Link {
+                start: begin,
+                end,
+                url,
+            });
+        }
+    }
+    out
+}
+
+fn split_with_offsets(chars: &[char]) -> Vec<(usize, &[char])> {
+    let mut out = Vec::new();
+    let mut begin = 0;
+    for (i, c) in chars.iter().enumerate() {
+        if c.is_whitespace() {
+            if i > begin {
+                out.push((begin, &chars[begin..i]));
+            }
+            begin = i + 1;
+        }
+    }
+    if chars.len() > begin {
+        out.push((begin, &chars[begin..]));
+    }
+    out
+}
+
+/// `src/app.rs:42` (optionally `:42:7`) becomes a `file://` URL.
+fn file_line_link(token: &str) -> Option<String> {
+    let mut parts = token.splitn(3, ':');
+    let path = parts.next()?;
+    let line = parts.next()?;
+    if path.is_empty() || !line.bytes().all(|b| b.is_ascii_digit()) {
+        return None;
+    }
+    // Require a file-ish shape so ordinary prose is not linked.
+    if !path.contains('.') && !path.contains('/') {
+        return None;
+    }
+    Some(format!("file://{path}#{line}"))
+}
+
+/// Link for a compiler diagnostic code, e.g. `E0308` to the rustc error
+/// index.
+pub fn diagnostic_code_link(code: &str) -> Option<String> {
+    let digits = code.strip_prefix('E')?;
+    if digits.len() == 4 && digits.bytes().all(|b| b.is_ascii_digit()) {
+        Some(format!("https://doc.rust-lang.org/error_codes/{code}.html"))
+    } else {
+        None
+    }
+}
+
+/// Wrap the cells of `row` between char offsets `start..end` (relative
+/// to `area.x`) in an OSC 8 hyperlink.
+pub fn apply_to_row(buffer: &mut Buffer, area: Rect, row: u16, link: &Link) {
+    if row >= area.y + area.height {
+        return;
+    }
+    for offset in link.start..link.end {
+        let x = area.x + offset as u16;
+        if x >= area.x + area.width {
+            break;
+        }
+        if let Some(cell) = buffer.cell_mut((x, row)) {
+            let symbol = cell.symbol().to_string();
+            cell.set_symbol(&format!(
+                "\x1b]8;;{}\x1b\\{symbol}\x1b]8;;\x1b\\",
+                link.url
+            ));
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn detects_urls_and_file_references() {
+        let links = detect_links("see https://example.com/a, then src/app.rs:42:7 fails");
+        assert_eq!(links.len(), 2);
+        assert_eq!(links[0].url, "https://example.com/a");
+        assert_eq!(links[0].start, 4);
+        assert_eq!(links[1].url, "file://src/app.rs#42");
+        assert!(detect_links("took 3:30 minutes").is_empty());
+        assert_eq!(
+            diagnostic_code_link("E0308").as_deref(),
+            Some("https://doc.rust-lang.org/error_codes/E0308.html")
+        );
+        assert_eq!(diagnostic_code_link("W123"), None);
+    }
+}
@@ -0,0 +1,179 @@
+//! Inline image rendering for the agent panel.
+//!
+//! Terminals that speak the kitty graphics protocol or the iTerm2 image
+//! protocol can draw raster images on top of the cell grid. The renderer
+//! reserves a region at the bottom of the agent history for the most
+//! recent image and the main loop emits the escape sequence after each
+//! frame; everywhere else images fall back to a text reference.
+
+use std::fmt::Write as _;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+
+use anyhow::{Context, Result};
+use base64::Engine;
+use crossterm::cursor::MoveTo;
+use ratatui::layout::Rect;
+
+/// The kitty protocol transmits base64 payloads in chunks of this size.
+const KITTY_CHUNK: usize = 4096;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ImageProtocol {
+    Kitty,
+    Iterm2,
+    None,
+}
+
+impl ImageProtocol {
+    /// Detect protocol support from the environment. Kitty sets
+    /// `KITTY_WINDOW_ID` and a `TERM` containing "kitty"; iTerm2 and
+    /// WezTerm advertise through `TERM_PROGRAM`/`LC_TERMINAL`.
+    pub fn detect() -> Self {
+        let term = std::env::var("TERM").unwrap_or_default();
+        if term.contains("kitty") || std::env::var_os("KITTY_WINDOW_ID").is_some() {
+            return ImageProtocol::Kitty;
+        }
+        let program = std::env::var("TERM_PROGRAM").unwrap_or_default();
+        if program == "iTerm.app"
+            || program == "WezTerm"
+            || std::env::var("LC_TERMINAL").as_deref() == Ok("iTerm2")
+        {
+            return ImageProtocol::Iterm2;
+        }
+        ImageProtocol::None
+    }
+
+    /// Whether this protocol can display the given file inline. Kitty's
+    /// direct-transmit path only accepts PNG data; iTerm2 takes any
+    /// common raster format.
+    pub fn supports(self, path: &Path) -> bool {
+        let ext = path
+            .extension()
+            .and_then(|e| e.to_str())
+            .map(str::to_ascii_lowercase);
+        match self {
+            ImageProtocol::Kitty => ext.as_deref() == Some("png"),
+            ImageProtocol::Iterm2 => matches!(
+                ext.as_deref(),
+                Some("png") | Some("jpg") | Some("jpeg") | Some("gif")
+            ),
+            ImageProtocol::None => false,
+        }
+    }
+}
+
+/// Local image paths referenced in a block of agent text: whitespace
+/// separated tokens with a raster-image extension, with surrounding
+/// markdown/punctuation trimmed.
+pub fn image_refs(text: &str) -> Vec<PathBuf> {
+    let mut out = Vec::new();
+    for token in text.split_whitespace() {
+        let token = token.trim_matches(|c| "()[]<>`'\",;:!".contains(c));
+        let path = Path::new(token);
+        let ext = path
+            .extension()
+            .and_then(|e| e.to_str())
+            .map(str::to_ascii_lowercase);
+        if matches!(
+            ext.as_deref(),
+            Some("png") | Some("jpg") | Some("jpeg") | Some("gif")
+        ) {
+            out.push(path.to_path_buf());
+        }
+    }
+    out
+}
+
+/// Emit the escape sequence that draws `path` at `area`, scaled to the
+/// cell rectangle by the terminal.
+pub fn emit_inline(
+    out: &mut impl Write,
+    protocol: ImageProtocol,
+    path: &Path,
+    area: Rect,
+) -> Result<()> {
+    let data =
+        std::fs::read(path).with_context(|| format!("failed to read {}", path.display()))?;
+    crossterm::queue!(out, MoveTo(area.x, area.y))?;
+    match protocol {
+        ImageProtocol::Kitty => out.write_all(kitty_sequence(&data, area).as_bytes())?,
+        ImageProtocol::Iterm2 => out.write_all(iterm2_sequence(&data, path, area).as_bytes())?,
+        ImageProtocol::None => {}
+    }
+    out.flush()?;
+    Ok(())
+}
+
+/// Remove previously transmitted images (kitty keeps them on a separate
+/// layer, so stale ones must be deleted explicitly).
+pub fn clear_inline(out: &mut impl Write, protocol: ImageProtocol) -> Result<()> {
+    if protocol == ImageProtocol::Kitty {
+        out.write_all(b"\x1b_Ga=d\x1b\\")?;
+        out.flush()?;
+    }
+    Ok(())
+}
+
+fn kitty_sequence(data: &[u8], area: Rect) -> String {
+    let payload = base64::engine::general_purpose::STANDARD.encode(data);
+    let chunks: Vec<&str> = payload
+        .as_bytes()
+        .chunks(KITTY_CHUNK)
+        .map(|c| std::str::from_utf8(c).expect("base64 is ascii"))
+        .collect();
+    let mut out = String::new();
+    for (i, chunk) in chunks.iter().enumerate() {
+        let more = if i + 1 < chunks.len() { 1 } else { 0 };
+        if i == 0 {
+            let _ = write!(
+                out,
+                "\x1b_Gf=100,a=T,c={},r={},m={more};{chunk}\x1b\\",
+                area.width, area.height
+            );
+        } else {
+            let _ = write!(out, "\x1b_Gm={more};{chunk}\x1b\\");
+        }
+    }
+    out
+}
+
+fn iterm2_sequence(data: &[u8], path: &Path, area: Rect) -> String {
+    let engine = &base64::engine::general_purpose::STANDARD;
+    let name = engine.encode(
+        path.file_name()
+            .map(|n| n.to_string_lossy().into_owned())
+            .unwrap_or_default(),
+    );
+    format!(
+        "\x1b]1337;File=name={name};size={};width={};height={};inline=1:{}\x07",
+        data.len(),
+        area.width,
+        area.height,
+        engine.encode(data)
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn image_refs_trims_markdown_punctuation() {
+        let text = "See (docs/diagram.png) and `shot.jpg`, but not notes.txt";
+        let refs = image_refs(text);
+        assert_eq!(
+            refs,
+            vec![PathBuf::from("docs/diagram.png"), PathBuf::from("shot.jpg")]
+        );
+    }
+
+    #[test]
+    fn kitty_sequence_chunks_large_payloads() {
+        let data = vec![0u8; KITTY_CHUNK * 2];
+        let area = Rect::new(0, 0, 40, 10);
+        let seq = kitty_sequence(&data, area);
+        assert!(seq.starts_with("\x1b_Gf=100,a=T,c=40,r=10,m=1;"));
+        assert!(seq.contains("\x1b_Gm=0;"));
+    }
+}
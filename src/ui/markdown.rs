@@ -0,0 +1,258 @@
+//! Minimal markdown styling for agent responses.
+//!
+//! This is not a full parser: it styles the constructs agents actually
+//! emit — headings, bold, inline code, bullet markers, and fenced code
+//! blocks with a small keyword-based highlight per language.
+
+use ratatui::style::{Modifier, Style};
+use ratatui::text::{Line, Span};
+
+use super::theme;
+
+/// The fenced code blocks of `text`, in order, without the fence lines.
+pub fn code_blocks(text: &str) -> Vec<String> {
+    let mut blocks = Vec::new();
+    let mut current: Option<String> = None;
+    for line in text.lines() {
+        if line.trim_start().starts_with("```") {
+            match current.take() {
+                Some(block) => blocks.push(block),
+                None => current = Some(String::new()),
+            }
+            continue;
+        }
+        if let Some(block) = &mut current {
+            block.push_str(line);
+            block.push('\n');
+        }
+    }
+    blocks
+}
+
+/// The fenced blocks of `text` tagged as shell (`sh`, `bash`, `shell`,
+/// `zsh`), without the fence lines — candidates for approval-gated
+/// execution in the terminal pane.
+pub fn shell_blocks(text: &str) -> Vec<String> {
+    let mut blocks = Vec::new();
+    let mut current: Option<(bool, String)> = None;
+    for line in text.lines() {
+        let trimmed = line.trim_start();
+        if let Some(rest) = trimmed.strip_prefix("```") {
+            match current.take() {
+                Some((true, block)) => blocks.push(block),
+                Some((false, _)) => {}
+                None => {
+                    let shell = matches!(rest.trim(), "sh" | "bash" | "shell" | "zsh");
+                    current = Some((shell, String::new()));
+                }
+            }
+            continue;
+        }
+        if let Some((_, block)) = &mut current {
+            block.push_str(line);
+            block.push('\n');
+        }
+    }
+    blocks
+}
+
+/// Style a markdown response into wrapped lines no wider than `width`.
+pub fn render(text: &str, width: usize) -> Vec<Line<'static>> {
+    let width = width.max(1);
+    let mut lines = Vec::new();
+    let mut fence_language: Option<String> = None;
+    for raw in text.lines() {
+        let trimmed = raw.trim_start();
+        if let Some(rest) = trimmed.strip_prefix("```") {
+            match fence_language.take() {
+                Some(_) => {}
+                None => fence_language = Some(rest.trim().to_string()),
+            }
+            lines.push(Line::from(Span::styled(
+                "───".to_string(),
+                Style::default().fg(theme::accent_dim()),
+            )));
+            continue;
+        }
+        if let Some(language) = &fence_language {
+            for segment in super::wrap_text(raw, width) {
+                lines.push(highlight_code_line(&segment, language));
+            }
+            continue;
+        }
+        if let Some(rest) = trimmed.strip_prefix('#') {
+            let title = rest.trim_start_matches('#').trim_start();
+            lines.push(Line::from(Span::styled(
+                title.to_string(),
+                Style::default()
+                    .fg(theme::accent())
+                    .add_modifier(Modifier::BOLD),
+            )));
+            continue;
+        }
+        for segment in super::wrap_text(raw, width) {
+            lines.push(Line::from(inline_spans(&segment)));
+        }
+    }
+    lines
+}
+
+/// Split a prose line into spans, toggling bold on `**` and inline-code
+/// style on backticks.
+fn inline_spans(text: &str) -> Vec<Span<'static>> {
+    let base = Style::default().fg(theme::agent_response());
+    let mut spans = Vec::new();
+    let mut current = String::new();
+    let mut bold = false;
+    let mut code = false;
+    let mut chars = text.chars().peekable();
+    let flush = |spans: &mut Vec<Span<'static>>, current: &mut String, bold: bool, code: bool| {
+        if current.is_empty() {
+            return;
+        }
+        let style = if code {
+            Style::default().fg(theme::accent()).bg(theme::cursor_line_bg())
+        } else if bold {
+            base.add_modifier(Modifier::BOLD)
+        } else {
+            base
+        };
+        spans.push(Span::styled(std::mem::take(current), style));
+    };
+    while let Some(c) = chars.next() {
+        match c {
+            '`' => {
+                flush(&mut spans, &mut current, bold, code);
+                code = !code;
+            }
+            '*' if !code && chars.peek() == Some(&'*') => {
+                chars.next();
+                flush(&mut spans, &mut current, bold, code);
+                bold = !bold;
+            }
+            _ => current.push(c),
+        }
+    }
+    flush(&mut spans, &mut current, bold, code);
+    if spans.is_empty() {
+        spans.push(Span::styled(String::new(), base));
+    }
+    spans
+}
+
+fn language_keywords(language: &str) -> &'static [&'static str] {
+    match language {
+        "rust" | "rs" => &[
+            "fn", "let", "mut", "pub", "impl", "struct", "enum", "trait", "match", "if", "else",
+            "for", "while", "loop", "return", "use", "mod", "self", "Self",
+        ],
+        "python" | "py" => &[
+            "def", "class", "return", "if", "elif", "else", "for", "while", "import", "from",
+            "with", "as", "try", "except", "lambda", "self",
+        ],
+        "javascript" | "typescript" | "js" | "ts" => &[
+            "function", "const", "let", "var", "return", "if", "else", "for", "while", "class",
+            "import", "export", "async", "await", "new",
+        ],
+        "go" => &[
+            "func", "var", "const", "type", "struct", "interface", "return", "if", "else", "for",
+            "range", "package", "import", "defer", "go",
+        ],
+        _ => &[],
+    }
+}
+
+/// One code-fence line: comments dim, strings green, keywords accented.
+fn highlight_code_line(text: &str, language: &str) -> Line<'static> {
+    let comment_prefix = match language {
+        "python" | "py" | "shell" | "sh" | "bash" | "toml" | "yaml" => "#",
+        _ => "//",
+    };
+    if let Some(at) = text.find(comment_prefix) {
+        if !in_string(&text[..at]) {
+            let (head, tail) = text.split_at(at);
+            let mut spans = code_spans(head, language);
+            spans.push(Span::styled(
+                tail.to_string(),
+                Style::default().fg(theme::agent_info()),
+            ));
+            return Line::from(spans);
+        }
+    }
+    Line::from(code_spans(text, language))
+}
+
+fn in_string(text: &str) -> bool {
+    text.chars().filter(|c| *c == '"').count() % 2 == 1
+}
+
+fn code_spans(text: &str, language: &str) -> Vec<Span<'static>> {
+    let keywords = language_keywords(language);
+    let mut spans = Vec::new();
+    let mut current = String::new();
+    let mut in_str = false;
+    let flush = |spans: &mut Vec<Span<'static>>, current: &mut String, in_str: bool| {
+        if current.is_empty() {
+            return;
+        }
+        let style = if in_str {
+            Style::default().fg(theme::success())
+        } else if keywords.contains(&current.as_str()) {
+            Style::default().fg(theme::accent())
+        } else {
+            Style::default().fg(theme::foreground())
+        };
+        spans.push(Span::styled(std::mem::take(current), style));
+    };
+    for c in text.chars() {
+        if c == '"' {
+            if in_str {
+                current.push(c);
+                flush(&mut spans, &mut current, true);
+                in_str = false;
+            } else {
+                flush(&mut spans, &mut current, false);
+                current.push(c);
+                in_str = true;
+            }
+            continue;
+        }
+        if !(in_str || c.is_alphanumeric() || c == '_') {
+            flush(&mut spans, &mut current, false);
+            spans.push(Span::styled(
+                c.to_string(),
+                Style::default().fg(theme::foreground()),
+            ));
+            continue;
+        }
+        current.push(c);
+    }
+    flush(&mut spans, &mut current, in_str);
+    spans
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn extracts_fenced_code_blocks() {
+        let text = "intro\n```rust\nfn main() {}\n```\nafter\n```\nplain\n```\n";
+        let blocks = code_blocks(text);
+        assert_eq!(blocks, vec!["fn main() {}\n", "plain\n"]);
+    }
+
+    #[test]
+    fn shell_blocks_skip_other_languages() {
+        let text = "run\n```sh\ncargo test\n```\nnot this\n```rust\nfn x() {}\n```\n";
+        assert_eq!(shell_blocks(text), vec!["cargo test\n"]);
+    }
+
+    #[test]
+    fn inline_spans_toggle_bold_and_code() {
+        let spans = inline_spans("use **bold** and `code` here");
+        assert!(spans.len() >= 5);
+        assert!(spans.iter().any(|s| s.content == "bold"));
+        assert!(spans.iter().any(|s| s.content == "code"));
+    }
+}
@@ -0,0 +1,2324 @@
+//! Rendering for every pane, the status bar, and modal overlays.
+
+pub mod ansi;
+pub mod hyperlink;
+pub mod images;
+pub mod markdown;
+pub mod overlay;
+pub mod pane;
+pub mod status;
+pub mod theme;
+
+use ratatui::layout::{Constraint, Layout, Position as ScreenPosition, Rect};
+use ratatui::style::{Color, Modifier, Style};
+use ratatui::text::{Line, Span};
+use ratatui::widgets::{Block, Borders, Clear, Paragraph};
+use ratatui::Frame;
+
+use crate::agent::AgentPanelEntry;
+use crate::app::App;
+use crate::editor::WrapMode;
+use crate::keyboard::palette_matches;
+use crate::layout::Focus;
+use crate::lsp::types::DiagnosticSeverity;
+use crate::workspace::icon_for;
+use overlay::{Overlay, SearchField};
+
+/// One wrapped segment of a line: the text plus the char offset of its
+/// first character within the line, so cursor and selection math can
+/// survive variable-length segments.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct WrapSegment {
+    pub text: String,
+    pub start: usize,
+}
+
+/// Display width of a string in terminal columns (CJK chars count as
+/// two, combining marks as zero).
+pub fn display_width(text: &str) -> usize {
+    text.chars()
+        .map(|c| unicode_width::UnicodeWidthChar::width(c).unwrap_or(0))
+        .sum()
+}
+
+/// Soft-wrap a line at word boundaries within `width` display columns.
+/// Break opportunities sit after whitespace and around wide (CJK)
+/// characters; a single word wider than the viewport is hard-split.
+pub fn wrap_line(text: &str, width: usize) -> Vec<WrapSegment> {
+    wrap_line_hanging(text, width, 0)
+}
+
+/// [`wrap_line`] where continuation segments are `hang` columns
+/// narrower, leaving room for a hanging indent drawn under the first
+/// segment.
+pub fn wrap_line_hanging(text: &str, width: usize, hang: usize) -> Vec<WrapSegment> {
+    let empty = || {
+        vec![WrapSegment {
+            text: String::new(),
+            start: 0,
+        }]
+    };
+    if width == 0 || text.is_empty() {
+        return empty();
+    }
+    let cont_width = width.saturating_sub(hang).max(1);
+    let mut limit = width;
+    let mut segments = Vec::new();
+    let mut seg = String::new();
+    let mut seg_start = 0usize;
+    let mut seg_chars = 0usize;
+    let mut seg_width = 0usize;
+    let mut flush = |seg: &mut String, seg_start: &mut usize, chars: &mut usize, w: &mut usize| {
+        segments.push(WrapSegment {
+            text: std::mem::take(seg),
+            start: *seg_start,
+        });
+        *seg_start += *chars;
+        *chars = 0;
+        *w = 0;
+    };
+    for (atom, atom_width) in wrap_atoms(text) {
+        if seg_width + atom_width > limit && seg_width > 0 {
+            flush(&mut seg, &mut seg_start, &mut seg_chars, &mut seg_width);
+            limit = cont_width;
+        }
+        if atom_width > limit {
+            // A single unbreakable run wider than the viewport: split it
+            // character by character.
+            for c in atom.chars() {
+                let cw = unicode_width::UnicodeWidthChar::width(c).unwrap_or(0);
+                if seg_width + cw > limit && seg_width > 0 {
+                    flush(&mut seg, &mut seg_start, &mut seg_chars, &mut seg_width);
+                    limit = cont_width;
+                }
+                seg.push(c);
+                seg_chars += 1;
+                seg_width += cw;
+            }
+        } else {
+            seg_chars += atom.chars().count();
+            seg_width += atom_width;
+            seg.push_str(atom);
+        }
+    }
+    if !seg.is_empty() || segments.is_empty() {
+        segments.push(WrapSegment {
+            text: seg,
+            start: seg_start,
+        });
+    }
+    segments
+}
+
+/// Char-exact wrap at exactly `width` display columns: the
+/// `WrapMode::CharWrap` fallback, with no regard for word boundaries.
+pub fn wrap_line_exact(text: &str, width: usize) -> Vec<WrapSegment> {
+    if width == 0 || text.is_empty() {
+        return vec![WrapSegment {
+            text: String::new(),
+            start: 0,
+        }];
+    }
+    let mut segments = Vec::new();
+    let mut seg = String::new();
+    let mut seg_start = 0usize;
+    let mut seg_chars = 0usize;
+    let mut seg_width = 0usize;
+    for c in text.chars() {
+        let cw = unicode_width::UnicodeWidthChar::width(c).unwrap_or(0);
+        if seg_width + cw > width && seg_width > 0 {
+            segments.push(WrapSegment {
+                text: std::mem::take(&mut seg),
+                start: seg_start,
+            });
+            seg_start += seg_chars;
+            seg_chars = 0;
+            seg_width = 0;
+        }
+        seg.push(c);
+        seg_chars += 1;
+        seg_width += cw;
+    }
+    if !seg.is_empty() || segments.is_empty() {
+        segments.push(WrapSegment {
+            text: seg,
+            start: seg_start,
+        });
+    }
+    segments
+}
+
+/// Split a line into unbreakable atoms: runs of narrow non-whitespace
+/// characters, single wide characters, and single whitespace characters.
+fn wrap_atoms(text: &str) -> Vec<(&str, usize)> {
+    let mut atoms = Vec::new();
+    let mut word_start = None;
+    let mut word_width = 0usize;
+    for (idx, c) in text.char_indices() {
+        let cw = unicode_width::UnicodeWidthChar::width(c).unwrap_or(0);
+        if c.is_whitespace() || cw > 1 {
+            if let Some(start) = word_start.take() {
+                atoms.push((&text[start..idx], word_width));
+                word_width = 0;
+            }
+            atoms.push((&text[idx..idx + c.len_utf8()], cw));
+        } else {
+            if word_start.is_none() {
+                word_start = Some(idx);
+            }
+            word_width += cw;
+        }
+    }
+    if let Some(start) = word_start {
+        atoms.push((&text[start..], word_width));
+    }
+    atoms
+}
+
+/// Window a line for no-wrap mode: the segment visible after skipping
+/// `scroll_col` display columns, plus whether text is clipped on either
+/// side.
+fn hscroll_window(text: &str, scroll_col: usize, width: usize) -> (WrapSegment, bool, bool) {
+    let mut chars = text.char_indices().peekable();
+    let mut start = 0usize;
+    let mut skipped = 0usize;
+    while skipped < scroll_col {
+        let Some((_, c)) = chars.next() else { break };
+        skipped += unicode_width::UnicodeWidthChar::width(c).unwrap_or(0);
+        start += 1;
+    }
+    let mut seg = String::new();
+    let mut used = 0usize;
+    let mut right = false;
+    for (_, c) in chars {
+        let cw = unicode_width::UnicodeWidthChar::width(c).unwrap_or(0);
+        if used + cw > width {
+            right = true;
+            break;
+        }
+        seg.push(c);
+        used += cw;
+    }
+    (WrapSegment { text: seg, start }, scroll_col > 0, right)
+}
+
+/// Break a line into segments and return just the text, for callers that
+/// do not need offsets.
+pub fn wrap_text(text: &str, width: usize) -> Vec<String> {
+    wrap_line(text, width)
+        .into_iter()
+        .map(|segment| segment.text)
+        .collect()
+}
+
+/// Draw dim onboarding hint lines over an empty pane. Callers gate on
+/// [`App::hint_visible`] so a hint vanishes the first time its pane is
+/// actually used.
+fn render_hints(frame: &mut Frame, inner: Rect, hints: &[&str]) {
+    let lines: Vec<Line> = hints
+        .iter()
+        .map(|hint| {
+            Line::from(Span::styled(
+                *hint,
+                Style::default().fg(theme::accent_dim()),
+            ))
+        })
+        .collect();
+    frame.render_widget(Paragraph::new(lines), inner);
+}
+
+/// Draw a slim scrollbar over a pane's right border and register the
+/// track in the layout for click/drag hit-testing. Skipped when the
+/// content fits in the viewport.
+fn render_scrollbar(
+    frame: &mut Frame,
+    app: &mut App,
+    pane: Focus,
+    area: Rect,
+    total: usize,
+    viewport: usize,
+    offset: usize,
+) {
+    if total <= viewport || viewport == 0 || area.width < 2 || area.height < 4 {
+        return;
+    }
+    let track = Rect {
+        x: area.x + area.width - 1,
+        y: area.y + 1,
+        width: 1,
+        height: area.height - 2,
+    };
+    let height = track.height as usize;
+    let thumb = (height * viewport / total).max(1);
+    let max = total - viewport;
+    let top = offset.min(max) * (height - thumb) / max.max(1);
+    for row in 0..height {
+        let in_thumb = row >= top && row < top + thumb;
+        if let Some(cell) = frame
+            .buffer_mut()
+            .cell_mut((track.x, track.y + row as u16))
+        {
+            if in_thumb {
+                cell.set_symbol("┃");
+                cell.set_style(Style::default().fg(theme::accent()));
+            } else {
+                cell.set_symbol("│");
+                cell.set_style(Style::default().fg(theme::border()));
+            }
+        }
+    }
+    app.layout.scrollbars.push(crate::layout::Scrollbar {
+        pane,
+        track,
+        total,
+        viewport,
+    });
+}
+
+fn border_style(app: &App, pane: Focus) -> Style {
+    if app.focus == pane && app.overlay.is_none() {
+        Style::default().fg(theme::border_focused())
+    } else {
+        Style::default().fg(theme::border())
+    }
+}
+
+pub fn render(frame: &mut Frame, app: &mut App) {
+    let full = frame.area();
+    app.layout.scrollbars.clear();
+    let [main, status] =
+        Layout::vertical([Constraint::Min(3), Constraint::Length(1)]).areas(full);
+
+    // Column layout: tree | editor+terminal | agent.
+    let mut constraints = Vec::new();
+    if app.layout.show_tree {
+        constraints.push(Constraint::Percentage(app.layout.tree_ratio));
+    }
+    constraints.push(Constraint::Min(20));
+    if app.layout.show_agent {
+        constraints.push(Constraint::Percentage(app.layout.agent_ratio));
+    }
+    let columns = Layout::horizontal(constraints).split(main);
+    let mut col = 0;
+    if app.layout.show_tree {
+        let left = columns[col];
+        col += 1;
+        if app.layout.show_git {
+            let [tree_area, git_area] =
+                Layout::vertical([Constraint::Percentage(60), Constraint::Percentage(40)])
+                    .areas(left);
+            app.layout.tree_area = tree_area;
+            app.layout.git_area = git_area;
+            pane::view_for(Focus::Git).render(frame, app, git_area);
+        } else {
+            app.layout.tree_area = left;
+            app.layout.git_area = Rect::default();
+        }
+        pane::view_for(Focus::Tree).render(frame, app, app.layout.tree_area);
+    } else {
+        app.layout.tree_area = Rect::default();
+        app.layout.git_area = Rect::default();
+    }
+
+    let mut center = columns[col];
+    col += 1;
+    // Breadcrumb strip above the editor, once a buffer is open.
+    if app.editor.active_buffer().is_some() && center.height > 4 {
+        let [crumb_area, rest] =
+            Layout::vertical([Constraint::Length(1), Constraint::Min(3)]).areas(center);
+        app.layout.breadcrumb_area = crumb_area;
+        render_breadcrumbs(frame, app, crumb_area);
+        center = rest;
+    } else {
+        app.layout.breadcrumb_area = Rect::default();
+    }
+    if app.layout.show_terminal {
+        let [editor_area, terminal_area] = Layout::vertical([
+            Constraint::Percentage(100 - app.layout.terminal_ratio),
+            Constraint::Percentage(app.layout.terminal_ratio),
+        ])
+        .areas(center);
+        app.layout.editor_area = editor_area;
+        app.layout.terminal_area = terminal_area;
+        pane::view_for(Focus::Terminal).render(frame, app, terminal_area);
+    } else {
+        app.layout.editor_area = center;
+        app.layout.terminal_area = Rect::default();
+    }
+    pane::view_for(Focus::Editor).render(frame, app, app.layout.editor_area);
+
+    if app.layout.show_agent {
+        app.layout.agent_area = columns[col];
+        pane::view_for(Focus::Agent).render(frame, app, app.layout.agent_area);
+    } else {
+        app.layout.agent_area = Rect::default();
+    }
+
+    render_status_bar(frame, app, status);
+    render_toasts(frame, app, main);
+
+    if app.overlay.is_some() {
+        render_overlay(frame, app, full);
+    }
+}
+
+fn severity_color(severity: crate::notify::Severity) -> Color {
+    use crate::notify::Severity;
+    match severity {
+        Severity::Info => theme::status_fg(),
+        Severity::Warning => theme::warning(),
+        Severity::Error => theme::error(),
+    }
+}
+
+/// Stack the live toasts in the bottom-right corner of the workspace,
+/// newest nearest the status bar.
+fn render_toasts(frame: &mut Frame, app: &App, area: Rect) {
+    let mut bottom = area.y + area.height;
+    for toast in app.notify.visible().collect::<Vec<_>>().into_iter().rev() {
+        if bottom < area.y + 3 {
+            break;
+        }
+        let width = (toast.message.chars().count() as u16 + 4)
+            .min(area.width.saturating_sub(2))
+            .max(20);
+        let rect = Rect {
+            x: area.x + area.width - width - 1,
+            y: bottom - 3,
+            width,
+            height: 3,
+        };
+        frame.render_widget(Clear, rect);
+        let block = Block::default()
+            .borders(Borders::ALL)
+            .border_style(Style::default().fg(severity_color(toast.severity)))
+            .title(format!(" {} ", toast.severity.label()));
+        let inner = block.inner(rect);
+        frame.render_widget(block, rect);
+        frame.render_widget(
+            Paragraph::new(toast.message.as_str())
+                .style(Style::default().fg(theme::foreground())),
+            inner,
+        );
+        bottom -= 3;
+    }
+}
+
+/// `dir ❯ file ❯ symbol` above the editor; the last segment is the
+/// brightest, earlier ones are click targets.
+fn render_breadcrumbs(frame: &mut Frame, app: &App, area: Rect) {
+    let segments = app.breadcrumb_segments();
+    let mut spans = vec![Span::raw(" ")];
+    for (i, (text, _)) in segments.iter().enumerate() {
+        if i > 0 {
+            spans.push(Span::styled(" ❯ ", Style::default().fg(theme::accent_dim())));
+        }
+        let color = if i + 1 == segments.len() {
+            theme::foreground()
+        } else {
+            theme::accent_dim()
+        };
+        spans.push(Span::styled(text.clone(), Style::default().fg(color)));
+    }
+    frame.render_widget(Paragraph::new(Line::from(spans)), area);
+}
+
+fn render_tree(frame: &mut Frame, app: &mut App, area: Rect) {
+    let inner_height = area.height.saturating_sub(2) as usize;
+    if app.tree.selected < app.tree.scroll {
+        app.tree.scroll = app.tree.selected;
+    } else if inner_height > 0 && app.tree.selected >= app.tree.scroll + inner_height {
+        app.tree.scroll = app.tree.selected + 1 - inner_height;
+    }
+    let mut lines = Vec::new();
+    for (i, entry) in app
+        .tree
+        .entries
+        .iter()
+        .enumerate()
+        .skip(app.tree.scroll)
+        .take(inner_height)
+    {
+        let name = entry
+            .path
+            .file_name()
+            .map(|n| n.to_string_lossy().into_owned())
+            .unwrap_or_default();
+        let indent = "  ".repeat(entry.depth);
+        let color = if entry.is_dir {
+            theme::tree_dir()
+        } else {
+            theme::tree_file()
+        };
+        let mut style = Style::default().fg(color);
+        if i == app.tree.selected {
+            style = style.bg(theme::selection_bg()).add_modifier(Modifier::BOLD);
+        }
+        lines.push(Line::from(Span::styled(
+            format!("{indent}{} {name}", icon_for(entry, app.tree.icon_set)),
+            style,
+        )));
+    }
+    let title = app
+        .tree
+        .root
+        .file_name()
+        .map(|n| n.to_string_lossy().into_owned())
+        .unwrap_or_else(|| "workspace".to_string());
+    let block = Block::default()
+        .borders(Borders::ALL)
+        .border_style(border_style(app, Focus::Tree))
+        .title(format!(" {title} [{}] ", app.tree.sort_mode.label()));
+    frame.render_widget(Paragraph::new(lines).block(block), area);
+}
+
+fn render_git(frame: &mut Frame, app: &App, area: Rect) {
+    let mut lines = Vec::new();
+    if !app.git.available {
+        lines.push(Line::from(Span::styled(
+            "not a git repository",
+            Style::default().fg(theme::accent_dim()),
+        )));
+    }
+    for (i, entry) in app.git.entries.iter().enumerate() {
+        let color = if entry.is_staged() {
+            theme::success()
+        } else {
+            theme::warning()
+        };
+        let mut style = Style::default().fg(color);
+        if i == app.git.selected && app.focus == Focus::Git {
+            style = style.bg(theme::selection_bg());
+        }
+        lines.push(Line::from(Span::styled(
+            format!("{} {}", entry.status, entry.path.display()),
+            style,
+        )));
+    }
+    let branch = app.git.branch.as_deref().unwrap_or("-");
+    let block = Block::default()
+        .borders(Borders::ALL)
+        .border_style(border_style(app, Focus::Git))
+        .title(format!(" git [{branch}] "));
+    frame.render_widget(Paragraph::new(lines).block(block), area);
+}
+
+fn render_editor(frame: &mut Frame, app: &mut App, area: Rect) {
+    let title = match app.editor.active_buffer() {
+        Some(buffer) => {
+            let mut spans = vec![Span::raw(format!(" {}", buffer.display_name()))];
+            if buffer.dirty {
+                spans.push(Span::styled("*", Style::default().fg(theme::dirty())));
+            }
+            if buffer.log_view {
+                spans.push(Span::styled(" [log]", Style::default().fg(theme::info())));
+            }
+            if buffer.read_only {
+                spans.push(Span::styled(" 🔒", Style::default().fg(theme::warning())));
+            }
+            if buffer.huge {
+                spans.push(Span::styled(" [huge]", Style::default().fg(theme::info())));
+            }
+            if buffer.follow {
+                let label = if buffer.follow_paused {
+                    " [follow: paused]"
+                } else {
+                    " [follow]"
+                };
+                spans.push(Span::styled(label, Style::default().fg(theme::info())));
+            }
+            spans.push(Span::raw(format!(
+                " [{}/{}] ",
+                app.editor.active + 1,
+                app.editor.buffers.len()
+            )));
+            Line::from(spans)
+        }
+        None => Line::from(" no file "),
+    };
+    let block = Block::default()
+        .borders(Borders::ALL)
+        .border_style(border_style(app, Focus::Editor))
+        .title(title);
+    let inner = block.inner(area);
+    frame.render_widget(block, area);
+
+    let show_line_numbers = app.editor.prefs.show_line_numbers;
+    let relative_numbers = app.editor.prefs.relative_line_numbers;
+    let wrap_mode = app.editor.prefs.wrap_mode;
+    let show_whitespace = app.editor.prefs.show_whitespace;
+    let editor_empty = app.editor.active_buffer().is_none();
+    let show_hint = app.hint_visible("editor", editor_empty);
+    let Some(buffer) = app.editor.active_buffer_mut() else {
+        if show_hint {
+            render_hints(
+                frame,
+                inner,
+                &["Open a file from the tree (Alt+1) or the palette (Ctrl+P)"],
+            );
+        }
+        return;
+    };
+
+    let gutter_width = if show_line_numbers {
+        (buffer.line_count().max(1).ilog10() as usize + 2).max(4)
+    } else {
+        0
+    };
+    let text_width = (inner.width as usize).saturating_sub(gutter_width).max(1);
+    let viewport = inner.height as usize;
+
+    // Keep the cursor inside the viewport (in buffer-line units).
+    if buffer.cursor.line < buffer.scroll_line {
+        buffer.scroll_line = buffer.cursor.line;
+    } else if viewport > 0 && buffer.cursor.line >= buffer.scroll_line + viewport {
+        buffer.scroll_line = buffer.cursor.line + 1 - viewport;
+    }
+    // In no-wrap mode also keep it horizontally visible (display cols).
+    if wrap_mode == WrapMode::NoWrap {
+        let cursor_line = buffer.rope.line(buffer.cursor.line).to_string();
+        let prefix: String = cursor_line.chars().take(buffer.cursor.col).collect();
+        let cursor_disp = display_width(&prefix);
+        if cursor_disp < buffer.scroll_col {
+            buffer.scroll_col = cursor_disp;
+        } else if cursor_disp >= buffer.scroll_col + text_width {
+            buffer.scroll_col = cursor_disp + 1 - text_width;
+        }
+    } else {
+        buffer.scroll_col = 0;
+    }
+
+    let selection = buffer.selection_range();
+    // Substring highlight rules for followed log files.
+    let log_rules = buffer
+        .follow
+        .then(|| app.config.log_highlight.clone().unwrap_or_default());
+    let diagnostics = buffer
+        .path
+        .as_ref()
+        .and_then(|p| app.diagnostics.get(p.as_path()));
+
+    let mut lines: Vec<Line> = Vec::new();
+    let mut cursor_screen: Option<(u16, u16)> = None;
+    let mut caret_screens: Vec<(u16, u16)> = Vec::new();
+    // Rows whose line continues past the viewport in no-wrap mode, for
+    // the overflow indicators.
+    let mut overflow_rows: Vec<(usize, bool, bool)> = Vec::new();
+    let mut row = 0usize;
+    let mut line_no = buffer.scroll_line;
+    while row < viewport && line_no < buffer.line_count() {
+        let raw = buffer.rope.line(line_no).to_string();
+        let raw = raw.trim_end_matches('\n');
+        if buffer.log_view {
+            // Log view: interpret escape codes as colors, one screen row
+            // per line, no wrapping.
+            let mut spans = Vec::new();
+            if gutter_width > 0 {
+                spans.push(Span::styled(
+                    format!("{:>width$} ", line_no + 1, width = gutter_width - 1),
+                    Style::default().fg(theme::gutter()),
+                ));
+            }
+            spans.extend(ansi::parse_spans(raw, text_width));
+            lines.push(Line::from(spans));
+            row += 1;
+            line_no += 1;
+            continue;
+        }
+        // Word wrap hangs continuation segments under the line's own
+        // indentation (clamped so deep nesting keeps some text width).
+        let hang = match wrap_mode {
+            WrapMode::WordWrap => {
+                let lead: String = raw.chars().take_while(|c| c.is_whitespace()).collect();
+                display_width(&lead).min(text_width / 2)
+            }
+            _ => 0,
+        };
+        let segments = match wrap_mode {
+            WrapMode::WordWrap => wrap_line_hanging(raw, text_width, hang),
+            WrapMode::CharWrap => wrap_line_exact(raw, text_width),
+            WrapMode::NoWrap => {
+                let (segment, left, right) = hscroll_window(raw, buffer.scroll_col, text_width);
+                if left || right {
+                    overflow_rows.push((row, left, right));
+                }
+                vec![segment]
+            }
+        };
+        let line_diag = diagnostics
+            .and_then(|ds| ds.iter().find(|d| d.range.start.line as usize == line_no));
+        let line_start_char = buffer.rope.line_to_char(line_no);
+        for (seg_idx, segment) in segments.iter().enumerate() {
+            if row >= viewport {
+                break;
+            }
+            let mut spans = Vec::new();
+            if gutter_width > 0 {
+                let (num, color) = if seg_idx == 0 {
+                    // Relative numbering shows the distance from the
+                    // cursor line; the cursor line keeps its absolute
+                    // number either way.
+                    let shown = if relative_numbers && line_no != buffer.cursor.line {
+                        line_no.abs_diff(buffer.cursor.line)
+                    } else {
+                        line_no + 1
+                    };
+                    let mark = if buffer.bookmarks.contains(&line_no) {
+                        '•'
+                    } else {
+                        ' '
+                    };
+                    (
+                        format!("{shown:>width$}{mark}", width = gutter_width - 1),
+                        if line_no == buffer.cursor.line {
+                            theme::gutter_current()
+                        } else {
+                            theme::gutter()
+                        },
+                    )
+                } else {
+                    (" ".repeat(gutter_width), theme::gutter())
+                };
+                let color = match line_diag {
+                    Some(diag) if seg_idx == 0 => match diag.severity {
+                        DiagnosticSeverity::Error => theme::error(),
+                        DiagnosticSeverity::Warning => theme::warning(),
+                        _ => theme::info(),
+                    },
+                    _ => color,
+                };
+                let mut num_style = Style::default().fg(color);
+                if seg_idx == 0 && line_no == buffer.cursor.line {
+                    num_style = num_style.add_modifier(Modifier::BOLD);
+                }
+                spans.push(Span::styled(num, num_style));
+            }
+            let seg_start = line_start_char + segment.start;
+            let seg_len = segment.text.chars().count();
+            let selected = selection
+                .map(|(s, e)| seg_start < e && s < seg_start + seg_len.max(1))
+                .unwrap_or(false);
+            let mut style = Style::default().fg(theme::foreground());
+            if let Some(rules) = &log_rules {
+                if rules.error.iter().any(|p| raw.contains(p.as_str())) {
+                    style = style.fg(theme::error());
+                } else if rules.warn.iter().any(|p| raw.contains(p.as_str())) {
+                    style = style.fg(theme::warning());
+                }
+            }
+            if selected {
+                style = style.bg(theme::selection_bg());
+            } else if line_no == buffer.cursor.line {
+                style = style.bg(theme::cursor_line_bg());
+            }
+            let pad = if seg_idx > 0 { hang } else { 0 };
+            if pad > 0 {
+                spans.push(Span::styled(" ".repeat(pad), style));
+            }
+            if show_whitespace {
+                spans.extend(whitespace_spans(&segment.text, style));
+            } else {
+                spans.push(Span::styled(segment.text.clone(), style));
+            }
+            let is_last = seg_idx + 1 == segments.len();
+            let covers = |col: usize| {
+                col >= segment.start
+                    && (col < segment.start + seg_len || (is_last && col <= segment.start + seg_len))
+            };
+            // X position in display columns, so wide (CJK) chars line up.
+            let seg_x = |col: usize| {
+                let prefix: String = segment.text.chars().take(col - segment.start).collect();
+                inner.x + (gutter_width + pad) as u16 + display_width(&prefix) as u16
+            };
+            if line_no == buffer.cursor.line && covers(buffer.cursor.col) {
+                cursor_screen = Some((seg_x(buffer.cursor.col), inner.y + row as u16));
+            }
+            for caret in &buffer.extra_cursors {
+                if caret.line == line_no && covers(caret.col) {
+                    caret_screens.push((seg_x(caret.col), inner.y + row as u16));
+                }
+            }
+            lines.push(Line::from(spans));
+            row += 1;
+        }
+        line_no += 1;
+    }
+    frame.render_widget(Paragraph::new(lines), inner);
+    // Overflow indicators for no-wrap mode: an ellipsis at the clipped
+    // edge(s) of each row whose line continues off screen.
+    for (row, left, right) in overflow_rows {
+        let y = inner.y + row as u16;
+        let mut mark = |x: u16| {
+            if let Some(cell) = frame.buffer_mut().cell_mut((x, y)) {
+                cell.set_char('…');
+                cell.set_fg(theme::accent_dim());
+            }
+        };
+        if left {
+            mark(inner.x + gutter_width as u16);
+        }
+        if right && inner.width > 0 {
+            mark(inner.x + inner.width - 1);
+        }
+    }
+    // Extra carets are drawn as reversed cells; the hardware cursor marks
+    // only the primary.
+    for (x, y) in caret_screens {
+        if let Some(cell) = frame.buffer_mut().cell_mut((x, y)) {
+            let style = cell.style().add_modifier(Modifier::REVERSED);
+            cell.set_style(style);
+        }
+    }
+    if let Some((total, offset)) = app
+        .editor
+        .active_buffer()
+        .map(|b| (b.line_count(), b.scroll_line))
+    {
+        let viewport = area.height.saturating_sub(2) as usize;
+        render_scrollbar(frame, app, Focus::Editor, area, total, viewport, offset);
+    }
+    if app.focus == Focus::Editor && app.overlay.is_none() {
+        if let Some((x, y)) = cursor_screen {
+            // In overwrite mode the cell under the cursor is underlined
+            // as well, so the mode is visible at the insertion point.
+            if app.editor.overwrite {
+                if let Some(cell) = frame.buffer_mut().cell_mut((x, y)) {
+                    let style = cell.style().add_modifier(Modifier::UNDERLINED);
+                    cell.set_style(style);
+                }
+            }
+            frame.set_cursor_position(ScreenPosition { x, y });
+        }
+    }
+}
+
+/// Segment text with whitespace made visible: runs of rewritten
+/// characters (dots, arrows, boxes — see [`unicode::reveal`]) are
+/// dimmed so the markers read as markup, not content.
+///
+/// [`unicode::reveal`]: crate::editor::unicode::reveal
+fn whitespace_spans(text: &str, base: Style) -> Vec<Span<'static>> {
+    let faint = base.fg(theme::accent_dim());
+    let style_for = |marked: bool| if marked { faint } else { base };
+    let mut spans = Vec::new();
+    let mut run = String::new();
+    let mut run_marked = false;
+    for c in text.chars() {
+        let (shown, marked) = crate::editor::unicode::reveal(c);
+        if marked != run_marked && !run.is_empty() {
+            spans.push(Span::styled(std::mem::take(&mut run), style_for(run_marked)));
+        }
+        run_marked = marked;
+        run.push(shown);
+    }
+    if !run.is_empty() {
+        spans.push(Span::styled(run, style_for(run_marked)));
+    }
+    spans
+}
+
+fn render_terminal(frame: &mut Frame, app: &mut App, area: Rect) {
+    let block = Block::default()
+        .borders(Borders::ALL)
+        .border_style(border_style(app, Focus::Terminal))
+        .title(if app.terminal.copy.is_some() {
+            " terminal [copy] "
+        } else if app.terminal.is_running() {
+            " terminal [running] "
+        } else {
+            " terminal "
+        });
+    let inner = block.inner(area);
+    frame.render_widget(block, area);
+    let output_height = inner.height.saturating_sub(1) as usize;
+    if app.terminal.follow {
+        app.terminal.scroll = app.terminal.lines.len().saturating_sub(output_height);
+    }
+    let copy = app
+        .terminal
+        .copy
+        .as_ref()
+        .map(|mode| (mode.cursor, app.terminal.copy_selection(), mode.query.clone()));
+    let mut lines: Vec<Line> = app
+        .terminal
+        .lines
+        .iter()
+        .enumerate()
+        .skip(app.terminal.scroll)
+        .take(output_height)
+        .map(|(idx, l)| {
+            let mut style = if l.starts_with("$ ") {
+                Style::default().fg(theme::accent())
+            } else {
+                Style::default().fg(theme::foreground())
+            };
+            // In copy mode the cursor line and selection get the usual
+            // selection background and search hits stand out reversed.
+            if let Some((cursor, selection, query)) = &copy {
+                let selected = selection.is_some_and(|(start, end)| (start..=end).contains(&idx));
+                if idx == *cursor {
+                    style = style.bg(theme::selection_bg()).add_modifier(Modifier::BOLD);
+                } else if selected {
+                    style = style.bg(theme::selection_bg());
+                }
+                return copy_mode_line(l, style, query);
+            }
+            // file:line tokens are underlined; Enter or a click opens them.
+            if let Some(loc) = crate::terminal::find_location(l) {
+                return Line::from(vec![
+                    Span::styled(l[..loc.start].to_string(), style),
+                    Span::styled(
+                        l[loc.start..loc.end].to_string(),
+                        style.add_modifier(Modifier::UNDERLINED),
+                    ),
+                    Span::styled(l[loc.end..].to_string(), style),
+                ]);
+            }
+            Line::from(Span::styled(l.clone(), style))
+        })
+        .collect();
+    while lines.len() < output_height {
+        lines.push(Line::default());
+    }
+    lines.push(match app.terminal.copy.as_ref() {
+        Some(mode) => match &mode.search_input {
+            Some(input) => Line::from(vec![
+                Span::styled("/", Style::default().fg(theme::accent())),
+                Span::raw(input.clone()),
+            ]),
+            None => Line::from(Span::styled(
+                "copy: j/k move · v select · y yank · / search · n/N next · Esc exit",
+                Style::default().fg(theme::accent_dim()),
+            )),
+        },
+        None => Line::from(vec![
+            Span::styled("$ ", Style::default().fg(theme::accent())),
+            Span::raw(app.terminal.input.clone()),
+        ]),
+    });
+    frame.render_widget(Paragraph::new(lines), inner);
+    let terminal_empty = app.terminal.lines.is_empty() && app.terminal.input.is_empty();
+    if app.hint_visible("terminal", terminal_empty) {
+        render_hints(
+            frame,
+            inner,
+            &[
+                "type a command and press Enter",
+                "Ctrl+C stops the running process",
+            ],
+        );
+    }
+    let visible: Vec<String> = app
+        .terminal
+        .lines
+        .iter()
+        .skip(app.terminal.scroll)
+        .take(output_height)
+        .cloned()
+        .collect();
+    for (row, text) in visible.iter().enumerate() {
+        for link in hyperlink::detect_links(text) {
+            hyperlink::apply_to_row(frame.buffer_mut(), inner, inner.y + row as u16, &link);
+        }
+    }
+    let (total, offset) = (app.terminal.lines.len(), app.terminal.scroll);
+    render_scrollbar(frame, app, Focus::Terminal, area, total, output_height, offset);
+    if app.focus == Focus::Terminal && app.overlay.is_none() {
+        let column = match app.terminal.copy.as_ref() {
+            Some(mode) => mode
+                .search_input
+                .as_ref()
+                .map(|input| 1 + input.chars().count() as u16),
+            None => Some(2 + app.terminal.input.chars().count() as u16),
+        };
+        if let Some(column) = column {
+            frame.set_cursor_position(ScreenPosition {
+                x: inner.x + column,
+                y: inner.y + inner.height.saturating_sub(1),
+            });
+        }
+    }
+}
+
+/// One scrollback line in copy mode: occurrences of the search query
+/// are rendered reversed so matches are visible at a glance.
+fn copy_mode_line(text: &str, base: Style, query: &str) -> Line<'static> {
+    if query.is_empty() || !text.contains(query) {
+        return Line::from(Span::styled(text.to_string(), base));
+    }
+    let hit = base
+        .fg(theme::accent())
+        .add_modifier(Modifier::REVERSED | Modifier::BOLD);
+    let mut spans = Vec::new();
+    let mut rest = text;
+    while let Some(pos) = rest.find(query) {
+        if pos > 0 {
+            spans.push(Span::styled(rest[..pos].to_string(), base));
+        }
+        spans.push(Span::styled(query.to_string(), hit));
+        rest = &rest[pos + query.len()..];
+    }
+    if !rest.is_empty() {
+        spans.push(Span::styled(rest.to_string(), base));
+    }
+    Line::from(spans)
+}
+
+fn render_agent(frame: &mut Frame, app: &mut App, area: Rect) {
+    let profile = app
+        .agent
+        .active_profile()
+        .map(|p| p.name.clone())
+        .unwrap_or_else(|| "no profile".to_string());
+    let busy = if app.agent.busy { " …" } else { "" };
+    // Flag selections pinned by workspace or global config, so a
+    // confidential repo's local-only pin is visible at a glance.
+    let source = match app.agent.profile_source {
+        "workspace" => " [workspace]",
+        "config" => " [config]",
+        _ => "",
+    };
+    let [history_area, composer_area] =
+        Layout::vertical([Constraint::Min(3), Constraint::Length(4)]).areas(area);
+
+    // With several profiles configured the title doubles as a tab bar:
+    // the active agent is bracketed, the others keep their own parked
+    // conversations.
+    let title = if app.agent.config.profiles.len() > 1 {
+        let tabs: Vec<String> = app
+            .agent
+            .config
+            .profiles
+            .iter()
+            .enumerate()
+            .map(|(i, p)| {
+                if i == app.agent.active {
+                    format!("[{}]", p.name)
+                } else {
+                    p.name.clone()
+                }
+            })
+            .collect();
+        format!(" agent: {}{source}{busy} ", tabs.join(" "))
+    } else {
+        format!(" agent: {profile}{source}{busy} ")
+    };
+    let block = Block::default()
+        .borders(Borders::ALL)
+        .border_style(border_style(app, Focus::Agent))
+        .title(title);
+    let inner = block.inner(history_area);
+    frame.render_widget(block, history_area);
+
+    // Reserve the bottom of the history for the most recent inline image;
+    // the main loop emits the actual escape sequence after the frame.
+    let (inner, image_area) = if app.inline_image().is_some() && inner.height > 6 {
+        let rows = (inner.height / 2).min(12);
+        let [text_area, image_area] =
+            Layout::vertical([Constraint::Min(3), Constraint::Length(rows)]).areas(inner);
+        (text_area, image_area)
+    } else {
+        (inner, Rect::default())
+    };
+    app.layout.agent_image_area = image_area;
+
+    let width = inner.width.saturating_sub(1) as usize;
+    let mut lines: Vec<Line> = Vec::new();
+    let entries = &app.conversation.entries;
+    let mut i = 0;
+    while i < entries.len() {
+        let entry = &entries[i];
+        if matches!(entry, AgentPanelEntry::Info(_)) {
+            let run = entries[i..]
+                .iter()
+                .take_while(|e| matches!(e, AgentPanelEntry::Info(_)))
+                .count();
+            if !app.conversation.show_info {
+                i += run;
+                continue;
+            }
+            if run >= 3 && !app.conversation.expand_info_groups {
+                lines.push(Line::from(Span::styled(
+                    format!("· {run} system messages (agent.expand-info shows them)"),
+                    Style::default().fg(theme::agent_info()),
+                )));
+                i += run;
+                continue;
+            }
+        }
+        i += 1;
+        if let AgentPanelEntry::Response(text) = entry {
+            lines.extend(markdown::render(text, width.max(1)));
+            continue;
+        }
+        if let AgentPanelEntry::Diff { path, diff } = entry {
+            lines.push(Line::from(Span::styled(
+                format!("~ wrote {}", path.display()),
+                Style::default()
+                    .fg(theme::warning())
+                    .add_modifier(Modifier::BOLD),
+            )));
+            if !app.conversation.show_diffs {
+                continue;
+            }
+            for raw_line in diff.lines() {
+                let style = match raw_line.chars().next() {
+                    Some('+') => Style::default().fg(theme::success()),
+                    Some('-') => Style::default().fg(theme::error()),
+                    _ => Style::default().fg(theme::agent_info()),
+                };
+                for segment in wrap_text(raw_line, width.max(1)) {
+                    lines.push(Line::from(Span::styled(segment, style)));
+                }
+            }
+            continue;
+        }
+        if let AgentPanelEntry::ToolOutput { name, output } = entry {
+            lines.push(Line::from(Span::styled(
+                format!("⚙ {name}"),
+                Style::default()
+                    .fg(theme::agent_info())
+                    .add_modifier(Modifier::BOLD),
+            )));
+            if app.conversation.show_diffs {
+                for raw_line in output.lines() {
+                    for segment in wrap_text(raw_line, width.max(1)) {
+                        lines.push(Line::from(Span::styled(
+                            format!("  {segment}"),
+                            Style::default().fg(theme::agent_info()),
+                        )));
+                    }
+                }
+            }
+            continue;
+        }
+        let (prefix, text, style) = match entry {
+            AgentPanelEntry::Info(t) => ("·", t, Style::default().fg(theme::agent_info())),
+            AgentPanelEntry::User(t) => (
+                ">",
+                t,
+                Style::default()
+                    .fg(theme::agent_user())
+                    .add_modifier(Modifier::BOLD),
+            ),
+            AgentPanelEntry::Response(t) => {
+                ("", t, Style::default().fg(theme::agent_response()))
+            }
+            AgentPanelEntry::Error(t) => ("!", t, Style::default().fg(theme::error())),
+            AgentPanelEntry::Image(path) => {
+                lines.push(Line::from(Span::styled(
+                    format!("▣ image: {}", path.display()),
+                    Style::default().fg(theme::agent_info()),
+                )));
+                continue;
+            }
+            AgentPanelEntry::Diff { .. } | AgentPanelEntry::ToolOutput { .. } => continue,
+        };
+        for raw_line in text.lines() {
+            for segment in wrap_text(raw_line, width.max(1)) {
+                let content = if prefix.is_empty() {
+                    segment
+                } else {
+                    format!("{prefix} {segment}")
+                };
+                lines.push(Line::from(Span::styled(content, style)));
+            }
+        }
+    }
+    let viewport = inner.height as usize;
+    let max_scroll = lines.len().saturating_sub(viewport);
+    app.conversation.scroll = app.conversation.scroll.min(max_scroll);
+    let total = lines.len();
+    let mut shown_offset = 0;
+    let visible: Vec<Line> = if lines.len() > viewport {
+        let offset = if app.conversation.scroll == 0 {
+            max_scroll
+        } else {
+            app.conversation.scroll
+        };
+        shown_offset = offset;
+        lines.into_iter().skip(offset).take(viewport).collect()
+    } else {
+        lines
+    };
+    render_scrollbar(
+        frame,
+        app,
+        Focus::Agent,
+        history_area,
+        total,
+        viewport,
+        shown_offset,
+    );
+    let visible_texts: Vec<String> = visible
+        .iter()
+        .map(|line| {
+            line.spans
+                .iter()
+                .map(|span| span.content.as_ref())
+                .collect()
+        })
+        .collect();
+    frame.render_widget(Paragraph::new(visible), inner);
+    for (row, text) in visible_texts.iter().enumerate() {
+        for link in hyperlink::detect_links(text) {
+            hyperlink::apply_to_row(frame.buffer_mut(), inner, inner.y + row as u16, &link);
+        }
+    }
+    // Startup Info lines do not count as a conversation.
+    let agent_empty = app.composer.is_empty()
+        && !app
+            .conversation
+            .entries
+            .iter()
+            .any(|e| !matches!(e, AgentPanelEntry::Info(_)));
+    if app.hint_visible("agent", agent_empty) {
+        let hint_area = Rect {
+            y: inner.y + inner.height.saturating_sub(2),
+            height: inner.height.min(2),
+            ..inner
+        };
+        render_hints(
+            frame,
+            hint_area,
+            &[
+                "write a prompt below; Ctrl+Enter sends it to the agent",
+                "switch profiles with agent.next-profile in the palette",
+            ],
+        );
+    }
+
+    let composer_title = match app.token_usage_summary() {
+        Some(usage) => format!(" compose (Ctrl+Enter to send) · {usage} "),
+        None => " compose (Ctrl+Enter to send) ".to_string(),
+    };
+    let composer_block = Block::default()
+        .borders(Borders::ALL)
+        .border_style(border_style(app, Focus::Agent))
+        .title(composer_title);
+    let composer_inner = composer_block.inner(composer_area);
+    frame.render_widget(composer_block, composer_area);
+    frame.render_widget(
+        Paragraph::new(app.composer.as_str()).style(Style::default().fg(theme::foreground())),
+        composer_inner,
+    );
+}
+
+fn render_status_bar(frame: &mut Frame, app: &mut App, area: Rect) {
+    let (left, left_color) = match app.notify.latest() {
+        Some(toast) => (toast.message.clone(), severity_color(toast.severity)),
+        None if app.lsp_indexing => (
+            "LSP indexing… features limited".to_string(),
+            theme::status_fg(),
+        ),
+        None => (
+            cursor_diagnostic_text(app).unwrap_or_else(|| app.root.display().to_string()),
+            theme::status_fg(),
+        ),
+    };
+    // The right side is the configured segment list; each rendered
+    // segment's span is recorded for click dispatch.
+    app.layout.status_area = area;
+    app.layout.status_hits.clear();
+    let mut right = String::new();
+    let mut hits: Vec<(usize, usize, crate::app::CommandId)> = Vec::new();
+    for segment in &app.status_segments {
+        let Some(text) = (segment.render)(app) else {
+            continue;
+        };
+        if !right.is_empty() {
+            right.push_str(" | ");
+        }
+        let start = right.chars().count();
+        right.push_str(&text);
+        if let Some(command) = segment.on_click {
+            hits.push((start, right.chars().count(), command));
+        }
+    }
+    if !right.is_empty() {
+        right.push(' ');
+    }
+    let left_width = (area.width as usize).saturating_sub(right.chars().count());
+    let base = area.x as usize + left_width;
+    for (start, end, command) in hits {
+        app.layout.status_hits.push(crate::layout::StatusHit {
+            start: (base + start) as u16,
+            end: (base + end) as u16,
+            command,
+        });
+    }
+    let mut left = left;
+    if left.chars().count() > left_width {
+        left = left.chars().take(left_width).collect();
+    }
+    let padding = " ".repeat(left_width.saturating_sub(left.chars().count()));
+    let left_text = left.clone();
+    let line = Line::from(vec![
+        Span::styled(left, Style::default().fg(left_color)),
+        Span::raw(padding),
+        Span::styled(right, Style::default().fg(theme::accent_dim())),
+    ]);
+    frame.render_widget(
+        Paragraph::new(line).style(Style::default().bg(theme::status_bg())),
+        area,
+    );
+    // Link a leading `[E0308]` diagnostic code to the rustc error index.
+    if let Some(code) = left_text
+        .strip_prefix('[')
+        .and_then(|rest| rest.split(']').next())
+    {
+        if let Some(url) = hyperlink::diagnostic_code_link(code) {
+            let link = hyperlink::Link {
+                start: 1,
+                end: 1 + code.chars().count(),
+                url,
+            };
+            hyperlink::apply_to_row(frame.buffer_mut(), area, area.y, &link);
+        }
+    }
+}
+
+/// The first diagnostic on the cursor line, formatted for the status bar.
+fn cursor_diagnostic_text(app: &App) -> Option<String> {
+    let buffer = app.editor.active_buffer()?;
+    let diagnostics = app.diagnostics.get(buffer.path.as_deref()?)?;
+    let diag = diagnostics
+        .iter()
+        .find(|d| d.range.start.line as usize == buffer.cursor.line)?;
+    Some(match &diag.code {
+        Some(code) => format!("[{code}] {}", diag.message.lines().next().unwrap_or("")),
+        None => diag.message.lines().next().unwrap_or("").to_string(),
+    })
+}
+
+/// A centered rect with the given percentage size of the frame.
+fn centered_rect(area: Rect, percent_x: u16, percent_y: u16) -> Rect {
+    let [_, mid, _] = Layout::vertical([
+        Constraint::Percentage((100 - percent_y) / 2),
+        Constraint::Percentage(percent_y),
+        Constraint::Percentage((100 - percent_y) / 2),
+    ])
+    .areas(area);
+    let [_, rect, _] = Layout::horizontal([
+        Constraint::Percentage((100 - percent_x) / 2),
+        Constraint::Percentage(percent_x),
+        Constraint::Percentage((100 - percent_x) / 2),
+    ])
+    .areas(mid);
+    rect
+}
+
+fn overlay_block(title: &str) -> Block<'_> {
+    Block::default()
+        .borders(Borders::ALL)
+        .border_style(Style::default().fg(theme::border_focused()))
+        .title(format!(" {title} "))
+}
+
+fn render_overlay(frame: &mut Frame, app: &App, full: Rect) {
+    let Some(overlay) = &app.overlay else { return };
+    match overlay {
+        Overlay::CommandPalette { input, selected } => {
+            let area = centered_rect(full, 60, 60);
+            frame.render_widget(Clear, area);
+            let block = overlay_block("Command Palette");
+            let inner = block.inner(area);
+            frame.render_widget(block, area);
+            let mut lines = vec![Line::from(vec![
+                Span::styled("> ", Style::default().fg(theme::accent())),
+                Span::raw(input.clone()),
+            ])];
+            for (i, (label, _)) in palette_matches(input)
+                .iter()
+                .enumerate()
+                .take(inner.height.saturating_sub(1) as usize)
+            {
+                let mut style = Style::default().fg(theme::foreground());
+                if i == *selected {
+                    style = style.bg(theme::selection_bg()).add_modifier(Modifier::BOLD);
+                }
+                lines.push(Line::from(Span::styled(label.to_string(), style)));
+            }
+            frame.render_widget(Paragraph::new(lines), inner);
+        }
+        Overlay::Prompt { action, input } => {
+            let area = centered_rect(full, 50, 20);
+            frame.render_widget(Clear, area);
+            let block = overlay_block(action.title());
+            let inner = block.inner(area);
+            frame.render_widget(block, area);
+            let shown = if action.masked() {
+                "•".repeat(input.chars().count())
+            } else {
+                input.clone()
+            };
+            frame.render_widget(
+                Paragraph::new(Line::from(vec![
+                    Span::styled("> ", Style::default().fg(theme::accent())),
+                    Span::raw(shown),
+                ])),
+                inner,
+            );
+        }
+        Overlay::AnsiDetected => {
+            let area = centered_rect(full, 55, 25);
+            frame.render_widget(Clear, area);
+            let block = overlay_block("ANSI Escape Codes Detected");
+            let inner = block.inner(area);
+            frame.render_widget(block, area);
+            let lines = vec![
+                Line::from("This file contains terminal color codes."),
+                Line::default(),
+                Line::from(Span::styled(
+                    "[v] view colorized (read-only)   [s] strip for editing   [Esc] keep as-is",
+                    Style::default().fg(theme::accent_dim()),
+                )),
+            ];
+            frame.render_widget(Paragraph::new(lines), inner);
+        }
+        Overlay::EncodingPicker { selected } => {
+            let area = centered_rect(full, 40, 45);
+            frame.render_widget(Clear, area);
+            let block = overlay_block("Reopen with Encoding");
+            let inner = block.inner(area);
+            frame.render_widget(block, area);
+            let lines: Vec<Line> = crate::editor::Encoding::ALL
+                .iter()
+                .enumerate()
+                .map(|(i, encoding)| {
+                    let mut style = Style::default().fg(theme::foreground());
+                    if i == *selected {
+                        style = style.bg(theme::selection_bg()).add_modifier(Modifier::BOLD);
+                    }
+                    Line::from(Span::styled(encoding.label(), style))
+                })
+                .collect();
+            frame.render_widget(Paragraph::new(lines), inner);
+        }
+        Overlay::LayoutPicker { names, selected } => {
+            let area = centered_rect(full, 40, 40);
+            frame.render_widget(Clear, area);
+            let block = overlay_block("Layout Preset");
+            let inner = block.inner(area);
+            frame.render_widget(block, area);
+            let lines: Vec<Line> = names
+                .iter()
+                .enumerate()
+                .map(|(i, name)| {
+                    let mut style = Style::default().fg(theme::foreground());
+                    if i == *selected {
+                        style = style.bg(theme::selection_bg()).add_modifier(Modifier::BOLD);
+                    }
+                    Line::from(Span::styled(name.clone(), style))
+                })
+                .collect();
+            frame.render_widget(Paragraph::new(lines), inner);
+        }
+        Overlay::ConversationHistory { items, selected } => {
+            let area = centered_rect(full, 70, 50);
+            frame.render_widget(Clear, area);
+            let block = overlay_block("Conversation History");
+            let inner = block.inner(area);
+            frame.render_widget(block, area);
+            let visible = inner.height.saturating_sub(2) as usize;
+            let start = selected.saturating_sub(visible.saturating_sub(1));
+            let mut lines: Vec<Line> = items
+                .iter()
+                .enumerate()
+                .skip(start)
+                .take(visible)
+                .map(|(i, item)| {
+                    let mut style = Style::default().fg(theme::foreground());
+                    if i == *selected {
+                        style = style.bg(theme::selection_bg()).add_modifier(Modifier::BOLD);
+                    }
+                    Line::from(vec![
+                        Span::styled(item.title.clone(), style),
+                        Span::styled(
+                            format!("  {} · {} entr{}", item.saved_at, item.entries,
+                                if item.entries == 1 { "y" } else { "ies" }),
+                            Style::default().fg(theme::accent_dim()),
+                        ),
+                    ])
+                })
+                .collect();
+            lines.push(Line::default());
+            lines.push(Line::from(Span::styled(
+                "[Enter] open   [r] rename   [d] delete   [Esc] close",
+                Style::default().fg(theme::accent_dim()),
+            )));
+            frame.render_widget(Paragraph::new(lines), inner);
+        }
+        Overlay::McpBrowser { rows, selected } => {
+            let area = centered_rect(full, 70, 60);
+            frame.render_widget(Clear, area);
+            let block = overlay_block("MCP Servers");
+            let inner = block.inner(area);
+            frame.render_widget(block, area);
+            let visible = inner.height.saturating_sub(2) as usize;
+            let start = selected.saturating_sub(visible.saturating_sub(1));
+            let mut lines: Vec<Line> = rows
+                .iter()
+                .enumerate()
+                .skip(start)
+                .take(visible)
+                .map(|(i, row)| {
+                    let mut style = Style::default().fg(theme::foreground());
+                    if i == *selected {
+                        style = style.bg(theme::selection_bg()).add_modifier(Modifier::BOLD);
+                    }
+                    let (label, detail) = match row {
+                        crate::ui::overlay::McpRow::Server(name) => {
+                            return Line::from(Span::styled(
+                                format!("⚡ {name}"),
+                                style.add_modifier(Modifier::BOLD),
+                            ));
+                        }
+                        crate::ui::overlay::McpRow::Tool { name, description } => {
+                            (format!("  ⚙ {name}"), description.clone())
+                        }
+                        crate::ui::overlay::McpRow::Resource { uri, name, .. } => {
+                            (format!("  ▤ {name}"), uri.clone())
+                        }
+                        crate::ui::overlay::McpRow::Prompt { name, description, .. } => {
+                            (format!("  ¶ {name}"), description.clone())
+                        }
+                    };
+                    Line::from(vec![
+                        Span::styled(label, style),
+                        Span::styled(
+                            format!("  {detail}"),
+                            Style::default().fg(theme::accent_dim()),
+                        ),
+                    ])
+                })
+                .collect();
+            lines.push(Line::default());
+            lines.push(Line::from(Span::styled(
+                "[Enter] read resource / expand prompt   [Esc] close",
+                Style::default().fg(theme::accent_dim()),
+            )));
+            frame.render_widget(Paragraph::new(lines), inner);
+        }
+        Overlay::ModelPicker { names, selected } => {
+            let area = centered_rect(full, 50, 60);
+            frame.render_widget(Clear, area);
+            let block = overlay_block("Select Model");
+            let inner = block.inner(area);
+            frame.render_widget(block, area);
+            let visible = inner.height.saturating_sub(2) as usize;
+            let start = selected.saturating_sub(visible.saturating_sub(1));
+            let mut lines: Vec<Line> = names
+                .iter()
+                .enumerate()
+                .skip(start)
+                .take(visible)
+                .map(|(i, name)| {
+                    let mut style = Style::default().fg(theme::foreground());
+                    if i == *selected {
+                        style = style.bg(theme::selection_bg()).add_modifier(Modifier::BOLD);
+                    }
+                    Line::from(Span::styled(name.clone(), style))
+                })
+                .collect();
+            lines.push(Line::default());
+            lines.push(Line::from(Span::styled(
+                "[Enter] use model   [Esc] close",
+                Style::default().fg(theme::accent_dim()),
+            )));
+            frame.render_widget(Paragraph::new(lines), inner);
+        }
+        Overlay::TaskPicker { tasks, selected } => {
+            let area = centered_rect(full, 60, 50);
+            frame.render_widget(Clear, area);
+            let block = overlay_block("Run Task");
+            let inner = block.inner(area);
+            frame.render_widget(block, area);
+            let visible = inner.height.saturating_sub(2) as usize;
+            let start = selected.saturating_sub(visible.saturating_sub(1));
+            let mut lines: Vec<Line> = tasks
+                .iter()
+                .enumerate()
+                .skip(start)
+                .take(visible)
+                .map(|(i, task)| {
+                    let mut style = Style::default().fg(theme::foreground());
+                    if i == *selected {
+                        style = style.bg(theme::selection_bg()).add_modifier(Modifier::BOLD);
+                    }
+                    Line::from(Span::styled(
+                        format!("{:<10} {:<20} {}", task.source, task.name, task.command),
+                        style,
+                    ))
+                })
+                .collect();
+            lines.push(Line::default());
+            lines.push(Line::from(Span::styled(
+                "[Enter] run in terminal   [Esc] close",
+                Style::default().fg(theme::accent_dim()),
+            )));
+            frame.render_widget(Paragraph::new(lines), inner);
+        }
+        Overlay::EntryActions { selected } => {
+            let area = centered_rect(full, 70, 60);
+            frame.render_widget(Clear, area);
+            let block = overlay_block("Conversation Entries");
+            let inner = block.inner(area);
+            frame.render_widget(block, area);
+            let visible = inner.height.saturating_sub(2) as usize;
+            let start = selected.saturating_sub(visible.saturating_sub(1));
+            let mut lines: Vec<Line> = app
+                .conversation
+                .entries
+                .iter()
+                .enumerate()
+                .skip(start)
+                .take(visible)
+                .map(|(i, entry)| {
+                    let mut style = Style::default().fg(theme::foreground());
+                    if i == *selected {
+                        style = style.bg(theme::selection_bg()).add_modifier(Modifier::BOLD);
+                    }
+                    let preview: String = entry
+                        .text()
+                        .lines()
+                        .next()
+                        .unwrap_or("")
+                        .chars()
+                        .take(inner.width.saturating_sub(8) as usize)
+                        .collect();
+                    Line::from(Span::styled(
+                        format!("{:<5} {preview}", entry.kind()),
+                        style,
+                    ))
+                })
+                .collect();
+            lines.push(Line::default());
+            lines.push(Line::from(Span::styled(
+                "[c] copy   [i] insert at cursor   [e] resend   [d] delete   [Esc] close",
+                Style::default().fg(theme::accent_dim()),
+            )));
+            frame.render_widget(Paragraph::new(lines), inner);
+        }
+        Overlay::TemplatePicker { names, selected } => {
+            let area = centered_rect(full, 50, 50);
+            frame.render_widget(Clear, area);
+            let block = overlay_block("Prompt Templates");
+            let inner = block.inner(area);
+            frame.render_widget(block, area);
+            let visible = inner.height.saturating_sub(2) as usize;
+            let start = selected.saturating_sub(visible.saturating_sub(1));
+            let mut lines: Vec<Line> = names
+                .iter()
+                .enumerate()
+                .skip(start)
+                .take(visible)
+                .map(|(i, name)| {
+                    let mut style = Style::default().fg(theme::foreground());
+                    if i == *selected {
+                        style = style.bg(theme::selection_bg()).add_modifier(Modifier::BOLD);
+                    }
+                    Line::from(Span::styled(format!("/{name}"), style))
+                })
+                .collect();
+            lines.push(Line::default());
+            lines.push(Line::from(Span::styled(
+                "[Enter] insert into composer   [Esc] close",
+                Style::default().fg(theme::accent_dim()),
+            )));
+            frame.render_widget(Paragraph::new(lines), inner);
+        }
+        Overlay::TranscriptLog { records, selected } => {
+            let area = centered_rect(full, 70, 60);
+            frame.render_widget(Clear, area);
+            let block = overlay_block("Agent Transcript Log");
+            let inner = block.inner(area);
+            frame.render_widget(block, area);
+            let detail_height = 6usize;
+            let visible = inner
+                .height
+                .saturating_sub(detail_height as u16 + 2)
+                .max(1) as usize;
+            let start = selected.saturating_sub(visible.saturating_sub(1));
+            let mut lines = Vec::new();
+            for (i, record) in records.iter().enumerate().skip(start).take(visible) {
+                let mut style = Style::default().fg(theme::foreground());
+                if i == *selected {
+                    style = style.bg(theme::selection_bg()).add_modifier(Modifier::BOLD);
+                }
+                let first = record.body.lines().next().unwrap_or_default();
+                lines.push(Line::from(vec![
+                    Span::styled(
+                        format!("{} {} {:<11} ", record.at, record.profile, record.direction),
+                        Style::default().fg(theme::accent_dim()),
+                    ),
+                    Span::styled(first.to_string(), style),
+                ]));
+            }
+            lines.push(Line::default());
+            if let Some(record) = records.get(*selected) {
+                for body_line in record.body.lines().take(detail_height) {
+                    lines.push(Line::from(Span::styled(
+                        body_line.to_string(),
+                        Style::default().fg(theme::agent_info()),
+                    )));
+                }
+            }
+            lines.push(Line::from(Span::styled(
+                "[↑/↓] select   [Esc] close",
+                Style::default().fg(theme::accent_dim()),
+            )));
+            frame.render_widget(Paragraph::new(lines), inner);
+        }
+        Overlay::AgentHistorySearch {
+            input,
+            hits,
+            selected,
+        } => {
+            let area = centered_rect(full, 70, 60);
+            frame.render_widget(Clear, area);
+            let block = overlay_block("Search Agent History");
+            let inner = block.inner(area);
+            frame.render_widget(block, area);
+            let mut lines = vec![Line::from(vec![
+                Span::styled("keyword ", Style::default().fg(theme::accent_dim())),
+                Span::styled(input.clone(), Style::default().fg(theme::foreground())),
+                Span::styled("▏", Style::default().fg(theme::accent())),
+            ])];
+            if hits.is_empty() && !input.is_empty() {
+                lines.push(Line::from(Span::styled(
+                    "no matches in saved conversations",
+                    Style::default().fg(theme::accent_dim()),
+                )));
+            }
+            let visible = inner.height.saturating_sub(3) as usize;
+            let start = selected.saturating_sub(visible.saturating_sub(1));
+            for (i, hit) in hits.iter().enumerate().skip(start).take(visible) {
+                let mut style = Style::default().fg(theme::foreground());
+                if i == *selected {
+                    style = style.bg(theme::selection_bg()).add_modifier(Modifier::BOLD);
+                }
+                lines.push(Line::from(vec![
+                    Span::styled(format!("{}  ", hit.title), style),
+                    Span::styled(
+                        hit.snippet.clone(),
+                        Style::default().fg(theme::accent_dim()),
+                    ),
+                ]));
+            }
+            lines.push(Line::default());
+            lines.push(Line::from(Span::styled(
+                "[Enter] open at hit   [Esc] close",
+                Style::default().fg(theme::accent_dim()),
+            )));
+            frame.render_widget(Paragraph::new(lines), inner);
+        }
+        Overlay::ToolWriteConflict { path, diff } => {
+            let area = centered_rect(full, 70, 60);
+            frame.render_widget(Clear, area);
+            let block = overlay_block("Agent Write Conflict");
+            let inner = block.inner(area);
+            frame.render_widget(block, area);
+            let mut lines = vec![
+                Line::from(format!(
+                    "{} has unsaved edits; the agent wants to write it:",
+                    path.display()
+                )),
+                Line::default(),
+            ];
+            for raw_line in diff.lines().take(inner.height.saturating_sub(5) as usize) {
+                let style = match raw_line.chars().next() {
+                    Some('+') => Style::default().fg(theme::success()),
+                    Some('-') => Style::default().fg(theme::error()),
+                    _ => Style::default().fg(theme::agent_info()),
+                };
+                lines.push(Line::from(Span::styled(raw_line.to_string(), style)));
+            }
+            lines.push(Line::default());
+            lines.push(Line::from(Span::styled(
+                "[a/Enter] take agent version   [b] keep buffer   [Esc] decide later",
+                Style::default().fg(theme::accent_dim()),
+            )));
+            frame.render_widget(Paragraph::new(lines), inner);
+        }
+        Overlay::MultiFileReview { files, selected } => {
+            let area = centered_rect(full, 80, 70);
+            frame.render_widget(Clear, area);
+            let block = overlay_block("Review Agent Edits");
+            let inner = block.inner(area);
+            frame.render_widget(block, area);
+            let mut lines: Vec<Line> = files
+                .iter()
+                .enumerate()
+                .map(|(i, file)| {
+                    let mut style = Style::default().fg(if file.accepted {
+                        theme::success()
+                    } else {
+                        theme::error()
+                    });
+                    if i == *selected {
+                        style = style.bg(theme::selection_bg()).add_modifier(Modifier::BOLD);
+                    }
+                    Line::from(Span::styled(
+                        format!(
+                            "[{}] {}",
+                            if file.accepted { 'x' } else { ' ' },
+                            file.path.display()
+                        ),
+                        style,
+                    ))
+                })
+                .collect();
+            lines.push(Line::default());
+            if let Some(file) = files.get(*selected) {
+                let budget = inner.height.saturating_sub(files.len() as u16 + 4) as usize;
+                for raw_line in file.diff.lines().take(budget) {
+                    let style = match raw_line.chars().next() {
+                        Some('+') => Style::default().fg(theme::success()),
+                        Some('-') => Style::default().fg(theme::error()),
+                        _ => Style::default().fg(theme::agent_info()),
+                    };
+                    lines.push(Line::from(Span::styled(raw_line.to_string(), style)));
+                }
+            }
+            lines.push(Line::default());
+            lines.push(Line::from(Span::styled(
+                "[Space] toggle   [a] accept all   [r] reject all   [Enter] apply   [Esc] reject",
+                Style::default().fg(theme::accent_dim()),
+            )));
+            frame.render_widget(Paragraph::new(lines), inner);
+        }
+        Overlay::CommandApproval { commands } => {
+            let area = centered_rect(full, 60, 40);
+            frame.render_widget(Clear, area);
+            let block = overlay_block("Run Proposed Command?");
+            let inner = block.inner(area);
+            frame.render_widget(block, area);
+            let mut lines = vec![
+                Line::from("The agent's reply proposes running:"),
+                Line::default(),
+            ];
+            for command in commands {
+                for raw in command
+                    .lines()
+                    .take(inner.height.saturating_sub(4) as usize)
+                {
+                    lines.push(Line::from(Span::styled(
+                        format!("  $ {raw}"),
+                        Style::default().fg(theme::warning()),
+                    )));
+                }
+            }
+            lines.push(Line::default());
+            lines.push(Line::from(Span::styled(
+                "[y/Enter] run in terminal   [n/Esc] dismiss",
+                Style::default().fg(theme::accent_dim()),
+            )));
+            frame.render_widget(Paragraph::new(lines), inner);
+        }
+        Overlay::ToolApproval { calls, .. } => {
+            let area = centered_rect(full, 60, 40);
+            frame.render_widget(Clear, area);
+            let block = overlay_block("Agent Tool Approval");
+            let inner = block.inner(area);
+            frame.render_widget(block, area);
+            let mut lines = vec![
+                Line::from("The agent wants to run tools that modify the workspace:"),
+                Line::default(),
+            ];
+            for call in calls.iter().take(inner.height.saturating_sub(4) as usize) {
+                let args = call.args.to_string();
+                let style = if crate::agent::tools::is_mutating(&call.name) {
+                    Style::default().fg(theme::warning())
+                } else {
+                    Style::default().fg(theme::foreground())
+                };
+                lines.push(Line::from(Span::styled(
+                    format!("  {} {args}", call.name),
+                    style,
+                )));
+            }
+            lines.push(Line::default());
+            lines.push(Line::from(Span::styled(
+                "[y/Enter] run   [n/Esc] deny (the model is told)",
+                Style::default().fg(theme::accent_dim()),
+            )));
+            frame.render_widget(Paragraph::new(lines), inner);
+        }
+        Overlay::RecoveryFound { files } => {
+            let area = centered_rect(full, 60, 40);
+            frame.render_widget(Clear, area);
+            let block = overlay_block("Unsaved Changes Recovered");
+            let inner = block.inner(area);
+            frame.render_widget(block, area);
+            let mut lines = vec![
+                Line::from(format!(
+                    "{} file(s) had unsaved changes when the last session ended:",
+                    files.len()
+                )),
+                Line::default(),
+            ];
+            for (original, _) in files.iter().take(inner.height.saturating_sub(4) as usize) {
+                lines.push(Line::from(format!("  {}", original.display())));
+            }
+            lines.push(Line::default());
+            lines.push(Line::from(Span::styled(
+                "[r/Enter] restore   [d] discard   [Esc] decide later",
+                Style::default().fg(theme::accent_dim()),
+            )));
+            frame.render_widget(Paragraph::new(lines), inner);
+        }
+        Overlay::DefinitionPicker { items, selected } => {
+            let area = centered_rect(full, 70, 50);
+            frame.render_widget(Clear, area);
+            let block = overlay_block("Go to Definition");
+            let inner = block.inner(area);
+            frame.render_widget(block, area);
+            let visible = inner.height.saturating_sub(2) as usize;
+            let start = selected.saturating_sub(visible.saturating_sub(1));
+            let mut lines: Vec<Line> = items
+                .iter()
+                .enumerate()
+                .skip(start)
+                .take(visible)
+                .map(|(i, (path, pos, preview))| {
+                    let mut style = Style::default().fg(theme::foreground());
+                    if i == *selected {
+                        style = style.bg(theme::selection_bg()).add_modifier(Modifier::BOLD);
+                    }
+                    Line::from(Span::styled(
+                        format!(
+                            "{}:{}  {}",
+                            app.workspace_relative(path).display(),
+                            pos.line + 1,
+                            preview
+                        ),
+                        style,
+                    ))
+                })
+                .collect();
+            lines.push(Line::default());
+            lines.push(Line::from(Span::styled(
+                "[Enter] jump   [Esc] cancel",
+                Style::default().fg(theme::accent_dim()),
+            )));
+            frame.render_widget(Paragraph::new(lines), inner);
+        }
+        Overlay::ConfirmPaste {
+            preview,
+            lines,
+            bytes,
+            target,
+            ..
+        } => {
+            let area = centered_rect(full, 60, 50);
+            frame.render_widget(Clear, area);
+            let block = overlay_block("Confirm Large Paste");
+            let inner = block.inner(area);
+            frame.render_widget(block, area);
+            let mut rows = vec![
+                Line::from(format!(
+                    "Paste {lines} lines ({bytes} bytes) into {target}?"
+                )),
+                Line::default(),
+            ];
+            for line in preview {
+                rows.push(Line::from(Span::styled(
+                    format!("│ {line}"),
+                    Style::default().fg(theme::agent_info()),
+                )));
+            }
+            if *lines > preview.len() {
+                rows.push(Line::from(Span::styled(
+                    format!("│ … {} more lines", lines - preview.len()),
+                    Style::default().fg(theme::accent_dim()),
+                )));
+            }
+            rows.push(Line::default());
+            rows.push(Line::from(Span::styled(
+                "[y/Enter] paste   [n/Esc] cancel",
+                Style::default().fg(theme::accent_dim()),
+            )));
+            frame.render_widget(Paragraph::new(rows), inner);
+        }
+        Overlay::ConfirmDelete { path } => {
+            let area = centered_rect(full, 50, 20);
+            frame.render_widget(Clear, area);
+            let block = overlay_block("Confirm Delete");
+            let inner = block.inner(area);
+            frame.render_widget(block, area);
+            let lines = vec![
+                Line::from(format!("Delete {}?", path.display())),
+                Line::from(Span::styled(
+                    "Trashed entries can be restored; permanent delete cannot be undone.",
+                    Style::default().fg(theme::warning()),
+                )),
+                Line::from(Span::styled(
+                    "[y] move to trash   [P] delete permanently   [n/Esc] cancel",
+                    Style::default().fg(theme::accent_dim()),
+                )),
+            ];
+            frame.render_widget(Paragraph::new(lines), inner);
+        }
+        Overlay::SearchReplace(state) => {
+            let area = centered_rect(full, 70, 70);
+            frame.render_widget(Clear, area);
+            let block = overlay_block("Replace in Files");
+            let inner = block.inner(area);
+            frame.render_widget(block, area);
+            let field_style = |f: SearchField| {
+                if state.field == f {
+                    Style::default().fg(theme::accent())
+                } else {
+                    Style::default().fg(theme::accent_dim())
+                }
+            };
+            let mut lines = vec![
+                Line::from(vec![
+                    Span::styled("search:  ", field_style(SearchField::Query)),
+                    Span::raw(state.query.clone()),
+                ]),
+                Line::from(vec![
+                    Span::styled("replace: ", field_style(SearchField::Replacement)),
+                    Span::raw(state.replacement.clone()),
+                ]),
+            ];
+            if state.searched {
+                lines.push(Line::from(Span::styled(
+                    format!(
+                        "{} match(es) in {} file(s) — Space toggles, Enter applies",
+                        state.total_matches(),
+                        state.files.len()
+                    ),
+                    Style::default().fg(theme::accent_dim()),
+                )));
+                for (i, file) in state.files.iter().enumerate() {
+                    let mark = if file.included { "[x]" } else { "[ ]" };
+                    let mut style = Style::default().fg(theme::foreground());
+                    if i == state.selected {
+                        style = style.bg(theme::selection_bg());
+                    }
+                    lines.push(Line::from(Span::styled(
+                        format!("{mark} {} ({})", file.path.display(), file.matches.len()),
+                        style,
+                    )));
+                    if i == state.selected {
+                        for (line_no, text) in file.matches.iter().take(4) {
+                            lines.push(Line::from(Span::styled(
+                                format!("      {line_no}: {text}"),
+                                Style::default().fg(theme::accent_dim()),
+                            )));
+                        }
+                    }
+                }
+            } else {
+                lines.push(Line::from(Span::styled(
+                    "Enter searches the workspace; Tab switches fields",
+                    Style::default().fg(theme::accent_dim()),
+                )));
+            }
+            frame.render_widget(Paragraph::new(lines), inner);
+        }
+        Overlay::WorkspaceEditPreview(state) => {
+            let area = centered_rect(full, 70, 70);
+            frame.render_widget(Clear, area);
+            let block = overlay_block(&state.title);
+            let inner = block.inner(area);
+            frame.render_widget(block, area);
+            let mut lines = vec![Line::from(Span::styled(
+                format!(
+                    "{} edit(s) across {} file(s); {} included — Space toggles, Enter applies, Esc cancels",
+                    state.total_edits(),
+                    state.files.len(),
+                    state.included_count()
+                ),
+                Style::default().fg(theme::accent_dim()),
+            ))];
+            for (i, file) in state.files.iter().enumerate() {
+                let mark = if file.included { "[x]" } else { "[ ]" };
+                let mut style = Style::default().fg(theme::foreground());
+                if i == state.selected {
+                    style = style.bg(theme::selection_bg());
+                }
+                lines.push(Line::from(Span::styled(
+                    format!("{mark} {} ({} edit(s))", file.path.display(), file.edits.len()),
+                    style,
+                )));
+                if i == state.selected {
+                    for preview in &file.preview {
+                        lines.push(Line::from(Span::styled(
+                            preview.clone(),
+                            Style::default().fg(theme::accent_dim()),
+                        )));
+                    }
+                }
+            }
+            frame.render_widget(Paragraph::new(lines), inner);
+        }
+        Overlay::AgentStats => {
+            let area = centered_rect(full, 60, 50);
+            frame.render_widget(Clear, area);
+            let block = overlay_block("Agent Statistics");
+            let inner = block.inner(area);
+            frame.render_widget(block, area);
+            let mut lines = Vec::new();
+            if app.agent_stats.is_empty() {
+                lines.push(Line::from(Span::styled(
+                    "No agent patches recorded this session.",
+                    Style::default().fg(theme::accent_dim()),
+                )));
+            } else {
+                lines.push(Line::from(Span::styled(
+                    format!(
+                        "{:<20} {:>9} {:>7} {:>9} {:>6}",
+                        "profile", "accepted", "edited", "rejected", "rate"
+                    ),
+                    Style::default().fg(theme::accent()),
+                )));
+                for (name, stats) in app.agent_stats.sorted_by_usefulness() {
+                    lines.push(Line::from(format!(
+                        "{:<20} {:>9} {:>7} {:>9} {:>5.0}%",
+                        name,
+                        stats.accepted,
+                        stats.edited,
+                        stats.rejected,
+                        stats.acceptance_rate() * 100.0
+                    )));
+                }
+            }
+            frame.render_widget(Paragraph::new(lines), inner);
+        }
+        Overlay::ThemePicker {
+            names, selected, ..
+        } => {
+            let area = centered_rect(full, 40, 50);
+            frame.render_widget(Clear, area);
+            let block = overlay_block("Select Theme (live preview)");
+            let inner = block.inner(area);
+            frame.render_widget(block, area);
+            let lines: Vec<Line> = names
+                .iter()
+                .enumerate()
+                .map(|(i, name)| {
+                    let mut style = Style::default().fg(theme::foreground());
+                    if i == *selected {
+                        style = style.bg(theme::selection_bg()).add_modifier(Modifier::BOLD);
+                    }
+                    Line::from(Span::styled(name.clone(), style))
+                })
+                .collect();
+            frame.render_widget(Paragraph::new(lines), inner);
+        }
+        Overlay::KeyboardHelp {
+            rows,
+            scroll,
+            filter,
+        } => {
+            let area = centered_rect(full, 70, 70);
+            frame.render_widget(Clear, area);
+            let block = overlay_block("Keyboard Shortcuts");
+            let inner = block.inner(area);
+            frame.render_widget(block, area);
+            let needle = filter.to_lowercase();
+            let mut lines = vec![
+                Line::from(vec![
+                    Span::styled("filter: ", Style::default().fg(theme::accent_dim())),
+                    Span::styled(filter.clone(), Style::default().fg(theme::foreground())),
+                    Span::styled("▏", Style::default().fg(theme::accent())),
+                ]),
+                Line::from(Span::styled(
+                    format!("{:<18} command", "keys"),
+                    Style::default().fg(theme::accent()),
+                )),
+            ];
+            let mut group = None;
+            for (scope, keys, command) in rows
+                .iter()
+                .filter(|(scope, keys, command)| {
+                    needle.is_empty()
+                        || scope.to_lowercase().contains(&needle)
+                        || keys.to_lowercase().contains(&needle)
+                        || command.to_lowercase().contains(&needle)
+                })
+                .skip(*scroll)
+            {
+                if group != Some(scope) {
+                    group = Some(scope);
+                    lines.push(Line::from(Span::styled(
+                        format!("── {scope} ──"),
+                        Style::default().fg(theme::accent_dim()),
+                    )));
+                }
+                lines.push(Line::from(vec![
+                    Span::styled(format!("{keys:<18} "), Style::default().fg(theme::accent())),
+                    Span::raw(command.clone()),
+                ]));
+            }
+            frame.render_widget(Paragraph::new(lines), inner);
+        }
+        Overlay::CharInspector { text } => {
+            let area = centered_rect(full, 40, 30);
+            frame.render_widget(Clear, area);
+            let block = overlay_block("Character");
+            let inner = block.inner(area);
+            frame.render_widget(block, area);
+            let lines: Vec<Line> = text.lines().map(Line::from).collect();
+            frame.render_widget(Paragraph::new(lines), inner);
+        }
+        Overlay::UnicodePicker { input, selected } => {
+            let area = centered_rect(full, 50, 60);
+            frame.render_widget(Clear, area);
+            let block = overlay_block("Insert Unicode Character");
+            let inner = block.inner(area);
+            frame.render_widget(block, area);
+            let mut lines = vec![Line::from(vec![
+                Span::styled("name or U+ ", Style::default().fg(theme::accent_dim())),
+                Span::styled(input.clone(), Style::default().fg(theme::foreground())),
+                Span::styled("▏", Style::default().fg(theme::accent())),
+            ])];
+            for (i, (c, name)) in crate::editor::unicode::search(input)
+                .into_iter()
+                .enumerate()
+                .take(inner.height.saturating_sub(1) as usize)
+            {
+                let mut style = Style::default().fg(theme::foreground());
+                if i == *selected {
+                    style = style.bg(theme::selection_bg()).add_modifier(Modifier::BOLD);
+                }
+                let shown = if c.is_control()
+                    || unicode_width::UnicodeWidthChar::width(c).unwrap_or(0) == 0
+                {
+                    ' '
+                } else {
+                    c
+                };
+                lines.push(Line::from(Span::styled(
+                    format!("{shown}  U+{:04X}  {name}", c as u32),
+                    style,
+                )));
+            }
+            frame.render_widget(Paragraph::new(lines), inner);
+        }
+        Overlay::Notifications { scroll } => {
+            let area = centered_rect(full, 70, 70);
+            frame.render_widget(Clear, area);
+            let block = overlay_block("Notifications");
+            let inner = block.inner(area);
+            frame.render_widget(block, area);
+            let mut lines = Vec::new();
+            if app.notify.history().len() == 0 {
+                lines.push(Line::from(Span::styled(
+                    "no notifications this session",
+                    Style::default().fg(theme::accent_dim()),
+                )));
+            }
+            for toast in app
+                .notify
+                .history()
+                .skip(*scroll)
+                .take(inner.height as usize)
+            {
+                lines.push(Line::from(vec![
+                    Span::styled(
+                        format!("{:>4}s ", toast.at.elapsed().as_secs()),
+                        Style::default().fg(theme::accent_dim()),
+                    ),
+                    Span::styled(
+                        format!("{:<6}", toast.severity.label()),
+                        Style::default().fg(severity_color(toast.severity)),
+                    ),
+                    Span::raw(toast.message.clone()),
+                ]));
+            }
+            frame.render_widget(Paragraph::new(lines), inner);
+        }
+        Overlay::Hover { text } => {
+            let area = centered_rect(full, 60, 50);
+            frame.render_widget(Clear, area);
+            let block = overlay_block("Hover");
+            let inner = block.inner(area);
+            frame.render_widget(block, area);
+            let width = inner.width.saturating_sub(1) as usize;
+            let lines: Vec<Line> = text
+                .lines()
+                .flat_map(|l| wrap_text(l, width.max(1)))
+                .take(inner.height as usize)
+                .map(Line::from)
+                .collect();
+            frame.render_widget(Paragraph::new(lines), inner);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn wrap_text_chunks_at_exact_width() {
+        assert_eq!(wrap_text("abcdef", 4), vec!["abcd", "ef"]);
+        assert_eq!(wrap_text("", 4), vec![""]);
+        assert_eq!(wrap_text("ab", 0), vec![""]);
+    }
+
+    #[test]
+    fn wrap_line_breaks_at_word_boundaries() {
+        let segments = wrap_line("hello world", 8);
+        assert_eq!(
+            segments,
+            vec![
+                WrapSegment {
+                    text: "hello ".to_string(),
+                    start: 0
+                },
+                WrapSegment {
+                    text: "world".to_string(),
+                    start: 6
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn wrap_line_hanging_narrows_continuations() {
+        let segments = wrap_line_hanging("  one two three", 9, 2);
+        assert_eq!(segments[0].text, "  one two");
+        // Continuations wrap at 9 - 2 = 7 columns.
+        assert!(segments[1..]
+            .iter()
+            .all(|seg| display_width(&seg.text) <= 7));
+        // Offsets still index into the unpadded line.
+        assert_eq!(segments[1].start, 9);
+    }
+
+    #[test]
+    fn wrap_line_exact_ignores_word_boundaries() {
+        let segments = wrap_line_exact("hello world", 4);
+        let texts: Vec<&str> = segments.iter().map(|seg| seg.text.as_str()).collect();
+        assert_eq!(texts, vec!["hell", "o wo", "rld"]);
+        assert_eq!(segments[2].start, 8);
+    }
+
+    #[test]
+    fn wrap_line_counts_wide_chars_as_two_columns() {
+        // Each CJK char is two columns wide, so only two fit per row.
+        let segments = wrap_line("你好世界", 4);
+        assert_eq!(segments.len(), 2);
+        assert_eq!(segments[0].text, "你好");
+        assert_eq!(segments[1].start, 2);
+    }
+
+    #[test]
+    fn hscroll_window_reports_clipped_edges() {
+        let (seg, left, right) = hscroll_window("abcdefgh", 2, 4);
+        assert_eq!(seg.text, "cdef");
+        assert_eq!(seg.start, 2);
+        assert!(left);
+        assert!(right);
+
+        let (seg, left, right) = hscroll_window("abc", 0, 4);
+        assert_eq!(seg.text, "abc");
+        assert!(!left);
+        assert!(!right);
+    }
+}
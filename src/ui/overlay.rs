@@ -0,0 +1,312 @@
+//! Modal overlay state. Rendering lives in `ui::render_overlay`.
+
+use std::path::PathBuf;
+
+use crate::lsp::types::TextEdit;
+
+/// What a text prompt overlay does with its input on Enter.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PromptAction {
+    NewFile,
+    NewDirectory,
+    RenameEntry,
+    GotoLine,
+    RenameSymbol,
+    CommitMessage,
+    AgentApiKey,
+    /// Passphrase or identity file for an encrypted (`.age`/`.gpg`) buffer.
+    DecryptSecret,
+    /// New title for the conversation selected in the history overlay.
+    RenameConversation,
+    /// Path of a `.clide-bundle` file to import.
+    ImportBundle,
+    /// File name for the conversation export; `.json` selects JSON,
+    /// anything else gets Markdown.
+    ExportConversation,
+    /// First half of a batch run: directory or path prefix of the files.
+    AgentBatchFiles,
+    /// Second half: the prompt template applied to each file.
+    AgentBatchPrompt,
+}
+
+impl PromptAction {
+    pub fn title(self) -> &'static str {
+        match self {
+            PromptAction::NewFile => "New File",
+            PromptAction::NewDirectory => "New Directory",
+            PromptAction::RenameEntry => "Rename",
+            PromptAction::GotoLine => "Go to Line",
+            PromptAction::RenameSymbol => "Rename Symbol",
+            PromptAction::CommitMessage => "Commit Message",
+            PromptAction::AgentApiKey => "Agent API Key",
+            PromptAction::DecryptSecret => "Unlock Encrypted File",
+            PromptAction::RenameConversation => "Rename Conversation",
+            PromptAction::ImportBundle => "Import Bundle (path)",
+            PromptAction::ExportConversation => "Export Conversation (file name)",
+            PromptAction::AgentBatchFiles => "Batch: Files (dir or path prefix)",
+            PromptAction::AgentBatchPrompt => "Batch: Prompt per File",
+        }
+    }
+
+    /// Secrets are rendered as bullets instead of the typed text.
+    pub fn masked(self) -> bool {
+        matches!(
+            self,
+            PromptAction::AgentApiKey | PromptAction::DecryptSecret
+        )
+    }
+}
+
+/// One file of a multi-file agent edit under review.
+#[derive(Debug, Clone)]
+pub struct ReviewFile {
+    pub path: PathBuf,
+    /// The full content the agent wants the file to have.
+    pub content: String,
+    pub diff: String,
+    pub accepted: bool,
+}
+
+/// One file's worth of matches in the replace-in-files preview.
+#[derive(Debug, Clone)]
+pub struct FileMatches {
+    pub path: PathBuf,
+    /// (1-based line number, line text) per match.
+    pub matches: Vec<(usize, String)>,
+    pub included: bool,
+}
+
+/// Which input field of the search overlay has focus.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SearchField {
+    Query,
+    Replacement,
+}
+
+/// State for the workspace search/replace overlay.
+pub struct SearchReplaceState {
+    pub query: String,
+    pub replacement: String,
+    pub field: SearchField,
+    pub files: Vec<FileMatches>,
+    pub selected: usize,
+    pub searched: bool,
+}
+
+impl SearchReplaceState {
+    pub fn new() -> Self {
+        Self {
+            query: String::new(),
+            replacement: String::new(),
+            field: SearchField::Query,
+            files: Vec::new(),
+            selected: 0,
+            searched: false,
+        }
+    }
+
+    pub fn total_matches(&self) -> usize {
+        self.files.iter().map(|f| f.matches.len()).sum()
+    }
+}
+
+/// One file's pending edits in a workspace-edit preview.
+#[derive(Debug, Clone)]
+pub struct PendingFileEdit {
+    pub path: PathBuf,
+    pub edits: Vec<TextEdit>,
+    pub included: bool,
+    /// Short "line N: old -> new" strings shown under the file row.
+    pub preview: Vec<String>,
+}
+
+/// Preview of a multi-file [`WorkspaceEdit`](crate::lsp::types::WorkspaceEdit)
+/// before it is applied, with per-file opt-out.
+pub struct WorkspaceEditPreviewState {
+    pub title: String,
+    pub files: Vec<PendingFileEdit>,
+    pub selected: usize,
+}
+
+impl WorkspaceEditPreviewState {
+    pub fn included_count(&self) -> usize {
+        self.files.iter().filter(|f| f.included).count()
+    }
+
+    pub fn total_edits(&self) -> usize {
+        self.files.iter().map(|f| f.edits.len()).sum()
+    }
+}
+
+/// One row of the MCP browser overlay.
+#[derive(Debug, Clone)]
+pub enum McpRow {
+    Server(String),
+    Tool {
+        name: String,
+        description: String,
+    },
+    Resource {
+        server: String,
+        uri: String,
+        name: String,
+    },
+    Prompt {
+        server: String,
+        name: String,
+        description: String,
+    },
+}
+
+/// The active modal overlay, if any.
+pub enum Overlay {
+    CommandPalette {
+        input: String,
+        selected: usize,
+    },
+    Prompt {
+        action: PromptAction,
+        input: String,
+    },
+    ConfirmDelete {
+        path: PathBuf,
+    },
+    /// The just-opened file contains ANSI escape codes: offer a colorized
+    /// read-only log view or stripping them for editing.
+    AnsiDetected,
+    /// Guard against dumping a huge clipboard into a buffer by accident:
+    /// holds the pending text plus a short preview and the target.
+    ConfirmPaste {
+        text: String,
+        preview: Vec<String>,
+        lines: usize,
+        bytes: usize,
+        target: String,
+    },
+    /// "Reopen with Encoding…" picker over [`Encoding::ALL`].
+    ///
+    /// [`Encoding::ALL`]: crate::editor::Encoding::ALL
+    EncodingPicker {
+        selected: usize,
+    },
+    /// Crash-recovery snapshots found at startup, as (original, swap)
+    /// path pairs; offers restoring the unsaved changes or discarding.
+    RecoveryFound {
+        files: Vec<(PathBuf, PathBuf)>,
+    },
+    /// Several go-to-definition candidates: path, target position, and a
+    /// preview of the target line.
+    DefinitionPicker {
+        items: Vec<(PathBuf, crate::editor::Position, String)>,
+        selected: usize,
+    },
+    SearchReplace(SearchReplaceState),
+    WorkspaceEditPreview(WorkspaceEditPreviewState),
+    Hover {
+        text: String,
+    },
+    /// Report on the character under the cursor.
+    CharInspector {
+        text: String,
+    },
+    /// "Insert Unicode Character…" picker, searchable by name or
+    /// codepoint.
+    UnicodePicker {
+        input: String,
+        selected: usize,
+    },
+    /// Per-profile patch acceptance statistics for the session.
+    AgentStats,
+    /// Effective keybindings: (scope, keys, command) rows generated from
+    /// the keymap and command registry; `filter` narrows them live.
+    KeyboardHelp {
+        rows: Vec<(String, String, String)>,
+        scroll: usize,
+        filter: String,
+    },
+    /// Scrollable history of every notification this session.
+    Notifications {
+        scroll: usize,
+    },
+    /// Named layout preset picker: built-ins plus `[layout-preset.*]`
+    /// tables from config.
+    LayoutPicker {
+        names: Vec<String>,
+        selected: usize,
+    },
+    /// An agent tool write collided with unsaved edits in the open
+    /// buffer; the write sits in `pending_tool_patches` until the user
+    /// picks a side.
+    ToolWriteConflict { path: PathBuf, diff: String },
+    /// A multi-file agent edit held for per-file review; accepted files
+    /// are written together when the overlay is confirmed.
+    MultiFileReview {
+        files: Vec<ReviewFile>,
+        selected: usize,
+    },
+    /// Shell commands an agent reply proposed in ```sh fences, held for
+    /// approval before running in the terminal pane.
+    CommandApproval { commands: Vec<String> },
+    /// Agent tool calls that include a mutating tool, held until the
+    /// user approves. Deny resumes the exchange with refusal results so
+    /// the model can react.
+    ToolApproval {
+        transcript: serde_json::Value,
+        calls: Vec<crate::agent::tools::ToolCall>,
+        round: usize,
+    },
+    /// Saved agent conversations: reopen, rename, or delete past chats.
+    ConversationHistory {
+        items: Vec<crate::agent::history::ConversationMeta>,
+        selected: usize,
+    },
+    /// Connected MCP servers with their tools, resources, and prompt
+    /// templates; Enter reads a resource or expands a prompt into the
+    /// composer.
+    McpBrowser {
+        rows: Vec<McpRow>,
+        selected: usize,
+    },
+    /// Models the active provider reported; Enter switches the profile's
+    /// `model` and persists it to `agents.toml`.
+    ModelPicker {
+        names: Vec<String>,
+        selected: usize,
+    },
+    /// Discovered project tasks; Enter runs the selected one in the
+    /// terminal pane.
+    TaskPicker {
+        tasks: Vec<crate::tasks::Task>,
+        selected: usize,
+    },
+    /// Per-entry actions over the live conversation: copy, insert code
+    /// at the cursor, edit-and-resend user prompts, delete.
+    EntryActions { selected: usize },
+    /// Prompt templates from `[agent.templates]`; Enter expands the
+    /// selected one into the composer.
+    TemplatePicker {
+        names: Vec<String>,
+        selected: usize,
+    },
+    /// The tail of the provider transcript log, newest last; the selected
+    /// record's full body is shown under the list.
+    TranscriptLog {
+        records: Vec<crate::agent::transcript::TranscriptRecord>,
+        selected: usize,
+    },
+    /// Live keyword search across all saved conversations; Enter reopens
+    /// the matching session near the hit.
+    AgentHistorySearch {
+        input: String,
+        hits: Vec<crate::agent::history::SearchHit>,
+        selected: usize,
+    },
+    /// Theme switcher with live preview; `previous` restores the active
+    /// palette on cancel.
+    ThemePicker {
+        names: Vec<String>,
+        selected: usize,
+        previous: crate::ui::theme::Theme,
+        previous_name: String,
+    },
+}
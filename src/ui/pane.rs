@@ -0,0 +1,158 @@
+//! Per-pane view objects behind one trait.
+//!
+//! Pane state stays on [`App`]; the views are stateless handles that
+//! route rendering and focused input to the pane's implementation, so a
+//! new pane (tests, debugger…) plugs in by adding a [`Focus`] slot, a
+//! view struct, and an entry in [`PANES`] instead of editing every
+//! dispatch site.
+
+use crossterm::event::{KeyEvent, KeyModifiers, MouseEvent};
+use ratatui::layout::Rect;
+use ratatui::Frame;
+
+use crate::app::App;
+use crate::layout::Focus;
+
+/// One workspace pane: its rendering plus focused key and click
+/// handling.
+pub trait PaneView: Sync {
+    /// The [`Focus`] slot this view occupies.
+    fn focus(&self) -> Focus;
+    /// The pane's base title; renders may decorate it.
+    fn title(&self) -> &'static str;
+    fn render(&self, frame: &mut Frame, app: &mut App, area: Rect);
+    /// A key pressed while this pane has focus.
+    fn handle_key(&self, app: &mut App, key: KeyEvent);
+    /// A left-button press inside the pane (focus is already set).
+    fn handle_mouse(&self, _app: &mut App, _mouse: MouseEvent) {}
+}
+
+/// Every registered pane, in no particular order; use [`view_for`] to
+/// look one up by focus slot.
+pub const PANES: &[&dyn PaneView] = &[&TreeView, &EditorView, &TerminalView, &AgentView, &GitView];
+
+pub fn view_for(focus: Focus) -> &'static dyn PaneView {
+    PANES
+        .iter()
+        .copied()
+        .find(|view| view.focus() == focus)
+        .expect("every Focus slot has a registered pane view")
+}
+
+pub struct TreeView;
+
+impl PaneView for TreeView {
+    fn focus(&self) -> Focus {
+        Focus::Tree
+    }
+
+    fn title(&self) -> &'static str {
+        "files"
+    }
+
+    fn render(&self, frame: &mut Frame, app: &mut App, area: Rect) {
+        super::render_tree(frame, app, area);
+    }
+
+    fn handle_key(&self, app: &mut App, key: KeyEvent) {
+        crate::keyboard::handle_tree_key(app, key);
+    }
+
+    fn handle_mouse(&self, app: &mut App, mouse: MouseEvent) {
+        app.tree_click(mouse.row);
+        app.tree_drag_start(mouse.row);
+    }
+}
+
+pub struct EditorView;
+
+impl PaneView for EditorView {
+    fn focus(&self) -> Focus {
+        Focus::Editor
+    }
+
+    fn title(&self) -> &'static str {
+        "editor"
+    }
+
+    fn render(&self, frame: &mut Frame, app: &mut App, area: Rect) {
+        super::render_editor(frame, app, area);
+    }
+
+    fn handle_key(&self, app: &mut App, key: KeyEvent) {
+        crate::keyboard::handle_editor_key(app, key);
+    }
+
+    fn handle_mouse(&self, app: &mut App, mouse: MouseEvent) {
+        let alt = mouse.modifiers.contains(KeyModifiers::ALT);
+        app.editor_click(mouse.column, mouse.row, alt);
+    }
+}
+
+pub struct TerminalView;
+
+impl PaneView for TerminalView {
+    fn focus(&self) -> Focus {
+        Focus::Terminal
+    }
+
+    fn title(&self) -> &'static str {
+        "terminal"
+    }
+
+    fn render(&self, frame: &mut Frame, app: &mut App, area: Rect) {
+        super::render_terminal(frame, app, area);
+    }
+
+    fn handle_key(&self, app: &mut App, key: KeyEvent) {
+        crate::keyboard::handle_terminal_key(app, key);
+    }
+
+    fn handle_mouse(&self, app: &mut App, mouse: MouseEvent) {
+        app.terminal_click(mouse.row);
+    }
+}
+
+pub struct AgentView;
+
+impl PaneView for AgentView {
+    fn focus(&self) -> Focus {
+        Focus::Agent
+    }
+
+    fn title(&self) -> &'static str {
+        "agent"
+    }
+
+    fn render(&self, frame: &mut Frame, app: &mut App, area: Rect) {
+        super::render_agent(frame, app, area);
+    }
+
+    fn handle_key(&self, app: &mut App, key: KeyEvent) {
+        crate::keyboard::handle_agent_key(app, key);
+    }
+}
+
+pub struct GitView;
+
+impl PaneView for GitView {
+    fn focus(&self) -> Focus {
+        Focus::Git
+    }
+
+    fn title(&self) -> &'static str {
+        "git"
+    }
+
+    fn render(&self, frame: &mut Frame, app: &mut App, area: Rect) {
+        super::render_git(frame, app, area);
+    }
+
+    fn handle_key(&self, app: &mut App, key: KeyEvent) {
+        crate::keyboard::handle_git_key(app, key);
+    }
+
+    fn handle_mouse(&self, app: &mut App, mouse: MouseEvent) {
+        app.git_click(mouse.row);
+    }
+}
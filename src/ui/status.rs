@@ -0,0 +1,258 @@
+//! Status bar segments.
+//!
+//! Each segment is a named entry in a fixed registry; subsystems (git,
+//! LSP, the task manager, the clock) contribute their piece as a render
+//! function over [`App`], and `[ui] status_segments` in config.toml
+//! picks the order and visibility. Segments with a click action run
+//! their command when the cell is clicked.
+
+use crate::app::{App, CommandId};
+
+/// One registered status bar segment.
+pub struct StatusSegment {
+    pub name: &'static str,
+    /// The segment's current text; `None` hides it this frame.
+    pub render: fn(&App) -> Option<String>,
+    /// Command executed when the segment is clicked.
+    pub on_click: Option<CommandId>,
+}
+
+/// Every segment subsystems have registered.
+pub const SEGMENTS: &[StatusSegment] = &[
+    StatusSegment {
+        name: "tasks",
+        render: tasks,
+        on_click: Some(CommandId::CancelTasks),
+    },
+    StatusSegment {
+        name: "vim",
+        render: vim,
+        on_click: Some(CommandId::ToggleVim),
+    },
+    StatusSegment {
+        name: "overwrite",
+        render: overwrite,
+        on_click: None,
+    },
+    StatusSegment {
+        name: "readonly",
+        render: readonly,
+        on_click: None,
+    },
+    StatusSegment {
+        name: "stats",
+        render: stats,
+        on_click: None,
+    },
+    StatusSegment {
+        name: "pane",
+        render: pane,
+        on_click: None,
+    },
+    StatusSegment {
+        name: "git-branch",
+        render: git_branch,
+        on_click: Some(CommandId::ToggleGit),
+    },
+    StatusSegment {
+        name: "lsp",
+        render: lsp,
+        on_click: None,
+    },
+    StatusSegment {
+        name: "language",
+        render: language,
+        on_click: None,
+    },
+    StatusSegment {
+        name: "indent",
+        render: indent,
+        on_click: Some(CommandId::CycleIndent),
+    },
+    StatusSegment {
+        name: "line-ending",
+        render: line_ending,
+        on_click: Some(CommandId::ToggleLineEnding),
+    },
+    StatusSegment {
+        name: "encoding",
+        render: encoding,
+        on_click: Some(CommandId::ToggleEncoding),
+    },
+    StatusSegment {
+        name: "position",
+        render: position,
+        on_click: Some(CommandId::GotoLine),
+    },
+    StatusSegment {
+        name: "clock",
+        render: clock,
+        on_click: None,
+    },
+    StatusSegment {
+        name: "tokens",
+        render: tokens,
+        on_click: None,
+    },
+];
+
+/// The segments shown when config does not say otherwise, matching the
+/// bar's historical layout.
+pub const DEFAULT_ORDER: &[&str] = &[
+    "tasks",
+    "vim",
+    "overwrite",
+    "readonly",
+    "stats",
+    "pane",
+    "language",
+    "indent",
+    "line-ending",
+    "encoding",
+    "position",
+    "tokens",
+];
+
+pub fn by_name(name: &str) -> Option<&'static StatusSegment> {
+    SEGMENTS.iter().find(|segment| segment.name == name)
+}
+
+/// Resolve configured names into segments, collecting the unknown ones
+/// so config typos surface as a warning instead of vanishing silently.
+pub fn resolve(names: &[String]) -> (Vec<&'static StatusSegment>, Vec<String>) {
+    let mut segments = Vec::new();
+    let mut unknown = Vec::new();
+    for name in names {
+        match by_name(name) {
+            Some(segment) => segments.push(segment),
+            None => unknown.push(name.clone()),
+        }
+    }
+    (segments, unknown)
+}
+
+pub fn default_segments() -> Vec<&'static StatusSegment> {
+    DEFAULT_ORDER
+        .iter()
+        .filter_map(|name| by_name(name))
+        .collect()
+}
+
+fn tasks(app: &App) -> Option<String> {
+    match app.tasks.running().len() {
+        0 => None,
+        n => Some(format!("⟳{n}")),
+    }
+}
+
+fn vim(app: &App) -> Option<String> {
+    app.editor
+        .prefs
+        .vim_mode
+        .then(|| app.vim.mode.label().to_string())
+}
+
+fn overwrite(app: &App) -> Option<String> {
+    app.editor.overwrite.then(|| "OVR".to_string())
+}
+
+fn readonly(app: &App) -> Option<String> {
+    if app.read_only {
+        return Some("RO".to_string());
+    }
+    app.editor
+        .active_buffer()
+        .filter(|buffer| buffer.read_only)
+        .map(|_| "🔒".to_string())
+}
+
+fn stats(app: &App) -> Option<String> {
+    if !app.editor.prefs.show_stats {
+        return None;
+    }
+    let buffer = app.editor.active_buffer()?;
+    Some(match buffer.selection_stats() {
+        Some(sel) => format!("sel {}W {}C", sel.words, sel.chars),
+        None => {
+            let all = buffer.stats();
+            format!("{}L {}W {}C", all.lines, all.words, all.chars)
+        }
+    })
+}
+
+fn pane(app: &App) -> Option<String> {
+    Some(super::pane::view_for(app.focus).title().to_string())
+}
+
+fn git_branch(app: &App) -> Option<String> {
+    app.git.branch.as_ref().map(|branch| format!("⎇ {branch}"))
+}
+
+fn lsp(app: &App) -> Option<String> {
+    app.lsp.as_ref()?;
+    Some(if app.lsp_indexing {
+        "LSP…".to_string()
+    } else {
+        "LSP".to_string()
+    })
+}
+
+fn language(app: &App) -> Option<String> {
+    let buffer = app.editor.active_buffer()?;
+    Some(buffer.language.as_deref().unwrap_or("plain").to_string())
+}
+
+fn indent(app: &App) -> Option<String> {
+    app.editor
+        .active_buffer()
+        .map(|_| app.editor.indent().label())
+}
+
+fn line_ending(app: &App) -> Option<String> {
+    app.editor
+        .active_buffer()
+        .map(|buffer| buffer.line_ending.label().to_string())
+}
+
+fn encoding(app: &App) -> Option<String> {
+    app.editor
+        .active_buffer()
+        .map(|buffer| buffer.encoding.label().to_string())
+}
+
+fn position(app: &App) -> Option<String> {
+    app.editor.active_buffer().map(|buffer| {
+        format!(
+            "Ln {}, Col {}",
+            buffer.cursor.line + 1,
+            buffer.cursor.col + 1
+        )
+    })
+}
+
+fn clock(_app: &App) -> Option<String> {
+    Some(chrono::Local::now().format("%H:%M").to_string())
+}
+
+fn tokens(app: &App) -> Option<String> {
+    app.token_usage_summary()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn resolve_reports_unknown_names() {
+        let names = vec!["clock".to_string(), "wibble".to_string()];
+        let (segments, unknown) = resolve(&names);
+        assert_eq!(segments.len(), 1);
+        assert_eq!(segments[0].name, "clock");
+        assert_eq!(unknown, vec!["wibble".to_string()]);
+    }
+
+    #[test]
+    fn default_order_names_are_all_registered() {
+        assert_eq!(default_segments().len(), DEFAULT_ORDER.len());
+    }
+}
@@ -0,0 +1,206 @@
+//! The runtime UI palette.
+//!
+//! Every pane pulls its colors from the active [`Theme`] through the
+//! role accessors below, so the look stays consistent. Built-in `dark`
+//! and `light` themes ship with the binary; user palettes live in
+//! `~/.config/clide/themes/<name>.toml` and can be switched or reloaded
+//! at runtime from the command palette.
+
+use std::path::PathBuf;
+use std::sync::RwLock;
+
+use anyhow::{bail, Context, Result};
+use ratatui::style::Color;
+use serde::Deserialize;
+
+macro_rules! roles {
+    ($($field:ident / $accessor:ident : $key:literal = $dark:expr, $light:expr;)*) => {
+        /// Colors for every UI role.
+        #[derive(Debug, Clone, Copy, PartialEq, Eq)]
+        pub struct Theme {
+            $(pub $field: Color,)*
+        }
+
+        impl Theme {
+            pub fn dark() -> Self {
+                Self { $($field: $dark,)* }
+            }
+
+            pub fn light() -> Self {
+                Self { $($field: $light,)* }
+            }
+
+            fn set_role(&mut self, key: &str, color: Color) -> bool {
+                match key {
+                    $($key => { self.$field = color; true })*
+                    _ => false,
+                }
+            }
+        }
+
+        $(pub fn $accessor() -> Color {
+            current().$field
+        })*
+    };
+}
+
+roles! {
+    foreground / foreground: "foreground" = Color::Gray, Color::Black;
+    accent / accent: "accent" = Color::Cyan, Color::Blue;
+    accent_dim / accent_dim: "accent-dim" = Color::DarkGray, Color::Gray;
+    selection_bg / selection_bg: "selection-bg" = Color::Rgb(50, 60, 80), Color::Rgb(190, 205, 230);
+    cursor_line_bg / cursor_line_bg: "cursor-line-bg" = Color::Rgb(35, 38, 46), Color::Rgb(230, 232, 238);
+    gutter / gutter: "gutter" = Color::DarkGray, Color::Gray;
+    gutter_current / gutter_current: "gutter-current" = Color::White, Color::Black;
+    border / border: "border" = Color::DarkGray, Color::Gray;
+    border_focused / border_focused: "border-focused" = Color::Cyan, Color::Blue;
+    status_bg / status_bg: "status-bg" = Color::Rgb(30, 33, 40), Color::Rgb(215, 218, 226);
+    status_fg / status_fg: "status-fg" = Color::Gray, Color::Black;
+    error / error: "error" = Color::Red, Color::Red;
+    warning / warning: "warning" = Color::Yellow, Color::Rgb(150, 110, 0);
+    info / info: "info" = Color::Blue, Color::Blue;
+    success / success: "success" = Color::Green, Color::Rgb(0, 110, 0);
+    dirty / dirty: "dirty" = Color::Yellow, Color::Rgb(150, 110, 0);
+    agent_user / agent_user: "agent-user" = Color::Cyan, Color::Blue;
+    agent_response / agent_response: "agent-response" = Color::Gray, Color::Black;
+    agent_info / agent_info: "agent-info" = Color::DarkGray, Color::Gray;
+    tree_dir / tree_dir: "tree-dir" = Color::Blue, Color::Blue;
+    tree_file / tree_file: "tree-file" = Color::Gray, Color::Black;
+}
+
+static CURRENT: RwLock<Option<Theme>> = RwLock::new(None);
+
+pub fn current() -> Theme {
+    CURRENT
+        .read()
+        .ok()
+        .and_then(|guard| *guard)
+        .unwrap_or_else(Theme::dark)
+}
+
+pub fn set_current(theme: Theme) {
+    if let Ok(mut guard) = CURRENT.write() {
+        *guard = Some(theme);
+    }
+}
+
+/// On-disk theme file: an optional base palette plus role overrides.
+#[derive(Debug, Deserialize)]
+struct ThemeFile {
+    /// `dark` (default) or `light`.
+    base: Option<String>,
+    #[serde(default)]
+    colors: std::collections::HashMap<String, String>,
+}
+
+impl Theme {
+    pub fn from_toml(text: &str) -> Result<Self> {
+        let file: ThemeFile = toml::from_str(text).context("failed to parse theme")?;
+        let mut theme = match file.base.as_deref() {
+            None | Some("dark") => Theme::dark(),
+            Some("light") => Theme::light(),
+            Some(other) => bail!("unknown base theme {other:?}"),
+        };
+        for (key, value) in &file.colors {
+            let color = parse_color(value)?;
+            if !theme.set_role(key, color) {
+                bail!("unknown color role {key:?}");
+            }
+        }
+        Ok(theme)
+    }
+}
+
+/// Parse `#rrggbb` or a basic terminal color name.
+fn parse_color(text: &str) -> Result<Color> {
+    if let Some(hex) = text.strip_prefix('#') {
+        if hex.len() != 6 {
+            bail!("bad hex color {text:?}");
+        }
+        let value =
+            u32::from_str_radix(hex, 16).with_context(|| format!("bad hex color {text:?}"))?;
+        return Ok(Color::Rgb(
+            (value >> 16) as u8,
+            (value >> 8) as u8,
+            value as u8,
+        ));
+    }
+    let color = match text.to_ascii_lowercase().as_str() {
+        "black" => Color::Black,
+        "red" => Color::Red,
+        "green" => Color::Green,
+        "yellow" => Color::Yellow,
+        "blue" => Color::Blue,
+        "magenta" => Color::Magenta,
+        "cyan" => Color::Cyan,
+        "gray" | "grey" => Color::Gray,
+        "darkgray" | "darkgrey" => Color::DarkGray,
+        "white" => Color::White,
+        other => bail!("unknown color {other:?}"),
+    };
+    Ok(color)
+}
+
+fn themes_dir() -> Option<PathBuf> {
+    dirs::config_dir().map(|dir| dir.join("clide").join("themes"))
+}
+
+/// The selectable theme names: built-ins first, then any `*.toml` files
+/// in the user themes directory.
+pub fn available_themes() -> Vec<String> {
+    let mut names = vec!["dark".to_string(), "light".to_string()];
+    if let Some(dir) = themes_dir() {
+        if let Ok(entries) = std::fs::read_dir(dir) {
+            for entry in entries.flatten() {
+                let path = entry.path();
+                if path.extension().and_then(|e| e.to_str()) == Some("toml") {
+                    if let Some(stem) = path.file_stem().and_then(|s| s.to_str()) {
+                        names.push(stem.to_string());
+                    }
+                }
+            }
+        }
+    }
+    names.sort();
+    names.dedup();
+    names
+}
+
+/// Resolve a theme name: a built-in, or a user theme file (re-read from
+/// disk each call, so reloading picks up edits).
+pub fn load_named(name: &str) -> Result<Theme> {
+    match name {
+        "dark" => Ok(Theme::dark()),
+        "light" => Ok(Theme::light()),
+        _ => {
+            let dir = themes_dir().context("no config directory")?;
+            let path = dir.join(format!("{name}.toml"));
+            let text = std::fs::read_to_string(&path)
+                .with_context(|| format!("failed to read {}", path.display()))?;
+            Theme::from_toml(&text)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn theme_file_overrides_base_roles() {
+        let theme = Theme::from_toml(
+            r##"
+            base = "light"
+            [colors]
+            accent = "#ff0080"
+            gutter = "darkgray"
+            "##,
+        )
+        .unwrap();
+        assert_eq!(theme.accent, Color::Rgb(255, 0, 128));
+        assert_eq!(theme.gutter, Color::DarkGray);
+        assert_eq!(theme.foreground, Theme::light().foreground);
+        assert!(Theme::from_toml("[colors]\nbogus = \"red\"").is_err());
+        assert!(parse_color("#12345").is_err());
+    }
+}
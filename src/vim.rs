@@ -0,0 +1,563 @@
+//! Optional Vim-style modal editing.
+//!
+//! When `vim = true` is set in the `[editor]` config table, a small
+//! state machine sits in front of the regular editor key handling:
+//! normal and visual mode consume unmodified keys (motions, counts,
+//! `d`/`c`/`y` operators), insert mode passes everything through except
+//! Esc. Modified chords keep their usual meaning in every mode, so
+//! Ctrl+S, the palette, and the keymap still work.
+
+use crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
+
+use crate::app::App;
+use crate::editor::{Buffer, Position};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum VimMode {
+    #[default]
+    Normal,
+    Insert,
+    Visual,
+}
+
+impl VimMode {
+    pub fn label(self) -> &'static str {
+        match self {
+            VimMode::Normal => "NORMAL",
+            VimMode::Insert => "INSERT",
+            VimMode::Visual => "VISUAL",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Operator {
+    Delete,
+    Change,
+    Yank,
+}
+
+/// Pending modal state: the mode plus any count, operator, or `g` prefix
+/// waiting for the rest of its command.
+#[derive(Debug, Default)]
+pub struct VimState {
+    pub mode: VimMode,
+    count: Option<usize>,
+    operator: Option<Operator>,
+    pending_g: bool,
+}
+
+impl VimState {
+    fn take_count(&mut self) -> usize {
+        self.count.take().unwrap_or(1).max(1)
+    }
+
+    fn reset_pending(&mut self) {
+        self.count = None;
+        self.operator = None;
+        self.pending_g = false;
+    }
+}
+
+/// Feed one editor key through the modal layer. Returns true when the
+/// key was consumed.
+pub fn handle_key(app: &mut App, key: KeyEvent) -> bool {
+    if key
+        .modifiers
+        .intersects(KeyModifiers::CONTROL | KeyModifiers::ALT)
+    {
+        return false;
+    }
+    match app.vim.mode {
+        VimMode::Insert => {
+            if key.code == KeyCode::Esc {
+                app.vim.mode = VimMode::Normal;
+                app.vim.reset_pending();
+                return true;
+            }
+            false
+        }
+        VimMode::Normal | VimMode::Visual => handle_command_key(app, key),
+    }
+}
+
+fn handle_command_key(app: &mut App, key: KeyEvent) -> bool {
+    let visual = app.vim.mode == VimMode::Visual;
+    let KeyCode::Char(c) = key.code else {
+        if key.code == KeyCode::Esc {
+            app.vim.reset_pending();
+            if visual {
+                leave_visual(app);
+            }
+            return true;
+        }
+        // Arrows, Home/End etc. keep the default behavior.
+        return false;
+    };
+
+    // A count prefix; a bare `0` is the line-start motion instead.
+    if c.is_ascii_digit() && !(c == '0' && app.vim.count.is_none()) {
+        let digit = c as usize - '0' as usize;
+        app.vim.count = Some(app.vim.count.unwrap_or(0).saturating_mul(10) + digit);
+        return true;
+    }
+
+    if app.vim.pending_g {
+        app.vim.pending_g = false;
+        if c == 'g' {
+            run_motion(app, Motion::FileStart);
+        } else {
+            app.vim.reset_pending();
+        }
+        return true;
+    }
+
+    if let Some(motion) = motion_for(c) {
+        run_motion(app, motion);
+        return true;
+    }
+
+    match c {
+        'g' => app.vim.pending_g = true,
+        'v' => {
+            if visual {
+                leave_visual(app);
+            } else {
+                app.vim.mode = VimMode::Visual;
+                if let Some(buffer) = app.editor.active_buffer_mut() {
+                    if buffer.anchor.is_none() {
+                        buffer.anchor = Some(buffer.cursor);
+                    }
+                }
+            }
+        }
+        'i' | 'a' | 'A' | 'I' | 'o' | 'O' => enter_insert(app, c),
+        'd' | 'c' | 'y' => {
+            let op = match c {
+                'd' => Operator::Delete,
+                'c' => Operator::Change,
+                _ => Operator::Yank,
+            };
+            if visual {
+                apply_to_selection(app, op);
+            } else if app.vim.operator == Some(op) {
+                // `dd`/`cc`/`yy`: the doubled operator works linewise.
+                app.vim.operator = None;
+                apply_linewise(app, op);
+            } else {
+                app.vim.operator = Some(op);
+            }
+        }
+        'x' => {
+            if visual {
+                apply_to_selection(app, Operator::Delete);
+            } else if can_edit(app) {
+                let count = app.vim.take_count();
+                if let Some(buffer) = app.editor.active_buffer_mut() {
+                    let start = buffer.char_index(buffer.cursor);
+                    let end = (start + count).min(buffer.rope.len_chars());
+                    if end > start {
+                        buffer.replace_range(start, end, "");
+                    }
+                }
+                app.notify_buffer_changed();
+            }
+        }
+        'p' => {
+            if can_edit(app) && !app.clipboard.is_empty() {
+                let text = app.clipboard.clone();
+                if let Some(buffer) = app.editor.active_buffer_mut() {
+                    buffer.insert_str(&text);
+                }
+                app.notify_buffer_changed();
+            }
+        }
+        'u' => {
+            let undone = app
+                .editor
+                .active_buffer_mut()
+                .is_some_and(|buffer| buffer.undo());
+            if undone {
+                app.notify_buffer_changed();
+            }
+        }
+        _ => {
+            app.vim.reset_pending();
+        }
+    }
+    true
+}
+
+/// Motions shared by plain movement, visual extension, and operators.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Motion {
+    Left,
+    Right,
+    Up,
+    Down,
+    WordForward,
+    WordBack,
+    WordEnd,
+    LineStart,
+    LineEnd,
+    FileStart,
+    FileEnd,
+}
+
+fn motion_for(c: char) -> Option<Motion> {
+    Some(match c {
+        'h' => Motion::Left,
+        'l' => Motion::Right,
+        'j' => Motion::Down,
+        'k' => Motion::Up,
+        'w' => Motion::WordForward,
+        'b' => Motion::WordBack,
+        'e' => Motion::WordEnd,
+        '0' => Motion::LineStart,
+        '$' => Motion::LineEnd,
+        'G' => Motion::FileEnd,
+        _ => return None,
+    })
+}
+
+fn run_motion(app: &mut App, motion: Motion) {
+    let count = app.vim.take_count();
+    if let Some(op) = app.vim.operator.take() {
+        apply_with_motion(app, op, motion, count);
+        return;
+    }
+    let select = app.vim.mode == VimMode::Visual;
+    let Some(buffer) = app.editor.active_buffer_mut() else {
+        return;
+    };
+    let target = motion_target(buffer, motion, count);
+    if select && buffer.anchor.is_none() {
+        buffer.anchor = Some(buffer.cursor);
+    }
+    if !select {
+        buffer.anchor = None;
+    }
+    buffer.extra_cursors.clear();
+    buffer.cursor = target;
+    buffer.clamp_cursor();
+}
+
+/// Where `motion` lands, `count` times, without moving the cursor.
+fn motion_target(buffer: &Buffer, motion: Motion, count: usize) -> Position {
+    let mut pos = buffer.cursor;
+    match motion {
+        Motion::Left => {
+            let idx = buffer.char_index(pos);
+            pos = buffer.position_of(idx.saturating_sub(count));
+        }
+        Motion::Right => {
+            let idx = buffer.char_index(pos);
+            pos = buffer.position_of(idx + count);
+        }
+        Motion::Up => {
+            pos.line = pos.line.saturating_sub(count);
+            pos.col = pos.col.min(buffer.line_len(pos.line));
+        }
+        Motion::Down => {
+            pos.line = (pos.line + count).min(buffer.line_count().saturating_sub(1));
+            pos.col = pos.col.min(buffer.line_len(pos.line));
+        }
+        Motion::WordForward => {
+            for _ in 0..count {
+                pos = word_forward(buffer, pos);
+            }
+        }
+        Motion::WordBack => {
+            for _ in 0..count {
+                pos = word_back(buffer, pos);
+            }
+        }
+        Motion::WordEnd => {
+            for _ in 0..count {
+                pos = word_end(buffer, pos);
+            }
+        }
+        Motion::LineStart => pos.col = 0,
+        Motion::LineEnd => pos.col = buffer.line_len(pos.line),
+        // `{count}gg`/`{count}G` jump to that line; bare `gg`/`G` go to
+        // the first/last line.
+        Motion::FileStart | Motion::FileEnd => {
+            let last = buffer.line_count().saturating_sub(1);
+            pos.line = if count > 1 {
+                (count - 1).min(last)
+            } else if motion == Motion::FileStart {
+                0
+            } else {
+                last
+            };
+            pos.col = 0;
+        }
+    }
+    pos
+}
+
+fn is_linewise(motion: Motion) -> bool {
+    matches!(
+        motion,
+        Motion::Up | Motion::Down | Motion::FileStart | Motion::FileEnd
+    )
+}
+
+fn apply_with_motion(app: &mut App, op: Operator, motion: Motion, count: usize) {
+    if op != Operator::Yank && !can_edit(app) {
+        return;
+    }
+    let Some(buffer) = app.editor.active_buffer_mut() else {
+        return;
+    };
+    let target = motion_target(buffer, motion, count);
+    let (start, end) = if is_linewise(motion) {
+        line_range(buffer, buffer.cursor.line.min(target.line), buffer.cursor.line.max(target.line))
+    } else {
+        let a = buffer.char_index(buffer.cursor);
+        let b = buffer.char_index(target);
+        let (start, mut end) = (a.min(b), a.max(b));
+        if motion == Motion::WordEnd {
+            end = (end + 1).min(buffer.rope.len_chars());
+        }
+        (start, end)
+    };
+    finish_operator(app, op, start, end);
+}
+
+fn apply_linewise(app: &mut App, op: Operator) {
+    if op != Operator::Yank && !can_edit(app) {
+        return;
+    }
+    let count = app.vim.take_count();
+    let Some(buffer) = app.editor.active_buffer() else {
+        return;
+    };
+    let first = buffer.cursor.line;
+    let last = (first + count - 1).min(buffer.line_count().saturating_sub(1));
+    let (start, end) = line_range(buffer, first, last);
+    finish_operator(app, op, start, end);
+}
+
+fn apply_to_selection(app: &mut App, op: Operator) {
+    if op != Operator::Yank && !can_edit(app) {
+        return;
+    }
+    let Some((start, end)) = app
+        .editor
+        .active_buffer()
+        .and_then(Buffer::selection_range)
+    else {
+        leave_visual(app);
+        return;
+    };
+    finish_operator(app, op, start, end);
+    if app.vim.mode == VimMode::Visual {
+        leave_visual(app);
+    }
+}
+
+/// Char range covering whole lines `first..=last`, newline included.
+fn line_range(buffer: &Buffer, first: usize, last: usize) -> (usize, usize) {
+    let start = buffer.rope.line_to_char(first);
+    let end = if last + 1 < buffer.line_count() {
+        buffer.rope.line_to_char(last + 1)
+    } else {
+        buffer.rope.len_chars()
+    };
+    (start, end)
+}
+
+fn finish_operator(app: &mut App, op: Operator, start: usize, end: usize) {
+    if end <= start {
+        return;
+    }
+    let Some(buffer) = app.editor.active_buffer_mut() else {
+        return;
+    };
+    let text = buffer.rope.slice(start..end).to_string();
+    match op {
+        Operator::Yank => {
+            buffer.anchor = None;
+            app.copy_to_clipboard(text);
+            app.set_status("yanked");
+        }
+        Operator::Delete | Operator::Change => {
+            buffer.replace_range(start, end, "");
+            app.copy_to_clipboard(text);
+            app.notify_buffer_changed();
+            if op == Operator::Change {
+                app.vim.mode = VimMode::Insert;
+            }
+        }
+    }
+}
+
+fn enter_insert(app: &mut App, c: char) {
+    // `o`/`O` open a new line, which edits the buffer.
+    if matches!(c, 'o' | 'O') && !can_edit(app) {
+        return;
+    }
+    let Some(buffer) = app.editor.active_buffer_mut() else {
+        return;
+    };
+    buffer.anchor = None;
+    let mut edited = false;
+    match c {
+        'a' => buffer.move_cursor(1, 0, false),
+        'A' => buffer.move_end(false),
+        'I' => buffer.move_home(false),
+        'o' => {
+            buffer.move_end(false);
+            buffer.insert_newline();
+            edited = true;
+        }
+        'O' => {
+            buffer.move_home(false);
+            buffer.insert_newline();
+            buffer.move_cursor(0, -1, false);
+            edited = true;
+        }
+        _ => {}
+    }
+    app.vim.mode = VimMode::Insert;
+    app.vim.reset_pending();
+    if edited {
+        app.notify_buffer_changed();
+    }
+}
+
+fn leave_visual(app: &mut App) {
+    app.vim.mode = VimMode::Normal;
+    if let Some(buffer) = app.editor.active_buffer_mut() {
+        buffer.anchor = None;
+    }
+}
+
+/// Edits are blocked in read-only mode and read-only buffer views, same
+/// as the regular key handler.
+fn can_edit(app: &mut App) -> bool {
+    if app.read_only {
+        app.set_status("read-only mode");
+        return false;
+    }
+    let blocked = app
+        .editor
+        .active_buffer()
+        .is_some_and(|b| b.is_read_only());
+    if blocked {
+        app.set_status("buffer is read-only");
+        return false;
+    }
+    true
+}
+
+/// `0` whitespace, `1` word chars, `2` punctuation - vim's three word
+/// classes.
+fn char_class(c: char) -> u8 {
+    if c.is_whitespace() {
+        0
+    } else if c.is_alphanumeric() || c == '_' {
+        1
+    } else {
+        2
+    }
+}
+
+fn char_at(buffer: &Buffer, idx: usize) -> Option<char> {
+    (idx < buffer.rope.len_chars()).then(|| buffer.rope.char(idx))
+}
+
+fn word_forward(buffer: &Buffer, pos: Position) -> Position {
+    let mut idx = buffer.char_index(pos);
+    let Some(start) = char_at(buffer, idx) else {
+        return pos;
+    };
+    let class = char_class(start);
+    while char_at(buffer, idx).is_some_and(|c| char_class(c) == class && class != 0) {
+        idx += 1;
+    }
+    while char_at(buffer, idx).is_some_and(|c| char_class(c) == 0) {
+        idx += 1;
+    }
+    buffer.position_of(idx)
+}
+
+fn word_back(buffer: &Buffer, pos: Position) -> Position {
+    let mut idx = buffer.char_index(pos);
+    while idx > 0 && char_at(buffer, idx - 1).is_some_and(|c| char_class(c) == 0) {
+        idx -= 1;
+    }
+    if idx == 0 {
+        return buffer.position_of(0);
+    }
+    let class = char_class(buffer.rope.char(idx - 1));
+    while idx > 0 && char_class(buffer.rope.char(idx - 1)) == class {
+        idx -= 1;
+    }
+    buffer.position_of(idx)
+}
+
+fn word_end(buffer: &Buffer, pos: Position) -> Position {
+    let mut idx = buffer.char_index(pos) + 1;
+    while char_at(buffer, idx).is_some_and(|c| char_class(c) == 0) {
+        idx += 1;
+    }
+    let Some(start) = char_at(buffer, idx) else {
+        return pos;
+    };
+    let class = char_class(start);
+    while char_at(buffer, idx + 1).is_some_and(|c| char_class(c) == class) {
+        idx += 1;
+    }
+    buffer.position_of(idx)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn buf(text: &str) -> Buffer {
+        Buffer::new(None, text)
+    }
+
+    #[test]
+    fn word_motions_respect_word_classes() {
+        let b = buf("foo bar_baz, qux\n");
+        let start = Position { line: 0, col: 0 };
+        assert_eq!(word_forward(&b, start), Position { line: 0, col: 4 });
+        assert_eq!(
+            word_forward(&b, Position { line: 0, col: 4 }),
+            Position { line: 0, col: 11 }
+        );
+        assert_eq!(word_end(&b, start), Position { line: 0, col: 2 });
+        assert_eq!(
+            word_back(&b, Position { line: 0, col: 13 }),
+            Position { line: 0, col: 11 }
+        );
+    }
+
+    #[test]
+    fn motion_targets_handle_counts_and_lines() {
+        let b = buf("one\ntwo\nthree\nfour\n");
+        let origin = Position { line: 0, col: 1 };
+        let mut b = b;
+        b.cursor = origin;
+        assert_eq!(
+            motion_target(&b, Motion::Down, 2),
+            Position { line: 2, col: 1 }
+        );
+        assert_eq!(
+            motion_target(&b, Motion::FileEnd, 1),
+            Position { line: 4, col: 0 }
+        );
+        assert_eq!(
+            motion_target(&b, Motion::FileEnd, 3),
+            Position { line: 2, col: 0 }
+        );
+        assert_eq!(
+            motion_target(&b, Motion::LineEnd, 1),
+            Position { line: 0, col: 3 }
+        );
+    }
+}
@@ -0,0 +1,664 @@
+//! Workspace state: the file tree pane and filesystem operations.
+
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use anyhow::{Context, Result};
+
+/// Which glyph family the tree renderer uses for entries.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum IconSet {
+    Emoji,
+    /// Plain Unicode glyphs that render everywhere, for terminals
+    /// without emoji or patched fonts.
+    Unicode,
+    /// Nerd Font private-use glyphs; requires a patched font.
+    NerdFont,
+}
+
+impl IconSet {
+    /// Pick an icon set for this terminal: an explicit `CLIDE_ICONS`
+    /// setting wins, then the conventional `NERD_FONT` marker variable,
+    /// then emoji.
+    pub fn detect() -> Self {
+        match std::env::var("CLIDE_ICONS").as_deref() {
+            Ok("emoji") => return IconSet::Emoji,
+            Ok("unicode") => return IconSet::Unicode,
+            Ok("nerd") | Ok("nerd-font") => return IconSet::NerdFont,
+            _ => {}
+        }
+        if std::env::var_os("NERD_FONT").is_some() {
+            IconSet::NerdFont
+        } else {
+            IconSet::Emoji
+        }
+    }
+
+    pub fn label(self) -> &'static str {
+        match self {
+            IconSet::Emoji => "emoji",
+            IconSet::Unicode => "unicode",
+            IconSet::NerdFont => "nerd-font",
+        }
+    }
+}
+
+/// How sibling entries are ordered inside each directory; directories
+/// always sort before files regardless of mode.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum SortMode {
+    /// Plain byte-wise filename comparison.
+    Name,
+    /// Natural ordering: digit runs compare numerically (`file2` before
+    /// `file10`) and letters compare case-insensitively, so names that
+    /// differ only in case or numbering stay adjacent.
+    #[default]
+    Natural,
+    /// Most recently modified first.
+    Mtime,
+    /// Largest first.
+    Size,
+}
+
+impl SortMode {
+    pub fn label(self) -> &'static str {
+        match self {
+            SortMode::Name => "name",
+            SortMode::Natural => "natural",
+            SortMode::Mtime => "mtime",
+            SortMode::Size => "size",
+        }
+    }
+
+    pub fn next(self) -> Self {
+        match self {
+            SortMode::Name => SortMode::Natural,
+            SortMode::Natural => SortMode::Mtime,
+            SortMode::Mtime => SortMode::Size,
+            SortMode::Size => SortMode::Name,
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "name" => Some(SortMode::Name),
+            "natural" => Some(SortMode::Natural),
+            "mtime" => Some(SortMode::Mtime),
+            "size" => Some(SortMode::Size),
+            _ => None,
+        }
+    }
+}
+
+/// Natural comparison: digit runs are compared as numbers, everything
+/// else case-insensitively per character. Ties (case-only differences)
+/// fall back to the plain ordering so the result stays total.
+fn natural_cmp(a: &str, b: &str) -> std::cmp::Ordering {
+    use std::cmp::Ordering;
+
+    let mut ac = a.chars().peekable();
+    let mut bc = b.chars().peekable();
+    loop {
+        match (ac.peek().copied(), bc.peek().copied()) {
+            (None, None) => return a.cmp(b),
+            (None, Some(_)) => return Ordering::Less,
+            (Some(_), None) => return Ordering::Greater,
+            (Some(x), Some(y)) if x.is_ascii_digit() && y.is_ascii_digit() => {
+                let xn = take_digits(&mut ac);
+                let yn = take_digits(&mut bc);
+                match cmp_digit_runs(&xn, &yn) {
+                    Ordering::Equal => {}
+                    ord => return ord,
+                }
+            }
+            (Some(x), Some(y)) => match x.to_lowercase().cmp(y.to_lowercase()) {
+                Ordering::Equal => {
+                    ac.next();
+                    bc.next();
+                }
+                ord => return ord,
+            },
+        }
+    }
+}
+
+fn take_digits(chars: &mut std::iter::Peekable<std::str::Chars>) -> String {
+    let mut out = String::new();
+    while let Some(c) = chars.peek().copied() {
+        if !c.is_ascii_digit() {
+            break;
+        }
+        out.push(c);
+        chars.next();
+    }
+    out
+}
+
+/// Compare two ASCII digit runs numerically without parsing (runs may
+/// exceed u64): strip leading zeros, then longer is larger, then
+/// lexicographic decides.
+fn cmp_digit_runs(a: &str, b: &str) -> std::cmp::Ordering {
+    let a = a.trim_start_matches('0');
+    let b = b.trim_start_matches('0');
+    a.len().cmp(&b.len()).then_with(|| a.cmp(b))
+}
+
+/// One visible row in the flattened tree.
+#[derive(Debug, Clone)]
+pub struct TreeEntry {
+    pub path: PathBuf,
+    pub depth: usize,
+    pub is_dir: bool,
+    pub expanded: bool,
+}
+
+/// The file tree pane: a lazily-expanded view of the workspace root.
+pub struct FileTree {
+    pub root: PathBuf,
+    pub entries: Vec<TreeEntry>,
+    pub selected: usize,
+    pub scroll: usize,
+    pub show_hidden: bool,
+    pub icon_set: IconSet,
+    pub sort_mode: SortMode,
+    expanded: Vec<PathBuf>,
+}
+
+/// One directory child with the metadata the sort modes need.
+struct Child {
+    path: PathBuf,
+    is_dir: bool,
+    mtime: std::time::SystemTime,
+    size: u64,
+}
+
+impl FileTree {
+    pub fn new(root: PathBuf) -> Self {
+        let mut tree = Self {
+            root,
+            entries: Vec::new(),
+            selected: 0,
+            scroll: 0,
+            show_hidden: false,
+            icon_set: IconSet::detect(),
+            sort_mode: SortMode::default(),
+            expanded: Vec::new(),
+        };
+        tree.refresh();
+        tree
+    }
+
+    /// Rebuild the flattened entry list from disk, preserving expansion.
+    pub fn refresh(&mut self) {
+        let entries = Self::scan(&self.root, self.show_hidden, self.sort_mode, &self.expanded);
+        self.adopt(entries);
+    }
+
+    /// Build the flattened entry list for `root`. Free of pane state so
+    /// background rescans can run it off-thread.
+    pub fn scan(
+        root: &Path,
+        show_hidden: bool,
+        sort_mode: SortMode,
+        expanded: &[PathBuf],
+    ) -> Vec<TreeEntry> {
+        let mut entries = Vec::new();
+        Self::scan_dir(&mut entries, root, 0, show_hidden, sort_mode, expanded);
+        entries
+    }
+
+    /// The directories currently expanded, for snapshotting a background
+    /// scan's inputs.
+    pub fn expanded_paths(&self) -> &[PathBuf] {
+        &self.expanded
+    }
+
+    /// Replace the entries with a finished scan, preserving the selection
+    /// by path where possible.
+    pub fn adopt(&mut self, entries: Vec<TreeEntry>) {
+        let selected_path = self.selected_entry().map(|e| e.path.clone());
+        self.entries = entries;
+        if let Some(path) = selected_path {
+            if let Some(idx) = self.entries.iter().position(|e| e.path == path) {
+                self.selected = idx;
+            }
+        }
+        self.selected = self.selected.min(self.entries.len().saturating_sub(1));
+    }
+
+    fn scan_dir(
+        out: &mut Vec<TreeEntry>,
+        dir: &Path,
+        depth: usize,
+        show_hidden: bool,
+        mode: SortMode,
+        expanded: &[PathBuf],
+    ) {
+        let mut children = match Self::read_children(dir, show_hidden) {
+            Ok(c) => c,
+            Err(_) => return,
+        };
+        children.sort_by(|a, b| {
+            let name = |c: &Child| c.path.file_name().map(|n| n.to_os_string());
+            b.is_dir.cmp(&a.is_dir).then_with(|| match mode {
+                SortMode::Name => name(a).cmp(&name(b)),
+                SortMode::Natural => natural_cmp(
+                    &a.path.file_name().unwrap_or_default().to_string_lossy(),
+                    &b.path.file_name().unwrap_or_default().to_string_lossy(),
+                ),
+                SortMode::Mtime => b.mtime.cmp(&a.mtime).then_with(|| name(a).cmp(&name(b))),
+                SortMode::Size => b.size.cmp(&a.size).then_with(|| name(a).cmp(&name(b))),
+            })
+        });
+        for child in children {
+            let is_expanded = child.is_dir && expanded.contains(&child.path);
+            out.push(TreeEntry {
+                path: child.path.clone(),
+                depth,
+                is_dir: child.is_dir,
+                expanded: is_expanded,
+            });
+            if is_expanded {
+                Self::scan_dir(out, &child.path, depth + 1, show_hidden, mode, expanded);
+            }
+        }
+    }
+
+    fn read_children(dir: &Path, show_hidden: bool) -> Result<Vec<Child>> {
+        let mut out = Vec::new();
+        for entry in fs::read_dir(dir)? {
+            let entry = entry?;
+            let name = entry.file_name();
+            let name = name.to_string_lossy();
+            if !show_hidden && name.starts_with('.') {
+                continue;
+            }
+            if name == "target" || name == "node_modules" {
+                continue;
+            }
+            let is_dir = entry.file_type().map(|t| t.is_dir()).unwrap_or(false);
+            let meta = entry.metadata().ok();
+            out.push(Child {
+                path: entry.path(),
+                is_dir,
+                mtime: meta
+                    .as_ref()
+                    .and_then(|m| m.modified().ok())
+                    .unwrap_or(std::time::SystemTime::UNIX_EPOCH),
+                size: meta.map(|m| m.len()).unwrap_or(0),
+            });
+        }
+        Ok(out)
+    }
+
+    /// Advance to the next sort mode and re-sort, returning the new
+    /// mode's label for the status line.
+    pub fn cycle_sort_mode(&mut self) -> &'static str {
+        self.sort_mode = self.sort_mode.next();
+        self.refresh();
+        self.sort_mode.label()
+    }
+
+    pub fn selected_entry(&self) -> Option<&TreeEntry> {
+        self.entries.get(self.selected)
+    }
+
+    pub fn move_selection(&mut self, delta: isize) {
+        if self.entries.is_empty() {
+            return;
+        }
+        let len = self.entries.len() as isize;
+        self.selected = (self.selected as isize + delta).clamp(0, len - 1) as usize;
+    }
+
+    /// Toggle expansion of the selected directory; returns the path of a
+    /// selected file (for the caller to open) when the entry is not a dir.
+    pub fn activate_selected(&mut self) -> Option<PathBuf> {
+        let entry = self.entries.get(self.selected)?.clone();
+        if entry.is_dir {
+            if let Some(idx) = self.expanded.iter().position(|p| p == &entry.path) {
+                self.expanded.remove(idx);
+            } else {
+                self.expanded.push(entry.path.clone());
+            }
+            self.refresh();
+            None
+        } else {
+            Some(entry.path)
+        }
+    }
+
+    /// Directories currently expanded, for session persistence.
+    pub fn expanded_dirs(&self) -> &[PathBuf] {
+        &self.expanded
+    }
+
+    /// Replace the expansion state, e.g. from a restored session.
+    pub fn set_expanded(&mut self, dirs: Vec<PathBuf>) {
+        self.expanded = dirs;
+        self.refresh();
+    }
+
+    pub fn toggle_hidden(&mut self) {
+        self.show_hidden = !self.show_hidden;
+        self.refresh();
+    }
+
+    pub fn create_file(&mut self, name: &str) -> Result<PathBuf> {
+        let parent = self.selected_dir();
+        let path = parent.join(name);
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir)?;
+        }
+        fs::File::create(&path).with_context(|| format!("failed to create {}", path.display()))?;
+        self.refresh();
+        Ok(path)
+    }
+
+    pub fn create_dir(&mut self, name: &str) -> Result<PathBuf> {
+        let parent = self.selected_dir();
+        let path = parent.join(name);
+        fs::create_dir_all(&path)?;
+        self.refresh();
+        Ok(path)
+    }
+
+    /// Move the selected entry to the system trash (the default delete).
+    pub fn trash_selected(&mut self) -> Result<PathBuf> {
+        let entry = self
+            .selected_entry()
+            .cloned()
+            .context("no entry selected")?;
+        trash::delete(&entry.path)
+            .with_context(|| format!("failed to trash {}", entry.path.display()))?;
+        self.refresh();
+        Ok(entry.path)
+    }
+
+    /// Permanently remove the selected entry from disk.
+    pub fn delete_selected(&mut self) -> Result<PathBuf> {
+        let entry = self
+            .selected_entry()
+            .cloned()
+            .context("no entry selected")?;
+        if entry.is_dir {
+            fs::remove_dir_all(&entry.path)?;
+        } else {
+            fs::remove_file(&entry.path)?;
+        }
+        self.refresh();
+        Ok(entry.path)
+    }
+
+    /// Restore the given path from the trash, if the platform supports
+    /// listing trash contents.
+    #[cfg(all(unix, not(target_os = "macos")))]
+    pub fn restore_from_trash(path: &Path) -> Result<()> {
+        let items = trash::os_limited::list().context("failed to list trash")?;
+        let item = items
+            .into_iter()
+            .filter(|item| item.original_path() == path)
+            .max_by_key(|item| item.time_deleted)
+            .with_context(|| format!("{} not found in trash", path.display()))?;
+        trash::os_limited::restore_all([item]).context("failed to restore from trash")
+    }
+
+    #[cfg(not(all(unix, not(target_os = "macos"))))]
+    pub fn restore_from_trash(path: &Path) -> Result<()> {
+        let _ = path;
+        anyhow::bail!("restoring from trash is not supported on this platform")
+    }
+
+    pub fn rename_selected(&mut self, new_name: &str) -> Result<PathBuf> {
+        let entry = self
+            .selected_entry()
+            .cloned()
+            .context("no entry selected")?;
+        let new_path = entry
+            .path
+            .parent()
+            .unwrap_or(&self.root)
+            .join(new_name);
+        fs::rename(&entry.path, &new_path)?;
+        self.refresh();
+        Ok(new_path)
+    }
+
+    /// Directory that new entries are created in: the selected directory,
+    /// or the parent of the selected file, or the root.
+    fn selected_dir(&self) -> PathBuf {
+        match self.selected_entry() {
+            Some(e) if e.is_dir => e.path.clone(),
+            Some(e) => e.path.parent().unwrap_or(&self.root).to_path_buf(),
+            None => self.root.clone(),
+        }
+    }
+}
+
+/// Glyph for a tree entry in the active icon set.
+pub fn icon_for(entry: &TreeEntry, icons: IconSet) -> &'static str {
+    let ext = entry.path.extension().and_then(|e| e.to_str());
+    match icons {
+        IconSet::Emoji => {
+            if entry.is_dir {
+                if entry.expanded {
+                    "📂"
+                } else {
+                    "📁"
+                }
+            } else {
+                match ext {
+                    Some("rs") => "🦀",
+                    Some("md") => "📝",
+                    Some("toml") | Some("json") | Some("yaml") | Some("yml") => "⚙️",
+                    Some("png") | Some("jpg") | Some("jpeg") | Some("gif") => "🖼️",
+                    Some("sh") => "💲",
+                    _ => "📄",
+                }
+            }
+        }
+        IconSet::Unicode => {
+            if entry.is_dir {
+                if entry.expanded {
+                    "▾"
+                } else {
+                    "▸"
+                }
+            } else {
+                match ext {
+                    Some("toml") | Some("json") | Some("yaml") | Some("yml") => "≡",
+                    Some("png") | Some("jpg") | Some("jpeg") | Some("gif") => "◇",
+                    _ => "·",
+                }
+            }
+        }
+        IconSet::NerdFont => {
+            if entry.is_dir {
+                if entry.expanded {
+                    "\u{f115}" // nf-fa-folder_open_o
+                } else {
+                    "\u{f114}" // nf-fa-folder_o
+                }
+            } else {
+                match ext {
+                    Some("rs") => "\u{e7a8}",                  // nf-dev-rust
+                    Some("py") => "\u{e73c}",                  // nf-dev-python
+                    Some("js") | Some("mjs") => "\u{e74e}",    // nf-dev-javascript
+                    Some("ts") | Some("tsx") => "\u{e628}",    // nf-seti-typescript
+                    Some("go") => "\u{e626}",                  // nf-seti-go
+                    Some("c") | Some("h") => "\u{e61e}",       // nf-custom-c
+                    Some("cpp") | Some("cc") | Some("hpp") => "\u{e61d}", // nf-custom-cpp
+                    Some("java") => "\u{e738}",                // nf-dev-java
+                    Some("md") => "\u{e73e}",                  // nf-dev-markdown
+                    Some("toml") | Some("yaml") | Some("yml") => "\u{e615}", // nf-seti-config
+                    Some("json") => "\u{e60b}",                // nf-seti-json
+                    Some("html") => "\u{e736}",                // nf-dev-html5
+                    Some("css") => "\u{e749}",                 // nf-dev-css3
+                    Some("sh") | Some("bash") => "\u{e795}",   // nf-dev-terminal
+                    Some("lock") => "\u{f023}",                // nf-fa-lock
+                    Some("png") | Some("jpg") | Some("jpeg") | Some("gif") => "\u{f1c5}",
+                    _ => "\u{f15b}",                           // nf-fa-file
+                }
+            }
+        }
+    }
+}
+
+/// Walk the workspace collecting files, skipping VCS and build directories.
+pub fn walk_files(root: &Path, limit: usize) -> Vec<PathBuf> {
+    let mut out = Vec::new();
+    let mut stack = vec![root.to_path_buf()];
+    while let Some(dir) = stack.pop() {
+        let Ok(entries) = fs::read_dir(&dir) else {
+            continue;
+        };
+        for entry in entries.flatten() {
+            if out.len() >= limit {
+                return out;
+            }
+            let path = entry.path();
+            let name = entry.file_name();
+            let name = name.to_string_lossy();
+            if name.starts_with('.') || name == "target" || name == "node_modules" {
+                continue;
+            }
+            if path.is_dir() {
+                stack.push(path);
+            } else {
+                out.push(path);
+            }
+        }
+    }
+    out
+}
+
+/// Built-in extension groups for header/source switching; every
+/// extension in one group pairs with every extension in the other.
+const HEADER_EXTS: &[&str] = &["h", "hh", "hpp", "hxx"];
+const SOURCE_EXTS: &[&str] = &["c", "cc", "cpp", "cxx", "m", "mm"];
+
+/// Candidate counterparts for "Switch to Related File", most likely
+/// first. Pure path math — the caller opens the first candidate that
+/// exists. `extra_groups` come from the `[related-files]` config table;
+/// every extension in a group is related to the others in it.
+pub fn related_candidates(path: &Path, extra_groups: &[Vec<String>]) -> Vec<PathBuf> {
+    let mut out = Vec::new();
+    let Some(stem) = path.file_stem().and_then(|s| s.to_str()) else {
+        return out;
+    };
+    let ext = path.extension().and_then(|s| s.to_str()).unwrap_or("");
+    let dir = path.parent().unwrap_or(Path::new(""));
+
+    if HEADER_EXTS.contains(&ext) {
+        out.extend(SOURCE_EXTS.iter().map(|e| dir.join(format!("{stem}.{e}"))));
+    } else if SOURCE_EXTS.contains(&ext) {
+        out.extend(HEADER_EXTS.iter().map(|e| dir.join(format!("{stem}.{e}"))));
+    }
+    for group in extra_groups {
+        if group.iter().any(|e| e == ext) {
+            out.extend(
+                group
+                    .iter()
+                    .filter(|e| e.as_str() != ext)
+                    .map(|e| dir.join(format!("{stem}.{e}"))),
+            );
+        }
+    }
+
+    // Test <-> implementation conventions per language.
+    match ext {
+        "rs" => {
+            // src/foo.rs <-> tests/foo.rs.
+            let swapped: PathBuf = path
+                .iter()
+                .map(|part| match part.to_str() {
+                    Some("src") => std::ffi::OsStr::new("tests"),
+                    Some("tests") => std::ffi::OsStr::new("src"),
+                    _ => part,
+                })
+                .collect();
+            if swapped != path {
+                out.push(swapped);
+            }
+        }
+        "py" => match stem.strip_prefix("test_") {
+            Some(base) => out.push(dir.join(format!("{base}.py"))),
+            None => out.push(dir.join(format!("test_{stem}.py"))),
+        },
+        "ts" | "tsx" | "js" | "jsx" => {
+            match stem.strip_suffix(".test").or_else(|| stem.strip_suffix(".spec")) {
+                Some(base) => out.push(dir.join(format!("{base}.{ext}"))),
+                None => {
+                    out.push(dir.join(format!("{stem}.test.{ext}")));
+                    out.push(dir.join(format!("{stem}.spec.{ext}")));
+                }
+            }
+        }
+        _ => {}
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn entry(path: &str, is_dir: bool) -> TreeEntry {
+        TreeEntry {
+            path: PathBuf::from(path),
+            depth: 0,
+            is_dir,
+            expanded: false,
+        }
+    }
+
+    #[test]
+    fn related_candidates_cover_common_conventions() {
+        let from_header = related_candidates(Path::new("lib/foo.h"), &[]);
+        assert!(from_header.contains(&PathBuf::from("lib/foo.cpp")));
+        let from_source = related_candidates(Path::new("lib/foo.cc"), &[]);
+        assert!(from_source.contains(&PathBuf::from("lib/foo.h")));
+
+        let rust = related_candidates(Path::new("src/parser.rs"), &[]);
+        assert_eq!(rust, vec![PathBuf::from("tests/parser.rs")]);
+
+        let py = related_candidates(Path::new("pkg/test_mod.py"), &[]);
+        assert_eq!(py, vec![PathBuf::from("pkg/mod.py")]);
+
+        let ts = related_candidates(Path::new("app/util.ts"), &[]);
+        assert_eq!(
+            ts,
+            vec![
+                PathBuf::from("app/util.test.ts"),
+                PathBuf::from("app/util.spec.ts")
+            ]
+        );
+
+        let groups = vec![vec!["vert".to_string(), "frag".to_string()]];
+        let shader = related_candidates(Path::new("fx/blur.vert"), &groups);
+        assert_eq!(shader, vec![PathBuf::from("fx/blur.frag")]);
+    }
+
+    #[test]
+    fn natural_cmp_orders_digit_runs_numerically() {
+        use std::cmp::Ordering;
+
+        assert_eq!(natural_cmp("file2", "file10"), Ordering::Less);
+        assert_eq!(natural_cmp("Chapter1", "chapter2"), Ordering::Less);
+        assert_eq!(natural_cmp("a", "ab"), Ordering::Less);
+        // Equal-number ties fall back to a total order.
+        assert_ne!(natural_cmp("file01", "file1"), Ordering::Equal);
+    }
+
+    #[test]
+    fn icon_tables_cover_each_set() {
+        let rust = entry("src/main.rs", false);
+        assert_eq!(icon_for(&rust, IconSet::Emoji), "🦀");
+        assert_eq!(icon_for(&rust, IconSet::NerdFont), "\u{e7a8}");
+        assert_eq!(icon_for(&rust, IconSet::Unicode), "·");
+
+        let dir = entry("src", true);
+        assert_eq!(icon_for(&dir, IconSet::Unicode), "▸");
+        assert_eq!(icon_for(&dir, IconSet::NerdFont), "\u{f114}");
+    }
+}